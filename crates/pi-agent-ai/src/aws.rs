@@ -0,0 +1,1018 @@
+//! AWS request signing (SigV4) and the credential provider chain.
+//!
+//! Credentials are resolved in the order the AWS SDKs use:
+//!
+//! 1. `AWS_ACCESS_KEY_ID` / `AWS_SECRET_ACCESS_KEY` (skipped when a profile
+//!    is requested explicitly)
+//! 2. Web identity from `AWS_WEB_IDENTITY_TOKEN_FILE` + `AWS_ROLE_ARN` (EKS IRSA)
+//! 3. The shared config/credentials profile: static keys, `credential_process`,
+//!    or `role_arn` assumed through STS from a `source_profile`,
+//!    `credential_source` or `web_identity_token_file`
+//! 4. The ECS/EKS container endpoint (`AWS_CONTAINER_CREDENTIALS_*_URI`)
+//!
+//! Temporary credentials are cached per profile until shortly before they
+//! expire; concurrent resolutions wait for a single refresh.
+
+use std::collections::{HashMap, HashSet};
+use std::net::{Ipv4Addr, Ipv6Addr};
+use std::path::PathBuf;
+use std::sync::OnceLock;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::http_client::{describe_error, http_clients};
+
+/// Refresh cached credentials this long before they expire.
+const EXPIRY_MARGIN_SECS: i64 = 300;
+
+/// ECS task metadata endpoint for `AWS_CONTAINER_CREDENTIALS_RELATIVE_URI`.
+const ECS_CONTAINER_HOST: &str = "http://169.254.170.2";
+
+/// Provider key used for HTTP client settings (proxy, TLS) of STS and
+/// container requests.
+const HTTP_PROVIDER: &str = "amazon-bedrock";
+
+// ---------- SigV4 ----------
+
+type HmacSha256 = Hmac<Sha256>;
+
+fn sha256_hash(data: &[u8]) -> String {
+    let mut hasher = Sha256::new();
+    hasher.update(data);
+    hex::encode(hasher.finalize())
+}
+
+fn hmac_sha256(key: &[u8], msg: &[u8]) -> Vec<u8> {
+    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
+    mac.update(msg);
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn get_signature_key(key: &str, date_stamp: &str, region: &str, service: &str) -> Vec<u8> {
+    let k_date = hmac_sha256(format!("AWS4{key}").as_bytes(), date_stamp.as_bytes());
+    let k_region = hmac_sha256(&k_date, region.as_bytes());
+    let k_service = hmac_sha256(&k_region, service.as_bytes());
+    hmac_sha256(&k_service, b"aws4_request")
+}
+
+/// Sign a request with SigV4 and return the headers to send.
+#[allow(clippy::too_many_arguments)]
+pub fn sign_request(
+    method: &str,
+    url_str: &str,
+    body: &[u8],
+    region: &str,
+    service: &str,
+    content_type: &str,
+    access_key: &str,
+    secret_key: &str,
+    session_token: Option<&str>,
+) -> Result<HashMap<String, String>, String> {
+    let now = chrono::Utc::now();
+    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+    let date_stamp = now.format("%Y%m%d").to_string();
+
+    let parsed = url::Url::parse(url_str).map_err(|e| format!("Invalid URL: {e}"))?;
+    let host = match (parsed.host_str(), parsed.port()) {
+        (Some(host), Some(port)) => format!("{host}:{port}"),
+        (Some(host), None) => host.to_string(),
+        (None, _) => String::new(),
+    };
+    let canonical_uri = parsed.path().to_string();
+    let canonical_querystring = parsed.query().unwrap_or("").to_string();
+
+    let payload_hash = sha256_hash(body);
+
+    // Build canonical headers
+    let mut signed_header_names =
+        vec!["content-type", "host", "x-amz-content-sha256", "x-amz-date"];
+    if session_token.is_some() {
+        signed_header_names.push("x-amz-security-token");
+    }
+    signed_header_names.sort();
+
+    let mut canonical_headers = String::new();
+    for name in &signed_header_names {
+        let value = match *name {
+            "content-type" => content_type.to_string(),
+            "host" => host.clone(),
+            "x-amz-content-sha256" => payload_hash.clone(),
+            "x-amz-date" => amz_date.clone(),
+            "x-amz-security-token" => session_token.unwrap_or("").to_string(),
+            _ => String::new(),
+        };
+        canonical_headers.push_str(&format!("{name}:{value}\n"));
+    }
+
+    let signed_headers = signed_header_names.join(";");
+
+    let canonical_request = format!(
+        "{method}\n{canonical_uri}\n{canonical_querystring}\n{canonical_headers}\n{signed_headers}\n{payload_hash}"
+    );
+
+    let credential_scope = format!("{date_stamp}/{region}/{service}/aws4_request");
+    let string_to_sign = format!(
+        "AWS4-HMAC-SHA256\n{amz_date}\n{credential_scope}\n{}",
+        sha256_hash(canonical_request.as_bytes())
+    );
+
+    let signing_key = get_signature_key(secret_key, &date_stamp, region, service);
+    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
+
+    let authorization = format!(
+        "AWS4-HMAC-SHA256 Credential={access_key}/{credential_scope}, SignedHeaders={signed_headers}, Signature={signature}"
+    );
+
+    let mut headers = HashMap::new();
+    headers.insert("content-type".to_string(), content_type.to_string());
+    headers.insert("x-amz-date".to_string(), amz_date);
+    headers.insert("x-amz-content-sha256".to_string(), payload_hash);
+    headers.insert("authorization".to_string(), authorization);
+
+    if let Some(token) = session_token {
+        headers.insert("x-amz-security-token".to_string(), token.to_string());
+    }
+
+    Ok(headers)
+}
+
+// ---------- Credentials ----------
+
+/// Resolved AWS credentials.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AwsCredentials {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+    pub session_token: Option<String>,
+    /// `None` for long-lived keys.
+    pub expiration: Option<DateTime<Utc>>,
+}
+
+impl AwsCredentials {
+    /// Whether the credentials are usable for at least the expiry margin.
+    pub fn is_fresh(&self) -> bool {
+        self.expiration
+            .is_none_or(|exp| Utc::now() + chrono::Duration::seconds(EXPIRY_MARGIN_SECS) < exp)
+    }
+}
+
+/// JSON shape shared by `credential_process` output and the container endpoint.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CredentialsJson {
+    #[serde(default)]
+    version: Option<u32>,
+    access_key_id: String,
+    secret_access_key: String,
+    #[serde(default, alias = "Token")]
+    session_token: Option<String>,
+    #[serde(default)]
+    expiration: Option<String>,
+}
+
+impl CredentialsJson {
+    fn into_credentials(self) -> Result<AwsCredentials, String> {
+        Ok(AwsCredentials {
+            access_key_id: self.access_key_id,
+            secret_access_key: self.secret_access_key,
+            session_token: self.session_token.filter(|t| !t.is_empty()),
+            expiration: self
+                .expiration
+                .as_deref()
+                .map(parse_expiration)
+                .transpose()?,
+        })
+    }
+}
+
+fn parse_expiration(value: &str) -> Result<DateTime<Utc>, String> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| format!("Invalid credential expiration {value:?}: {e}"))
+}
+
+// ---------- Shared config files ----------
+
+type Profiles = HashMap<String, HashMap<String, String>>;
+
+/// Parse an AWS INI file. In the config file, sections other than
+/// `default` are written `[profile name]`.
+fn parse_ini(content: &str, is_config: bool, profiles: &mut Profiles) {
+    let mut current: Option<String> = None;
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with(';') {
+            continue;
+        }
+        if let Some(section) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+            let section = section.trim();
+            current = if !is_config || section == "default" {
+                Some(section.to_string())
+            } else {
+                section
+                    .strip_prefix("profile ")
+                    .map(|name| name.trim().to_string())
+            };
+            continue;
+        }
+        if let (Some(profile), Some((key, value))) = (&current, line.split_once('=')) {
+            profiles
+                .entry(profile.clone())
+                .or_default()
+                .insert(key.trim().to_ascii_lowercase(), value.trim().to_string());
+        }
+    }
+}
+
+/// Describes one role to assume.
+#[derive(Debug, Clone)]
+struct RoleConfig {
+    role_arn: String,
+    session_name: Option<String>,
+    external_id: Option<String>,
+    duration_seconds: Option<String>,
+    region: Option<String>,
+}
+
+impl RoleConfig {
+    fn from_profile(profile: &HashMap<String, String>, role_arn: &str) -> Self {
+        Self {
+            role_arn: role_arn.to_string(),
+            session_name: profile.get("role_session_name").cloned(),
+            external_id: profile.get("external_id").cloned(),
+            duration_seconds: profile.get("duration_seconds").cloned(),
+            region: profile.get("region").cloned(),
+        }
+    }
+}
+
+/// Where the first credentials in a profile chain come from.
+enum BaseSource {
+    Resolved(AwsCredentials),
+    Process(String),
+    WebIdentity {
+        token_file: String,
+        role: RoleConfig,
+    },
+    Environment,
+    Container,
+}
+
+// ---------- Chain ----------
+
+/// The AWS credential provider chain with a per-profile cache.
+pub struct AwsCredentialChain {
+    /// Environment overrides; `None` reads the process environment.
+    env: Option<HashMap<String, String>>,
+    cache: tokio::sync::Mutex<HashMap<String, AwsCredentials>>,
+}
+
+impl Default for AwsCredentialChain {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AwsCredentialChain {
+    /// Chain reading the process environment.
+    pub fn new() -> Self {
+        Self {
+            env: None,
+            cache: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Chain reading only `env` (for tests and embedders).
+    pub fn with_env(env: HashMap<String, String>) -> Self {
+        Self {
+            env: Some(env),
+            cache: tokio::sync::Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn var(&self, name: &str) -> Option<String> {
+        let value = match &self.env {
+            Some(env) => env.get(name).cloned(),
+            None => std::env::var(name).ok(),
+        };
+        value.filter(|v| !v.is_empty())
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.var("HOME")
+            .or_else(|| self.var("USERPROFILE"))
+            .map(PathBuf::from)
+    }
+
+    fn config_file(&self) -> Option<PathBuf> {
+        self.var("AWS_CONFIG_FILE")
+            .map(PathBuf::from)
+            .or_else(|| self.home_dir().map(|h| h.join(".aws").join("config")))
+    }
+
+    fn credentials_file(&self) -> Option<PathBuf> {
+        self.var("AWS_SHARED_CREDENTIALS_FILE")
+            .map(PathBuf::from)
+            .or_else(|| self.home_dir().map(|h| h.join(".aws").join("credentials")))
+    }
+
+    /// Profiles from the config file, overlaid by the credentials file.
+    fn load_profiles(&self) -> Profiles {
+        let mut profiles = Profiles::new();
+        if let Some(content) = self
+            .config_file()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+        {
+            parse_ini(&content, true, &mut profiles);
+        }
+        if let Some(content) = self
+            .credentials_file()
+            .and_then(|p| std::fs::read_to_string(p).ok())
+        {
+            parse_ini(&content, false, &mut profiles);
+        }
+        profiles
+    }
+
+    fn profile_name(&self, profile: Option<&str>) -> String {
+        profile
+            .map(str::to_string)
+            .or_else(|| self.var("AWS_PROFILE"))
+            .unwrap_or_else(|| "default".to_string())
+    }
+
+    fn env_credentials(&self) -> Option<AwsCredentials> {
+        Some(AwsCredentials {
+            access_key_id: self.var("AWS_ACCESS_KEY_ID")?,
+            secret_access_key: self.var("AWS_SECRET_ACCESS_KEY")?,
+            session_token: self.var("AWS_SESSION_TOKEN"),
+            expiration: None,
+        })
+    }
+
+    fn has_container_endpoint(&self) -> bool {
+        self.var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI").is_some()
+            || self.var("AWS_CONTAINER_CREDENTIALS_FULL_URI").is_some()
+    }
+
+    /// Cheap check for whether any credential source is configured; does not
+    /// contact any endpoint.
+    pub fn has_source(&self, profile: Option<&str>) -> bool {
+        self.env_credentials().is_some()
+            || (self.var("AWS_WEB_IDENTITY_TOKEN_FILE").is_some()
+                && self.var("AWS_ROLE_ARN").is_some())
+            || self.has_container_endpoint()
+            || self
+                .load_profiles()
+                .contains_key(&self.profile_name(profile))
+    }
+
+    /// Resolve credentials for `profile` (or `AWS_PROFILE`, or `default`).
+    ///
+    /// Returns `Ok(None)` when no source is configured and an error when a
+    /// configured source fails.
+    pub async fn resolve(
+        &self,
+        profile: Option<&str>,
+        region: &str,
+    ) -> Result<Option<AwsCredentials>, String> {
+        if profile.is_none()
+            && let Some(credentials) = self.env_credentials()
+        {
+            return Ok(Some(credentials));
+        }
+
+        let name = self.profile_name(profile);
+        let mut cache = self.cache.lock().await;
+        if let Some(cached) = cache.get(&name)
+            && cached.is_fresh()
+        {
+            return Ok(Some(cached.clone()));
+        }
+
+        let resolved = self.resolve_uncached(profile, &name, region).await?;
+        if let Some(credentials) = &resolved {
+            if credentials.expiration.is_some() {
+                cache.insert(name, credentials.clone());
+            } else {
+                cache.remove(&name);
+            }
+        }
+        Ok(resolved)
+    }
+
+    async fn resolve_uncached(
+        &self,
+        explicit_profile: Option<&str>,
+        name: &str,
+        region: &str,
+    ) -> Result<Option<AwsCredentials>, String> {
+        if explicit_profile.is_none()
+            && let (Some(token_file), Some(role_arn)) = (
+                self.var("AWS_WEB_IDENTITY_TOKEN_FILE"),
+                self.var("AWS_ROLE_ARN"),
+            )
+        {
+            let role = RoleConfig {
+                role_arn,
+                session_name: self.var("AWS_ROLE_SESSION_NAME"),
+                external_id: None,
+                duration_seconds: None,
+                region: None,
+            };
+            return self
+                .assume_role_with_web_identity(&token_file, &role, region)
+                .await
+                .map(Some);
+        }
+
+        let profiles = self.load_profiles();
+        if profiles.contains_key(name) {
+            return self
+                .resolve_profile(&profiles, name, region)
+                .await
+                .map(Some);
+        }
+        if explicit_profile.is_some() || self.var("AWS_PROFILE").is_some() {
+            return Err(format!("AWS profile {name:?} not found"));
+        }
+
+        if self.has_container_endpoint() {
+            return self.resolve_container().await.map(Some);
+        }
+        Ok(None)
+    }
+
+    /// Resolve a profile, following `source_profile` links and assuming each
+    /// role from the innermost credentials outwards.
+    async fn resolve_profile(
+        &self,
+        profiles: &Profiles,
+        name: &str,
+        region: &str,
+    ) -> Result<AwsCredentials, String> {
+        let mut roles: Vec<RoleConfig> = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = name.to_string();
+
+        let base = loop {
+            if !visited.insert(current.clone()) {
+                return Err(format!(
+                    "AWS profile {current:?} has a source_profile cycle"
+                ));
+            }
+            let profile = profiles
+                .get(&current)
+                .ok_or_else(|| format!("AWS profile {current:?} not found"))?;
+            let static_keys = match (
+                profile.get("aws_access_key_id"),
+                profile.get("aws_secret_access_key"),
+            ) {
+                (Some(access), Some(secret)) => Some(AwsCredentials {
+                    access_key_id: access.clone(),
+                    secret_access_key: secret.clone(),
+                    session_token: profile.get("aws_session_token").cloned(),
+                    expiration: None,
+                }),
+                _ => None,
+            };
+
+            // Source profiles prefer their own static keys over further roles.
+            if !roles.is_empty()
+                && let Some(keys) = static_keys.clone()
+            {
+                break BaseSource::Resolved(keys);
+            }
+
+            if let Some(role_arn) = profile.get("role_arn") {
+                let role = RoleConfig::from_profile(profile, role_arn);
+                if let Some(token_file) = profile.get("web_identity_token_file") {
+                    break BaseSource::WebIdentity {
+                        token_file: token_file.clone(),
+                        role,
+                    };
+                }
+                roles.push(role);
+                if let Some(source) = profile.get("source_profile") {
+                    if *source == current {
+                        break BaseSource::Resolved(static_keys.ok_or_else(|| {
+                            format!("AWS profile {current:?} sources itself but has no keys")
+                        })?);
+                    }
+                    current = source.clone();
+                    continue;
+                }
+                break match profile.get("credential_source").map(String::as_str) {
+                    Some("Environment") => BaseSource::Environment,
+                    Some("EcsContainer") => BaseSource::Container,
+                    Some(other) => {
+                        return Err(format!("Unsupported credential_source {other:?}"));
+                    }
+                    None => {
+                        return Err(format!(
+                            "AWS profile {current:?} sets role_arn without source_profile or credential_source"
+                        ));
+                    }
+                };
+            }
+
+            if let Some(command) = profile.get("credential_process") {
+                break BaseSource::Process(command.clone());
+            }
+            break BaseSource::Resolved(
+                static_keys.ok_or_else(|| format!("AWS profile {current:?} has no credentials"))?,
+            );
+        };
+
+        let mut credentials = match base {
+            BaseSource::Resolved(credentials) => credentials,
+            BaseSource::Process(command) => run_credential_process(&command).await?,
+            BaseSource::WebIdentity { token_file, role } => {
+                self.assume_role_with_web_identity(&token_file, &role, region)
+                    .await?
+            }
+            BaseSource::Environment => self
+                .env_credentials()
+                .ok_or("credential_source Environment requires AWS_ACCESS_KEY_ID")?,
+            BaseSource::Container => self.resolve_container().await?,
+        };
+        for role in roles.iter().rev() {
+            credentials = self.assume_role(&credentials, role, region).await?;
+        }
+        Ok(credentials)
+    }
+
+    fn sts_endpoint(&self, region: &str) -> String {
+        self.var("AWS_ENDPOINT_URL_STS")
+            .or_else(|| self.var("AWS_ENDPOINT_URL"))
+            .unwrap_or_else(|| format!("https://sts.{region}.amazonaws.com/"))
+    }
+
+    fn session_name(&self, role: &RoleConfig) -> String {
+        role.session_name
+            .clone()
+            .unwrap_or_else(|| format!("pi-agent-{}", Utc::now().timestamp()))
+    }
+
+    async fn assume_role(
+        &self,
+        source: &AwsCredentials,
+        role: &RoleConfig,
+        region: &str,
+    ) -> Result<AwsCredentials, String> {
+        let region = role.region.as_deref().unwrap_or(region);
+        let session_name = self.session_name(role);
+        let mut form = vec![
+            ("Action", "AssumeRole"),
+            ("Version", "2011-06-15"),
+            ("RoleArn", role.role_arn.as_str()),
+            ("RoleSessionName", session_name.as_str()),
+        ];
+        if let Some(external_id) = &role.external_id {
+            form.push(("ExternalId", external_id));
+        }
+        if let Some(duration) = &role.duration_seconds {
+            form.push(("DurationSeconds", duration));
+        }
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs(form)
+            .finish();
+
+        let endpoint = self.sts_endpoint(region);
+        let headers = sign_request(
+            "POST",
+            &endpoint,
+            body.as_bytes(),
+            region,
+            "sts",
+            "application/x-www-form-urlencoded; charset=utf-8",
+            &source.access_key_id,
+            &source.secret_access_key,
+            source.session_token.as_deref(),
+        )?;
+        let mut request = http_clients()
+            .client(HTTP_PROVIDER, &endpoint)
+            .post(&endpoint)
+            .body(body);
+        for (name, value) in &headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+        sts_call(request, "AssumeRole").await
+    }
+
+    async fn assume_role_with_web_identity(
+        &self,
+        token_file: &str,
+        role: &RoleConfig,
+        region: &str,
+    ) -> Result<AwsCredentials, String> {
+        let token = std::fs::read_to_string(token_file)
+            .map_err(|e| format!("Cannot read web identity token {token_file}: {e}"))?;
+        let region = role.region.as_deref().unwrap_or(region);
+        let session_name = self.session_name(role);
+        let body = url::form_urlencoded::Serializer::new(String::new())
+            .extend_pairs([
+                ("Action", "AssumeRoleWithWebIdentity"),
+                ("Version", "2011-06-15"),
+                ("RoleArn", role.role_arn.as_str()),
+                ("RoleSessionName", session_name.as_str()),
+                ("WebIdentityToken", token.trim()),
+            ])
+            .finish();
+
+        let endpoint = self.sts_endpoint(region);
+        let request = http_clients()
+            .client(HTTP_PROVIDER, &endpoint)
+            .post(&endpoint)
+            .header(
+                "content-type",
+                "application/x-www-form-urlencoded; charset=utf-8",
+            )
+            .body(body);
+        sts_call(request, "AssumeRoleWithWebIdentity").await
+    }
+
+    /// ECS task role or EKS Pod Identity credentials.
+    async fn resolve_container(&self) -> Result<AwsCredentials, String> {
+        let url = match (
+            self.var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI"),
+            self.var("AWS_CONTAINER_CREDENTIALS_FULL_URI"),
+        ) {
+            (Some(relative), _) => format!("{ECS_CONTAINER_HOST}{relative}"),
+            (None, Some(full)) => {
+                check_container_uri(&full)?;
+                full
+            }
+            (None, None) => return Err("No container credentials endpoint configured".into()),
+        };
+        let token = match self.var("AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE") {
+            Some(path) => Some(
+                std::fs::read_to_string(&path)
+                    .map_err(|e| format!("Cannot read container token {path}: {e}"))?
+                    .trim()
+                    .to_string(),
+            ),
+            None => self.var("AWS_CONTAINER_AUTHORIZATION_TOKEN"),
+        };
+
+        let mut request = http_clients().client(HTTP_PROVIDER, &url).get(&url);
+        if let Some(token) = token {
+            request = request.header("authorization", token);
+        }
+        let response = request.send().await.map_err(|e| {
+            format!(
+                "Container credentials request failed: {}",
+                describe_error(&e)
+            )
+        })?;
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+        if !status.is_success() {
+            return Err(format!(
+                "Container credentials request failed (HTTP {status}): {body}"
+            ));
+        }
+        serde_json::from_str::<CredentialsJson>(&body)
+            .map_err(|e| format!("Invalid container credentials: {e}"))?
+            .into_credentials()
+    }
+}
+
+/// Only send the container authorization token over HTTPS, or over HTTP to
+/// a loopback address or the ECS/EKS link-local endpoints, as the AWS SDKs do.
+fn check_container_uri(uri: &str) -> Result<(), String> {
+    let url = url::Url::parse(uri)
+        .map_err(|e| format!("Invalid AWS_CONTAINER_CREDENTIALS_FULL_URI {uri}: {e}"))?;
+    let allowed = match (url.scheme(), url.host()) {
+        ("https", Some(_)) => true,
+        ("http", Some(url::Host::Domain(domain))) => domain.eq_ignore_ascii_case("localhost"),
+        ("http", Some(url::Host::Ipv4(ip))) => {
+            ip.is_loopback()
+                || ip == Ipv4Addr::new(169, 254, 170, 2)
+                || ip == Ipv4Addr::new(169, 254, 170, 23)
+        }
+        ("http", Some(url::Host::Ipv6(ip))) => {
+            ip.is_loopback() || ip == Ipv6Addr::new(0xfd00, 0xec2, 0, 0, 0, 0, 0, 0x23)
+        }
+        _ => false,
+    };
+    if allowed {
+        Ok(())
+    } else {
+        Err(format!(
+            "AWS_CONTAINER_CREDENTIALS_FULL_URI must use https or a loopback or ECS/EKS \
+             container address: {uri}"
+        ))
+    }
+}
+
+/// Run a `credential_process` command and parse its JSON output.
+async fn run_credential_process(command: &str) -> Result<AwsCredentials, String> {
+    #[cfg(windows)]
+    let mut process = {
+        let mut process = tokio::process::Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    };
+    #[cfg(not(windows))]
+    let mut process = {
+        let mut process = tokio::process::Command::new("sh");
+        process.args(["-c", command]);
+        process
+    };
+    let output = process
+        .output()
+        .await
+        .map_err(|e| format!("Failed to run credential_process: {e}"))?;
+    if !output.status.success() {
+        return Err(format!(
+            "credential_process exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let parsed: CredentialsJson = serde_json::from_slice(&output.stdout)
+        .map_err(|e| format!("Invalid credential_process output: {e}"))?;
+    if parsed.version != Some(1) {
+        return Err("credential_process output must have \"Version\": 1".into());
+    }
+    parsed.into_credentials()
+}
+
+/// Text of the first `<tag>` element in an STS XML response.
+fn xml_tag<'a>(xml: &'a str, tag: &str) -> Option<&'a str> {
+    let open = format!("<{tag}>");
+    let start = xml.find(&open)? + open.len();
+    let end = xml[start..].find(&format!("</{tag}>"))? + start;
+    Some(xml[start..end].trim())
+}
+
+async fn sts_call(
+    request: reqwest::RequestBuilder,
+    action: &str,
+) -> Result<AwsCredentials, String> {
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("STS {action} failed: {}", describe_error(&e)))?;
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    if !status.is_success() {
+        let detail = xml_tag(&body, "Message").unwrap_or(&body);
+        return Err(format!("STS {action} failed (HTTP {status}): {detail}"));
+    }
+    let field = |tag: &str| {
+        xml_tag(&body, tag)
+            .map(str::to_string)
+            .ok_or_else(|| format!("STS {action} response is missing {tag}"))
+    };
+    Ok(AwsCredentials {
+        access_key_id: field("AccessKeyId")?,
+        secret_access_key: field("SecretAccessKey")?,
+        session_token: Some(field("SessionToken")?),
+        expiration: Some(parse_expiration(&field("Expiration")?)?),
+    })
+}
+
+/// Process-wide chain shared by all Bedrock requests.
+pub fn aws_credentials() -> &'static AwsCredentialChain {
+    static CHAIN: OnceLock<AwsCredentialChain> = OnceLock::new();
+    CHAIN.get_or_init(AwsCredentialChain::new)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{stub_server, test_dir};
+
+    fn sts_response(action: &str, key: &str) -> String {
+        let expiration = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        format!(
+            "<{action}Response><{action}Result><Credentials><AccessKeyId>{key}</AccessKeyId><SecretAccessKey>secret</SecretAccessKey><SessionToken>session</SessionToken><Expiration>{expiration}</Expiration></Credentials></{action}Result></{action}Response>"
+        )
+    }
+
+    fn env(dir: &std::path::Path, vars: &[(&str, String)]) -> HashMap<String, String> {
+        let mut env: HashMap<String, String> = vars
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.clone()))
+            .collect();
+        env.insert("HOME".to_string(), dir.display().to_string());
+        env
+    }
+
+    #[test]
+    fn test_parse_ini_profiles() {
+        let mut profiles = Profiles::new();
+        parse_ini(
+            "[default]\nregion = us-west-2\n# comment\n[profile dev]\nrole_arn = arn:aws:iam::1:role/dev\n[sso-session x]\nsso_region = us-east-1\n",
+            true,
+            &mut profiles,
+        );
+        parse_ini("[dev]\naws_access_key_id = AKIA\n", false, &mut profiles);
+        assert_eq!(profiles["default"]["region"], "us-west-2");
+        assert_eq!(profiles["dev"]["role_arn"], "arn:aws:iam::1:role/dev");
+        assert_eq!(profiles["dev"]["aws_access_key_id"], "AKIA");
+        assert!(!profiles.contains_key("sso-session x"));
+    }
+
+    #[tokio::test]
+    async fn test_static_profile_and_env_precedence() {
+        let dir = test_dir();
+        std::fs::create_dir_all(dir.as_path().join(".aws")).unwrap();
+        std::fs::write(
+            dir.as_path().join(".aws/credentials"),
+            "[work]\naws_access_key_id = AKIAWORK\naws_secret_access_key = s\n",
+        )
+        .unwrap();
+
+        let chain = AwsCredentialChain::with_env(env(
+            dir.as_path(),
+            &[
+                ("AWS_ACCESS_KEY_ID", "AKIAENV".to_string()),
+                ("AWS_SECRET_ACCESS_KEY", "s".to_string()),
+            ],
+        ));
+        let from_env = chain.resolve(None, "us-east-1").await.unwrap().unwrap();
+        assert_eq!(from_env.access_key_id, "AKIAENV");
+        let from_profile = chain
+            .resolve(Some("work"), "us-east-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(from_profile.access_key_id, "AKIAWORK");
+        assert!(chain.resolve(Some("missing"), "us-east-1").await.is_err());
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_credential_process() {
+        let dir = test_dir();
+        let config = dir.as_path().join("config");
+        std::fs::write(
+            &config,
+            "[default]\ncredential_process = echo '{\"Version\": 1, \"AccessKeyId\": \"AKIAPROC\", \"SecretAccessKey\": \"s\", \"SessionToken\": \"t\"}'\n",
+        )
+        .unwrap();
+        let chain = AwsCredentialChain::with_env(env(
+            dir.as_path(),
+            &[("AWS_CONFIG_FILE", config.display().to_string())],
+        ));
+        assert!(chain.has_source(None));
+        let credentials = chain.resolve(None, "us-east-1").await.unwrap().unwrap();
+        assert_eq!(credentials.access_key_id, "AKIAPROC");
+        assert_eq!(credentials.session_token.as_deref(), Some("t"));
+    }
+
+    #[tokio::test]
+    async fn test_assume_role_from_source_profile_is_cached() {
+        let dir = test_dir();
+        let config = dir.as_path().join("config");
+        std::fs::write(
+            &config,
+            "[profile base]\naws_access_key_id = AKIABASE\naws_secret_access_key = s\n[profile admin]\nrole_arn = arn:aws:iam::1:role/admin\nsource_profile = base\nrole_session_name = test\n",
+        )
+        .unwrap();
+        let (sts, requests) =
+            stub_server(vec![(200, sts_response("AssumeRole", "ASIAROLE"))]).await;
+        let chain = AwsCredentialChain::with_env(env(
+            dir.as_path(),
+            &[
+                ("AWS_CONFIG_FILE", config.display().to_string()),
+                ("AWS_ENDPOINT_URL_STS", sts),
+            ],
+        ));
+
+        let first = chain
+            .resolve(Some("admin"), "us-east-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first.access_key_id, "ASIAROLE");
+        assert!(first.expiration.is_some());
+        // Served from the cache; the stub only answers once.
+        let second = chain
+            .resolve(Some("admin"), "us-east-1")
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(first, second);
+
+        let request = &requests.await.unwrap()[0];
+        assert!(request.contains("Action=AssumeRole"));
+        assert!(request.contains("RoleSessionName=test"));
+        assert!(request.contains("Credential=AKIABASE/"));
+    }
+
+    #[tokio::test]
+    async fn test_web_identity_from_env() {
+        let dir = test_dir();
+        let token_file = dir.as_path().join("token");
+        std::fs::write(&token_file, "jwt-token\n").unwrap();
+        let (sts, requests) = stub_server(vec![(
+            200,
+            sts_response("AssumeRoleWithWebIdentity", "ASIAWEB"),
+        )])
+        .await;
+        let chain = AwsCredentialChain::with_env(env(
+            dir.as_path(),
+            &[
+                (
+                    "AWS_WEB_IDENTITY_TOKEN_FILE",
+                    token_file.display().to_string(),
+                ),
+                ("AWS_ROLE_ARN", "arn:aws:iam::1:role/pod".to_string()),
+                ("AWS_ENDPOINT_URL_STS", sts),
+            ],
+        ));
+        let credentials = chain.resolve(None, "us-east-1").await.unwrap().unwrap();
+        assert_eq!(credentials.access_key_id, "ASIAWEB");
+        let request = &requests.await.unwrap()[0];
+        assert!(request.contains("WebIdentityToken=jwt-token"));
+        assert!(!request.to_ascii_lowercase().contains("authorization:"));
+    }
+
+    #[tokio::test]
+    async fn test_container_endpoint_with_token_file() {
+        let dir = test_dir();
+        let token_file = dir.as_path().join("eks-token");
+        std::fs::write(&token_file, "pod-token").unwrap();
+        let expiration = (Utc::now() + chrono::Duration::hours(1)).to_rfc3339();
+        let (endpoint, requests) = stub_server(vec![(
+            200,
+            format!(
+                r#"{{"AccessKeyId":"ASIAPOD","SecretAccessKey":"s","Token":"t","Expiration":"{expiration}"}}"#
+            ),
+        )])
+        .await;
+        let chain = AwsCredentialChain::with_env(env(
+            dir.as_path(),
+            &[
+                ("AWS_CONTAINER_CREDENTIALS_FULL_URI", endpoint),
+                (
+                    "AWS_CONTAINER_AUTHORIZATION_TOKEN_FILE",
+                    token_file.display().to_string(),
+                ),
+            ],
+        ));
+        let credentials = chain.resolve(None, "us-east-1").await.unwrap().unwrap();
+        assert_eq!(credentials.access_key_id, "ASIAPOD");
+        assert_eq!(credentials.session_token.as_deref(), Some("t"));
+        let request = &requests.await.unwrap()[0];
+        assert!(
+            request
+                .to_ascii_lowercase()
+                .contains("authorization: pod-token")
+        );
+    }
+
+    #[test]
+    fn test_container_uri_allowlist() {
+        for uri in [
+            "https://creds.example.com/role",
+            "http://127.0.0.1:9000/creds",
+            "http://localhost/creds",
+            "http://169.254.170.2/v2/credentials",
+            "http://169.254.170.23/v1/credentials",
+            "http://[fd00:ec2::23]/v1/credentials",
+            "http://[::1]:8080/creds",
+        ] {
+            assert!(check_container_uri(uri).is_ok(), "{uri}");
+        }
+        for uri in [
+            "http://attacker.example.com/creds",
+            "http://10.0.0.5/creds",
+            "file:///etc/passwd",
+        ] {
+            assert!(check_container_uri(uri).is_err(), "{uri}");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sts_error_is_reported() {
+        let dir = test_dir();
+        let token_file = dir.as_path().join("token");
+        std::fs::write(&token_file, "jwt").unwrap();
+        let (sts, _) = stub_server(vec![(
+            403,
+            "<ErrorResponse><Error><Code>AccessDenied</Code><Message>Not authorized</Message></Error></ErrorResponse>".to_string(),
+        )])
+        .await;
+        let chain = AwsCredentialChain::with_env(env(
+            dir.as_path(),
+            &[
+                (
+                    "AWS_WEB_IDENTITY_TOKEN_FILE",
+                    token_file.display().to_string(),
+                ),
+                ("AWS_ROLE_ARN", "arn:aws:iam::1:role/pod".to_string()),
+                ("AWS_ENDPOINT_URL_STS", sts),
+            ],
+        ));
+        let error = chain.resolve(None, "us-east-1").await.unwrap_err();
+        assert!(error.contains("Not authorized"));
+    }
+}
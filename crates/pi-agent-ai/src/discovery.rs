@@ -0,0 +1,461 @@
+//! Model discovery from provider model-list endpoints.
+//!
+//! OpenAI-compatible servers (LM Studio, llama.cpp, vLLM, ...) are listed
+//! through `/v1/models`, Ollama through `/api/tags` and `/api/show`, and
+//! OpenRouter through its model metadata endpoint. Every entry becomes a
+//! [`Model`] on the OpenAI completions API; capabilities the endpoint does
+//! not report are inferred from the model ID.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use futures::future::join_all;
+use pi_agent_core::types::{Model, ModelCost};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+
+use crate::http_client::{describe_error, http_clients};
+
+/// Context window assumed when the endpoint does not report one.
+pub const DEFAULT_CONTEXT_WINDOW: u64 = 128_000;
+
+/// Output limit assumed when the endpoint does not report one.
+pub const DEFAULT_MAX_TOKENS: u64 = 8_192;
+
+/// Timeout for each discovery request.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Model IDs containing any of these are not chat models.
+const NON_CHAT_MARKERS: &[&str] = &["embed", "rerank", "whisper", "tts", "dall-e", "moderation"];
+
+/// Model IDs containing any of these are assumed to reason.
+const REASONING_MARKERS: &[&str] = &[
+    "deepseek-r1",
+    "qwq",
+    "qwen3",
+    "gpt-oss",
+    "magistral",
+    "reason",
+    "thinking",
+    "o1",
+    "o3",
+    "o4-",
+];
+
+/// Model IDs containing any of these are assumed to accept images.
+const VISION_MARKERS: &[&str] = &[
+    "vision",
+    "llava",
+    "-vl",
+    "vl-",
+    "gemma3",
+    "pixtral",
+    "llama4",
+    "moondream",
+    "minicpm-v",
+    "gpt-4o",
+];
+
+/// Kind of model-list endpoint.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiscoveryKind {
+    /// OpenAI-compatible `/v1/models`.
+    OpenAi,
+    /// Ollama `/api/tags` plus `/api/show` per model.
+    Ollama,
+    /// OpenRouter model metadata, including pricing.
+    OpenRouter,
+}
+
+impl DiscoveryKind {
+    /// The kind implied by a provider name: "ollama" and "openrouter" have
+    /// their own endpoints, anything else is OpenAI-compatible.
+    pub fn for_provider(provider: &str) -> Self {
+        match provider {
+            "ollama" => Self::Ollama,
+            "openrouter" => Self::OpenRouter,
+            _ => Self::OpenAi,
+        }
+    }
+
+    /// Well-known server URL, if the kind has one.
+    pub fn default_base_url(&self) -> Option<&'static str> {
+        match self {
+            Self::OpenAi => None,
+            Self::Ollama => Some("http://localhost:11434"),
+            Self::OpenRouter => Some("https://openrouter.ai/api/v1"),
+        }
+    }
+}
+
+/// An endpoint to discover models from.
+#[derive(Debug, Clone)]
+pub struct DiscoverySource {
+    /// Provider name given to the discovered models.
+    pub provider: String,
+    pub kind: DiscoveryKind,
+    /// Server URL: the OpenAI-style API base (".../v1") for OpenAI-compatible
+    /// servers and OpenRouter, the server root for Ollama.
+    pub base_url: String,
+    /// Sent as a bearer token when set.
+    pub api_key: Option<String>,
+    pub headers: HashMap<String, String>,
+}
+
+/// List the models `source` serves.
+pub async fn discover_models(source: &DiscoverySource) -> Result<Vec<Model>, String> {
+    let base = source.base_url.trim_end_matches('/');
+    match source.kind {
+        DiscoveryKind::OpenAi => {
+            let url = if base.ends_with("/v1") {
+                format!("{base}/models")
+            } else {
+                format!("{base}/v1/models")
+            };
+            Ok(parse_openai_models(source, &get_json(source, &url).await?))
+        }
+        DiscoveryKind::OpenRouter => Ok(parse_openrouter_models(
+            source,
+            &get_json(source, &format!("{base}/models")).await?,
+        )),
+        DiscoveryKind::Ollama => {
+            let tags = get_json(source, &format!("{base}/api/tags")).await?;
+            let names = parse_ollama_tags(&tags);
+            let show_url = format!("{base}/api/show");
+            let shows = join_all(
+                names
+                    .iter()
+                    .map(|name| post_json(source, &show_url, json!({ "model": name }))),
+            )
+            .await;
+            Ok(names
+                .iter()
+                .zip(shows)
+                .filter_map(|(name, show)| {
+                    // A model whose details cannot be read is still listed,
+                    // with inferred capabilities.
+                    parse_ollama_model(source, name, show.as_ref().ok())
+                })
+                .collect())
+        }
+    }
+}
+
+async fn get_json(source: &DiscoverySource, url: &str) -> Result<Value, String> {
+    let client = http_clients().client(&source.provider, url);
+    send(source, url, client.get(url)).await
+}
+
+async fn post_json(source: &DiscoverySource, url: &str, body: Value) -> Result<Value, String> {
+    let client = http_clients().client(&source.provider, url);
+    send(source, url, client.post(url).json(&body)).await
+}
+
+async fn send(
+    source: &DiscoverySource,
+    url: &str,
+    mut request: reqwest::RequestBuilder,
+) -> Result<Value, String> {
+    request = request
+        .timeout(REQUEST_TIMEOUT)
+        .header("Accept", "application/json");
+    if let Some(api_key) = &source.api_key {
+        request = request.bearer_auth(api_key);
+    }
+    for (name, value) in &source.headers {
+        request = request.header(name, value);
+    }
+    let response = request
+        .send()
+        .await
+        .map_err(|e| format!("{url}: {}", describe_error(&e)))?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("{url}: HTTP {status}: {}", body.trim()));
+    }
+    response
+        .json()
+        .await
+        .map_err(|e| format!("{url}: invalid response: {e}"))
+}
+
+fn contains_any(id: &str, markers: &[&str]) -> bool {
+    let id = id.to_lowercase();
+    markers.iter().any(|marker| id.contains(marker))
+}
+
+fn first_u64(value: &Value, pointers: &[&str]) -> Option<u64> {
+    pointers
+        .iter()
+        .find_map(|pointer| value.pointer(pointer)?.as_u64())
+        .filter(|n| *n > 0)
+}
+
+/// OpenRouter prices are USD per token, as strings; models use USD per
+/// million tokens.
+fn price(value: &Value, field: &str) -> f64 {
+    value
+        .get(field)
+        .and_then(|v| match v {
+            Value::String(s) => s.parse::<f64>().ok(),
+            other => other.as_f64(),
+        })
+        .filter(|p| *p > 0.0)
+        .map(|p| p * 1_000_000.0)
+        .unwrap_or(0.0)
+}
+
+fn model(
+    source: &DiscoverySource,
+    base_url: String,
+    id: &str,
+    name: Option<&str>,
+    context_window: Option<u64>,
+    max_tokens: Option<u64>,
+) -> Model {
+    let context_window = context_window.unwrap_or(DEFAULT_CONTEXT_WINDOW);
+    let mut input = vec!["text".to_string()];
+    if contains_any(id, VISION_MARKERS) {
+        input.push("image".to_string());
+    }
+    Model {
+        id: id.to_string(),
+        name: name.unwrap_or(id).to_string(),
+        api: "openai-completions".to_string(),
+        provider: source.provider.clone(),
+        base_url,
+        reasoning: contains_any(id, REASONING_MARKERS),
+        input,
+        cost: ModelCost::default(),
+        context_window,
+        max_tokens: max_tokens.unwrap_or(DEFAULT_MAX_TOKENS.min(context_window)),
+        headers: None,
+        compat: None,
+    }
+}
+
+/// Models from an OpenAI-style `{"data": [{"id": ...}]}` listing. Context
+/// windows are read from the fields llama.cpp, vLLM and LM Studio add.
+pub fn parse_openai_models(source: &DiscoverySource, listing: &Value) -> Vec<Model> {
+    let Some(entries) = listing.get("data").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let id = entry.get("id")?.as_str()?;
+            if contains_any(id, NON_CHAT_MARKERS) {
+                return None;
+            }
+            let context_window = first_u64(
+                entry,
+                &[
+                    "/context_length",
+                    "/context_window",
+                    "/max_context_length",
+                    "/max_model_len",
+                    "/meta/n_ctx_train",
+                ],
+            );
+            Some(model(
+                source,
+                source.base_url.clone(),
+                id,
+                entry.get("name").and_then(Value::as_str),
+                context_window,
+                None,
+            ))
+        })
+        .collect()
+}
+
+/// Models from OpenRouter's listing, with modalities, reasoning support and
+/// pricing taken from the metadata.
+pub fn parse_openrouter_models(source: &DiscoverySource, listing: &Value) -> Vec<Model> {
+    let Some(entries) = listing.get("data").and_then(Value::as_array) else {
+        return Vec::new();
+    };
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let id = entry.get("id")?.as_str()?;
+            let mut model = model(
+                source,
+                source.base_url.clone(),
+                id,
+                entry.get("name").and_then(Value::as_str),
+                first_u64(entry, &["/context_length", "/top_provider/context_length"]),
+                first_u64(entry, &["/top_provider/max_completion_tokens"]),
+            );
+            let has = |pointer: &str, item: &str| {
+                entry
+                    .pointer(pointer)
+                    .and_then(Value::as_array)
+                    .is_some_and(|items| items.iter().any(|v| v.as_str() == Some(item)))
+            };
+            if entry.pointer("/architecture/input_modalities").is_some() {
+                model.input = vec!["text".to_string()];
+                if has("/architecture/input_modalities", "image") {
+                    model.input.push("image".to_string());
+                }
+            }
+            if entry.get("supported_parameters").is_some() {
+                model.reasoning = has("/supported_parameters", "reasoning");
+            }
+            if let Some(pricing) = entry.get("pricing") {
+                model.cost = ModelCost {
+                    input: price(pricing, "prompt"),
+                    output: price(pricing, "completion"),
+                    cache_read: price(pricing, "input_cache_read"),
+                    cache_write: price(pricing, "input_cache_write"),
+                };
+            }
+            Some(model)
+        })
+        .collect()
+}
+
+/// Model names from Ollama's `/api/tags`.
+pub fn parse_ollama_tags(tags: &Value) -> Vec<String> {
+    tags.get("models")
+        .and_then(Value::as_array)
+        .into_iter()
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .get("name")
+                .or_else(|| entry.get("model"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+        .collect()
+}
+
+/// A model from Ollama's `/api/show` details, served through Ollama's
+/// OpenAI-compatible API. Embedding-only models are skipped.
+pub fn parse_ollama_model(
+    source: &DiscoverySource,
+    name: &str,
+    show: Option<&Value>,
+) -> Option<Model> {
+    let base_url = format!("{}/v1", source.base_url.trim_end_matches('/'));
+    let Some(show) = show else {
+        return (!contains_any(name, NON_CHAT_MARKERS))
+            .then(|| model(source, base_url, name, None, None, None));
+    };
+
+    let capabilities: Option<Vec<&str>> = show
+        .get("capabilities")
+        .and_then(Value::as_array)
+        .map(|items| items.iter().filter_map(Value::as_str).collect());
+    if let Some(capabilities) = &capabilities
+        && !capabilities.contains(&"completion")
+    {
+        return None;
+    }
+    let context_window = show
+        .get("model_info")
+        .and_then(Value::as_object)
+        .and_then(|info| {
+            info.iter()
+                .find(|(key, _)| key.ends_with(".context_length"))
+                .and_then(|(_, value)| value.as_u64())
+        });
+
+    let mut model = model(source, base_url, name, None, context_window, None);
+    if let Some(capabilities) = capabilities {
+        model.input = vec!["text".to_string()];
+        if capabilities.contains(&"vision") {
+            model.input.push("image".to_string());
+        }
+        model.reasoning = capabilities.contains(&"thinking");
+    }
+    Some(model)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn source(kind: DiscoveryKind, base_url: &str) -> DiscoverySource {
+        DiscoverySource {
+            provider: "local".to_string(),
+            kind,
+            base_url: base_url.to_string(),
+            api_key: None,
+            headers: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn test_parse_openai_models() {
+        let source = source(DiscoveryKind::OpenAi, "http://localhost:8080/v1");
+        let listing = json!({
+            "data": [
+                { "id": "qwen2.5-vl-7b", "meta": { "n_ctx_train": 32768 } },
+                { "id": "deepseek-r1-distill", "max_model_len": 65536 },
+                { "id": "nomic-embed-text" }
+            ]
+        });
+        let models = parse_openai_models(&source, &listing);
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].context_window, 32768);
+        assert_eq!(models[0].input, vec!["text", "image"]);
+        assert!(!models[0].reasoning);
+        assert_eq!(models[1].context_window, 65536);
+        assert!(models[1].reasoning);
+        assert_eq!(models[1].base_url, "http://localhost:8080/v1");
+        assert_eq!(models[1].provider, "local");
+    }
+
+    #[test]
+    fn test_parse_openrouter_models() {
+        let source = source(DiscoveryKind::OpenRouter, "https://openrouter.ai/api/v1");
+        let listing = json!({
+            "data": [{
+                "id": "acme/thinker",
+                "name": "Acme: Thinker",
+                "context_length": 200000,
+                "architecture": { "input_modalities": ["text", "image"] },
+                "supported_parameters": ["tools", "reasoning"],
+                "pricing": { "prompt": "0.000003", "completion": "0.000015", "input_cache_read": "0" },
+                "top_provider": { "max_completion_tokens": 64000 }
+            }]
+        });
+        let models = parse_openrouter_models(&source, &listing);
+        let model = &models[0];
+        assert_eq!(model.name, "Acme: Thinker");
+        assert_eq!(model.context_window, 200000);
+        assert_eq!(model.max_tokens, 64000);
+        assert_eq!(model.input, vec!["text", "image"]);
+        assert!(model.reasoning);
+        assert!((model.cost.input - 3.0).abs() < 1e-9);
+        assert!((model.cost.output - 15.0).abs() < 1e-9);
+        assert_eq!(model.cost.cache_read, 0.0);
+    }
+
+    #[test]
+    fn test_parse_ollama_model() {
+        let source = source(DiscoveryKind::Ollama, "http://localhost:11434/");
+        let tags = json!({ "models": [{ "name": "llama3.2:latest" }, { "name": "bge-m3" }] });
+        assert_eq!(parse_ollama_tags(&tags), vec!["llama3.2:latest", "bge-m3"]);
+
+        let show = json!({
+            "capabilities": ["completion", "vision", "thinking"],
+            "model_info": { "llama.context_length": 131072 }
+        });
+        let model = parse_ollama_model(&source, "llama3.2:latest", Some(&show)).unwrap();
+        assert_eq!(model.base_url, "http://localhost:11434/v1");
+        assert_eq!(model.context_window, 131072);
+        assert_eq!(model.input, vec!["text", "image"]);
+        assert!(model.reasoning);
+
+        let embedding = json!({ "capabilities": ["embedding"] });
+        assert!(parse_ollama_model(&source, "bge-m3", Some(&embedding)).is_none());
+        let fallback = parse_ollama_model(&source, "qwen3:8b", None).unwrap();
+        assert_eq!(fallback.context_window, DEFAULT_CONTEXT_WINDOW);
+        assert!(fallback.reasoning);
+    }
+}
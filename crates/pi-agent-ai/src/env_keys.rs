@@ -13,14 +13,11 @@ pub fn get_env_api_key(provider: &str) -> Option<String> {
             .or_else(|| env::var("ANTHROPIC_API_KEY").ok()),
 
         "google-vertex" => {
-            let has_credentials = env::var("GOOGLE_APPLICATION_CREDENTIALS").is_ok()
-                || std::path::Path::new(&format!(
-                    "{}/.config/gcloud/application_default_credentials.json",
-                    env::var("HOME").unwrap_or_default()
-                ))
-                .exists();
-            let has_project =
-                env::var("GOOGLE_CLOUD_PROJECT").is_ok() || env::var("GCLOUD_PROJECT").is_ok();
+            let auth = crate::google_auth::google_auth();
+            let has_credentials = auth.has_credentials();
+            let has_project = env::var("GOOGLE_CLOUD_PROJECT").is_ok()
+                || env::var("GCLOUD_PROJECT").is_ok()
+                || (has_credentials && auth.project_id().is_some());
             let has_location = env::var("GOOGLE_CLOUD_LOCATION").is_ok();
 
             if has_credentials && has_project && has_location {
@@ -31,13 +28,8 @@ pub fn get_env_api_key(provider: &str) -> Option<String> {
         }
 
         "amazon-bedrock" => {
-            if env::var("AWS_PROFILE").is_ok()
-                || (env::var("AWS_ACCESS_KEY_ID").is_ok()
-                    && env::var("AWS_SECRET_ACCESS_KEY").is_ok())
-                || env::var("AWS_BEARER_TOKEN_BEDROCK").is_ok()
-                || env::var("AWS_CONTAINER_CREDENTIALS_RELATIVE_URI").is_ok()
-                || env::var("AWS_CONTAINER_CREDENTIALS_FULL_URI").is_ok()
-                || env::var("AWS_WEB_IDENTITY_TOKEN_FILE").is_ok()
+            if env::var("AWS_BEARER_TOKEN_BEDROCK").is_ok()
+                || crate::aws::aws_credentials().has_source(None)
             {
                 Some("<authenticated>".to_string())
             } else {
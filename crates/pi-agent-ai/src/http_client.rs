@@ -0,0 +1,359 @@
+//! Shared, configurable HTTP clients for providers and OAuth flows.
+//!
+//! Clients are built from [`HttpClientConfig`] (environment, then global
+//! settings, then per-provider settings) and pooled per provider and origin,
+//! so connections are reused across requests.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{OnceLock, RwLock};
+use std::time::Duration;
+
+use pi_agent_core::proxy::ProxyStreamOptions;
+use pi_agent_core::types::SimpleStreamOptions;
+use serde::{Deserialize, Serialize};
+
+/// Default connect timeout.
+pub const DEFAULT_CONNECT_TIMEOUT_MS: u64 = 30_000;
+
+/// Default idle timeout: the longest a response stream may go without data.
+pub const DEFAULT_IDLE_TIMEOUT_MS: u64 = 600_000;
+
+/// HTTP client settings. Unset fields inherit from the next-lower layer.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct HttpClientConfig {
+    /// Proxy URL for all requests (e.g. `http://proxy.corp:3128`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub proxy: Option<String>,
+
+    /// Proxy URL for plain `http://` requests; `proxy` is used when unset.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http_proxy: Option<String>,
+
+    /// Comma-separated hosts that bypass the proxy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub no_proxy: Option<String>,
+
+    /// PEM bundle of additional trusted root certificates.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ca_cert_file: Option<PathBuf>,
+
+    /// PEM client certificate for mutual TLS.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_cert_file: Option<PathBuf>,
+
+    /// PEM (PKCS#8) private key for `client_cert_file`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub client_key_file: Option<PathBuf>,
+
+    /// Connect timeout in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub connect_timeout_ms: Option<u64>,
+
+    /// Maximum time without receiving data, in milliseconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idle_timeout_ms: Option<u64>,
+}
+
+impl HttpClientConfig {
+    /// Settings from `HTTPS_PROXY`/`ALL_PROXY`, `HTTP_PROXY`, `NO_PROXY` and
+    /// `SSL_CERT_FILE`.
+    pub fn from_env() -> Self {
+        let var = |names: &[&str]| {
+            names
+                .iter()
+                .find_map(|name| std::env::var(name).ok().filter(|v| !v.is_empty()))
+        };
+        Self {
+            proxy: var(&["HTTPS_PROXY", "https_proxy", "ALL_PROXY", "all_proxy"]),
+            http_proxy: var(&["HTTP_PROXY", "http_proxy"]),
+            no_proxy: var(&["NO_PROXY", "no_proxy"]),
+            ca_cert_file: var(&["SSL_CERT_FILE"]).map(PathBuf::from),
+            ..Default::default()
+        }
+    }
+
+    /// Layer `overrides` on top of `self`.
+    pub fn merged(&self, overrides: &HttpClientConfig) -> HttpClientConfig {
+        HttpClientConfig {
+            proxy: overrides.proxy.clone().or_else(|| self.proxy.clone()),
+            // A proxy set in a higher layer also replaces a lower-layer
+            // HTTP-only proxy.
+            http_proxy: overrides.http_proxy.clone().or_else(|| {
+                if overrides.proxy.is_some() {
+                    None
+                } else {
+                    self.http_proxy.clone()
+                }
+            }),
+            no_proxy: overrides.no_proxy.clone().or_else(|| self.no_proxy.clone()),
+            ca_cert_file: overrides
+                .ca_cert_file
+                .clone()
+                .or_else(|| self.ca_cert_file.clone()),
+            client_cert_file: overrides
+                .client_cert_file
+                .clone()
+                .or_else(|| self.client_cert_file.clone()),
+            client_key_file: overrides
+                .client_key_file
+                .clone()
+                .or_else(|| self.client_key_file.clone()),
+            connect_timeout_ms: overrides.connect_timeout_ms.or(self.connect_timeout_ms),
+            idle_timeout_ms: overrides.idle_timeout_ms.or(self.idle_timeout_ms),
+        }
+    }
+}
+
+/// Build a client from a fully merged configuration.
+pub fn build_client(config: &HttpClientConfig) -> Result<reqwest::Client, String> {
+    let mut builder = reqwest::Client::builder()
+        .connect_timeout(Duration::from_millis(
+            config
+                .connect_timeout_ms
+                .unwrap_or(DEFAULT_CONNECT_TIMEOUT_MS),
+        ))
+        .read_timeout(Duration::from_millis(
+            config.idle_timeout_ms.unwrap_or(DEFAULT_IDLE_TIMEOUT_MS),
+        ));
+
+    // reqwest uses the first proxy that matches, so the HTTP-only proxy
+    // goes first.
+    let proxies = [
+        (config.http_proxy.as_deref(), true),
+        (config.proxy.as_deref(), false),
+    ];
+    for (proxy_url, http_only) in proxies {
+        let Some(proxy_url) = proxy_url else {
+            continue;
+        };
+        let proxy = if http_only {
+            reqwest::Proxy::http(proxy_url)
+        } else {
+            reqwest::Proxy::all(proxy_url)
+        };
+        let proxy = proxy
+            .map_err(|e| format!("Invalid proxy URL {proxy_url}: {e}"))?
+            .no_proxy(
+                config
+                    .no_proxy
+                    .as_deref()
+                    .and_then(reqwest::NoProxy::from_string),
+            );
+        builder = builder.proxy(proxy);
+    }
+
+    if let Some(path) = &config.ca_cert_file {
+        let pem = std::fs::read(path)
+            .map_err(|e| format!("Failed to read CA bundle {}: {e}", path.display()))?;
+        let certs = reqwest::Certificate::from_pem_bundle(&pem)
+            .map_err(|e| format!("Invalid CA bundle {}: {e}", path.display()))?;
+        for cert in certs {
+            builder = builder.add_root_certificate(cert);
+        }
+    }
+
+    match (&config.client_cert_file, &config.client_key_file) {
+        (Some(cert_path), Some(key_path)) => {
+            let cert = std::fs::read(cert_path).map_err(|e| {
+                format!(
+                    "Failed to read client certificate {}: {e}",
+                    cert_path.display()
+                )
+            })?;
+            let key = std::fs::read(key_path)
+                .map_err(|e| format!("Failed to read client key {}: {e}", key_path.display()))?;
+            let identity = reqwest::Identity::from_pkcs8_pem(&cert, &key)
+                .map_err(|e| format!("Invalid client certificate: {e}"))?;
+            builder = builder.identity(identity);
+        }
+        (None, None) => {}
+        _ => {
+            return Err("clientCertFile and clientKeyFile must be set together".to_string());
+        }
+    }
+
+    builder
+        .build()
+        .map_err(|e| format!("Failed to build HTTP client: {e}"))
+}
+
+/// Pool key for a base URL: its scheme, host and port.
+fn origin(base_url: &str) -> String {
+    match reqwest::Url::parse(base_url) {
+        Ok(url) => format!(
+            "{}://{}:{}",
+            url.scheme(),
+            url.host_str().unwrap_or_default(),
+            url.port_or_known_default().unwrap_or_default()
+        ),
+        Err(_) => base_url.to_string(),
+    }
+}
+
+#[derive(Default)]
+struct FactoryState {
+    defaults: HttpClientConfig,
+    providers: HashMap<String, HttpClientConfig>,
+    clients: HashMap<String, reqwest::Client>,
+}
+
+/// Builds and caches HTTP clients per provider and origin.
+#[derive(Default)]
+pub struct HttpClientFactory {
+    state: RwLock<FactoryState>,
+}
+
+impl HttpClientFactory {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Replace the configuration and drop cached clients. Every resulting
+    /// configuration is validated by building a client from it.
+    pub fn configure(
+        &self,
+        defaults: HttpClientConfig,
+        providers: HashMap<String, HttpClientConfig>,
+    ) -> Result<(), String> {
+        let env = HttpClientConfig::from_env();
+        build_client(&env.merged(&defaults))?;
+        for (provider, config) in &providers {
+            build_client(&env.merged(&defaults).merged(config))
+                .map_err(|e| format!("{provider}: {e}"))?;
+        }
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        *state = FactoryState {
+            defaults,
+            providers,
+            clients: HashMap::new(),
+        };
+        Ok(())
+    }
+
+    /// Effective configuration for a provider.
+    pub fn config_for(&self, provider: &str) -> HttpClientConfig {
+        let state = self.state.read().unwrap_or_else(|e| e.into_inner());
+        let config = HttpClientConfig::from_env().merged(&state.defaults);
+        match state.providers.get(provider) {
+            Some(overrides) => config.merged(overrides),
+            None => config,
+        }
+    }
+
+    /// Pooled client for requests from `provider` to `base_url`.
+    pub fn client(&self, provider: &str, base_url: &str) -> reqwest::Client {
+        let key = format!("{provider} {}", origin(base_url));
+        if let Some(client) = self
+            .state
+            .read()
+            .unwrap_or_else(|e| e.into_inner())
+            .clients
+            .get(&key)
+        {
+            return client.clone();
+        }
+
+        let client = build_client(&self.config_for(provider)).unwrap_or_else(|e| {
+            tracing::warn!("{e}; using a default HTTP client for {provider}");
+            reqwest::Client::new()
+        });
+        let mut state = self.state.write().unwrap_or_else(|e| e.into_inner());
+        state.clients.entry(key).or_insert(client).clone()
+    }
+}
+
+/// The process-wide client factory.
+pub fn http_clients() -> &'static HttpClientFactory {
+    static FACTORY: OnceLock<HttpClientFactory> = OnceLock::new();
+    FACTORY.get_or_init(HttpClientFactory::new)
+}
+
+/// Options for [`pi_agent_core::proxy::stream_proxy`] that send through the
+/// shared client for `proxy_url`.
+pub fn proxy_stream_options(
+    base: SimpleStreamOptions,
+    auth_token: String,
+    proxy_url: String,
+) -> ProxyStreamOptions {
+    ProxyStreamOptions {
+        client: Some(http_clients().client("proxy", &proxy_url)),
+        base,
+        auth_token,
+        proxy_url,
+    }
+}
+
+/// Describe a transport error so that timeouts and connection failures are
+/// recognized as retryable by callers.
+pub fn describe_error(error: &reqwest::Error) -> String {
+    if error.is_timeout() {
+        format!("{error} (request timeout)")
+    } else if error.is_connect() {
+        format!("{error} (network error)")
+    } else {
+        error.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merged_prefers_overrides() {
+        let base = HttpClientConfig {
+            proxy: Some("http://env-proxy:3128".to_string()),
+            http_proxy: Some("http://env-http-proxy:3128".to_string()),
+            connect_timeout_ms: Some(1000),
+            ..Default::default()
+        };
+        let overrides = HttpClientConfig {
+            proxy: Some("http://provider-proxy:8080".to_string()),
+            idle_timeout_ms: Some(5000),
+            ..Default::default()
+        };
+        let merged = base.merged(&overrides);
+        assert_eq!(merged.proxy.as_deref(), Some("http://provider-proxy:8080"));
+        assert_eq!(merged.http_proxy, None);
+        assert_eq!(merged.connect_timeout_ms, Some(1000));
+        assert_eq!(merged.idle_timeout_ms, Some(5000));
+    }
+
+    #[test]
+    fn test_origin_ignores_path() {
+        assert_eq!(
+            origin("https://api.anthropic.com/v1"),
+            "https://api.anthropic.com:443"
+        );
+        assert_eq!(
+            origin("http://localhost:11434/v1"),
+            "http://localhost:11434"
+        );
+    }
+
+    #[test]
+    fn test_build_client_validates_config() {
+        assert!(build_client(&HttpClientConfig::default()).is_ok());
+        let missing_key = HttpClientConfig {
+            client_cert_file: Some(PathBuf::from("/nonexistent/cert.pem")),
+            ..Default::default()
+        };
+        assert!(build_client(&missing_key).unwrap_err().contains("together"));
+        let missing_ca = HttpClientConfig {
+            ca_cert_file: Some(PathBuf::from("/nonexistent/ca.pem")),
+            ..Default::default()
+        };
+        assert!(build_client(&missing_ca).is_err());
+    }
+
+    #[test]
+    fn test_factory_pools_clients_per_origin() {
+        let factory = HttpClientFactory::new();
+        factory.client("openai", "https://api.openai.com/v1");
+        factory.client("openai", "https://api.openai.com/v1/responses");
+        factory.client("openai", "https://example.com");
+        assert_eq!(factory.state.read().unwrap().clients.len(), 2);
+    }
+}
@@ -1,11 +1,20 @@
+pub mod aws;
+pub mod discovery;
 pub mod env_keys;
+pub mod google_auth;
 pub mod header_utils;
+pub mod http_client;
 pub mod models;
 pub mod models_generated;
 pub mod oauth;
 pub mod providers;
+pub mod rate_limit;
 pub mod register;
 pub mod registry;
+pub mod response_format;
 pub mod simple_options;
 pub mod sse;
 pub mod stream;
+#[cfg(test)]
+mod test_support;
+pub mod token_counter;
@@ -0,0 +1,177 @@
+//! OAuth 2.0 device authorization grant (RFC 8628) for headless logins.
+
+use std::time::Duration;
+
+use serde::{Deserialize, Serialize};
+
+use crate::oauth::types::TokenResponse;
+
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+
+/// Response to a device authorization request.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DeviceCodeResponse {
+    pub device_code: String,
+    /// Code the user enters at `verification_uri`.
+    pub user_code: String,
+    /// GitHub and most providers use `verification_uri`; Google uses `verification_url`.
+    #[serde(alias = "verification_url")]
+    pub verification_uri: String,
+    /// Seconds until `device_code` expires.
+    pub expires_in: u64,
+    /// Minimum polling interval in seconds.
+    #[serde(default = "default_interval")]
+    pub interval: u64,
+}
+
+fn default_interval() -> u64 {
+    5
+}
+
+/// Error body returned while polling.
+#[derive(Debug, Deserialize)]
+struct DeviceTokenError {
+    error: String,
+    #[serde(default)]
+    error_description: Option<String>,
+}
+
+/// Request a device and user code.
+pub async fn request_device_code(
+    device_code_endpoint: &str,
+    client_id: &str,
+    scope: &str,
+) -> Result<DeviceCodeResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let client = crate::http_client::http_clients().client("oauth", device_code_endpoint);
+    let response = client
+        .post(device_code_endpoint)
+        .header("Accept", "application/json")
+        .form(&[("client_id", client_id), ("scope", scope)])
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Device code request failed (HTTP {status}): {body}").into());
+    }
+    Ok(response.json().await?)
+}
+
+/// Poll the token endpoint until the user approves, denies, or the code expires.
+///
+/// Honors `slow_down` by increasing the interval by five seconds, as the
+/// RFC requires.
+pub async fn poll_device_token(
+    token_endpoint: &str,
+    client_id: &str,
+    device: &DeviceCodeResponse,
+) -> Result<TokenResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let client = crate::http_client::http_clients().client("oauth", token_endpoint);
+    let deadline = tokio::time::Instant::now() + Duration::from_secs(device.expires_in);
+    let mut interval = Duration::from_secs(device.interval.max(1));
+
+    loop {
+        tokio::time::sleep(interval).await;
+        if tokio::time::Instant::now() >= deadline {
+            return Err("Device code expired before authorization completed".into());
+        }
+
+        let response = client
+            .post(token_endpoint)
+            .header("Accept", "application/json")
+            .form(&[
+                ("client_id", client_id),
+                ("device_code", device.device_code.as_str()),
+                ("grant_type", DEVICE_CODE_GRANT),
+            ])
+            .send()
+            .await?;
+        let status = response.status();
+        let body = response.text().await.unwrap_or_default();
+
+        // GitHub reports pending states with HTTP 200, others with 400.
+        if let Ok(token) = serde_json::from_str::<TokenResponse>(&body) {
+            return Ok(token);
+        }
+        match serde_json::from_str::<DeviceTokenError>(&body) {
+            Ok(error) => match error.error.as_str() {
+                "authorization_pending" => {}
+                "slow_down" => interval += Duration::from_secs(5),
+                _ => {
+                    let detail = error.error_description.unwrap_or(error.error);
+                    return Err(format!("Device authorization failed: {detail}").into());
+                }
+            },
+            Err(_) => {
+                return Err(format!("Device token request failed (HTTP {status}): {body}").into());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Token endpoint answering each request with the next body.
+    async fn stub_endpoint(bodies: Vec<&'static str>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            for body in bodies {
+                let (mut socket, _) = listener.accept().await.unwrap();
+                let mut buf = [0u8; 4096];
+                let mut request = Vec::new();
+                while !String::from_utf8_lossy(&request).contains("grant_type") {
+                    let n = socket.read(&mut buf).await.unwrap();
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                }
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                socket.write_all(response.as_bytes()).await.unwrap();
+            }
+        });
+        endpoint
+    }
+
+    fn device() -> DeviceCodeResponse {
+        serde_json::from_str(
+            r#"{"device_code":"dc","user_code":"ABCD-1234","verification_url":"https://example.com/device","expires_in":60,"interval":0}"#,
+        )
+        .unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_poll_until_authorized() {
+        let endpoint = stub_endpoint(vec![
+            r#"{"error":"authorization_pending"}"#,
+            r#"{"access_token":"gho_token","token_type":"bearer"}"#,
+        ])
+        .await;
+        let device = device();
+        assert_eq!(device.verification_uri, "https://example.com/device");
+        let token = poll_device_token(&endpoint, "client", &device)
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "gho_token");
+    }
+
+    #[tokio::test]
+    async fn test_poll_access_denied() {
+        let endpoint = stub_endpoint(vec![
+            r#"{"error":"access_denied","error_description":"The user denied access"}"#,
+        ])
+        .await;
+        let error = poll_device_token(&endpoint, "client", &device())
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("denied"));
+    }
+}
@@ -1,3 +1,4 @@
+use crate::oauth::device_code::{DeviceCodeResponse, poll_device_token, request_device_code};
 use crate::oauth::pkce::{OAuthFlowStart, build_authorization_url, exchange_authorization_code};
 use crate::oauth::types::{OAuthCredentials, OAuthProvider};
 
@@ -7,6 +8,10 @@ pub struct GithubCopilotOAuthConfig {
     pub auth_url: String,
     pub token_url: String,
     pub redirect_uri: String,
+    /// Device authorization endpoint for headless logins.
+    pub device_code_url: String,
+    /// Exchanges a GitHub OAuth token for a short-lived Copilot API token.
+    pub copilot_token_url: String,
 }
 
 impl GithubCopilotOAuthConfig {
@@ -25,6 +30,8 @@ impl Default for GithubCopilotOAuthConfig {
             auth_url: "https://github.com/login/oauth/authorize".to_string(),
             token_url: "https://github.com/login/oauth/access_token".to_string(),
             redirect_uri: "http://localhost:8787/oauth/callback".to_string(),
+            device_code_url: "https://github.com/login/device/code".to_string(),
+            copilot_token_url: "https://api.github.com/copilot_internal/v2/token".to_string(),
         }
     }
 }
@@ -64,3 +71,62 @@ pub async fn exchange_github_copilot_code(
         OAuthProvider::GithubCopilot,
     ))
 }
+
+/// Start the GitHub device flow; show `user_code` and `verification_uri` to the user.
+pub async fn start_github_copilot_device_flow(
+    config: &GithubCopilotOAuthConfig,
+) -> Result<DeviceCodeResponse, Box<dyn std::error::Error + Send + Sync>> {
+    request_device_code(&config.device_code_url, &config.client_id, "read:user").await
+}
+
+/// Wait for the user to approve the device code, then exchange the GitHub
+/// token for Copilot credentials.
+pub async fn complete_github_copilot_device_flow(
+    config: &GithubCopilotOAuthConfig,
+    device: &DeviceCodeResponse,
+) -> Result<OAuthCredentials, Box<dyn std::error::Error + Send + Sync>> {
+    let github = poll_device_token(&config.token_url, &config.client_id, device).await?;
+    exchange_copilot_token(config, &github.access_token).await
+}
+
+/// Copilot token endpoint response.
+#[derive(Debug, serde::Deserialize)]
+struct CopilotTokenResponse {
+    token: String,
+    /// Unix seconds.
+    expires_at: i64,
+}
+
+/// Exchange a GitHub OAuth token for a Copilot API token.
+///
+/// The GitHub token is kept as the refresh token: Copilot tokens last about
+/// half an hour and are renewed by repeating this exchange.
+pub async fn exchange_copilot_token(
+    config: &GithubCopilotOAuthConfig,
+    github_token: &str,
+) -> Result<OAuthCredentials, Box<dyn std::error::Error + Send + Sync>> {
+    let client = crate::http_client::http_clients().client("oauth", &config.copilot_token_url);
+    let response = client
+        .get(&config.copilot_token_url)
+        .header("Authorization", format!("token {github_token}"))
+        .header("Accept", "application/json")
+        .header("User-Agent", "pi-agent")
+        .send()
+        .await?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(format!("Copilot token exchange failed (HTTP {status}): {body}").into());
+    }
+    let token: CopilotTokenResponse = response.json().await?;
+
+    Ok(OAuthCredentials {
+        access_token: token.token,
+        refresh_token: Some(github_token.to_string()),
+        expires_at: Some(token.expires_at),
+        token_type: "Bearer".to_string(),
+        scopes: Vec::new(),
+        provider: OAuthProvider::GithubCopilot,
+    })
+}
@@ -1,4 +1,6 @@
-use crate::oauth::pkce::{OAuthFlowStart, build_authorization_url, exchange_authorization_code};
+use crate::oauth::pkce::{
+    OAuthFlowStart, build_authorization_url, exchange_authorization_code, refresh_access_token,
+};
 use crate::oauth::types::{OAuthCredentials, OAuthProvider};
 
 /// Google Antigravity OAuth configuration.
@@ -64,3 +66,24 @@ pub async fn exchange_google_antigravity_code(
         OAuthProvider::GoogleAntigravity,
     ))
 }
+
+/// Refresh Google Antigravity OAuth credentials with a stored refresh token.
+pub async fn refresh_google_antigravity_token(
+    config: &GoogleAntigravityOAuthConfig,
+    refresh_token: &str,
+) -> Result<OAuthCredentials, Box<dyn std::error::Error + Send + Sync>> {
+    let token = refresh_access_token(
+        &config.token_url,
+        &config.client_id,
+        refresh_token,
+        &[],
+        &[],
+    )
+    .await?;
+
+    Ok(OAuthCredentials::from_refresh_response(
+        token,
+        OAuthProvider::GoogleAntigravity,
+        refresh_token,
+    ))
+}
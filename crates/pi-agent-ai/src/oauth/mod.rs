@@ -1,9 +1,12 @@
 pub mod anthropic;
+pub mod device_code;
 pub mod github_copilot;
 pub mod google_antigravity;
 pub mod google_gemini_cli;
 pub mod openai_codex;
 pub mod pkce;
+pub mod refresh;
 pub mod types;
 
+pub use refresh::refresh_oauth_credentials;
 pub use types::*;
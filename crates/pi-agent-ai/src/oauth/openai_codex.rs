@@ -1,4 +1,6 @@
-use crate::oauth::pkce::{OAuthFlowStart, build_authorization_url, exchange_authorization_code};
+use crate::oauth::pkce::{
+    OAuthFlowStart, build_authorization_url, exchange_authorization_code, refresh_access_token,
+};
 use crate::oauth::types::{OAuthCredentials, OAuthProvider};
 
 /// OpenAI Codex OAuth configuration.
@@ -81,3 +83,24 @@ pub async fn exchange_openai_codex_code(
         OAuthProvider::OpenaiCodex,
     ))
 }
+
+/// Refresh OpenAI Codex OAuth credentials with a stored refresh token.
+pub async fn refresh_openai_codex_token(
+    config: &OpenAICodexOAuthConfig,
+    refresh_token: &str,
+) -> Result<OAuthCredentials, Box<dyn std::error::Error + Send + Sync>> {
+    let token = refresh_access_token(
+        &config.token_url,
+        &config.client_id,
+        refresh_token,
+        &[],
+        &[],
+    )
+    .await?;
+
+    Ok(OAuthCredentials::from_refresh_response(
+        token,
+        OAuthProvider::OpenaiCodex,
+        refresh_token,
+    ))
+}
@@ -123,8 +123,6 @@ pub async fn exchange_authorization_code(
     extra_form: &[(&str, &str)],
     extra_headers: &[(&str, &str)],
 ) -> Result<super::types::TokenResponse, Box<dyn std::error::Error + Send + Sync>> {
-    let client = reqwest::Client::new();
-
     let mut form_data: Vec<(&str, &str)> = vec![
         ("grant_type", "authorization_code"),
         ("code", code),
@@ -134,7 +132,39 @@ pub async fn exchange_authorization_code(
     ];
     form_data.extend_from_slice(extra_form);
 
-    let mut builder = client.post(token_endpoint).form(&form_data);
+    post_token_request(token_endpoint, &form_data, extra_headers, "token exchange").await
+}
+
+/// Exchange a refresh token for a new access token (generic for all providers).
+///
+/// Providers may omit `refresh_token` from the response, in which case the
+/// caller should keep using the one it already has.
+pub async fn refresh_access_token(
+    token_endpoint: &str,
+    client_id: &str,
+    refresh_token: &str,
+    extra_form: &[(&str, &str)],
+    extra_headers: &[(&str, &str)],
+) -> Result<super::types::TokenResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let mut form_data: Vec<(&str, &str)> = vec![
+        ("grant_type", "refresh_token"),
+        ("refresh_token", refresh_token),
+        ("client_id", client_id),
+    ];
+    form_data.extend_from_slice(extra_form);
+
+    post_token_request(token_endpoint, &form_data, extra_headers, "token refresh").await
+}
+
+async fn post_token_request(
+    token_endpoint: &str,
+    form_data: &[(&str, &str)],
+    extra_headers: &[(&str, &str)],
+    operation: &str,
+) -> Result<super::types::TokenResponse, Box<dyn std::error::Error + Send + Sync>> {
+    let client = crate::http_client::http_clients().client("oauth", token_endpoint);
+
+    let mut builder = client.post(token_endpoint).form(form_data);
     for (key, value) in extra_headers {
         builder = builder.header(*key, *value);
     }
@@ -144,7 +174,7 @@ pub async fn exchange_authorization_code(
     let status = response.status();
     if !status.is_success() {
         let body = response.text().await.unwrap_or_default();
-        return Err(format!("OAuth token exchange failed (HTTP {status}): {body}").into());
+        return Err(format!("OAuth {operation} failed (HTTP {status}): {body}").into());
     }
 
     let token: super::types::TokenResponse = response.json().await?;
@@ -236,4 +266,40 @@ mod tests {
         assert!(flow.auth_url.contains("access_type=offline"));
         assert!(flow.auth_url.contains("prompt=consent"));
     }
+
+    #[tokio::test]
+    async fn test_refresh_access_token_posts_refresh_grant() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/token", listener.local_addr().unwrap());
+        let server = tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            while !String::from_utf8_lossy(&request).contains("refresh_token=rt-1") {
+                let n = socket.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break;
+                }
+                request.extend_from_slice(&buf[..n]);
+            }
+            let body = r#"{"access_token":"at-2","expires_in":3600}"#;
+            let response = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            );
+            socket.write_all(response.as_bytes()).await.unwrap();
+            String::from_utf8_lossy(&request).into_owned()
+        });
+
+        let token = refresh_access_token(&endpoint, "client", "rt-1", &[], &[])
+            .await
+            .unwrap();
+        assert_eq!(token.access_token, "at-2");
+        assert!(token.refresh_token.is_none());
+        let request = server.await.unwrap();
+        assert!(request.contains("grant_type=refresh_token"));
+        assert!(request.contains("client_id=client"));
+    }
 }
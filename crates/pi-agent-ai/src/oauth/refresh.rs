@@ -0,0 +1,49 @@
+//! Provider-agnostic OAuth token refresh.
+
+use crate::oauth::anthropic::{AnthropicOAuthConfig, refresh_anthropic_token};
+use crate::oauth::github_copilot::{GithubCopilotOAuthConfig, exchange_copilot_token};
+use crate::oauth::google_antigravity::{
+    GoogleAntigravityOAuthConfig, refresh_google_antigravity_token,
+};
+use crate::oauth::google_gemini_cli::{
+    GoogleGeminiCliOAuthConfig, refresh_google_gemini_cli_token,
+};
+use crate::oauth::openai_codex::{OpenAICodexOAuthConfig, refresh_openai_codex_token};
+use crate::oauth::types::{OAuthCredentials, OAuthProvider};
+
+/// Refresh credentials for `provider` using the default endpoints.
+///
+/// `client_id` must be the client the refresh token was issued to. For
+/// GitHub Copilot the refresh token is the GitHub OAuth token, which is
+/// exchanged for a new Copilot token.
+pub async fn refresh_oauth_credentials(
+    provider: &OAuthProvider,
+    client_id: &str,
+    refresh_token: &str,
+) -> Result<OAuthCredentials, Box<dyn std::error::Error + Send + Sync>> {
+    match provider {
+        OAuthProvider::Anthropic => {
+            refresh_anthropic_token(&AnthropicOAuthConfig::new(client_id), refresh_token).await
+        }
+        OAuthProvider::OpenaiCodex => {
+            refresh_openai_codex_token(&OpenAICodexOAuthConfig::new(client_id), refresh_token).await
+        }
+        OAuthProvider::GoogleGeminiCli => {
+            refresh_google_gemini_cli_token(
+                &GoogleGeminiCliOAuthConfig::new(client_id),
+                refresh_token,
+            )
+            .await
+        }
+        OAuthProvider::GoogleAntigravity => {
+            refresh_google_antigravity_token(
+                &GoogleAntigravityOAuthConfig::new(client_id),
+                refresh_token,
+            )
+            .await
+        }
+        OAuthProvider::GithubCopilot => {
+            exchange_copilot_token(&GithubCopilotOAuthConfig::new(client_id), refresh_token).await
+        }
+    }
+}
@@ -23,6 +23,13 @@ impl OAuthProvider {
         }
     }
 
+    /// Look up a provider by its pi-agent-core provider key.
+    pub fn from_provider_key(key: &str) -> Option<Self> {
+        get_oauth_providers()
+            .into_iter()
+            .find(|provider| provider.provider_key() == key)
+    }
+
     /// Get the corresponding pi-agent-core provider key.
     pub fn provider_key(&self) -> &str {
         match self {
@@ -83,6 +90,20 @@ impl OAuthCredentials {
         }
     }
 
+    /// Create `OAuthCredentials` from a refresh-grant `TokenResponse`, keeping
+    /// `refresh_token` when the provider does not rotate it.
+    pub fn from_refresh_response(
+        token: TokenResponse,
+        provider: OAuthProvider,
+        refresh_token: &str,
+    ) -> Self {
+        let mut credentials = Self::from_token_response(token, provider);
+        if credentials.refresh_token.is_none() {
+            credentials.refresh_token = Some(refresh_token.to_string());
+        }
+        credentials
+    }
+
     /// Check if the credentials are expired.
     pub fn is_expired(&self) -> bool {
         if let Some(expires_at) = self.expires_at {
@@ -215,6 +236,29 @@ mod tests {
         assert!(!creds.is_expired());
     }
 
+    #[test]
+    fn test_from_refresh_response_keeps_refresh_token() {
+        let token = TokenResponse {
+            access_token: "new-access".to_string(),
+            refresh_token: None,
+            expires_in: Some(3600),
+            token_type: "Bearer".to_string(),
+            scope: None,
+        };
+
+        let creds = OAuthCredentials::from_refresh_response(
+            token,
+            OAuthProvider::GoogleGeminiCli,
+            "old-refresh",
+        );
+        assert_eq!(creds.access_token, "new-access");
+        assert_eq!(creds.refresh_token.as_deref(), Some("old-refresh"));
+        assert_eq!(
+            OAuthProvider::from_provider_key("google-gemini-cli"),
+            Some(OAuthProvider::GoogleGeminiCli)
+        );
+    }
+
     #[test]
     fn test_from_token_response_minimal() {
         let token = TokenResponse {
@@ -14,9 +14,14 @@ use pi_agent_core::types::*;
 
 use super::github_copilot_headers::{build_copilot_dynamic_headers, has_copilot_vision_input};
 use crate::env_keys::get_env_api_key;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::calculate_cost;
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
-use crate::simple_options::{adjust_max_tokens_for_thinking, build_base_options};
+use crate::response_format::{forced_tool, json_format};
+use crate::simple_options::{
+    adjust_max_tokens_for_thinking, build_base_options, warn_unsupported_options,
+};
 use crate::sse::SseParser;
 
 // ---------- Claude Code Stealth Mode ----------
@@ -221,6 +226,38 @@ fn convert_content_blocks(content: &[ContentBlock]) -> Value {
     json!(blocks)
 }
 
+// ---------- Citations ----------
+
+/// Map an Anthropic citation object (`char_location`, `page_location`,
+/// `content_block_location`, `web_search_result_location`, ...) to a `Citation`.
+fn parse_citation(value: &Value) -> Citation {
+    let get_str = |key: &str| value.get(key).and_then(|v| v.as_str()).map(String::from);
+    let get_u64 = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|k| value.get(*k).and_then(|v| v.as_u64()))
+    };
+    Citation {
+        url: get_str("url"),
+        title: get_str("document_title").or_else(|| get_str("title")),
+        cited_text: get_str("cited_text"),
+        document_index: get_u64(&["document_index", "search_result_index"]),
+        start_index: get_u64(&["start_char_index", "start_page_number", "start_block_index"]),
+        end_index: get_u64(&["end_char_index", "end_page_number", "end_block_index"]),
+        provider_data: Some(value.clone()),
+    }
+}
+
+/// Replay citations in Anthropic's native shape. Only citations that were
+/// produced by Anthropic (and so carry the raw payload) can be sent back.
+fn convert_citations(citations: Option<&[Citation]>) -> Option<Value> {
+    let citations = citations.filter(|c| !c.is_empty())?;
+    let raw: Vec<Value> = citations
+        .iter()
+        .map(|c| c.provider_data.clone())
+        .collect::<Option<Vec<_>>>()?;
+    Some(json!(raw))
+}
+
 // ---------- Convert messages ----------
 
 fn convert_messages(
@@ -296,12 +333,22 @@ fn convert_messages(
                     match block {
                         ContentBlock::Text(t) => {
                             if !t.text.trim().is_empty() {
-                                blocks.push(json!({
+                                let mut text_block = json!({
                                     "type": "text",
                                     "text": sanitize_surrogates(&t.text)
-                                }));
+                                });
+                                if let Some(citations) = convert_citations(t.citations.as_deref()) {
+                                    text_block["citations"] = citations;
+                                }
+                                blocks.push(text_block);
                             }
                         }
+                        ContentBlock::RedactedThinking(r) => {
+                            blocks.push(json!({
+                                "type": "redacted_thinking",
+                                "data": r.data
+                            }));
+                        }
                         ContentBlock::Thinking(t) => {
                             if t.thinking.trim().is_empty() {
                                 continue;
@@ -436,6 +483,22 @@ fn convert_tools(tools: &[Tool], is_oauth: bool) -> Vec<Value> {
 
 // ---------- Build request params ----------
 
+fn convert_tool_choice(choice: &ToolChoice, is_oauth: bool) -> Value {
+    match choice {
+        ToolChoice::Auto => json!({"type": "auto"}),
+        ToolChoice::None => json!({"type": "none"}),
+        ToolChoice::Required => json!({"type": "any"}),
+        ToolChoice::Tool(name) => {
+            let name = if is_oauth {
+                to_claude_code_name(name)
+            } else {
+                name.clone()
+            };
+            json!({"type": "tool", "name": name})
+        }
+    }
+}
+
 fn build_params(
     model: &Model,
     context: &Context,
@@ -500,6 +563,16 @@ fn build_params(
     if let Some(temp) = options.base.temperature {
         params["temperature"] = json!(temp);
     }
+    if let Some(top_p) = options.base.top_p {
+        params["top_p"] = json!(top_p);
+    }
+    if let Some(top_k) = options.base.top_k {
+        params["top_k"] = json!(top_k);
+    }
+    if let Some(stop) = &options.base.stop_sequences {
+        params["stop_sequences"] = json!(stop);
+    }
+    warn_unsupported_options(model, &options.base, &["topP", "topK", "stopSequences"]);
 
     if let Some(tools) = &context.tools {
         if !tools.is_empty() {
@@ -524,6 +597,41 @@ fn build_params(
 
     if let Some(tc) = &options.tool_choice {
         params["tool_choice"] = tc.clone();
+    } else if let Some(choice) = &options.base.tool_choice
+        && params.get("tools").is_some()
+    {
+        let thinking = params.get("thinking").is_some();
+        if thinking && matches!(choice, ToolChoice::Required | ToolChoice::Tool(_)) {
+            // Extended thinking only allows "auto" and "none".
+            tracing::warn!(
+                "{} does not support forced tool use with thinking enabled; using auto",
+                model.id
+            );
+        } else {
+            params["tool_choice"] = convert_tool_choice(choice, is_oauth);
+        }
+    }
+
+    // Structured output: no native JSON mode, so force a tool whose input
+    // schema is the requested schema.
+    if let Some(tool) = json_format(options.base.response_format.as_ref()).and_then(forced_tool) {
+        let mut tools = params
+            .get("tools")
+            .and_then(|t| t.as_array())
+            .cloned()
+            .unwrap_or_default();
+        tools.push(json!({
+            "name": tool.name,
+            "description": tool.description,
+            "input_schema": tool.parameters
+        }));
+        params["tools"] = json!(tools);
+        params["tool_choice"] = json!({"type": "tool", "name": tool.name});
+        // Forced tool use is rejected when extended thinking is enabled.
+        if let Some(obj) = params.as_object_mut() {
+            obj.remove("thinking");
+            obj.remove("output_config");
+        }
     }
 
     params
@@ -608,6 +716,54 @@ fn build_headers(
     headers
 }
 
+// ---------- Token counting ----------
+
+/// Count the input tokens of `context` with `POST /v1/messages/count_tokens`.
+pub async fn count_tokens_anthropic(
+    model: &Model,
+    context: &Context,
+    api_key: &str,
+) -> Result<u64, String> {
+    let is_oauth = is_oauth_token(api_key);
+    let options = AnthropicOptions {
+        base: StreamOptions::default(),
+        thinking_enabled: false,
+        thinking_budget_tokens: None,
+        effort: None,
+        interleaved_thinking: false,
+        tool_choice: None,
+    };
+    let mut params = build_params(model, context, is_oauth, &options);
+    if let Some(obj) = params.as_object_mut() {
+        obj.remove("stream");
+        obj.remove("max_tokens");
+    }
+
+    let url = format!("{}/v1/messages/count_tokens", model.base_url);
+    let mut request = http_clients().client(&model.provider, &url).post(&url);
+    for (k, v) in &build_headers(model, api_key, is_oauth, false, None, None) {
+        request = request.header(k.as_str(), v.as_str());
+    }
+    let response = request
+        .json(&params)
+        .send()
+        .await
+        .map_err(|e| format!("Token count request failed: {}", describe_error(&e)))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid token count response: {e}"))?;
+    if !status.is_success() {
+        return Err(format!(
+            "Token count request failed (HTTP {status}): {body}"
+        ));
+    }
+    body["input_tokens"]
+        .as_u64()
+        .ok_or_else(|| format!("Token count response has no input_tokens: {body}"))
+}
+
 // ---------- Stream functions ----------
 
 /// Stream from Anthropic Messages API using raw HTTP + SSE parsing.
@@ -653,20 +809,35 @@ pub fn stream_anthropic(
         let params = build_params(&model, &context, is_oauth, &options);
 
         let url = format!("{}/v1/messages", model.base_url);
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &url);
 
         let mut request = client.post(&url);
         for (k, v) in &headers {
             request = request.header(k.as_str(), v.as_str());
         }
 
-        let response = match request.json(&params).send().await {
-            Ok(resp) => resp,
+        let response = match send_rate_limited(
+            &model.provider,
+            request.json(&params),
+            options.base.max_retry_delay_ms,
+            &cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
+                resp
+            }
             Err(e) => {
-                output.stop_reason = StopReason::Error;
+                let reason = if e.is_aborted() {
+                    StopReason::Aborted
+                } else {
+                    StopReason::Error
+                };
+                output.stop_reason = reason.clone();
                 output.error_message = Some(format!("HTTP error: {e}"));
                 stream_clone.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
+                    reason,
                     error: output,
                 });
                 return;
@@ -712,7 +883,7 @@ pub fn stream_anthropic(
                 Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
@@ -776,6 +947,7 @@ pub fn stream_anthropic(
                                 output.content.push(ContentBlock::Text(TextContent {
                                     text: String::new(),
                                     text_signature: None,
+                                    citations: None,
                                 }));
                                 let ci = output.content.len() - 1;
                                 // Map Anthropic index to our content index
@@ -803,6 +975,21 @@ pub fn stream_anthropic(
                                     partial: output.clone(),
                                 });
                             }
+                            "redacted_thinking" => {
+                                let data = content_block
+                                    .get("data")
+                                    .and_then(|v| v.as_str())
+                                    .unwrap_or("")
+                                    .to_string();
+                                output.content.push(ContentBlock::RedactedThinking(
+                                    RedactedThinkingContent { data },
+                                ));
+                                let ci = output.content.len() - 1;
+                                while block_indices.len() <= index {
+                                    block_indices.push(0);
+                                }
+                                block_indices[index] = ci;
+                            }
                             "tool_use" => {
                                 let id = content_block
                                     .get("id")
@@ -890,6 +1077,15 @@ pub fn stream_anthropic(
                                     partial: output.clone(),
                                 });
                             }
+                            "citations_delta" => {
+                                if let Some(citation) = delta.get("citation")
+                                    && let Some(ContentBlock::Text(t)) = output.content.get_mut(ci)
+                                {
+                                    t.citations
+                                        .get_or_insert_with(Vec::new)
+                                        .push(parse_citation(citation));
+                                }
+                            }
                             "signature_delta" => {
                                 let sig = delta
                                     .get("signature")
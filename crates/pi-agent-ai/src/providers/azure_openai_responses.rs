@@ -10,13 +10,17 @@ use pi_agent_core::event_stream::{
 use pi_agent_core::types::*;
 
 use crate::env_keys::get_env_api_key;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::supports_xhigh;
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
+use crate::response_format::{json_format, openai_responses_text_format};
 use crate::simple_options::{build_base_options, clamp_reasoning};
 use crate::sse::SseParser;
 
 use super::openai_responses_shared::{
-    convert_responses_messages, convert_responses_tools, process_responses_events,
+    apply_responses_sampling, convert_responses_messages, convert_responses_tool_choice,
+    convert_responses_tools, process_responses_events,
 };
 
 // =============================================================================
@@ -220,6 +224,20 @@ fn build_params(
         params["tools"] = json!(convert_responses_tools(tools, None));
     }
 
+    if let Some(choice) = options.and_then(|o| o.base.tool_choice.as_ref())
+        && context.tools.as_ref().is_some_and(|t| !t.is_empty())
+    {
+        params["tool_choice"] = convert_responses_tool_choice(choice);
+    }
+
+    if let Some(opts) = options {
+        apply_responses_sampling(model, &opts.base, &mut params);
+    }
+
+    if let Some(format) = options.and_then(|o| json_format(o.base.response_format.as_ref())) {
+        params["text"] = json!({"format": openai_responses_text_format(format)});
+    }
+
     if model.reasoning {
         let has_reasoning_opts = options
             .map(|o| o.reasoning_effort.is_some() || o.reasoning_summary.is_some())
@@ -308,19 +326,34 @@ pub fn stream_azure_openai_responses(
         let params = build_params(&model, &context, Some(&options), &deployment_name);
         let url = resolve_azure_responses_url(&base_url);
 
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &url);
         let mut request = client.post(&url);
         for (k, v) in &headers {
             request = request.header(k.as_str(), v.as_str());
         }
 
-        let response = match request.json(&params).send().await {
-            Ok(resp) => resp,
+        let response = match send_rate_limited(
+            &model.provider,
+            request.json(&params),
+            options.base.max_retry_delay_ms,
+            &cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
+                resp
+            }
             Err(e) => {
-                output.stop_reason = StopReason::Error;
+                let reason = if e.is_aborted() {
+                    StopReason::Aborted
+                } else {
+                    StopReason::Error
+                };
+                output.stop_reason = reason.clone();
                 output.error_message = Some(format!("HTTP error: {e}"));
                 stream_clone.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
+                    reason,
                     error: output,
                 });
                 return;
@@ -364,7 +397,7 @@ pub fn stream_azure_openai_responses(
                 Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
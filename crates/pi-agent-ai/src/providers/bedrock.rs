@@ -2,9 +2,7 @@ use std::collections::HashMap;
 
 use base64::Engine;
 use futures::StreamExt;
-use hmac::{Hmac, Mac};
 use serde_json::{Value, json};
-use sha2::{Digest, Sha256};
 use tokio_util::sync::CancellationToken;
 
 use pi_agent_core::event_stream::{
@@ -15,10 +13,16 @@ use pi_agent_core::sanitize::sanitize_surrogates;
 use pi_agent_core::transform::transform_messages;
 use pi_agent_core::types::*;
 
+use crate::aws::{self, AwsCredentials, aws_credentials};
 use crate::env_keys::get_env_api_key;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::calculate_cost;
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
-use crate::simple_options::{adjust_max_tokens_for_thinking, build_base_options, clamp_reasoning};
+use crate::response_format::{forced_tool, json_format};
+use crate::simple_options::{
+    adjust_max_tokens_for_thinking, build_base_options, clamp_reasoning, warn_unsupported_options,
+};
 
 // ---------- BedrockOptions ----------
 
@@ -41,28 +45,7 @@ pub enum BedrockToolChoice {
     Tool { name: String },
 }
 
-// ---------- AWS SigV4 Signing ----------
-
-type HmacSha256 = Hmac<Sha256>;
-
-fn sha256_hash(data: &[u8]) -> String {
-    let mut hasher = Sha256::new();
-    hasher.update(data);
-    hex::encode(hasher.finalize())
-}
-
-fn hmac_sha256(key: &[u8], msg: &[u8]) -> Vec<u8> {
-    let mut mac = HmacSha256::new_from_slice(key).expect("HMAC can take key of any size");
-    mac.update(msg);
-    mac.finalize().into_bytes().to_vec()
-}
-
-fn get_signature_key(key: &str, date_stamp: &str, region: &str, service: &str) -> Vec<u8> {
-    let k_date = hmac_sha256(format!("AWS4{key}").as_bytes(), date_stamp.as_bytes());
-    let k_region = hmac_sha256(&k_date, region.as_bytes());
-    let k_service = hmac_sha256(&k_region, service.as_bytes());
-    hmac_sha256(&k_service, b"aws4_request")
-}
+// ---------- AWS authentication ----------
 
 /// Resolve the AWS region from options, environment, or default.
 fn resolve_region(options_region: Option<&str>) -> String {
@@ -84,22 +67,21 @@ fn resolve_region(options_region: Option<&str>) -> String {
     "us-east-1".to_string()
 }
 
-/// Resolve AWS credentials from environment variables.
-/// Returns (access_key_id, secret_access_key, session_token).
-fn resolve_aws_credentials() -> Option<(String, String, Option<String>)> {
+/// Resolve AWS credentials through the provider chain (see [`crate::aws`]).
+async fn resolve_aws_credentials(
+    profile: Option<&str>,
+    region: &str,
+) -> Result<Option<AwsCredentials>, String> {
     // Check for skip-auth mode (proxy that doesn't need real creds)
     if std::env::var("AWS_BEDROCK_SKIP_AUTH").ok().as_deref() == Some("1") {
-        return Some((
-            "dummy-access-key".to_string(),
-            "dummy-secret-key".to_string(),
-            None,
-        ));
-    }
-
-    let access_key = std::env::var("AWS_ACCESS_KEY_ID").ok()?;
-    let secret_key = std::env::var("AWS_SECRET_ACCESS_KEY").ok()?;
-    let session_token = std::env::var("AWS_SESSION_TOKEN").ok();
-    Some((access_key, secret_key, session_token))
+        return Ok(Some(AwsCredentials {
+            access_key_id: "dummy-access-key".to_string(),
+            secret_access_key: "dummy-secret-key".to_string(),
+            session_token: None,
+            expiration: None,
+        }));
+    }
+    aws_credentials().resolve(profile, region).await
 }
 
 /// Resolve the AWS bearer token for Bedrock.
@@ -117,70 +99,17 @@ fn sign_request(
     secret_key: &str,
     session_token: Option<&str>,
 ) -> Result<HashMap<String, String>, String> {
-    let now = chrono::Utc::now();
-    let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
-    let date_stamp = now.format("%Y%m%d").to_string();
-
-    let parsed = url::Url::parse(url_str).map_err(|e| format!("Invalid URL: {e}"))?;
-    let host = parsed.host_str().unwrap_or("").to_string();
-    let canonical_uri = parsed.path().to_string();
-    let canonical_querystring = parsed.query().unwrap_or("").to_string();
-
-    let service = "bedrock";
-    let content_type = "application/json";
-    let payload_hash = sha256_hash(body);
-
-    // Build canonical headers
-    let mut signed_header_names =
-        vec!["content-type", "host", "x-amz-content-sha256", "x-amz-date"];
-    if session_token.is_some() {
-        signed_header_names.push("x-amz-security-token");
-    }
-    signed_header_names.sort();
-
-    let mut canonical_headers = String::new();
-    for name in &signed_header_names {
-        let value = match *name {
-            "content-type" => content_type.to_string(),
-            "host" => host.clone(),
-            "x-amz-content-sha256" => payload_hash.clone(),
-            "x-amz-date" => amz_date.clone(),
-            "x-amz-security-token" => session_token.unwrap_or("").to_string(),
-            _ => String::new(),
-        };
-        canonical_headers.push_str(&format!("{name}:{value}\n"));
-    }
-
-    let signed_headers = signed_header_names.join(";");
-
-    let canonical_request = format!(
-        "{method}\n{canonical_uri}\n{canonical_querystring}\n{canonical_headers}\n{signed_headers}\n{payload_hash}"
-    );
-
-    let credential_scope = format!("{date_stamp}/{region}/{service}/aws4_request");
-    let string_to_sign = format!(
-        "AWS4-HMAC-SHA256\n{amz_date}\n{credential_scope}\n{}",
-        sha256_hash(canonical_request.as_bytes())
-    );
-
-    let signing_key = get_signature_key(secret_key, &date_stamp, region, service);
-    let signature = hex::encode(hmac_sha256(&signing_key, string_to_sign.as_bytes()));
-
-    let authorization = format!(
-        "AWS4-HMAC-SHA256 Credential={access_key}/{credential_scope}, SignedHeaders={signed_headers}, Signature={signature}"
-    );
-
-    let mut headers = HashMap::new();
-    headers.insert("content-type".to_string(), content_type.to_string());
-    headers.insert("x-amz-date".to_string(), amz_date);
-    headers.insert("x-amz-content-sha256".to_string(), payload_hash);
-    headers.insert("authorization".to_string(), authorization);
-
-    if let Some(token) = session_token {
-        headers.insert("x-amz-security-token".to_string(), token.to_string());
-    }
-
-    Ok(headers)
+    aws::sign_request(
+        method,
+        url_str,
+        body,
+        region,
+        "bedrock",
+        "application/json",
+        access_key,
+        secret_key,
+        session_token,
+    )
 }
 
 // ---------- Helper functions ----------
@@ -551,6 +480,15 @@ fn convert_tool_config(
     Some(config)
 }
 
+fn map_tool_choice(choice: &ToolChoice) -> BedrockToolChoice {
+    match choice {
+        ToolChoice::Auto => BedrockToolChoice::Auto,
+        ToolChoice::None => BedrockToolChoice::None,
+        ToolChoice::Required => BedrockToolChoice::Any,
+        ToolChoice::Tool(name) => BedrockToolChoice::Tool { name: name.clone() },
+    }
+}
+
 // ---------- Build additional model request fields ----------
 
 fn build_additional_model_request_fields(model: &Model, options: &BedrockOptions) -> Option<Value> {
@@ -844,6 +782,7 @@ fn handle_content_block_delta(
                 let block = ContentBlock::Text(TextContent {
                     text: String::new(),
                     text_signature: None,
+                    citations: None,
                 });
                 output.content.push(block);
                 blocks.push(StreamBlock {
@@ -1085,8 +1024,39 @@ pub fn stream_bedrock(
         let messages = convert_messages(&context.messages, &model, &cache_retention);
         let system =
             build_system_prompt(context.system_prompt.as_deref(), &model, &cache_retention);
-        let tool_config = convert_tool_config(context.tools.as_deref(), &options.tool_choice);
-        let additional_fields = build_additional_model_request_fields(&model, &options);
+        // Structured output: Converse has no native JSON mode, so force a tool
+        // whose input schema is the requested schema. Forced tool use cannot be
+        // combined with extended thinking, so reasoning fields are dropped.
+        let forced = json_format(options.base.response_format.as_ref()).and_then(forced_tool);
+        let (tools, tool_choice) = match &forced {
+            Some(tool) => {
+                let mut tools = context.tools.clone().unwrap_or_default();
+                tools.push(tool.clone());
+                let choice = BedrockToolChoice::Tool {
+                    name: tool.name.clone(),
+                };
+                (Some(tools), Some(choice))
+            }
+            None => (
+                context.tools.clone(),
+                options
+                    .tool_choice
+                    .clone()
+                    .or_else(|| options.base.tool_choice.as_ref().map(map_tool_choice)),
+            ),
+        };
+        let tool_config = convert_tool_config(tools.as_deref(), &tool_choice);
+        let mut additional_fields = if forced.is_some() {
+            None
+        } else {
+            build_additional_model_request_fields(&model, &options)
+        };
+        // Converse has no top-k field; Claude accepts it as a native field.
+        if let Some(top_k) = options.base.top_k
+            && model.id.contains("anthropic.claude")
+        {
+            additional_fields.get_or_insert_with(|| json!({}))["top_k"] = json!(top_k);
+        }
 
         let mut body = json!({
             "modelId": model.id,
@@ -1104,6 +1074,17 @@ pub fn stream_bedrock(
         if let Some(temp) = options.base.temperature {
             inference_config["temperature"] = json!(temp);
         }
+        if let Some(top_p) = options.base.top_p {
+            inference_config["topP"] = json!(top_p);
+        }
+        if let Some(stop) = &options.base.stop_sequences {
+            inference_config["stopSequences"] = json!(stop);
+        }
+        let mut supported = vec!["topP", "stopSequences"];
+        if model.id.contains("anthropic.claude") {
+            supported.push("topK");
+        }
+        warn_unsupported_options(&model, &options.base, &supported);
         if inference_config.as_object().is_some_and(|o| !o.is_empty()) {
             body["inferenceConfig"] = inference_config;
         }
@@ -1120,7 +1101,7 @@ pub fn stream_bedrock(
         let url = build_bedrock_url(&region, &model.id);
 
         // Build request with authentication
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &url);
         let mut request = client.post(&url);
 
         // Determine auth method and set headers
@@ -1128,15 +1109,38 @@ pub fn stream_bedrock(
             request = request
                 .header("content-type", "application/json")
                 .header("authorization", format!("Bearer {bearer}"));
-        } else if let Some((access_key, secret_key, session_token)) = resolve_aws_credentials() {
+        } else {
+            let credentials = match resolve_aws_credentials(options.profile.as_deref(), &region)
+                .await
+            {
+                Ok(Some(credentials)) => credentials,
+                Ok(None) => {
+                    output.stop_reason = StopReason::Error;
+                    output.error_message = Some("No AWS credentials available. Set AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY, AWS_BEARER_TOKEN_BEDROCK, AWS_PROFILE, or configure ~/.aws/config.".to_string());
+                    stream_clone.push(AssistantMessageEvent::Error {
+                        reason: StopReason::Error,
+                        error: output,
+                    });
+                    return;
+                }
+                Err(e) => {
+                    output.stop_reason = StopReason::Error;
+                    output.error_message = Some(format!("AWS credential error: {e}"));
+                    stream_clone.push(AssistantMessageEvent::Error {
+                        reason: StopReason::Error,
+                        error: output,
+                    });
+                    return;
+                }
+            };
             let headers = match sign_request(
                 "POST",
                 &url,
                 &body_bytes,
                 &region,
-                &access_key,
-                &secret_key,
-                session_token.as_deref(),
+                &credentials.access_key_id,
+                &credentials.secret_access_key,
+                credentials.session_token.as_deref(),
             ) {
                 Ok(h) => h,
                 Err(e) => {
@@ -1152,24 +1156,30 @@ pub fn stream_bedrock(
             for (k, v) in &headers {
                 request = request.header(k.as_str(), v.as_str());
             }
-        } else {
-            // No credentials available
-            output.stop_reason = StopReason::Error;
-            output.error_message = Some("No AWS credentials available. Set AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY, AWS_BEARER_TOKEN_BEDROCK, or AWS_PROFILE.".to_string());
-            stream_clone.push(AssistantMessageEvent::Error {
-                reason: StopReason::Error,
-                error: output,
-            });
-            return;
         }
 
-        let response = match request.body(body_bytes).send().await {
-            Ok(resp) => resp,
+        let response = match send_rate_limited(
+            &model.provider,
+            request.body(body_bytes),
+            options.base.max_retry_delay_ms,
+            &cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
+                resp
+            }
             Err(e) => {
-                output.stop_reason = StopReason::Error;
+                let reason = if e.is_aborted() {
+                    StopReason::Aborted
+                } else {
+                    StopReason::Error
+                };
+                output.stop_reason = reason.clone();
                 output.error_message = Some(format!("HTTP error: {e}"));
                 stream_clone.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
+                    reason,
                     error: output,
                 });
                 return;
@@ -1212,7 +1222,7 @@ pub fn stream_bedrock(
                 Ok(bytes) => bytes,
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
@@ -1728,6 +1738,7 @@ mod tests {
                 stop_reason: StopReason::ToolUse,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             }),
             Message::ToolResult(ToolResultMessage {
                 tool_call_id: "call_1".to_string(),
@@ -1735,6 +1746,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Result 1".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: None,
                 is_error: false,
@@ -1746,6 +1758,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Result 2".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: None,
                 is_error: false,
@@ -1779,6 +1792,7 @@ mod tests {
                 ContentBlock::Text(TextContent {
                     text: "Here is my answer".to_string(),
                     text_signature: None,
+                    citations: None,
                 }),
             ],
             api: "bedrock-converse-stream".to_string(),
@@ -1788,6 +1802,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         })];
 
         let result = convert_messages(&messages, &model, &CacheRetention::None);
@@ -1981,6 +1996,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "Found results".to_string(),
                 text_signature: None,
+                citations: None,
             })],
             details: None,
             is_error: false,
@@ -2001,6 +2017,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "File not found".to_string(),
                 text_signature: None,
+                citations: None,
             })],
             details: None,
             is_error: true,
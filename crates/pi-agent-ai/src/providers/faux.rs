@@ -0,0 +1,834 @@
+//! Scriptable fake provider for agent-loop testing.
+//!
+//! [`FauxProvider`] answers each request with the next [`FauxTurn`] from its
+//! script: text, thinking, tool calls, errors with a chosen [`StopReason`] or
+//! a context overflow, optionally streamed in small chunks with delays. A turn
+//! can also assert on the [`Context`] it receives. Once the script is used up
+//! the provider echoes the last user message.
+//!
+//! The built-in registries only include it when `PI_FAUX_SCRIPT` names a
+//! script (a JSON array of turns); `--provider faux` then selects
+//! [`faux_model`]. Tests register their own instance.
+
+use std::collections::VecDeque;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use pi_agent_core::agent_types::StreamFnBox;
+use pi_agent_core::event_stream::{
+    AssistantMessageEventStream, create_assistant_message_event_stream,
+};
+use pi_agent_core::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+
+use crate::registry::{ApiProvider, ApiRegistry};
+
+pub const FAUX_API: &str = "faux";
+pub const FAUX_PROVIDER: &str = "faux";
+/// Source id used when registering, for `unregister_by_source`.
+pub const FAUX_SOURCE_ID: &str = "faux";
+/// Environment variable naming a JSON script for the built-in instance.
+pub const FAUX_SCRIPT_ENV: &str = "PI_FAUX_SCRIPT";
+
+/// Whether `PI_FAUX_SCRIPT` is set, enabling the built-in faux provider.
+pub fn faux_enabled() -> bool {
+    std::env::var_os(FAUX_SCRIPT_ENV).is_some_and(|path| !path.is_empty())
+}
+
+/// The model served by the faux provider.
+pub fn faux_model() -> Model {
+    Model {
+        id: "faux-1".to_string(),
+        name: "Faux (scripted test model)".to_string(),
+        api: FAUX_API.to_string(),
+        provider: FAUX_PROVIDER.to_string(),
+        base_url: String::new(),
+        reasoning: true,
+        input: vec!["text".to_string(), "image".to_string()],
+        cost: ModelCost::default(),
+        context_window: 128000,
+        max_tokens: 16384,
+        headers: None,
+        compat: None,
+    }
+}
+
+/// One piece of a scripted response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum FauxStep {
+    Text {
+        text: String,
+    },
+    Thinking {
+        thinking: String,
+    },
+    ToolCall {
+        name: String,
+        #[serde(default)]
+        arguments: Value,
+        #[serde(default)]
+        id: Option<String>,
+    },
+    /// End the response with an error.
+    Error {
+        message: String,
+        #[serde(default = "default_error_reason", rename = "stopReason")]
+        stop_reason: StopReason,
+    },
+    /// End the response with a context overflow error.
+    Overflow,
+}
+
+fn default_error_reason() -> StopReason {
+    StopReason::Error
+}
+
+/// Declarative checks on the received context.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FauxExpectation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub message_count: Option<usize>,
+    /// Text the last message (user or tool result) must contain.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub last_message_contains: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt_contains: Option<String>,
+    /// Tool names that must be offered.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tools: Option<Vec<String>>,
+}
+
+impl FauxExpectation {
+    fn check(&self, context: &Context) -> Result<(), String> {
+        if let Some(count) = self.message_count
+            && context.messages.len() != count
+        {
+            return Err(format!(
+                "expected {count} messages, got {}",
+                context.messages.len()
+            ));
+        }
+        if let Some(needle) = &self.last_message_contains {
+            let text = context
+                .messages
+                .last()
+                .map(message_text)
+                .unwrap_or_default();
+            if !text.contains(needle.as_str()) {
+                return Err(format!(
+                    "expected last message to contain {needle:?}, got {text:?}"
+                ));
+            }
+        }
+        if let Some(needle) = &self.system_prompt_contains
+            && !context
+                .system_prompt
+                .as_deref()
+                .unwrap_or_default()
+                .contains(needle.as_str())
+        {
+            return Err(format!("expected system prompt to contain {needle:?}"));
+        }
+        for name in self.tools.iter().flatten() {
+            if !context.tools.iter().flatten().any(|t| &t.name == name) {
+                return Err(format!("expected tool {name:?} to be offered"));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Custom assertion on the received context, for Rust tests.
+pub type FauxAssertion = Arc<dyn Fn(&Context) -> Result<(), String> + Send + Sync>;
+
+/// One scripted response.
+#[derive(Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct FauxTurn {
+    pub steps: Vec<FauxStep>,
+    /// Characters per streamed delta; the whole text at once when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub chunk_size: Option<usize>,
+    /// Delay before each delta, in milliseconds.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub expect: Option<FauxExpectation>,
+    #[serde(skip)]
+    pub assert: Option<FauxAssertion>,
+}
+
+impl FauxTurn {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::default().then_text(text)
+    }
+
+    pub fn tool_call(name: impl Into<String>, arguments: Value) -> Self {
+        Self::default().then_tool_call(name, arguments)
+    }
+
+    pub fn error(message: impl Into<String>, stop_reason: StopReason) -> Self {
+        Self {
+            steps: vec![FauxStep::Error {
+                message: message.into(),
+                stop_reason,
+            }],
+            ..Default::default()
+        }
+    }
+
+    pub fn overflow() -> Self {
+        Self {
+            steps: vec![FauxStep::Overflow],
+            ..Default::default()
+        }
+    }
+
+    pub fn then_text(mut self, text: impl Into<String>) -> Self {
+        self.steps.push(FauxStep::Text { text: text.into() });
+        self
+    }
+
+    pub fn then_thinking(mut self, thinking: impl Into<String>) -> Self {
+        self.steps.push(FauxStep::Thinking {
+            thinking: thinking.into(),
+        });
+        self
+    }
+
+    pub fn then_tool_call(mut self, name: impl Into<String>, arguments: Value) -> Self {
+        self.steps.push(FauxStep::ToolCall {
+            name: name.into(),
+            arguments,
+            id: None,
+        });
+        self
+    }
+
+    /// Stream `chunk_size` characters at a time, waiting `delay_ms` before each.
+    pub fn slowly(mut self, chunk_size: usize, delay_ms: u64) -> Self {
+        self.chunk_size = Some(chunk_size.max(1));
+        self.delay_ms = Some(delay_ms);
+        self
+    }
+
+    pub fn expect(mut self, expectation: FauxExpectation) -> Self {
+        self.expect = Some(expectation);
+        self
+    }
+
+    pub fn assert(
+        mut self,
+        assertion: impl Fn(&Context) -> Result<(), String> + Send + Sync + 'static,
+    ) -> Self {
+        self.assert = Some(Arc::new(assertion));
+        self
+    }
+
+    fn check(&self, context: &Context) -> Result<(), String> {
+        if let Some(expect) = &self.expect {
+            expect.check(context)?;
+        }
+        match &self.assert {
+            Some(assert) => assert(context),
+            None => Ok(()),
+        }
+    }
+}
+
+#[derive(Default)]
+struct FauxState {
+    turns: VecDeque<FauxTurn>,
+    contexts: Vec<Context>,
+    failures: Vec<String>,
+    next_tool_call: usize,
+}
+
+/// Scripted provider; see the module docs.
+#[derive(Default)]
+pub struct FauxProvider {
+    state: Mutex<FauxState>,
+}
+
+impl FauxProvider {
+    pub fn new(turns: Vec<FauxTurn>) -> Self {
+        Self {
+            state: Mutex::new(FauxState {
+                turns: turns.into(),
+                ..Default::default()
+            }),
+        }
+    }
+
+    /// Load a JSON array of turns.
+    pub fn from_script_file(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read faux script {}: {e}", path.display()))?;
+        let turns: Vec<FauxTurn> = serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid faux script {}: {e}", path.display()))?;
+        Ok(Self::new(turns))
+    }
+
+    /// Script from `PI_FAUX_SCRIPT`, or an empty (echoing) script.
+    pub fn from_env() -> Self {
+        match std::env::var(FAUX_SCRIPT_ENV) {
+            Ok(path) if !path.is_empty() => Self::from_script_file(Path::new(&path))
+                .unwrap_or_else(|e| {
+                    tracing::warn!("{e}");
+                    Self::default()
+                }),
+            _ => Self::default(),
+        }
+    }
+
+    pub fn push_turn(&self, turn: FauxTurn) {
+        self.lock().turns.push_back(turn);
+    }
+
+    /// Scripted turns not yet used.
+    pub fn remaining(&self) -> usize {
+        self.lock().turns.len()
+    }
+
+    /// Every context received, in order.
+    pub fn received(&self) -> Vec<Context> {
+        self.lock().contexts.clone()
+    }
+
+    /// Failed context assertions, in order.
+    pub fn failures(&self) -> Vec<String> {
+        self.lock().failures.clone()
+    }
+
+    /// Stream function for `Agent`/`AgentSession` that bypasses the registry.
+    pub fn stream_fn(self: &Arc<Self>) -> StreamFnBox {
+        let provider = self.clone();
+        Arc::new(move |model, context, options| {
+            provider.stream_simple(model, context, options, CancellationToken::new())
+        })
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, FauxState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn run(
+        &self,
+        model: &Model,
+        context: &Context,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        let stream = create_assistant_message_event_stream();
+        let (turn, tool_call_base) = {
+            let mut state = self.lock();
+            state.contexts.push(context.clone());
+            let turn = state.turns.pop_front().unwrap_or_else(|| {
+                let text = context
+                    .messages
+                    .last()
+                    .map(message_text)
+                    .unwrap_or_default();
+                FauxTurn::text(format!("faux: {text}"))
+            });
+            let base = state.next_tool_call;
+            state.next_tool_call += turn
+                .steps
+                .iter()
+                .filter(|s| matches!(s, FauxStep::ToolCall { .. }))
+                .count();
+            (turn, base)
+        };
+
+        if let Err(failure) = turn.check(context) {
+            self.lock().failures.push(failure.clone());
+            let mut error = AssistantMessage::empty(model);
+            error.stop_reason = StopReason::Error;
+            error.error_message = Some(format!("faux: context assertion failed: {failure}"));
+            stream.push(AssistantMessageEvent::Error {
+                reason: StopReason::Error,
+                error,
+            });
+            return stream;
+        }
+
+        let input_tokens = serde_json::to_string(context).map_or(0, |s| s.len() as u64 / 4);
+        let model = model.clone();
+        let out = stream.clone();
+        tokio::spawn(async move {
+            play_turn(turn, model, input_tokens, tool_call_base, out, cancel).await;
+        });
+        stream
+    }
+}
+
+impl ApiProvider for FauxProvider {
+    fn api(&self) -> &str {
+        FAUX_API
+    }
+
+    fn stream(
+        &self,
+        model: &Model,
+        context: &Context,
+        _options: &StreamOptions,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        self.run(model, context, cancel)
+    }
+
+    fn stream_simple(
+        &self,
+        model: &Model,
+        context: &Context,
+        _options: &SimpleStreamOptions,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        self.run(model, context, cancel)
+    }
+}
+
+/// Register `provider` under the `faux` api and source id, replacing any
+/// previous instance.
+pub fn register_faux_provider(registry: &mut ApiRegistry, provider: Arc<FauxProvider>) {
+    registry.register_with_source(provider, Some(FAUX_SOURCE_ID.to_string()));
+}
+
+fn message_text(message: &Message) -> String {
+    let blocks = match message {
+        Message::User(user) => match &user.content {
+            UserContent::Text(text) => return text.clone(),
+            UserContent::Blocks(blocks) => blocks,
+        },
+        Message::Assistant(assistant) => &assistant.content,
+        Message::ToolResult(result) => &result.content,
+    };
+    blocks
+        .iter()
+        .filter_map(|b| b.as_text().map(|t| t.text.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Split `text` into deltas of `chunk_size` characters.
+fn chunks(text: &str, chunk_size: Option<usize>) -> Vec<String> {
+    let Some(size) = chunk_size else {
+        return vec![text.to_string()];
+    };
+    let chars: Vec<char> = text.chars().collect();
+    chars
+        .chunks(size.max(1))
+        .map(|c| c.iter().collect())
+        .collect()
+}
+
+/// Wait before a delta. Returns false when the request was aborted.
+async fn pause(delay_ms: Option<u64>, cancel: &CancellationToken) -> bool {
+    if let Some(ms) = delay_ms.filter(|ms| *ms > 0) {
+        tokio::select! {
+            _ = tokio::time::sleep(Duration::from_millis(ms)) => {}
+            _ = cancel.cancelled() => return false,
+        }
+    }
+    !cancel.is_cancelled()
+}
+
+fn finish_error(
+    out: &AssistantMessageEventStream,
+    mut message: AssistantMessage,
+    reason: StopReason,
+    error_message: String,
+) {
+    message.stop_reason = reason.clone();
+    message.error_message = Some(error_message);
+    out.push(AssistantMessageEvent::Error {
+        reason,
+        error: message,
+    });
+}
+
+async fn play_turn(
+    turn: FauxTurn,
+    model: Model,
+    input_tokens: u64,
+    tool_call_base: usize,
+    out: AssistantMessageEventStream,
+    cancel: CancellationToken,
+) {
+    let mut message = AssistantMessage::empty(&model);
+    message.usage.input = input_tokens;
+    out.push(AssistantMessageEvent::Start {
+        partial: message.clone(),
+    });
+
+    let mut output_chars = 0usize;
+    let mut tool_calls = 0usize;
+    macro_rules! abort_if_cancelled {
+        ($ok:expr) => {
+            if !$ok {
+                finish_error(
+                    &out,
+                    message,
+                    StopReason::Aborted,
+                    "Request was aborted".to_string(),
+                );
+                return;
+            }
+        };
+    }
+
+    for step in turn.steps {
+        let index = message.content.len();
+        match step {
+            FauxStep::Text { text } => {
+                message.content.push(ContentBlock::Text(TextContent {
+                    text: String::new(),
+                    text_signature: None,
+                    citations: None,
+                }));
+                out.push(AssistantMessageEvent::TextStart {
+                    content_index: index,
+                    partial: message.clone(),
+                });
+                for delta in chunks(&text, turn.chunk_size) {
+                    abort_if_cancelled!(pause(turn.delay_ms, &cancel).await);
+                    if let Some(ContentBlock::Text(t)) = message.content.get_mut(index) {
+                        t.text.push_str(&delta);
+                    }
+                    out.push(AssistantMessageEvent::TextDelta {
+                        content_index: index,
+                        delta,
+                        partial: message.clone(),
+                    });
+                }
+                output_chars += text.len();
+                out.push(AssistantMessageEvent::TextEnd {
+                    content_index: index,
+                    content: text,
+                    partial: message.clone(),
+                });
+            }
+            FauxStep::Thinking { thinking } => {
+                message
+                    .content
+                    .push(ContentBlock::Thinking(ThinkingContent {
+                        thinking: String::new(),
+                        thinking_signature: None,
+                    }));
+                out.push(AssistantMessageEvent::ThinkingStart {
+                    content_index: index,
+                    partial: message.clone(),
+                });
+                for delta in chunks(&thinking, turn.chunk_size) {
+                    abort_if_cancelled!(pause(turn.delay_ms, &cancel).await);
+                    if let Some(ContentBlock::Thinking(t)) = message.content.get_mut(index) {
+                        t.thinking.push_str(&delta);
+                    }
+                    out.push(AssistantMessageEvent::ThinkingDelta {
+                        content_index: index,
+                        delta,
+                        partial: message.clone(),
+                    });
+                }
+                output_chars += thinking.len();
+                out.push(AssistantMessageEvent::ThinkingEnd {
+                    content_index: index,
+                    content: thinking,
+                    partial: message.clone(),
+                });
+            }
+            FauxStep::ToolCall {
+                name,
+                arguments,
+                id,
+            } => {
+                let id = id.unwrap_or_else(|| format!("faux_call_{}", tool_call_base + tool_calls));
+                tool_calls += 1;
+                message.content.push(ContentBlock::ToolCall(ToolCall {
+                    id,
+                    name,
+                    arguments: Value::Object(Default::default()),
+                    thought_signature: None,
+                }));
+                out.push(AssistantMessageEvent::ToolCallStart {
+                    content_index: index,
+                    partial: message.clone(),
+                });
+                let arguments = if arguments.is_null() {
+                    Value::Object(Default::default())
+                } else {
+                    arguments
+                };
+                let json = arguments.to_string();
+                for delta in chunks(&json, turn.chunk_size) {
+                    abort_if_cancelled!(pause(turn.delay_ms, &cancel).await);
+                    out.push(AssistantMessageEvent::ToolCallDelta {
+                        content_index: index,
+                        delta,
+                        partial: message.clone(),
+                    });
+                }
+                output_chars += json.len();
+                let Some(ContentBlock::ToolCall(tool_call)) = message.content.get_mut(index) else {
+                    continue;
+                };
+                tool_call.arguments = arguments;
+                let tool_call = tool_call.clone();
+                out.push(AssistantMessageEvent::ToolCallEnd {
+                    content_index: index,
+                    tool_call,
+                    partial: message.clone(),
+                });
+            }
+            FauxStep::Error {
+                message: error_message,
+                stop_reason,
+            } => {
+                finish_error(&out, message, stop_reason, error_message);
+                return;
+            }
+            FauxStep::Overflow => {
+                let error_message = format!(
+                    "prompt is too long: {} tokens > {} maximum",
+                    model.context_window + 1,
+                    model.context_window
+                );
+                finish_error(&out, message, StopReason::Error, error_message);
+                return;
+            }
+        }
+        abort_if_cancelled!(!cancel.is_cancelled());
+    }
+
+    message.usage.output = output_chars as u64 / 4;
+    message.usage.total_tokens = message.usage.input + message.usage.output;
+    let reason = if tool_calls > 0 {
+        StopReason::ToolUse
+    } else {
+        StopReason::Stop
+    };
+    message.stop_reason = reason.clone();
+    out.push(AssistantMessageEvent::Done { reason, message });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::StreamExt;
+    use pi_agent_core::agent::{Agent, AgentOptions, PartialAgentState};
+    use pi_agent_core::agent_types::{AgentMessage, AgentTool, AgentToolResult};
+    use pi_agent_core::overflow::is_context_overflow;
+
+    fn context(text: &str) -> Context {
+        Context {
+            system_prompt: Some("You are a test".to_string()),
+            messages: vec![Message::User(UserMessage {
+                content: UserContent::Text(text.to_string()),
+                timestamp: 0,
+            })],
+            tools: None,
+        }
+    }
+
+    async fn run(
+        provider: &FauxProvider,
+        context: &Context,
+        cancel: CancellationToken,
+    ) -> (Vec<&'static str>, AssistantMessage) {
+        let stream = provider.stream_simple(
+            &faux_model(),
+            context,
+            &SimpleStreamOptions::default(),
+            cancel,
+        );
+        let events: Vec<_> = stream.clone().collect().await;
+        let types = events.iter().map(|e| e.event_type()).collect();
+        (types, stream.result().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_text_and_tool_call() {
+        let provider = FauxProvider::new(vec![
+            FauxTurn::text("Reading").then_tool_call("read", serde_json::json!({"path": "a.rs"})),
+        ]);
+        let (types, message) = run(&provider, &context("hi"), CancellationToken::new()).await;
+        assert_eq!(
+            types,
+            vec![
+                "start",
+                "text_start",
+                "text_delta",
+                "text_end",
+                "toolcall_start",
+                "toolcall_delta",
+                "toolcall_end",
+                "done"
+            ]
+        );
+        assert_eq!(message.stop_reason, StopReason::ToolUse);
+        let ContentBlock::ToolCall(call) = &message.content[1] else {
+            panic!("expected tool call");
+        };
+        assert_eq!(call.id, "faux_call_0");
+        assert_eq!(call.arguments["path"], "a.rs");
+    }
+
+    #[tokio::test]
+    async fn test_errors_overflow_and_echo() {
+        let provider = FauxProvider::new(vec![
+            FauxTurn::error("overloaded", StopReason::Error),
+            FauxTurn::overflow(),
+        ]);
+        let (_, message) = run(&provider, &context("hi"), CancellationToken::new()).await;
+        assert_eq!(message.error_message.as_deref(), Some("overloaded"));
+        let (_, message) = run(&provider, &context("hi"), CancellationToken::new()).await;
+        assert!(is_context_overflow(&message, None));
+        let (_, message) = run(&provider, &context("hello"), CancellationToken::new()).await;
+        assert_eq!(message.content[0].as_text().unwrap().text, "faux: hello");
+    }
+
+    #[tokio::test]
+    async fn test_context_expectations() {
+        let provider = FauxProvider::new(vec![
+            FauxTurn::text("ok").expect(FauxExpectation {
+                last_message_contains: Some("hi".to_string()),
+                system_prompt_contains: Some("test".to_string()),
+                ..Default::default()
+            }),
+            FauxTurn::text("ok").assert(|ctx| {
+                if ctx.messages.len() == 3 {
+                    Ok(())
+                } else {
+                    Err(format!("{} messages", ctx.messages.len()))
+                }
+            }),
+        ]);
+        let (_, message) = run(&provider, &context("hi"), CancellationToken::new()).await;
+        assert_eq!(message.stop_reason, StopReason::Stop);
+        let (_, message) = run(&provider, &context("hi"), CancellationToken::new()).await;
+        assert_eq!(message.stop_reason, StopReason::Error);
+        assert_eq!(provider.failures(), vec!["1 messages".to_string()]);
+        assert_eq!(provider.received().len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_slow_stream_abort() {
+        let provider = FauxProvider::new(vec![FauxTurn::text("a long answer").slowly(2, 20)]);
+        let cancel = CancellationToken::new();
+        let trigger = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(Duration::from_millis(50)).await;
+            trigger.cancel();
+        });
+        let (types, message) = run(&provider, &context("hi"), cancel).await;
+        assert_eq!(message.stop_reason, StopReason::Aborted);
+        assert!(types.iter().filter(|t| **t == "text_delta").count() < 7);
+    }
+
+    #[test]
+    fn test_script_parsing() {
+        let turns: Vec<FauxTurn> = serde_json::from_str(
+            r#"[
+                {"steps": [{"type": "text", "text": "hi"}], "chunkSize": 1, "delayMs": 5},
+                {"steps": [{"type": "tool_call", "name": "bash", "arguments": {"command": "ls"}}]},
+                {"steps": [{"type": "error", "message": "stopped", "stopReason": "aborted"}]},
+                {"steps": [{"type": "overflow"}], "expect": {"messageCount": 3}}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(turns.len(), 4);
+        assert_eq!(turns[0].chunk_size, Some(1));
+        assert!(matches!(
+            &turns[2].steps[0],
+            FauxStep::Error {
+                stop_reason: StopReason::Aborted,
+                ..
+            }
+        ));
+        assert_eq!(turns[3].expect.as_ref().unwrap().message_count, Some(3));
+    }
+
+    struct EchoTool {
+        definition: Tool,
+    }
+
+    #[async_trait::async_trait]
+    impl AgentTool for EchoTool {
+        fn name(&self) -> &str {
+            "echo"
+        }
+
+        fn label(&self) -> &str {
+            "Echo"
+        }
+
+        fn definition(&self) -> &Tool {
+            &self.definition
+        }
+
+        async fn execute(
+            &self,
+            _tool_call_id: &str,
+            params: Value,
+            _cancel: CancellationToken,
+            _on_update: Option<Box<dyn Fn(AgentToolResult) + Send + Sync>>,
+        ) -> Result<AgentToolResult, Box<dyn std::error::Error + Send + Sync>> {
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text(TextContent {
+                    text: format!("echoed {}", params["text"].as_str().unwrap_or_default()),
+                    text_signature: None,
+                    citations: None,
+                })],
+                details: None,
+            })
+        }
+    }
+
+    #[tokio::test]
+    async fn test_drives_agent_tool_loop() {
+        let provider = Arc::new(FauxProvider::new(vec![
+            FauxTurn::tool_call("echo", serde_json::json!({"text": "ping"})),
+            FauxTurn::text("done").expect(FauxExpectation {
+                message_count: Some(3),
+                last_message_contains: Some("echoed ping".to_string()),
+                tools: Some(vec!["echo".to_string()]),
+                ..Default::default()
+            }),
+        ]));
+        let tool: Arc<dyn AgentTool> = Arc::new(EchoTool {
+            definition: Tool {
+                name: "echo".to_string(),
+                description: "Echo text".to_string(),
+                parameters: serde_json::json!({"type": "object"}),
+            },
+        });
+        let mut agent = Agent::new(AgentOptions {
+            initial_state: Some(PartialAgentState {
+                system_prompt: None,
+                model: Some(faux_model()),
+                thinking_level: None,
+                tools: Some(vec![tool]),
+                messages: None,
+            }),
+            stream_fn: Some(provider.stream_fn()),
+            ..Default::default()
+        });
+        agent.prompt_text("go").await.unwrap();
+
+        assert!(provider.failures().is_empty(), "{:?}", provider.failures());
+        assert_eq!(provider.remaining(), 0);
+        let last = agent
+            .state()
+            .messages
+            .last()
+            .and_then(AgentMessage::as_message);
+        let Some(Message::Assistant(last)) = last else {
+            panic!("expected assistant message");
+        };
+        assert_eq!(last.content[0].as_text().unwrap().text, "done");
+    }
+}
@@ -13,9 +13,13 @@ use pi_agent_core::sanitize::sanitize_surrogates;
 use pi_agent_core::transform::transform_messages;
 use pi_agent_core::types::*;
 
+use super::google_shared::{apply_google_sampling, google_tool_config, parse_grounding_citations};
 use crate::env_keys::get_env_api_key;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::calculate_cost;
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
+use crate::response_format::{apply_google_response_format, json_format};
 use crate::simple_options::{build_base_options, clamp_reasoning};
 
 // ---------- Tool call counter ----------
@@ -471,6 +475,10 @@ fn build_params(model: &Model, context: &Context, options: &GoogleOptions) -> Va
     if let Some(max_tokens) = options.base.max_tokens {
         generation_config["maxOutputTokens"] = json!(max_tokens);
     }
+    apply_google_sampling(&options.base, &mut generation_config);
+    if let Some(format) = json_format(options.base.response_format.as_ref()) {
+        apply_google_response_format(format, &mut generation_config);
+    }
 
     // Build the config object (used as top-level fields in the REST API request)
     let mut config: Value = json!({});
@@ -504,6 +512,8 @@ fn build_params(model: &Model, context: &Context, options: &GoogleOptions) -> Va
                         "mode": map_tool_choice(choice)
                     }
                 });
+            } else if let Some(choice) = &options.base.tool_choice {
+                config["toolConfig"] = google_tool_config(choice);
             }
         }
     }
@@ -589,6 +599,53 @@ fn build_stream_url(model: &Model, api_key: &str) -> String {
     }
 }
 
+// ---------- Token counting ----------
+
+/// Count the input tokens of `context` with `models/{id}:countTokens`.
+pub async fn count_tokens_google(
+    model: &Model,
+    context: &Context,
+    api_key: &str,
+) -> Result<u64, String> {
+    let options = GoogleOptions {
+        base: StreamOptions::default(),
+        tool_choice: None,
+        thinking_enabled: false,
+        thinking_budget_tokens: None,
+        thinking_level: None,
+    };
+    let mut request_body = build_params(model, context, &options);
+    request_body["model"] = json!(format!("models/{}", model.id));
+    let url = build_stream_url(model, api_key)
+        .replacen(":streamGenerateContent?alt=sse&", ":countTokens?", 1)
+        .replacen(":streamGenerateContent?alt=sse", ":countTokens", 1);
+
+    let mut request = http_clients()
+        .client(&model.provider, &model.base_url)
+        .post(&url);
+    for (k, v) in &build_headers(model, api_key, None) {
+        request = request.header(k.as_str(), v.as_str());
+    }
+    let response = request
+        .json(&json!({ "generateContentRequest": request_body }))
+        .send()
+        .await
+        .map_err(|e| format!("Token count request failed: {}", describe_error(&e)))?;
+    let status = response.status();
+    let body: Value = response
+        .json()
+        .await
+        .map_err(|e| format!("Invalid token count response: {e}"))?;
+    if !status.is_success() {
+        return Err(format!(
+            "Token count request failed (HTTP {status}): {body}"
+        ));
+    }
+    body["totalTokens"]
+        .as_u64()
+        .ok_or_else(|| format!("Token count response has no totalTokens: {body}"))
+}
+
 // ---------- Gemini model helpers ----------
 
 fn is_gemini3_pro_model(model: &Model) -> bool {
@@ -699,19 +756,34 @@ pub fn stream_google(
         let params = build_params(&model, &context, &options);
         let url = build_stream_url(&model, &api_key);
 
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &model.base_url);
         let mut request = client.post(&url);
         for (k, v) in &headers {
             request = request.header(k.as_str(), v.as_str());
         }
 
-        let response = match request.json(&params).send().await {
-            Ok(resp) => resp,
+        let response = match send_rate_limited(
+            &model.provider,
+            request.json(&params),
+            options.base.max_retry_delay_ms,
+            &cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
+                resp
+            }
             Err(e) => {
-                output.stop_reason = StopReason::Error;
+                let reason = if e.is_aborted() {
+                    StopReason::Aborted
+                } else {
+                    StopReason::Error
+                };
+                output.stop_reason = reason.clone();
                 output.error_message = Some(format!("HTTP error: {e}"));
                 stream_clone.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
+                    reason,
                     error: output,
                 });
                 return;
@@ -774,7 +846,7 @@ pub fn stream_google(
                 Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
@@ -890,6 +962,7 @@ pub fn stream_google(
                                             output.content.push(ContentBlock::Text(TextContent {
                                                 text: String::new(),
                                                 text_signature: None,
+                                                citations: None,
                                             }));
                                             let ci = block_index(&output);
                                             stream_clone.push(AssistantMessageEvent::TextStart {
@@ -1046,6 +1119,20 @@ pub fn stream_google(
                             }
                         }
 
+                        // Attach grounding sources to the most recent text block
+                        if let Some(metadata) = candidate.get("groundingMetadata") {
+                            let citations = parse_grounding_citations(metadata);
+                            if !citations.is_empty()
+                                && let Some(ContentBlock::Text(t)) = output
+                                    .content
+                                    .iter_mut()
+                                    .rev()
+                                    .find(|b| matches!(b, ContentBlock::Text(_)))
+                            {
+                                t.citations = Some(citations);
+                            }
+                        }
+
                         // Handle finish reason
                         if let Some(reason) = candidate.get("finishReason").and_then(|r| r.as_str())
                         {
@@ -1423,6 +1510,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Hello world".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: "google-generative-ai".to_string(),
                 provider: "google".to_string(),
@@ -1431,6 +1519,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1451,6 +1540,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Found results".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: None,
                 is_error: false,
@@ -1477,6 +1567,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "command not found".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: None,
                 is_error: true,
@@ -1543,6 +1634,38 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_params_with_response_format() {
+        let model = test_google_model();
+        let context = Context {
+            system_prompt: None,
+            messages: vec![Message::User(UserMessage {
+                content: UserContent::Text("Name a color".to_string()),
+                timestamp: 0,
+            })],
+            tools: None,
+        };
+        let options = GoogleOptions {
+            base: StreamOptions {
+                response_format: Some(ResponseFormat::JsonSchema {
+                    name: "color".to_string(),
+                    schema: json!({"type": "object", "properties": {"name": {"type": "string"}}}),
+                    description: None,
+                    strict: None,
+                }),
+                ..Default::default()
+            },
+            tool_choice: None,
+            thinking_enabled: false,
+            thinking_budget_tokens: None,
+            thinking_level: None,
+        };
+        let params = build_params(&model, &context, &options);
+
+        assert_eq!(params["responseMimeType"], "application/json");
+        assert_eq!(params["responseJsonSchema"]["type"], "object");
+    }
+
     #[test]
     fn test_build_params_with_thinking() {
         let model = test_google_model();
@@ -1623,6 +1746,43 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_build_params_with_neutral_tool_choice_and_sampling() {
+        let model = test_google_model();
+        let context = Context {
+            system_prompt: None,
+            messages: vec![Message::User(UserMessage {
+                content: UserContent::Text("search for rust".to_string()),
+                timestamp: 0,
+            })],
+            tools: Some(vec![Tool {
+                name: "search".to_string(),
+                description: "Search the web".to_string(),
+                parameters: json!({"type": "object"}),
+            }]),
+        };
+        let options = GoogleOptions {
+            base: StreamOptions {
+                tool_choice: Some(ToolChoice::Tool("search".to_string())),
+                top_k: Some(40),
+                seed: Some(7),
+                stop_sequences: Some(vec!["END".to_string()]),
+                ..Default::default()
+            },
+            tool_choice: None,
+            thinking_enabled: false,
+            thinking_budget_tokens: None,
+            thinking_level: None,
+        };
+        let params = build_params(&model, &context, &options);
+        let config = &params["toolConfig"]["functionCallingConfig"];
+        assert_eq!(config["mode"], "ANY");
+        assert_eq!(config["allowedFunctionNames"][0], "search");
+        assert_eq!(params["topK"], 40);
+        assert_eq!(params["seed"], 7);
+        assert_eq!(params["stopSequences"][0], "END");
+    }
+
     #[test]
     fn test_get_google_budget_25_pro() {
         let mut model = test_google_model();
@@ -1735,6 +1895,7 @@ mod tests {
                     ContentBlock::Text(TextContent {
                         text: "Here is the answer".to_string(),
                         text_signature: None,
+                        citations: None,
                     }),
                 ],
                 api: "google-generative-ai".to_string(),
@@ -1744,6 +1905,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1773,6 +1935,7 @@ mod tests {
                     ContentBlock::Text(TextContent {
                         text: "Answer".to_string(),
                         text_signature: None,
+                        citations: None,
                     }),
                 ],
                 api: "anthropic-messages".to_string(),
@@ -1782,6 +1945,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1811,6 +1975,7 @@ mod tests {
                 stop_reason: StopReason::ToolUse,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1837,6 +2002,7 @@ mod tests {
                     content: vec![ContentBlock::Text(TextContent {
                         text: "Result 1".to_string(),
                         text_signature: None,
+                        citations: None,
                     })],
                     details: None,
                     is_error: false,
@@ -1848,6 +2014,7 @@ mod tests {
                     content: vec![ContentBlock::Text(TextContent {
                         text: "Result 2".to_string(),
                         text_signature: None,
+                        citations: None,
                     })],
                     details: None,
                     is_error: false,
@@ -1894,4 +2061,26 @@ mod tests {
         // No signature
         assert_eq!(resolve_thought_signature(true, None), None);
     }
+
+    #[test]
+    fn test_parse_grounding_citations() {
+        let metadata = json!({
+            "groundingChunks": [
+                {"web": {"uri": "https://example.com/a", "title": "a.com"}},
+                {"web": {"uri": "https://example.com/b", "title": "b.com"}}
+            ],
+            "groundingSupports": [{
+                "segment": {"startIndex": 0, "endIndex": 12, "text": "Spain won."},
+                "groundingChunkIndices": [0]
+            }]
+        });
+        let citations = parse_grounding_citations(&metadata);
+        assert_eq!(citations.len(), 2);
+        assert_eq!(citations[0].url.as_deref(), Some("https://example.com/a"));
+        assert_eq!(citations[0].cited_text.as_deref(), Some("Spain won."));
+        assert_eq!(citations[0].end_index, Some(12));
+        // Unreferenced chunk is still reported as a source
+        assert_eq!(citations[1].title.as_deref(), Some("b.com"));
+        assert!(citations[1].start_index.is_none());
+    }
 }
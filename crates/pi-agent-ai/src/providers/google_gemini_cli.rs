@@ -29,8 +29,12 @@ use pi_agent_core::sanitize::sanitize_surrogates;
 use pi_agent_core::transform::transform_messages;
 use pi_agent_core::types::*;
 
+use super::google_shared::{apply_google_sampling, google_tool_config};
+use crate::http_client::{describe_error, http_clients};
 use crate::models::calculate_cost;
+use crate::rate_limit::{RequestError, send_rate_limited};
 use crate::registry::ApiProvider;
+use crate::response_format::{apply_google_response_format, json_format};
 use crate::simple_options::{adjust_max_tokens_for_thinking, build_base_options, clamp_reasoning};
 
 // ---------- Constants ----------
@@ -589,6 +593,10 @@ pub fn build_request(
     if let Some(mt) = options.base.max_tokens {
         generation_config["maxOutputTokens"] = json!(mt);
     }
+    apply_google_sampling(&options.base, &mut generation_config);
+    if let Some(format) = json_format(options.base.response_format.as_ref()) {
+        apply_google_response_format(format, &mut generation_config);
+    }
 
     if options.thinking_enabled && model.reasoning {
         let mut tc = json!({ "includeThoughts": true });
@@ -621,6 +629,8 @@ pub fn build_request(
             if let Some(choice) = &options.tool_choice {
                 request["toolConfig"] =
                     json!({ "functionCallingConfig": { "mode": map_tool_choice(choice) } });
+            } else if let Some(choice) = &options.base.tool_choice {
+                request["toolConfig"] = google_tool_config(choice);
             }
         }
     }
@@ -729,7 +739,7 @@ async fn stream_response_owned(
 
         let chunk = match chunk_result {
             Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
-            Err(e) => return Err(format!("Stream error: {e}")),
+            Err(e) => return Err(format!("Stream error: {}", describe_error(&e))),
         };
 
         sse_buffer.push_str(&chunk);
@@ -837,6 +847,7 @@ async fn stream_response_owned(
                                     output.content.push(ContentBlock::Text(TextContent {
                                         text: String::new(),
                                         text_signature: None,
+                                        citations: None,
                                     }));
                                     if !started {
                                         stream.push(AssistantMessageEvent::Start {
@@ -1176,7 +1187,7 @@ async fn run_stream(
 
     let body_json =
         serde_json::to_string(&request_body).map_err(|e| format!("Serialize error: {e}"))?;
-    let client = reqwest::Client::new();
+    let client = http_clients().client(&model.provider, &endpoints[0]);
 
     // --- Initial fetch with retries ---
     let mut ok_response: Option<reqwest::Response> = None;
@@ -1201,8 +1212,16 @@ async fn run_stream(
         }
         req = req.body(body_json.clone());
 
-        match req.send().await {
-            Ok(resp) => {
+        match send_rate_limited(
+            &model.provider,
+            req,
+            options.base.max_retry_delay_ms,
+            cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
                 if resp.status().is_success() {
                     ok_response = Some(resp);
                     break;
@@ -1235,7 +1254,7 @@ async fn run_stream(
                     extract_error_message(&et)
                 ));
             }
-            Err(e) => {
+            Err(RequestError::Http(e)) => {
                 last_err = Some(if let Some(src) = e.source() {
                     format!("Network error: {src}")
                 } else {
@@ -1247,6 +1266,7 @@ async fn run_stream(
                 }
                 return Err(last_err.unwrap());
             }
+            Err(e) => return Err(e.to_string()),
         }
     }
 
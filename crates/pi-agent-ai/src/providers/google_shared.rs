@@ -1,6 +1,11 @@
 //! Google shared utilities for OAuth and API access across Google providers
 //! (Google Generative AI, Google Vertex, Google Gemini CLI, Google Antigravity).
 
+use std::collections::HashSet;
+
+use pi_agent_core::types::{Citation, StreamOptions, ToolChoice};
+use serde_json::{Value, json};
+
 /// Google OAuth scopes.
 pub mod scopes {
     pub const GENERATIVE_LANGUAGE: &str = "https://www.googleapis.com/auth/generative-language";
@@ -37,3 +42,106 @@ pub fn is_google_model(model_id: &str) -> bool {
         || id.starts_with("google/")
         || id.starts_with("google-")
 }
+
+/// Apply the neutral sampling options to a Gemini `generationConfig` object.
+/// Gemini supports all of them.
+pub fn apply_google_sampling(options: &StreamOptions, generation_config: &mut Value) {
+    if let Some(top_p) = options.top_p {
+        generation_config["topP"] = json!(top_p);
+    }
+    if let Some(top_k) = options.top_k {
+        generation_config["topK"] = json!(top_k);
+    }
+    if let Some(stop) = &options.stop_sequences {
+        generation_config["stopSequences"] = json!(stop);
+    }
+    if let Some(seed) = options.seed {
+        generation_config["seed"] = json!(seed);
+    }
+    if let Some(penalty) = options.frequency_penalty {
+        generation_config["frequencyPenalty"] = json!(penalty);
+    }
+    if let Some(penalty) = options.presence_penalty {
+        generation_config["presencePenalty"] = json!(penalty);
+    }
+}
+
+/// Build a Gemini `toolConfig` from a provider-neutral tool choice. A named
+/// tool maps to mode `ANY` restricted to that function.
+pub fn google_tool_config(choice: &ToolChoice) -> Value {
+    let function_calling_config = match choice {
+        ToolChoice::Auto => json!({"mode": "AUTO"}),
+        ToolChoice::None => json!({"mode": "NONE"}),
+        ToolChoice::Required => json!({"mode": "ANY"}),
+        ToolChoice::Tool(name) => json!({"mode": "ANY", "allowedFunctionNames": [name]}),
+    };
+    json!({ "functionCallingConfig": function_calling_config })
+}
+
+/// Convert a candidate's `groundingMetadata` into citations.
+///
+/// Each grounding support yields one citation per referenced chunk, carrying
+/// the supported segment's offsets in the response text. Chunks that no
+/// support references are still reported as plain source citations.
+pub fn parse_grounding_citations(metadata: &Value) -> Vec<Citation> {
+    let chunks: Vec<&Value> = metadata
+        .get("groundingChunks")
+        .and_then(|c| c.as_array())
+        .map(|arr| arr.iter().collect())
+        .unwrap_or_default();
+    let chunk_citation = |index: usize| -> Option<Citation> {
+        let chunk = chunks.get(index)?;
+        let source = chunk.get("web").or_else(|| chunk.get("retrievedContext"))?;
+        Some(Citation {
+            url: source.get("uri").and_then(|v| v.as_str()).map(String::from),
+            title: source
+                .get("title")
+                .and_then(|v| v.as_str())
+                .map(String::from),
+            provider_data: Some((*chunk).clone()),
+            ..Default::default()
+        })
+    };
+
+    let mut citations = Vec::new();
+    let mut referenced = HashSet::new();
+    let supports = metadata
+        .get("groundingSupports")
+        .and_then(|s| s.as_array())
+        .into_iter()
+        .flatten();
+    for support in supports {
+        let segment = support.get("segment");
+        let indices = support
+            .get("groundingChunkIndices")
+            .and_then(|i| i.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|i| i.as_u64());
+        for index in indices {
+            let Some(mut citation) = chunk_citation(index as usize) else {
+                continue;
+            };
+            referenced.insert(index as usize);
+            citation.cited_text = segment
+                .and_then(|s| s.get("text"))
+                .and_then(|t| t.as_str())
+                .map(String::from);
+            citation.start_index = segment
+                .and_then(|s| s.get("startIndex"))
+                .and_then(|v| v.as_u64());
+            citation.end_index = segment
+                .and_then(|s| s.get("endIndex"))
+                .and_then(|v| v.as_u64());
+            citations.push(citation);
+        }
+    }
+    for index in 0..chunks.len() {
+        if !referenced.contains(&index)
+            && let Some(citation) = chunk_citation(index)
+        {
+            citations.push(citation);
+        }
+    }
+    citations
+}
@@ -12,8 +12,13 @@ use pi_agent_core::sanitize::sanitize_surrogates;
 use pi_agent_core::transform::transform_messages;
 use pi_agent_core::types::*;
 
+use super::google_shared::{apply_google_sampling, google_tool_config, parse_grounding_citations};
+use crate::google_auth::google_auth;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::calculate_cost;
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
+use crate::response_format::{apply_google_response_format, json_format};
 use crate::simple_options::{build_base_options, clamp_reasoning};
 
 // ---------- Tool call counter ----------
@@ -440,6 +445,10 @@ fn resolve_project(options: &GoogleVertexOptions) -> Result<String, String> {
         }
     }
 
+    if let Some(project) = google_auth().project_id() {
+        return Ok(project);
+    }
+
     Err(
         "Vertex AI requires a project ID. Set GOOGLE_CLOUD_PROJECT/GCLOUD_PROJECT or pass project in options."
             .to_string(),
@@ -467,8 +476,11 @@ fn resolve_location(options: &GoogleVertexOptions) -> Result<String, String> {
 
 // ---------- Obtain an access token for Vertex AI ----------
 
-/// Attempts to get a Google Cloud access token via `gcloud auth print-access-token`.
-/// Falls back to checking for an explicit key in the options/headers.
+/// Get a Google Cloud access token for Vertex AI.
+///
+/// Uses an explicit token from the options/headers, then Application Default
+/// Credentials (see [`crate::google_auth`]), and finally
+/// `gcloud auth print-access-token` when no ADC file is configured.
 async fn get_access_token(options: &GoogleVertexOptions) -> Result<String, String> {
     // Check if an explicit access token was provided in headers
     if let Some(headers) = &options.base.headers {
@@ -491,6 +503,10 @@ async fn get_access_token(options: &GoogleVertexOptions) -> Result<String, Strin
         }
     }
 
+    if let Some(token) = google_auth().access_token().await? {
+        return Ok(token);
+    }
+
     // Try to get an access token via gcloud CLI (blocking call, use spawn_blocking)
     let result = tokio::task::spawn_blocking(|| {
         std::process::Command::new("gcloud")
@@ -534,6 +550,10 @@ fn build_params(model: &Model, context: &Context, options: &GoogleVertexOptions)
     if let Some(max_tokens) = options.base.max_tokens {
         generation_config["maxOutputTokens"] = json!(max_tokens);
     }
+    apply_google_sampling(&options.base, &mut generation_config);
+    if let Some(format) = json_format(options.base.response_format.as_ref()) {
+        apply_google_response_format(format, &mut generation_config);
+    }
 
     let mut config: Value = json!({});
 
@@ -563,6 +583,8 @@ fn build_params(model: &Model, context: &Context, options: &GoogleVertexOptions)
                         "mode": map_tool_choice(choice)
                     }
                 });
+            } else if let Some(choice) = &options.base.tool_choice {
+                config["toolConfig"] = google_tool_config(choice);
             }
         }
     }
@@ -790,19 +812,34 @@ pub fn stream_google_vertex(
         let params = build_params(&model, &context, &options);
         let url = build_stream_url(&model, &project, &location);
 
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &url);
         let mut request = client.post(&url);
         for (k, v) in &headers {
             request = request.header(k.as_str(), v.as_str());
         }
 
-        let response = match request.json(&params).send().await {
-            Ok(resp) => resp,
+        let response = match send_rate_limited(
+            &model.provider,
+            request.json(&params),
+            options.base.max_retry_delay_ms,
+            &cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
+                resp
+            }
             Err(e) => {
-                output.stop_reason = StopReason::Error;
+                let reason = if e.is_aborted() {
+                    StopReason::Aborted
+                } else {
+                    StopReason::Error
+                };
+                output.stop_reason = reason.clone();
                 output.error_message = Some(format!("HTTP error: {e}"));
                 stream_clone.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
+                    reason,
                     error: output,
                 });
                 return;
@@ -865,7 +902,7 @@ pub fn stream_google_vertex(
                 Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
@@ -973,6 +1010,7 @@ pub fn stream_google_vertex(
                                             output.content.push(ContentBlock::Text(TextContent {
                                                 text: String::new(),
                                                 text_signature: None,
+                                                citations: None,
                                             }));
                                             let ci = block_index(&output);
                                             stream_clone.push(AssistantMessageEvent::TextStart {
@@ -1126,6 +1164,20 @@ pub fn stream_google_vertex(
                             }
                         }
 
+                        // Attach grounding sources to the most recent text block
+                        if let Some(metadata) = candidate.get("groundingMetadata") {
+                            let citations = parse_grounding_citations(metadata);
+                            if !citations.is_empty()
+                                && let Some(ContentBlock::Text(t)) = output
+                                    .content
+                                    .iter_mut()
+                                    .rev()
+                                    .find(|b| matches!(b, ContentBlock::Text(_)))
+                            {
+                                t.citations = Some(citations);
+                            }
+                        }
+
                         // Handle finish reason
                         if let Some(reason) = candidate.get("finishReason").and_then(|r| r.as_str())
                         {
@@ -1754,6 +1806,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Hello world".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: "google-vertex".to_string(),
                 provider: "google-vertex".to_string(),
@@ -1762,6 +1815,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1782,6 +1836,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Found results".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: None,
                 is_error: false,
@@ -1883,6 +1938,7 @@ mod tests {
                     ContentBlock::Text(TextContent {
                         text: "Here is the answer".to_string(),
                         text_signature: None,
+                        citations: None,
                     }),
                 ],
                 api: "google-vertex".to_string(),
@@ -1892,6 +1948,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1919,6 +1976,7 @@ mod tests {
                     ContentBlock::Text(TextContent {
                         text: "Answer".to_string(),
                         text_signature: None,
+                        citations: None,
                     }),
                 ],
                 api: "anthropic-messages".to_string(),
@@ -1928,6 +1986,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })],
             tools: None,
         };
@@ -1,6 +1,7 @@
 pub mod anthropic;
 pub mod azure_openai_responses;
 pub mod bedrock;
+pub mod faux;
 pub mod github_copilot_headers;
 pub mod google;
 pub mod google_gemini_cli;
@@ -10,3 +11,4 @@ pub mod openai;
 pub mod openai_codex_responses;
 pub mod openai_responses;
 pub mod openai_responses_shared;
+pub mod replay;
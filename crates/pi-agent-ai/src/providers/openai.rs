@@ -14,9 +14,12 @@ use pi_agent_core::types::*;
 
 use super::github_copilot_headers::{build_copilot_dynamic_headers, has_copilot_vision_input};
 use crate::env_keys::get_env_api_key;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::{calculate_cost, supports_xhigh};
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
-use crate::simple_options::{build_base_options, clamp_reasoning};
+use crate::response_format::{json_format, openai_completions_response_format};
+use crate::simple_options::{build_base_options, clamp_reasoning, warn_unsupported_options};
 use crate::sse::SseParser;
 
 // ---------- Resolved compat (all fields required) ----------
@@ -647,6 +650,15 @@ fn maybe_add_openrouter_anthropic_cache_control(model: &Model, messages: &mut [V
 
 // ---------- Build request params ----------
 
+fn convert_tool_choice(choice: &ToolChoice) -> Value {
+    match choice {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool(name) => json!({"type": "function", "function": {"name": name}}),
+    }
+}
+
 fn build_params(model: &Model, context: &Context, options: &OpenAICompletionsOptions) -> Value {
     let compat = get_compat(model);
     let mut messages = convert_messages(model, context, &compat);
@@ -677,6 +689,32 @@ fn build_params(model: &Model, context: &Context, options: &OpenAICompletionsOpt
     if let Some(temp) = options.base.temperature {
         params["temperature"] = json!(temp);
     }
+    if let Some(top_p) = options.base.top_p {
+        params["top_p"] = json!(top_p);
+    }
+    if let Some(stop) = &options.base.stop_sequences {
+        params["stop"] = json!(stop);
+    }
+    if let Some(seed) = options.base.seed {
+        params["seed"] = json!(seed);
+    }
+    if let Some(penalty) = options.base.frequency_penalty {
+        params["frequency_penalty"] = json!(penalty);
+    }
+    if let Some(penalty) = options.base.presence_penalty {
+        params["presence_penalty"] = json!(penalty);
+    }
+    warn_unsupported_options(
+        model,
+        &options.base,
+        &[
+            "topP",
+            "stopSequences",
+            "seed",
+            "frequencyPenalty",
+            "presencePenalty",
+        ],
+    );
 
     if let Some(tools) = &context.tools {
         if !tools.is_empty() {
@@ -689,6 +727,14 @@ fn build_params(model: &Model, context: &Context, options: &OpenAICompletionsOpt
 
     if let Some(tc) = &options.tool_choice {
         params["tool_choice"] = tc.clone();
+    } else if let Some(choice) = &options.base.tool_choice
+        && context.tools.as_ref().is_some_and(|t| !t.is_empty())
+    {
+        params["tool_choice"] = convert_tool_choice(choice);
+    }
+
+    if let Some(format) = json_format(options.base.response_format.as_ref()) {
+        params["response_format"] = openai_completions_response_format(format);
     }
 
     // Thinking format handling
@@ -831,19 +877,34 @@ pub fn stream_openai_completions(
             }
         };
 
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &model.base_url);
         let mut request = client.post(&url);
         for (k, v) in &headers {
             request = request.header(k.as_str(), v.as_str());
         }
 
-        let response = match request.json(&params).send().await {
-            Ok(resp) => resp,
+        let response = match send_rate_limited(
+            &model.provider,
+            request.json(&params),
+            options.base.max_retry_delay_ms,
+            &cancel,
+        )
+        .await
+        {
+            Ok((resp, rate_limit)) => {
+                output.rate_limit = rate_limit;
+                resp
+            }
             Err(e) => {
-                output.stop_reason = StopReason::Error;
+                let reason = if e.is_aborted() {
+                    StopReason::Aborted
+                } else {
+                    StopReason::Error
+                };
+                output.stop_reason = reason.clone();
                 output.error_message = Some(format!("HTTP error: {e}"));
                 stream_clone.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
+                    reason,
                     error: output,
                 });
                 return;
@@ -955,7 +1016,7 @@ pub fn stream_openai_completions(
                 Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
@@ -1068,6 +1129,7 @@ pub fn stream_openai_completions(
                             output.content.push(ContentBlock::Text(TextContent {
                                 text: String::new(),
                                 text_signature: None,
+                                citations: None,
                             }));
                             let ci = output.content.len() - 1;
                             current_block = Some(CurrentBlock::Text(ci));
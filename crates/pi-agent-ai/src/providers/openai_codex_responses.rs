@@ -10,14 +10,18 @@ use pi_agent_core::event_stream::{
 use pi_agent_core::types::*;
 
 use crate::env_keys::get_env_api_key;
+use crate::http_client::{describe_error, http_clients};
 use crate::models::supports_xhigh;
+use crate::rate_limit::send_rate_limited;
 use crate::registry::ApiProvider;
+use crate::response_format::{json_format, openai_responses_text_format};
 use crate::simple_options::{build_base_options, clamp_reasoning};
 use crate::sse::SseParser;
 
 use super::openai_responses_shared::{
-    ConvertResponsesMessagesOptions, ConvertResponsesToolsOptions, convert_responses_messages,
-    convert_responses_tools, process_responses_events,
+    ConvertResponsesMessagesOptions, ConvertResponsesToolsOptions, apply_responses_sampling,
+    convert_responses_messages, convert_responses_tool_choice, convert_responses_tools,
+    process_responses_events,
 };
 
 // =============================================================================
@@ -378,6 +382,20 @@ fn build_request_body(
         body["tools"] = json!(convert_responses_tools(tools, Some(&tool_opts)));
     }
 
+    if let Some(choice) = options.and_then(|o| o.base.tool_choice.as_ref())
+        && context.tools.as_ref().is_some_and(|t| !t.is_empty())
+    {
+        body["tool_choice"] = convert_responses_tool_choice(choice);
+    }
+
+    if let Some(opts) = options {
+        apply_responses_sampling(model, &opts.base, &mut body);
+    }
+
+    if let Some(format) = options.and_then(|o| json_format(o.base.response_format.as_ref())) {
+        body["text"]["format"] = openai_responses_text_format(format);
+    }
+
     if let Some(opts) = options {
         if let Some(effort) = &opts.reasoning_effort {
             let clamped = clamp_reasoning_effort(&model.id, effort);
@@ -504,7 +522,7 @@ pub fn stream_openai_codex_responses(
         let url = resolve_codex_url(Some(&model.base_url));
 
         // Retry logic
-        let client = reqwest::Client::new();
+        let client = http_clients().client(&model.provider, &url);
         let mut response: Option<reqwest::Response> = None;
         let mut last_error: Option<String> = None;
 
@@ -525,8 +543,16 @@ pub fn stream_openai_codex_responses(
             }
             request = request.body(body_json.clone());
 
-            match request.send().await {
-                Ok(resp) => {
+            match send_rate_limited(
+                &model.provider,
+                request,
+                options.base.max_retry_delay_ms,
+                &cancel,
+            )
+            .await
+            {
+                Ok((resp, rate_limit)) => {
+                    output.rate_limit = rate_limit;
                     if resp.status().is_success() {
                         response = Some(resp);
                         break;
@@ -555,7 +581,7 @@ pub fn stream_openai_codex_responses(
                 }
                 Err(e) => {
                     let err_msg = e.to_string();
-                    if err_msg.contains("aborted") || err_msg.contains("Request was aborted") {
+                    if e.is_aborted() || err_msg.contains("aborted") {
                         output.stop_reason = StopReason::Aborted;
                         output.error_message = Some("Request was aborted".to_string());
                         stream_clone.push(AssistantMessageEvent::Error {
@@ -623,7 +649,7 @@ pub fn stream_openai_codex_responses(
                 Ok(bytes) => String::from_utf8_lossy(&bytes).to_string(),
                 Err(e) => {
                     output.stop_reason = StopReason::Error;
-                    output.error_message = Some(format!("Stream error: {e}"));
+                    output.error_message = Some(format!("Stream error: {}", describe_error(&e)));
                     stream_clone.push(AssistantMessageEvent::Error {
                         reason: StopReason::Error,
                         error: output,
@@ -9,6 +9,7 @@ use pi_agent_core::transform::transform_messages;
 use pi_agent_core::types::*;
 
 use crate::models::calculate_cost;
+use crate::simple_options::warn_unsupported_options;
 
 // =============================================================================
 // Utilities
@@ -229,10 +230,11 @@ pub fn convert_responses_messages(
                                 msg_id =
                                     Some(format!("msg_{}", short_hash(msg_id.as_ref().unwrap())));
                             }
+                            let annotations = convert_annotations(t.citations.as_deref());
                             output.push(json!({
                                 "type": "message",
                                 "role": "assistant",
-                                "content": [{"type": "output_text", "text": sanitize_surrogates(&t.text), "annotations": []}],
+                                "content": [{"type": "output_text", "text": sanitize_surrogates(&t.text), "annotations": annotations}],
                                 "status": "completed",
                                 "id": msg_id.unwrap_or_default()
                             }));
@@ -352,6 +354,62 @@ pub fn convert_responses_tools(
         .collect()
 }
 
+/// Convert a provider-neutral tool choice to the Responses API `tool_choice`.
+pub fn convert_responses_tool_choice(choice: &ToolChoice) -> Value {
+    match choice {
+        ToolChoice::Auto => json!("auto"),
+        ToolChoice::None => json!("none"),
+        ToolChoice::Required => json!("required"),
+        ToolChoice::Tool(name) => json!({"type": "function", "name": name}),
+    }
+}
+
+// =============================================================================
+// Sampling options
+// =============================================================================
+
+/// Apply the sampling options the Responses API accepts. Only `top_p` is
+/// supported, and reasoning models reject it.
+pub fn apply_responses_sampling(model: &Model, options: &StreamOptions, params: &mut Value) {
+    if let Some(top_p) = options.top_p {
+        if model.reasoning {
+            tracing::warn!("{} is a reasoning model; ignoring `topP`", model.id);
+        } else {
+            params["top_p"] = json!(top_p);
+        }
+    }
+    warn_unsupported_options(model, options, &["topP"]);
+}
+
+// =============================================================================
+// Annotations
+// =============================================================================
+
+/// Map an `output_text` annotation (`url_citation`, `file_citation`, ...) to a `Citation`.
+fn parse_annotation(value: &Value) -> Citation {
+    let get_str = |key: &str| value.get(key).and_then(|v| v.as_str()).map(String::from);
+    Citation {
+        url: get_str("url"),
+        title: get_str("title").or_else(|| get_str("filename")),
+        cited_text: None,
+        document_index: value.get("index").and_then(|v| v.as_u64()),
+        start_index: value.get("start_index").and_then(|v| v.as_u64()),
+        end_index: value.get("end_index").and_then(|v| v.as_u64()),
+        provider_data: Some(value.clone()),
+    }
+}
+
+/// Replay annotations for citations that originated from a Responses API;
+/// anything else is sent as an empty annotation list.
+fn convert_annotations(citations: Option<&[Citation]>) -> Value {
+    let raw: Option<Vec<Value>> = citations
+        .unwrap_or_default()
+        .iter()
+        .map(|c| c.provider_data.clone())
+        .collect();
+    json!(raw.unwrap_or_default())
+}
+
 // =============================================================================
 // Stream processing
 // =============================================================================
@@ -429,6 +487,7 @@ pub fn process_responses_events(
                         output.content.push(ContentBlock::Text(TextContent {
                             text: String::new(),
                             text_signature: None,
+                            citations: None,
                         }));
                         stream.push(AssistantMessageEvent::TextStart {
                             content_index: block_index,
@@ -770,10 +829,21 @@ pub fn process_responses_events(
                                 .and_then(|v| v.as_str())
                                 .map(|s| s.to_string());
 
+                            let citations: Vec<Citation> = item
+                                .get("content")
+                                .and_then(|v| v.as_array())
+                                .into_iter()
+                                .flatten()
+                                .filter_map(|c| c.get("annotations").and_then(|a| a.as_array()))
+                                .flatten()
+                                .map(parse_annotation)
+                                .collect();
+
                             if let Some(ContentBlock::Text(t)) = output.content.get_mut(block_index)
                             {
                                 t.text = final_text.clone();
                                 t.text_signature = item_id;
+                                t.citations = (!citations.is_empty()).then_some(citations);
                             }
                             stream.push(AssistantMessageEvent::TextEnd {
                                 content_index: block_index,
@@ -960,6 +1030,18 @@ pub fn map_stop_reason(status: &str) -> StopReason {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_convert_responses_tool_choice() {
+        assert_eq!(
+            convert_responses_tool_choice(&ToolChoice::Required),
+            json!("required")
+        );
+        assert_eq!(
+            convert_responses_tool_choice(&ToolChoice::Tool("bash".to_string())),
+            json!({"type": "function", "name": "bash"})
+        );
+    }
+
     #[test]
     fn test_short_hash_deterministic() {
         let h1 = short_hash("hello world");
@@ -1223,4 +1305,55 @@ mod tests {
         let result = convert_responses_messages(&model, &context, &providers, Some(&opts));
         assert!(result.is_empty());
     }
+
+    #[test]
+    fn test_annotations_round_trip_as_citations() {
+        let annotation = json!({
+            "type": "url_citation",
+            "url": "https://www.rust-lang.org",
+            "title": "Rust",
+            "start_index": 0,
+            "end_index": 4
+        });
+        let citation = parse_annotation(&annotation);
+        assert_eq!(citation.url.as_deref(), Some("https://www.rust-lang.org"));
+        assert_eq!(citation.end_index, Some(4));
+
+        let model = Model {
+            id: "gpt-4o".to_string(),
+            name: "GPT-4o".to_string(),
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            base_url: "https://api.openai.com".to_string(),
+            reasoning: false,
+            input: vec!["text".to_string()],
+            cost: ModelCost::default(),
+            context_window: 128000,
+            max_tokens: 4096,
+            headers: None,
+            compat: None,
+        };
+        let context = Context {
+            system_prompt: None,
+            messages: vec![Message::Assistant(AssistantMessage {
+                content: vec![ContentBlock::Text(TextContent {
+                    text: "Rust".to_string(),
+                    text_signature: Some("msg_abc".to_string()),
+                    citations: Some(vec![citation]),
+                })],
+                api: "openai-responses".to_string(),
+                provider: "openai".to_string(),
+                model: "gpt-4o".to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 0,
+                rate_limit: None,
+            })],
+            tools: None,
+        };
+        let providers: HashSet<&str> = ["openai"].into_iter().collect();
+        let result = convert_responses_messages(&model, &context, &providers, None);
+        assert_eq!(result[0]["content"][0]["annotations"][0], annotation);
+    }
 }
@@ -0,0 +1,476 @@
+//! Record/replay provider for deterministic, offline tests.
+//!
+//! In record mode [`ReplayProvider`] wraps a real provider, forwards its
+//! events unchanged and appends each request context and response to a JSON
+//! fixture file. In replay mode it answers from the fixture instead: requests
+//! are matched by [`request_fingerprint`], and in strict mode a request with
+//! no unused recording fails with an error event.
+
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use futures::StreamExt;
+use pi_agent_core::event_stream::{
+    AssistantMessageEventStream, create_assistant_message_event_stream,
+};
+use pi_agent_core::proxy::{ProxyAssistantMessageEvent, ProxyMessageBuilder};
+use pi_agent_core::types::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use tokio_util::sync::CancellationToken;
+
+use crate::registry::ApiProvider;
+
+/// One recorded request and its response.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayInteraction {
+    pub fingerprint: String,
+    pub model: String,
+    pub context: Context,
+    pub events: Vec<ProxyAssistantMessageEvent>,
+    /// Final message, replayed verbatim with the terminal event.
+    pub message: AssistantMessage,
+}
+
+/// Contents of a fixture file.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ReplayFixture {
+    pub interactions: Vec<ReplayInteraction>,
+}
+
+impl ReplayFixture {
+    pub fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("Failed to read fixture {}: {e}", path.display()))?;
+        serde_json::from_str(&content)
+            .map_err(|e| format!("Invalid fixture {}: {e}", path.display()))
+    }
+
+    /// Write the fixture atomically.
+    pub fn save(&self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        let json = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json).map_err(|e| e.to_string())?;
+        std::fs::rename(&tmp, path).map_err(|e| e.to_string())
+    }
+}
+
+/// Stable hash of a request: api, provider, model id and context, with
+/// timestamps removed so re-running a scenario yields the same fingerprint.
+pub fn request_fingerprint(model: &Model, context: &Context) -> String {
+    let mut context = serde_json::to_value(context).unwrap_or(Value::Null);
+    strip_timestamps(&mut context);
+    let request = serde_json::json!({
+        "api": model.api,
+        "provider": model.provider,
+        "model": model.id,
+        "context": context,
+    });
+    hex::encode(Sha256::digest(request.to_string().as_bytes()))
+}
+
+fn strip_timestamps(value: &mut Value) {
+    match value {
+        Value::Object(map) => {
+            map.remove("timestamp");
+            map.values_mut().for_each(strip_timestamps);
+        }
+        Value::Array(items) => items.iter_mut().for_each(strip_timestamps),
+        _ => {}
+    }
+}
+
+enum Mode {
+    Record(Arc<dyn ApiProvider>),
+    Replay { strict: bool },
+}
+
+struct ReplayState {
+    fixture: ReplayFixture,
+    used: Vec<bool>,
+}
+
+impl ReplayState {
+    /// Pick the recording for a request: the first unused one with the same
+    /// fingerprint; outside strict mode, the last used match or the next
+    /// unused recording in order.
+    fn take(&mut self, fingerprint: &str, strict: bool) -> Option<ReplayInteraction> {
+        let interactions = &self.fixture.interactions;
+        let matching = |i: &usize| interactions[*i].fingerprint == fingerprint;
+        let index = (0..interactions.len())
+            .filter(matching)
+            .find(|i| !self.used[*i])
+            .or_else(|| {
+                if strict {
+                    return None;
+                }
+                (0..interactions.len())
+                    .rev()
+                    .find(matching)
+                    .or_else(|| (0..interactions.len()).find(|i| !self.used[*i]))
+            })?;
+        self.used[index] = true;
+        Some(interactions[index].clone())
+    }
+}
+
+/// Provider that records responses to, or replays them from, a fixture file.
+pub struct ReplayProvider {
+    api: String,
+    mode: Mode,
+    path: PathBuf,
+    state: Arc<Mutex<ReplayState>>,
+}
+
+impl ReplayProvider {
+    /// Record `inner`'s responses to `path`, replacing any previous recording.
+    pub fn record(inner: Arc<dyn ApiProvider>, path: impl Into<PathBuf>) -> Self {
+        Self {
+            api: inner.api().to_string(),
+            mode: Mode::Record(inner),
+            path: path.into(),
+            state: Arc::new(Mutex::new(ReplayState {
+                fixture: ReplayFixture::default(),
+                used: Vec::new(),
+            })),
+        }
+    }
+
+    /// Replay responses from `path`, registered under `api`.
+    pub fn replay(api: &str, path: impl Into<PathBuf>) -> Result<Self, String> {
+        let path = path.into();
+        let fixture = ReplayFixture::load(&path)?;
+        let used = vec![false; fixture.interactions.len()];
+        Ok(Self {
+            api: api.to_string(),
+            mode: Mode::Replay { strict: false },
+            path,
+            state: Arc::new(Mutex::new(ReplayState { fixture, used })),
+        })
+    }
+
+    /// Fail requests that have no unused recording with a matching fingerprint.
+    pub fn strict(mut self, strict: bool) -> Self {
+        if let Mode::Replay { strict: s } = &mut self.mode {
+            *s = strict;
+        }
+        self
+    }
+
+    /// Recordings not yet replayed (or, when recording, always zero).
+    pub fn unused(&self) -> usize {
+        let state = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        state.used.iter().filter(|used| !**used).count()
+    }
+
+    /// Forward `inner` and append the exchange to the fixture on completion.
+    fn record_stream(
+        &self,
+        model: &Model,
+        context: &Context,
+        mut inner: AssistantMessageEventStream,
+    ) -> AssistantMessageEventStream {
+        let out = create_assistant_message_event_stream();
+        let out_clone = out.clone();
+        let state = self.state.clone();
+        let path = self.path.clone();
+        let fingerprint = request_fingerprint(model, context);
+        let model_id = model.id.clone();
+        let context = context.clone();
+
+        tokio::spawn(async move {
+            let mut events = Vec::new();
+            while let Some(event) = inner.next().await {
+                events.push(ProxyAssistantMessageEvent::from_event(&event));
+                let message = match &event {
+                    AssistantMessageEvent::Done { message, .. } => Some(message.clone()),
+                    AssistantMessageEvent::Error { error, .. } => Some(error.clone()),
+                    _ => None,
+                };
+                out_clone.push(event);
+                if let Some(message) = message {
+                    let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+                    state.fixture.interactions.push(ReplayInteraction {
+                        fingerprint,
+                        model: model_id,
+                        context,
+                        events,
+                        message,
+                    });
+                    if let Err(e) = state.fixture.save(&path) {
+                        tracing::warn!("Failed to save replay fixture: {e}");
+                    }
+                    return;
+                }
+            }
+            out_clone.end(None);
+        });
+
+        out
+    }
+
+    fn replay_stream(
+        &self,
+        model: &Model,
+        context: &Context,
+        strict: bool,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        let stream = create_assistant_message_event_stream();
+        let fingerprint = request_fingerprint(model, context);
+        let interaction = self
+            .state
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .take(&fingerprint, strict);
+
+        let Some(interaction) = interaction else {
+            let mut error = AssistantMessage::empty(model);
+            error.stop_reason = StopReason::Error;
+            error.error_message = Some(format!(
+                "Replay: no recorded response for request {fingerprint} (model {})",
+                model.id
+            ));
+            stream.push(AssistantMessageEvent::Error {
+                reason: StopReason::Error,
+                error,
+            });
+            return stream;
+        };
+
+        let mut builder = ProxyMessageBuilder::new(model);
+        for event in interaction.events {
+            if cancel.is_cancelled() {
+                let mut error = builder.partial().clone();
+                error.stop_reason = StopReason::Aborted;
+                error.error_message = Some("Request was aborted".to_string());
+                stream.push(AssistantMessageEvent::Error {
+                    reason: StopReason::Aborted,
+                    error,
+                });
+                return stream;
+            }
+            let Some(event) = builder.apply(event) else {
+                continue;
+            };
+            stream.push(match event {
+                AssistantMessageEvent::Done { reason, .. } => AssistantMessageEvent::Done {
+                    reason,
+                    message: interaction.message.clone(),
+                },
+                AssistantMessageEvent::Error { reason, .. } => AssistantMessageEvent::Error {
+                    reason,
+                    error: interaction.message.clone(),
+                },
+                event => event,
+            });
+        }
+        if !stream.is_done() {
+            stream.end(None);
+        }
+        stream
+    }
+}
+
+impl ApiProvider for ReplayProvider {
+    fn api(&self) -> &str {
+        &self.api
+    }
+
+    fn stream(
+        &self,
+        model: &Model,
+        context: &Context,
+        options: &StreamOptions,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        match &self.mode {
+            Mode::Record(inner) => {
+                let stream = inner.stream(model, context, options, cancel);
+                self.record_stream(model, context, stream)
+            }
+            Mode::Replay { strict } => self.replay_stream(model, context, *strict, cancel),
+        }
+    }
+
+    fn stream_simple(
+        &self,
+        model: &Model,
+        context: &Context,
+        options: &SimpleStreamOptions,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        match &self.mode {
+            Mode::Record(inner) => {
+                let stream = inner.stream_simple(model, context, options, cancel);
+                self.record_stream(model, context, stream)
+            }
+            Mode::Replay { strict } => self.replay_stream(model, context, *strict, cancel),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Answers every request with a tool call followed by "echo: <last user text>".
+    struct EchoProvider;
+
+    impl ApiProvider for EchoProvider {
+        fn api(&self) -> &str {
+            "echo"
+        }
+
+        fn stream(
+            &self,
+            model: &Model,
+            context: &Context,
+            options: &StreamOptions,
+            cancel: CancellationToken,
+        ) -> AssistantMessageEventStream {
+            let simple = SimpleStreamOptions {
+                base: options.clone(),
+                reasoning: None,
+                thinking_budgets: None,
+            };
+            self.stream_simple(model, context, &simple, cancel)
+        }
+
+        fn stream_simple(
+            &self,
+            model: &Model,
+            context: &Context,
+            _options: &SimpleStreamOptions,
+            _cancel: CancellationToken,
+        ) -> AssistantMessageEventStream {
+            let text = match context.messages.last() {
+                Some(Message::User(user)) => match &user.content {
+                    UserContent::Text(text) => text.clone(),
+                    UserContent::Blocks(_) => String::new(),
+                },
+                _ => String::new(),
+            };
+            let stream = create_assistant_message_event_stream();
+            let mut message = AssistantMessage::empty(model);
+            stream.push(AssistantMessageEvent::Start {
+                partial: message.clone(),
+            });
+            message.content.push(ContentBlock::Text(TextContent {
+                text: format!("echo: {text}"),
+                text_signature: None,
+                citations: None,
+            }));
+            stream.push(AssistantMessageEvent::TextStart {
+                content_index: 0,
+                partial: message.clone(),
+            });
+            stream.push(AssistantMessageEvent::TextDelta {
+                content_index: 0,
+                delta: format!("echo: {text}"),
+                partial: message.clone(),
+            });
+            stream.push(AssistantMessageEvent::TextEnd {
+                content_index: 0,
+                content: format!("echo: {text}"),
+                partial: message.clone(),
+            });
+            message.usage.output = 3;
+            stream.push(AssistantMessageEvent::Done {
+                reason: StopReason::Stop,
+                message,
+            });
+            stream
+        }
+    }
+
+    fn model() -> Model {
+        Model {
+            id: "echo-1".to_string(),
+            name: "Echo".to_string(),
+            api: "echo".to_string(),
+            provider: "echo".to_string(),
+            base_url: String::new(),
+            reasoning: false,
+            input: vec!["text".to_string()],
+            cost: ModelCost::default(),
+            context_window: 128000,
+            max_tokens: 4096,
+            headers: None,
+            compat: None,
+        }
+    }
+
+    fn context(text: &str, timestamp: i64) -> Context {
+        Context {
+            system_prompt: None,
+            messages: vec![Message::User(UserMessage {
+                content: UserContent::Text(text.to_string()),
+                timestamp,
+            })],
+            tools: None,
+        }
+    }
+
+    async fn run(provider: &ReplayProvider, context: &Context) -> (Vec<String>, AssistantMessage) {
+        let stream = provider.stream_simple(
+            &model(),
+            context,
+            &SimpleStreamOptions::default(),
+            CancellationToken::new(),
+        );
+        let events: Vec<_> = stream.clone().collect().await;
+        let types = events.iter().map(|e| e.event_type().to_string()).collect();
+        (types, stream.result().await.unwrap())
+    }
+
+    fn fixture_path() -> PathBuf {
+        std::env::temp_dir().join(format!("pi-replay-{}.json", uuid::Uuid::new_v4()))
+    }
+
+    #[tokio::test]
+    async fn test_record_then_replay() {
+        let path = fixture_path();
+        let recorder = ReplayProvider::record(Arc::new(EchoProvider), &path);
+        let (recorded_types, recorded) = run(&recorder, &context("hi", 1)).await;
+        assert_eq!(recorded.content[0].as_text().unwrap().text, "echo: hi");
+
+        // Timestamps differ between runs but the fingerprint does not.
+        let replayer = ReplayProvider::replay("echo", &path).unwrap().strict(true);
+        let (types, replayed) = run(&replayer, &context("hi", 2)).await;
+        assert_eq!(types, recorded_types);
+        assert_eq!(replayed.content[0].as_text().unwrap().text, "echo: hi");
+        assert_eq!(replayed.usage.output, 3);
+        assert_eq!(replayer.unused(), 0);
+        let _ = std::fs::remove_file(&path);
+    }
+
+    #[tokio::test]
+    async fn test_strict_mode_rejects_unexpected_request() {
+        let path = fixture_path();
+        let recorder = ReplayProvider::record(Arc::new(EchoProvider), &path);
+        run(&recorder, &context("hi", 1)).await;
+
+        let strict = ReplayProvider::replay("echo", &path).unwrap().strict(true);
+        let (_, message) = run(&strict, &context("something else", 1)).await;
+        assert_eq!(message.stop_reason, StopReason::Error);
+        assert!(
+            message
+                .error_message
+                .unwrap()
+                .contains("no recorded response")
+        );
+
+        // Lenient mode falls back to the next unused recording.
+        let lenient = ReplayProvider::replay("echo", &path).unwrap();
+        let (_, message) = run(&lenient, &context("something else", 1)).await;
+        assert_eq!(message.content[0].as_text().unwrap().text, "echo: hi");
+        let _ = std::fs::remove_file(&path);
+    }
+}
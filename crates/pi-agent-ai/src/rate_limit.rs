@@ -0,0 +1,507 @@
+//! Client-side rate limiting shared by all HTTP providers.
+//!
+//! Every provider request goes through [`send_rate_limited`], which:
+//! - waits on an optional per-provider token bucket (requests per minute),
+//! - honors `Retry-After` / `retry-after-ms` on 429 and 503 responses,
+//! - pauses a provider whose `x-ratelimit-*` / `anthropic-ratelimit-*`
+//!   headers report an exhausted quota until the advertised reset,
+//! - never waits longer than `max_retry_delay_ms`.
+//!
+//! Parsed quota headers are returned to providers as [`RateLimitInfo`] so they
+//! can be attached to the `AssistantMessage`.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use pi_agent_core::types::RateLimitInfo;
+use regex::Regex;
+use reqwest::header::HeaderMap;
+use tokio_util::sync::CancellationToken;
+
+use crate::http_client::describe_error;
+
+/// Default cap on client-side waits, matching the Gemini CLI provider.
+pub const DEFAULT_MAX_WAIT_MS: u64 = 60_000;
+
+/// Retries performed here for 429/503 responses carrying `Retry-After`.
+const MAX_RATE_LIMIT_RETRIES: u32 = 2;
+
+// ---------- Header parsing ----------
+
+/// Server-requested delay in milliseconds from `retry-after-ms` or
+/// `retry-after` (seconds or an HTTP date).
+pub fn parse_retry_after(headers: &HeaderMap) -> Option<u64> {
+    if let Some(ms) = header_str(headers, "retry-after-ms").and_then(|v| v.parse::<f64>().ok())
+        && ms.is_finite()
+        && ms >= 0.0
+    {
+        return Some(ms.ceil() as u64);
+    }
+    let value = header_str(headers, "retry-after")?;
+    if let Ok(secs) = value.parse::<f64>() {
+        return (secs.is_finite() && secs >= 0.0).then(|| (secs * 1000.0).ceil() as u64);
+    }
+    let date = chrono::DateTime::parse_from_rfc2822(value).ok()?;
+    let diff = date.timestamp_millis() - chrono::Utc::now().timestamp_millis();
+    Some(diff.max(0) as u64)
+}
+
+/// Parse quota headers (`x-ratelimit-*`, `anthropic-ratelimit-*`) and
+/// `Retry-After`. Returns `None` if the response carries none of them.
+pub fn parse_rate_limit_headers(headers: &HeaderMap) -> Option<RateLimitInfo> {
+    let now = chrono::Utc::now().timestamp_millis();
+    let number = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| header_str(headers, name)?.parse::<u64>().ok())
+    };
+    let reset = |names: &[&str]| {
+        names
+            .iter()
+            .find_map(|name| parse_reset(header_str(headers, name)?, now))
+    };
+
+    let info = RateLimitInfo {
+        requests_limit: number(&[
+            "anthropic-ratelimit-requests-limit",
+            "x-ratelimit-limit-requests",
+            "x-ratelimit-limit",
+        ]),
+        requests_remaining: number(&[
+            "anthropic-ratelimit-requests-remaining",
+            "x-ratelimit-remaining-requests",
+            "x-ratelimit-remaining",
+        ]),
+        requests_reset_at: reset(&[
+            "anthropic-ratelimit-requests-reset",
+            "x-ratelimit-reset-requests",
+            "x-ratelimit-reset",
+        ]),
+        tokens_limit: number(&[
+            "anthropic-ratelimit-tokens-limit",
+            "x-ratelimit-limit-tokens",
+        ]),
+        tokens_remaining: number(&[
+            "anthropic-ratelimit-tokens-remaining",
+            "x-ratelimit-remaining-tokens",
+        ]),
+        tokens_reset_at: reset(&[
+            "anthropic-ratelimit-tokens-reset",
+            "x-ratelimit-reset-tokens",
+        ]),
+        retry_after_ms: parse_retry_after(headers),
+    };
+
+    (info != RateLimitInfo::default()).then_some(info)
+}
+
+fn header_str<'a>(headers: &'a HeaderMap, name: &str) -> Option<&'a str> {
+    headers.get(name)?.to_str().ok().map(str::trim)
+}
+
+/// Parse a reset header into a Unix timestamp in milliseconds. Accepts
+/// RFC 3339 timestamps (Anthropic), Go-style durations such as `6m0s` or
+/// `20ms` (OpenAI, Groq), epoch seconds/milliseconds and plain seconds.
+fn parse_reset(value: &str, now_ms: i64) -> Option<i64> {
+    if let Ok(date) = chrono::DateTime::parse_from_rfc3339(value) {
+        return Some(date.timestamp_millis());
+    }
+    if let Ok(number) = value.parse::<f64>() {
+        if !number.is_finite() || number < 0.0 {
+            return None;
+        }
+        return Some(if number > 1e12 {
+            number as i64
+        } else if number > 1e9 {
+            (number * 1000.0) as i64
+        } else {
+            now_ms + (number * 1000.0) as i64
+        });
+    }
+    parse_duration_ms(value).map(|ms| now_ms + ms as i64)
+}
+
+/// Parse a Go-style duration (`1h2m3.5s`, `250ms`) into milliseconds.
+fn parse_duration_ms(value: &str) -> Option<u64> {
+    static RE: OnceLock<Regex> = OnceLock::new();
+    let re = RE.get_or_init(|| Regex::new(r"([0-9]+(?:\.[0-9]+)?)(ms|h|m|s)").unwrap());
+    let mut total = 0.0;
+    let mut consumed = 0;
+    for captures in re.captures_iter(value) {
+        let whole = captures.get(0)?;
+        consumed += whole.len();
+        let amount: f64 = captures[1].parse().ok()?;
+        total += match &captures[2] {
+            "h" => amount * 3_600_000.0,
+            "m" => amount * 60_000.0,
+            "s" => amount * 1000.0,
+            _ => amount,
+        };
+    }
+    (consumed > 0 && consumed == value.len()).then(|| total.ceil() as u64)
+}
+
+// ---------- Token buckets ----------
+
+/// Client-side request budget for one provider.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct RateLimitConfig {
+    pub requests_per_minute: u32,
+    /// Maximum burst size; defaults to `requests_per_minute`.
+    pub burst: Option<u32>,
+}
+
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    tokens: f64,
+    refill_per_ms: f64,
+    last_refill: Instant,
+}
+
+impl Bucket {
+    fn new(config: RateLimitConfig) -> Self {
+        let rpm = config.requests_per_minute.max(1) as f64;
+        let capacity = config.burst.unwrap_or(config.requests_per_minute).max(1) as f64;
+        Self {
+            capacity,
+            tokens: capacity,
+            refill_per_ms: rpm / 60_000.0,
+            last_refill: Instant::now(),
+        }
+    }
+
+    /// Take one token, returning how long the caller must wait for it.
+    /// Tokens may go negative so concurrent callers queue up in order.
+    fn reserve(&mut self, now: Instant) -> u64 {
+        let elapsed = now.saturating_duration_since(self.last_refill).as_millis() as f64;
+        self.tokens = (self.tokens + elapsed * self.refill_per_ms).min(self.capacity);
+        self.last_refill = now;
+        self.tokens -= 1.0;
+        if self.tokens >= 0.0 {
+            0
+        } else {
+            (-self.tokens / self.refill_per_ms).ceil() as u64
+        }
+    }
+
+    /// Give back a token taken by [`Bucket::reserve`].
+    fn refund(&mut self) {
+        self.tokens = (self.tokens + 1.0).min(self.capacity);
+    }
+}
+
+#[derive(Debug, Default)]
+struct ProviderState {
+    bucket: Option<Bucket>,
+    /// Set from `Retry-After` or an exhausted quota header.
+    blocked_until: Option<Instant>,
+}
+
+/// Per-provider request limiter shared by all providers in the process.
+#[derive(Debug, Default)]
+pub struct RateLimiter {
+    providers: Mutex<HashMap<String, ProviderState>>,
+}
+
+impl RateLimiter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set (or clear, with `None`) the token bucket for a provider.
+    pub fn configure(&self, provider: &str, config: Option<RateLimitConfig>) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let state = providers.entry(provider.to_string()).or_default();
+        state.bucket = config.map(Bucket::new);
+    }
+
+    /// Reserve a request slot, returning the wait in milliseconds.
+    pub fn reserve(&self, provider: &str) -> u64 {
+        let now = Instant::now();
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let Some(state) = providers.get_mut(provider) else {
+            return 0;
+        };
+        let blocked = state
+            .blocked_until
+            .map(|until| until.saturating_duration_since(now).as_millis() as u64)
+            .unwrap_or(0);
+        let bucket = state.bucket.as_mut().map(|b| b.reserve(now)).unwrap_or(0);
+        blocked.max(bucket)
+    }
+
+    /// Return the slot of a request that was not sent or that the server
+    /// rejected with 429; the wait a 429 asks for is applied by
+    /// [`RateLimiter::observe`] instead.
+    pub fn refund(&self, provider: &str) {
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(bucket) = providers.get_mut(provider).and_then(|s| s.bucket.as_mut()) {
+            bucket.refund();
+        }
+    }
+
+    /// Record server-reported limits: pause the provider for `Retry-After`,
+    /// or until the reset time of an exhausted quota.
+    pub fn observe(&self, provider: &str, info: &RateLimitInfo) {
+        let now_ms = chrono::Utc::now().timestamp_millis();
+        let mut pause_ms = info.retry_after_ms.unwrap_or(0);
+        for (remaining, reset_at) in [
+            (info.requests_remaining, info.requests_reset_at),
+            (info.tokens_remaining, info.tokens_reset_at),
+        ] {
+            if remaining == Some(0)
+                && let Some(reset_at) = reset_at
+            {
+                pause_ms = pause_ms.max((reset_at - now_ms).max(0) as u64);
+            }
+        }
+        if pause_ms == 0 {
+            return;
+        }
+        let until = Instant::now() + Duration::from_millis(pause_ms);
+        let mut providers = self.providers.lock().unwrap_or_else(|e| e.into_inner());
+        let state = providers.entry(provider.to_string()).or_default();
+        if state.blocked_until.is_none_or(|current| current < until) {
+            state.blocked_until = Some(until);
+        }
+    }
+}
+
+/// The process-wide limiter used by [`send_rate_limited`].
+pub fn rate_limiter() -> &'static RateLimiter {
+    static LIMITER: OnceLock<RateLimiter> = OnceLock::new();
+    LIMITER.get_or_init(RateLimiter::new)
+}
+
+// ---------- Sending ----------
+
+/// Failure to obtain a response from [`send_rate_limited`].
+#[derive(Debug)]
+pub enum RequestError {
+    /// Transport-level failure.
+    Http(reqwest::Error),
+    /// The required wait exceeds `max_retry_delay_ms`.
+    RateLimited(String),
+    /// Cancelled while waiting.
+    Aborted,
+}
+
+impl RequestError {
+    pub fn is_aborted(&self) -> bool {
+        matches!(self, RequestError::Aborted)
+    }
+}
+
+impl fmt::Display for RequestError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RequestError::Http(e) => write!(f, "{}", describe_error(e)),
+            RequestError::RateLimited(msg) => write!(f, "{msg}"),
+            RequestError::Aborted => write!(f, "Request was aborted"),
+        }
+    }
+}
+
+/// Send a provider request through the shared rate limiter.
+///
+/// Returns the response (successful or not) together with the quota
+/// information parsed from its headers.
+pub async fn send_rate_limited(
+    provider: &str,
+    request: reqwest::RequestBuilder,
+    max_retry_delay_ms: Option<u64>,
+    cancel: &CancellationToken,
+) -> Result<(reqwest::Response, Option<Box<RateLimitInfo>>), RequestError> {
+    let limiter = rate_limiter();
+    let max_wait = max_retry_delay_ms.unwrap_or(DEFAULT_MAX_WAIT_MS);
+    let mut pending = Some(request);
+    let mut attempt = 0;
+
+    loop {
+        let wait = limiter.reserve(provider);
+        if max_wait > 0 && wait > max_wait {
+            limiter.refund(provider);
+            return Err(RequestError::RateLimited(format!(
+                "Rate limit for {provider}: next request allowed in {}s (max wait: {}s)",
+                wait.div_ceil(1000),
+                max_wait.div_ceil(1000)
+            )));
+        }
+        if wait > 0 {
+            tracing::debug!("Rate limit for {provider}: waiting {wait}ms");
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_millis(wait)) => {}
+                _ = cancel.cancelled() => {
+                    limiter.refund(provider);
+                    return Err(RequestError::Aborted);
+                }
+            }
+        }
+
+        // Keep the original builder for a possible retry; bodies that cannot
+        // be cloned are sent once.
+        let Some(original) = pending.take() else {
+            unreachable!("request is only consumed on the final attempt");
+        };
+        let (this_request, retryable) = match original.try_clone() {
+            Some(clone) => {
+                pending = Some(original);
+                (clone, attempt < MAX_RATE_LIMIT_RETRIES)
+            }
+            None => (original, false),
+        };
+        let response = this_request.send().await.map_err(RequestError::Http)?;
+        let info = parse_rate_limit_headers(response.headers());
+        if let Some(info) = &info {
+            limiter.observe(provider, info);
+        }
+
+        let status = response.status().as_u16();
+        if status == 429 {
+            limiter.refund(provider);
+        }
+        let retry_after = info.as_ref().and_then(|i| i.retry_after_ms);
+        if retryable
+            && matches!(status, 429 | 503)
+            && retry_after.is_some_and(|ms| max_wait == 0 || ms <= max_wait)
+        {
+            attempt += 1;
+            continue;
+        }
+        return Ok((response, info.map(Box::new)));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &str)]) -> HeaderMap {
+        let mut map = HeaderMap::new();
+        for (name, value) in pairs {
+            map.insert(*name, value.parse().unwrap());
+        }
+        map
+    }
+
+    #[test]
+    fn test_parse_retry_after() {
+        assert_eq!(
+            parse_retry_after(&headers(&[("retry-after", "2")])),
+            Some(2000)
+        );
+        assert_eq!(
+            parse_retry_after(&headers(&[
+                ("retry-after-ms", "150.5"),
+                ("retry-after", "2")
+            ])),
+            Some(151)
+        );
+        assert_eq!(parse_retry_after(&headers(&[])), None);
+    }
+
+    #[test]
+    fn test_parse_duration_ms() {
+        assert_eq!(parse_duration_ms("6m0s"), Some(360_000));
+        assert_eq!(parse_duration_ms("1h2m3.5s"), Some(3_723_500));
+        assert_eq!(parse_duration_ms("20ms"), Some(20));
+        assert_eq!(parse_duration_ms("soon"), None);
+    }
+
+    #[test]
+    fn test_parse_openai_headers() {
+        let info = parse_rate_limit_headers(&headers(&[
+            ("x-ratelimit-limit-requests", "500"),
+            ("x-ratelimit-remaining-requests", "499"),
+            ("x-ratelimit-reset-requests", "120ms"),
+            ("x-ratelimit-remaining-tokens", "29000"),
+        ]))
+        .unwrap();
+        assert_eq!(info.requests_limit, Some(500));
+        assert_eq!(info.requests_remaining, Some(499));
+        assert!(info.requests_reset_at.is_some());
+        assert_eq!(info.tokens_remaining, Some(29000));
+    }
+
+    #[test]
+    fn test_parse_anthropic_headers() {
+        let info = parse_rate_limit_headers(&headers(&[
+            ("anthropic-ratelimit-tokens-remaining", "0"),
+            ("anthropic-ratelimit-tokens-reset", "2030-01-01T00:00:00Z"),
+        ]))
+        .unwrap();
+        assert_eq!(info.tokens_remaining, Some(0));
+        assert_eq!(info.tokens_reset_at, Some(1_893_456_000_000));
+        assert!(parse_rate_limit_headers(&headers(&[("content-type", "text/plain")])).is_none());
+    }
+
+    #[test]
+    fn test_token_bucket_spaces_requests() {
+        let limiter = RateLimiter::new();
+        limiter.configure(
+            "test",
+            Some(RateLimitConfig {
+                requests_per_minute: 60,
+                burst: Some(2),
+            }),
+        );
+        assert_eq!(limiter.reserve("test"), 0);
+        assert_eq!(limiter.reserve("test"), 0);
+        let wait = limiter.reserve("test");
+        assert!((900..=1000).contains(&wait), "wait was {wait}");
+        assert_eq!(limiter.reserve("unconfigured"), 0);
+
+        // A rejected request gives its slot back.
+        limiter.refund("test");
+        let after_refund = limiter.reserve("test");
+        assert!(after_refund <= wait, "wait was {after_refund}");
+    }
+
+    #[tokio::test]
+    async fn test_rejected_and_cancelled_requests_keep_their_slot() {
+        let provider = "test-unsent-requests";
+        rate_limiter().configure(
+            provider,
+            Some(RateLimitConfig {
+                requests_per_minute: 1,
+                burst: Some(1),
+            }),
+        );
+        assert_eq!(rate_limiter().reserve(provider), 0);
+        let client = reqwest::Client::new();
+
+        let result = send_rate_limited(
+            provider,
+            client.get("http://127.0.0.1:9"),
+            Some(1000),
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(matches!(result, Err(RequestError::RateLimited(_))));
+
+        let cancel = CancellationToken::new();
+        cancel.cancel();
+        let result =
+            send_rate_limited(provider, client.get("http://127.0.0.1:9"), Some(0), &cancel).await;
+        assert!(matches!(result, Err(RequestError::Aborted)));
+
+        let wait = rate_limiter().reserve(provider);
+        assert!((59_000..=60_000).contains(&wait), "wait was {wait}");
+    }
+
+    #[test]
+    fn test_observe_exhausted_quota_blocks_provider() {
+        let limiter = RateLimiter::new();
+        let info = RateLimitInfo {
+            requests_remaining: Some(0),
+            requests_reset_at: Some(chrono::Utc::now().timestamp_millis() + 5000),
+            ..Default::default()
+        };
+        limiter.observe("test", &info);
+        let wait = limiter.reserve("test");
+        assert!((4000..=5000).contains(&wait), "wait was {wait}");
+    }
+}
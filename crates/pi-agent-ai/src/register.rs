@@ -3,6 +3,7 @@ use std::sync::Arc;
 use crate::providers::anthropic::AnthropicProvider;
 use crate::providers::azure_openai_responses::AzureOpenAIResponsesProvider;
 use crate::providers::bedrock::BedrockProvider;
+use crate::providers::faux::{FauxProvider, faux_enabled, register_faux_provider};
 use crate::providers::google::GoogleProvider;
 use crate::providers::google_gemini_cli::GoogleGeminiCliProvider;
 use crate::providers::google_vertex::GoogleVertexProvider;
@@ -22,6 +23,9 @@ pub fn register_builtin_providers(registry: &mut ApiRegistry) {
     registry.register(Arc::new(GoogleGeminiCliProvider));
     registry.register(Arc::new(GoogleVertexProvider));
     registry.register(Arc::new(BedrockProvider));
+    if faux_enabled() {
+        register_faux_provider(registry, Arc::new(FauxProvider::from_env()));
+    }
 }
 
 /// Create a new registry with all built-in providers already registered.
@@ -0,0 +1,251 @@
+//! Provider-neutral structured output.
+//!
+//! Providers with a native JSON mode map [`ResponseFormat`] directly
+//! (OpenAI `response_format` / `text.format`, Gemini `responseJsonSchema`).
+//! Providers without one (Anthropic, Bedrock) fall back to a forced tool
+//! call whose input schema is the requested schema.
+
+use pi_agent_core::types::*;
+use serde_json::{Value, json};
+
+/// Tool name used for the forced-tool fallback of `ResponseFormat::JsonObject`.
+pub const JSON_OBJECT_TOOL_NAME: &str = "json_output";
+
+/// Returns the format only if it asks for JSON output.
+pub fn json_format(format: Option<&ResponseFormat>) -> Option<&ResponseFormat> {
+    format.filter(|f| !matches!(f, ResponseFormat::Text))
+}
+
+/// The JSON Schema a structured response must satisfy, if any.
+pub fn response_schema(format: &ResponseFormat) -> Option<&Value> {
+    match format {
+        ResponseFormat::JsonSchema { schema, .. } => Some(schema),
+        _ => None,
+    }
+}
+
+/// Tool definition used to emulate structured output on providers that
+/// only support it through a forced tool call.
+pub fn forced_tool(format: &ResponseFormat) -> Option<Tool> {
+    match format {
+        ResponseFormat::Text => None,
+        ResponseFormat::JsonObject => Some(Tool {
+            name: JSON_OBJECT_TOOL_NAME.to_string(),
+            description: "Respond with a JSON object.".to_string(),
+            parameters: json!({"type": "object"}),
+        }),
+        ResponseFormat::JsonSchema {
+            name,
+            schema,
+            description,
+            ..
+        } => Some(Tool {
+            name: name.clone(),
+            description: description
+                .clone()
+                .unwrap_or_else(|| format!("Respond with a `{name}` object.")),
+            parameters: schema.clone(),
+        }),
+    }
+}
+
+/// OpenAI Chat Completions `response_format` parameter.
+pub fn openai_completions_response_format(format: &ResponseFormat) -> Value {
+    match format {
+        ResponseFormat::Text => json!({"type": "text"}),
+        ResponseFormat::JsonObject => json!({"type": "json_object"}),
+        ResponseFormat::JsonSchema {
+            name,
+            schema,
+            description,
+            strict,
+        } => {
+            let mut json_schema = json!({"name": name, "schema": schema});
+            if let Some(description) = description {
+                json_schema["description"] = json!(description);
+            }
+            if let Some(strict) = strict {
+                json_schema["strict"] = json!(strict);
+            }
+            json!({"type": "json_schema", "json_schema": json_schema})
+        }
+    }
+}
+
+/// OpenAI Responses API `text.format` parameter.
+pub fn openai_responses_text_format(format: &ResponseFormat) -> Value {
+    match format {
+        ResponseFormat::Text => json!({"type": "text"}),
+        ResponseFormat::JsonObject => json!({"type": "json_object"}),
+        ResponseFormat::JsonSchema {
+            name,
+            schema,
+            description,
+            strict,
+        } => {
+            let mut text_format = json!({"type": "json_schema", "name": name, "schema": schema});
+            if let Some(description) = description {
+                text_format["description"] = json!(description);
+            }
+            if let Some(strict) = strict {
+                text_format["strict"] = json!(strict);
+            }
+            text_format
+        }
+    }
+}
+
+/// Apply the format to a Gemini `generationConfig` object.
+pub fn apply_google_response_format(format: &ResponseFormat, generation_config: &mut Value) {
+    match format {
+        ResponseFormat::Text => {}
+        ResponseFormat::JsonObject => {
+            generation_config["responseMimeType"] = json!("application/json");
+        }
+        ResponseFormat::JsonSchema { schema, .. } => {
+            generation_config["responseMimeType"] = json!("application/json");
+            // JSON Schema variant of `responseSchema`, matching the
+            // `parametersJsonSchema` used for tool declarations.
+            generation_config["responseJsonSchema"] = schema.clone();
+        }
+    }
+}
+
+/// Extract the structured value from a completed response: the arguments of
+/// the forced tool call if present, otherwise the text content parsed as JSON.
+pub fn extract_structured_output(
+    message: &AssistantMessage,
+    format: &ResponseFormat,
+) -> Result<Value, String> {
+    if let Some(tool) = forced_tool(format)
+        && let Some(tc) = message
+            .content
+            .iter()
+            .filter_map(|b| b.as_tool_call())
+            .find(|tc| tc.name == tool.name)
+    {
+        return Ok(tc.arguments.clone());
+    }
+
+    let text: String = message
+        .content
+        .iter()
+        .filter_map(|b| b.as_text().map(|t| t.text.as_str()))
+        .collect();
+    let trimmed = strip_code_fence(text.trim());
+    if trimmed.is_empty() {
+        return Err("Model returned no structured output".to_string());
+    }
+    serde_json::from_str(trimmed).map_err(|e| format!("Model output is not valid JSON: {e}"))
+}
+
+/// Strip a surrounding Markdown code fence (```json ... ```), if any.
+fn strip_code_fence(text: &str) -> &str {
+    let Some(rest) = text.strip_prefix("```") else {
+        return text;
+    };
+    let body = rest.split_once('\n').map(|(_, body)| body).unwrap_or("");
+    body.strip_suffix("```").unwrap_or(body).trim()
+}
+
+/// Validate a structured value against a JSON Schema.
+pub fn validate_structured_output(value: &Value, schema: &Value) -> Result<(), String> {
+    let validator = jsonschema::options()
+        .build(schema)
+        .map_err(|e| format!("Invalid response schema: {e}"))?;
+    let errors: Vec<String> = validator
+        .iter_errors(value)
+        .map(|err| {
+            let path = err.instance_path.to_string();
+            let path = if path.is_empty() {
+                "root".to_string()
+            } else {
+                path
+            };
+            format!("  - {path}: {err}")
+        })
+        .collect();
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(format!(
+            "Structured output does not match schema:\n{}",
+            errors.join("\n")
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn person_format() -> ResponseFormat {
+        ResponseFormat::JsonSchema {
+            name: "person".to_string(),
+            schema: json!({
+                "type": "object",
+                "properties": {"name": {"type": "string"}, "age": {"type": "integer"}},
+                "required": ["name", "age"]
+            }),
+            description: None,
+            strict: Some(true),
+        }
+    }
+
+    fn message_with(content: Vec<ContentBlock>) -> AssistantMessage {
+        AssistantMessage {
+            content,
+            api: "test".to_string(),
+            provider: "test".to_string(),
+            model: "test".to_string(),
+            usage: Usage::default(),
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            timestamp: 0,
+            rate_limit: None,
+        }
+    }
+
+    #[test]
+    fn test_openai_formats() {
+        let completions = openai_completions_response_format(&person_format());
+        assert_eq!(completions["type"], "json_schema");
+        assert_eq!(completions["json_schema"]["name"], "person");
+        assert_eq!(completions["json_schema"]["strict"], true);
+
+        let responses = openai_responses_text_format(&person_format());
+        assert_eq!(responses["type"], "json_schema");
+        assert_eq!(responses["schema"]["required"][0], "name");
+    }
+
+    #[test]
+    fn test_extract_from_forced_tool_call() {
+        let message = message_with(vec![ContentBlock::ToolCall(ToolCall {
+            id: "toolu_1".to_string(),
+            name: "person".to_string(),
+            arguments: json!({"name": "Ada", "age": 36}),
+            thought_signature: None,
+        })]);
+        let value = extract_structured_output(&message, &person_format()).unwrap();
+        assert_eq!(value["name"], "Ada");
+    }
+
+    #[test]
+    fn test_extract_from_fenced_text() {
+        let message = message_with(vec![ContentBlock::Text(TextContent {
+            text: "```json\n{\"name\": \"Ada\", \"age\": 36}\n```".to_string(),
+            text_signature: None,
+            citations: None,
+        })]);
+        let value = extract_structured_output(&message, &person_format()).unwrap();
+        assert_eq!(value["age"], 36);
+    }
+
+    #[test]
+    fn test_validate_structured_output() {
+        let schema = response_schema(&person_format()).unwrap().clone();
+        assert!(validate_structured_output(&json!({"name": "Ada", "age": 36}), &schema).is_ok());
+        let err = validate_structured_output(&json!({"name": "Ada"}), &schema).unwrap_err();
+        assert!(err.contains("age"));
+    }
+}
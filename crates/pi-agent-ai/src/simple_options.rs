@@ -24,6 +24,37 @@ pub fn build_base_options(
         session_id: options.base.session_id.clone(),
         headers: options.base.headers.clone(),
         max_retry_delay_ms: options.base.max_retry_delay_ms,
+        response_format: options.base.response_format.clone(),
+        tool_choice: options.base.tool_choice.clone(),
+        top_p: options.base.top_p,
+        top_k: options.base.top_k,
+        stop_sequences: options.base.stop_sequences.clone(),
+        seed: options.base.seed,
+        frequency_penalty: options.base.frequency_penalty,
+        presence_penalty: options.base.presence_penalty,
+    }
+}
+
+/// Log a warning for each sampling option the target API does not support.
+/// `supported` lists the camelCase option names the provider maps.
+pub fn warn_unsupported_options(model: &Model, options: &StreamOptions, supported: &[&str]) {
+    let requested = [
+        ("topP", options.top_p.is_some()),
+        ("topK", options.top_k.is_some()),
+        ("stopSequences", options.stop_sequences.is_some()),
+        ("seed", options.seed.is_some()),
+        ("frequencyPenalty", options.frequency_penalty.is_some()),
+        ("presencePenalty", options.presence_penalty.is_some()),
+    ];
+    for (name, set) in requested {
+        if set && !supported.contains(&name) {
+            tracing::warn!(
+                "{} ({}) does not support `{}`; ignoring it",
+                model.id,
+                model.api,
+                name
+            );
+        }
     }
 }
 
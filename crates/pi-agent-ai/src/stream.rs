@@ -1,8 +1,12 @@
 use pi_agent_core::event_stream::AssistantMessageEventStream;
 use pi_agent_core::types::*;
+use serde_json::Value;
 use tokio_util::sync::CancellationToken;
 
 use crate::registry::ApiRegistry;
+use crate::response_format::{
+    extract_structured_output, json_format, response_schema, validate_structured_output,
+};
 
 fn resolve_provider<'a>(
     registry: &'a ApiRegistry,
@@ -64,3 +68,165 @@ pub async fn complete_simple(
         .await
         .ok_or_else(|| "Stream ended without producing a result".to_string())
 }
+
+/// Result of a schema-constrained completion.
+#[derive(Debug, Clone)]
+pub struct StructuredCompletion {
+    /// The parsed (and, for `JsonSchema`, validated) JSON value.
+    pub value: Value,
+    /// The raw assistant message it was extracted from.
+    pub message: AssistantMessage,
+}
+
+/// Complete with a JSON `response_format` and return the parsed result,
+/// validated against the schema when one is given.
+pub async fn complete_structured(
+    model: &Model,
+    context: &Context,
+    options: &SimpleStreamOptions,
+    registry: &ApiRegistry,
+    cancel: CancellationToken,
+) -> Result<StructuredCompletion, String> {
+    let format = json_format(options.base.response_format.as_ref())
+        .cloned()
+        .ok_or_else(|| "complete_structured requires a JSON response_format".to_string())?;
+
+    let message = complete_simple(model, context, options, registry, cancel).await?;
+    if matches!(message.stop_reason, StopReason::Error | StopReason::Aborted) {
+        return Err(message
+            .error_message
+            .clone()
+            .unwrap_or_else(|| format!("Request ended with {}", message.stop_reason)));
+    }
+
+    let value = extract_structured_output(&message, &format)?;
+    match response_schema(&format) {
+        Some(schema) => validate_structured_output(&value, schema)?,
+        None if !value.is_object() => {
+            return Err("Structured output is not a JSON object".to_string());
+        }
+        None => {}
+    }
+
+    Ok(StructuredCompletion { value, message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::registry::ApiProvider;
+    use pi_agent_core::event_stream::create_assistant_message_event_stream;
+    use serde_json::json;
+    use std::sync::Arc;
+
+    /// Provider that answers every request with a fixed text body.
+    struct FixedTextProvider(&'static str);
+
+    impl ApiProvider for FixedTextProvider {
+        fn api(&self) -> &str {
+            "fixed-text"
+        }
+
+        fn stream(
+            &self,
+            model: &Model,
+            _context: &Context,
+            _options: &StreamOptions,
+            _cancel: CancellationToken,
+        ) -> AssistantMessageEventStream {
+            let stream = create_assistant_message_event_stream();
+            let mut message = AssistantMessage::empty(model);
+            message.content.push(ContentBlock::Text(TextContent {
+                text: self.0.to_string(),
+                text_signature: None,
+                citations: None,
+            }));
+            stream.push(AssistantMessageEvent::Done {
+                reason: StopReason::Stop,
+                message,
+            });
+            stream
+        }
+
+        fn stream_simple(
+            &self,
+            model: &Model,
+            context: &Context,
+            options: &SimpleStreamOptions,
+            cancel: CancellationToken,
+        ) -> AssistantMessageEventStream {
+            self.stream(model, context, &options.base, cancel)
+        }
+    }
+
+    fn fixture(text: &'static str) -> (Model, Context, ApiRegistry) {
+        let model = Model {
+            id: "fixed".to_string(),
+            name: "Fixed".to_string(),
+            api: "fixed-text".to_string(),
+            provider: "test".to_string(),
+            base_url: String::new(),
+            reasoning: false,
+            input: vec!["text".to_string()],
+            cost: ModelCost::default(),
+            context_window: 1000,
+            max_tokens: 100,
+            headers: None,
+            compat: None,
+        };
+        let context = Context {
+            system_prompt: None,
+            messages: Vec::new(),
+            tools: None,
+        };
+        let mut registry = ApiRegistry::new();
+        registry.register(Arc::new(FixedTextProvider(text)));
+        (model, context, registry)
+    }
+
+    fn count_options() -> SimpleStreamOptions {
+        SimpleStreamOptions {
+            base: StreamOptions {
+                response_format: Some(ResponseFormat::JsonSchema {
+                    name: "count".to_string(),
+                    schema: json!({
+                        "type": "object",
+                        "properties": {"count": {"type": "integer"}},
+                        "required": ["count"]
+                    }),
+                    description: None,
+                    strict: None,
+                }),
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_complete_structured_validates_schema() {
+        let (model, context, registry) = fixture("{\"count\": 3}");
+        let result = complete_structured(
+            &model,
+            &context,
+            &count_options(),
+            &registry,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(result.value["count"], 3);
+
+        let (model, context, registry) = fixture("{\"count\": \"three\"}");
+        let err = complete_structured(
+            &model,
+            &context,
+            &count_options(),
+            &registry,
+            CancellationToken::new(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.contains("does not match schema"));
+    }
+}
@@ -0,0 +1,433 @@
+//! Token counting for context management.
+//!
+//! [`TokenCounter`] is the extension point; [`counter_for_model`] picks a
+//! default for a model:
+//!
+//! - OpenAI-family models use the real `o200k_base` / `cl100k_base` BPE
+//!   vocabularies, which are bundled (no network access).
+//! - Claude models use `cl100k_base` scaled by [`ANTHROPIC_BPE_SCALE`].
+//!   Anthropic has not published the Claude 3+ tokenizer, so this is an
+//!   approximation; [`Calibration`] corrects it against reported usage.
+//! - Everything else uses `o200k_base`, which tracks modern vocabularies far
+//!   better than a byte-length heuristic, especially for CJK text and code.
+//!
+//! [`HeuristicCounter`] is the zero-cost fallback: ASCII characters / 4,
+//! rounded up, plus one token per non-ASCII character. Exact counts are
+//! available from provider endpoints through [`count_tokens_remote`].
+
+use std::sync::{Arc, OnceLock};
+
+use base64::Engine;
+use pi_agent_core::types::*;
+use tiktoken_rs::CoreBPE;
+
+/// Scale applied to `cl100k_base` counts for Claude models, whose
+/// tokenizer produces noticeably more tokens for the same text.
+pub const ANTHROPIC_BPE_SCALE: f64 = 1.15;
+
+/// Image cost when the dimensions cannot be read from the data.
+pub const IMAGE_TOKENS_FALLBACK: u64 = 1200;
+
+/// Largest image cost; providers downscale larger images.
+const IMAGE_TOKENS_MAX: u64 = 1600;
+
+/// Long-edge size providers downscale images to.
+const IMAGE_MAX_EDGE: f64 = 1568.0;
+
+/// Per-message framing (role markers, separators) added by BPE counters.
+const BPE_MESSAGE_OVERHEAD: u64 = 4;
+
+/// Counts tokens for messages sent to a model.
+pub trait TokenCounter: Send + Sync {
+    /// Short identifier, e.g. `o200k_base`.
+    fn name(&self) -> &str;
+
+    /// Tokens in a piece of text.
+    fn count_text(&self, text: &str) -> u64;
+
+    /// Tokens added per message for role and separator framing.
+    fn message_overhead(&self) -> u64 {
+        0
+    }
+
+    /// Tokens for an image block.
+    fn count_image(&self, image: &ImageContent) -> u64 {
+        estimate_image_tokens(image)
+    }
+
+    /// Tokens for one content block.
+    fn count_block(&self, block: &ContentBlock) -> u64 {
+        match block {
+            ContentBlock::Text(t) => self.count_text(&t.text),
+            ContentBlock::Thinking(t) => self.count_text(&t.thinking),
+            ContentBlock::RedactedThinking(r) => self.count_text(&r.data),
+            ContentBlock::Image(image) => self.count_image(image),
+            ContentBlock::ToolCall(tc) => {
+                self.count_text(&tc.name) + self.count_text(&tc.arguments.to_string())
+            }
+        }
+    }
+
+    /// Tokens for a message.
+    fn count_message(&self, message: &Message) -> u64 {
+        let content = match message {
+            Message::User(m) => match &m.content {
+                UserContent::Text(t) => self.count_text(t),
+                UserContent::Blocks(blocks) => blocks.iter().map(|b| self.count_block(b)).sum(),
+            },
+            Message::Assistant(m) => m.content.iter().map(|b| self.count_block(b)).sum(),
+            Message::ToolResult(m) => m
+                .content
+                .iter()
+                .filter(|b| matches!(b, ContentBlock::Text(_) | ContentBlock::Image(_)))
+                .map(|b| self.count_block(b))
+                .sum(),
+        };
+        content + self.message_overhead()
+    }
+
+    /// Tokens for a list of messages.
+    fn count_messages(&self, messages: &[Message]) -> u64 {
+        messages.iter().map(|m| self.count_message(m)).sum()
+    }
+
+    /// Tokens for tool definitions sent with a request.
+    fn count_tools(&self, tools: &[Tool]) -> u64 {
+        tools
+            .iter()
+            .map(|tool| {
+                self.count_text(&tool.name)
+                    + self.count_text(&tool.description)
+                    + self.count_text(&tool.parameters.to_string())
+            })
+            .sum()
+    }
+}
+
+/// The byte-length heuristic: about four ASCII characters per token, and
+/// one token per non-ASCII character (CJK text is roughly one token per
+/// character in modern vocabularies).
+#[derive(Debug, Clone, Copy, Default)]
+pub struct HeuristicCounter;
+
+impl TokenCounter for HeuristicCounter {
+    fn name(&self) -> &str {
+        "heuristic"
+    }
+
+    fn count_text(&self, text: &str) -> u64 {
+        let ascii = text.bytes().filter(u8::is_ascii).count();
+        let other = text.chars().filter(|c| !c.is_ascii()).count();
+        (ascii as f64 / 4.0).ceil() as u64 + other as u64
+    }
+}
+
+/// A BPE vocabulary, optionally scaled to approximate a related tokenizer.
+#[derive(Clone, Copy)]
+pub struct BpeCounter {
+    name: &'static str,
+    bpe: &'static CoreBPE,
+    scale: f64,
+}
+
+impl BpeCounter {
+    /// `o200k_base` (GPT-4o, GPT-4.1, GPT-5, o-series).
+    pub fn o200k() -> Self {
+        Self {
+            name: "o200k_base",
+            bpe: tiktoken_rs::o200k_base_singleton(),
+            scale: 1.0,
+        }
+    }
+
+    /// `cl100k_base` (GPT-4, GPT-3.5).
+    pub fn cl100k() -> Self {
+        Self {
+            name: "cl100k_base",
+            bpe: tiktoken_rs::cl100k_base_singleton(),
+            scale: 1.0,
+        }
+    }
+
+    /// Approximation of the Claude tokenizer.
+    pub fn anthropic() -> Self {
+        Self {
+            name: "cl100k_base~claude",
+            scale: ANTHROPIC_BPE_SCALE,
+            ..Self::cl100k()
+        }
+    }
+}
+
+impl std::fmt::Debug for BpeCounter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("BpeCounter")
+            .field("name", &self.name)
+            .field("scale", &self.scale)
+            .finish()
+    }
+}
+
+impl TokenCounter for BpeCounter {
+    fn name(&self) -> &str {
+        self.name
+    }
+
+    fn count_text(&self, text: &str) -> u64 {
+        if text.is_empty() {
+            return 0;
+        }
+        let tokens = self.bpe.encode_ordinary(text).len() as f64;
+        (tokens * self.scale).ceil() as u64
+    }
+
+    fn message_overhead(&self) -> u64 {
+        BPE_MESSAGE_OVERHEAD
+    }
+}
+
+/// Default counter for `model`. Counters are shared, so this is cheap to
+/// call per request.
+pub fn counter_for_model(model: &Model) -> Arc<dyn TokenCounter> {
+    static O200K: OnceLock<Arc<dyn TokenCounter>> = OnceLock::new();
+    static CL100K: OnceLock<Arc<dyn TokenCounter>> = OnceLock::new();
+    static ANTHROPIC: OnceLock<Arc<dyn TokenCounter>> = OnceLock::new();
+
+    let id = model.id.to_ascii_lowercase();
+    if model.api == "anthropic-messages" || model.provider == "anthropic" || id.contains("claude") {
+        ANTHROPIC
+            .get_or_init(|| Arc::new(BpeCounter::anthropic()))
+            .clone()
+    } else if id.starts_with("gpt-4") && !id.starts_with("gpt-4o") && !id.starts_with("gpt-4.")
+        || id.starts_with("gpt-3.5")
+    {
+        CL100K
+            .get_or_init(|| Arc::new(BpeCounter::cl100k()))
+            .clone()
+    } else {
+        O200K.get_or_init(|| Arc::new(BpeCounter::o200k())).clone()
+    }
+}
+
+/// Context tokens a provider reported for a response: the prompt (including
+/// cached parts) plus the generated output.
+pub fn usage_context_tokens(usage: &Usage) -> u64 {
+    usage.input + usage.output + usage.cache_read + usage.cache_write
+}
+
+/// Anchors local estimates on the last usage a provider reported.
+///
+/// `reported` is the provider's count for a prefix of the conversation and
+/// `estimated` the local counter's estimate for the same prefix. Tokens
+/// added after the prefix are scaled by their ratio, which corrects a
+/// counter's systematic bias for the current model and content mix.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Calibration {
+    pub reported: u64,
+    pub estimated: u64,
+}
+
+impl Calibration {
+    /// Ratios outside this range indicate the estimate does not cover the
+    /// same prefix (e.g. a system prompt change) and are clamped.
+    const RATIO_RANGE: (f64, f64) = (0.5, 2.0);
+
+    /// Reported / estimated, clamped; 1.0 without an estimate.
+    pub fn ratio(&self) -> f64 {
+        if self.estimated == 0 {
+            return 1.0;
+        }
+        (self.reported as f64 / self.estimated as f64)
+            .clamp(Self::RATIO_RANGE.0, Self::RATIO_RANGE.1)
+    }
+
+    /// Calibrated total for the prefix plus `estimated_delta` new tokens.
+    pub fn apply(&self, estimated_delta: u64) -> u64 {
+        self.reported + (estimated_delta as f64 * self.ratio()).round() as u64
+    }
+}
+
+/// Estimate an image's token cost from its pixel dimensions
+/// (`width * height / 750` after downscaling to a 1568px long edge).
+pub fn estimate_image_tokens(image: &ImageContent) -> u64 {
+    let Some((width, height)) = image_dimensions(&image.data) else {
+        return IMAGE_TOKENS_FALLBACK;
+    };
+    let (mut w, mut h) = (width as f64, height as f64);
+    let long_edge = w.max(h);
+    if long_edge > IMAGE_MAX_EDGE {
+        let scale = IMAGE_MAX_EDGE / long_edge;
+        w *= scale;
+        h *= scale;
+    }
+    ((w * h / 750.0).ceil() as u64).clamp(1, IMAGE_TOKENS_MAX)
+}
+
+/// Read PNG, GIF or JPEG dimensions from base64 data without decoding
+/// the whole image.
+fn image_dimensions(data: &str) -> Option<(u32, u32)> {
+    // Enough for the JPEG frame header in images with large EXIF blocks.
+    const PREFIX_CHARS: usize = 128 * 1024;
+    let prefix = &data.as_bytes()[..data.len().min(PREFIX_CHARS) / 4 * 4];
+    let bytes = base64::engine::general_purpose::STANDARD
+        .decode(prefix)
+        .ok()?;
+
+    if bytes.starts_with(b"\x89PNG\r\n\x1a\n") && bytes.len() >= 24 {
+        let width = u32::from_be_bytes(bytes[16..20].try_into().ok()?);
+        let height = u32::from_be_bytes(bytes[20..24].try_into().ok()?);
+        return Some((width, height));
+    }
+    if bytes.starts_with(b"GIF8") && bytes.len() >= 10 {
+        let width = u16::from_le_bytes([bytes[6], bytes[7]]) as u32;
+        let height = u16::from_le_bytes([bytes[8], bytes[9]]) as u32;
+        return Some((width, height));
+    }
+    if bytes.starts_with(&[0xFF, 0xD8]) {
+        let mut i = 2;
+        while i + 9 < bytes.len() {
+            if bytes[i] != 0xFF {
+                i += 1;
+                continue;
+            }
+            let marker = bytes[i + 1];
+            let length = u16::from_be_bytes([bytes[i + 2], bytes[i + 3]]) as usize;
+            // SOF0..SOF15, excluding DHT (C4), JPG (C8) and DAC (CC).
+            if (0xC0..=0xCF).contains(&marker) && ![0xC4, 0xC8, 0xCC].contains(&marker) {
+                let height = u16::from_be_bytes([bytes[i + 5], bytes[i + 6]]) as u32;
+                let width = u16::from_be_bytes([bytes[i + 7], bytes[i + 8]]) as u32;
+                return Some((width, height));
+            }
+            i += 2 + length;
+        }
+    }
+    None
+}
+
+/// Exact input token count from the provider's count endpoint.
+///
+/// Supported for the Anthropic Messages API (`/v1/messages/count_tokens`)
+/// and the Gemini API (`:countTokens`); returns `Ok(None)` for other APIs.
+pub async fn count_tokens_remote(
+    model: &Model,
+    context: &Context,
+    api_key: &str,
+) -> Result<Option<u64>, String> {
+    match model.api.as_str() {
+        "anthropic-messages" => {
+            crate::providers::anthropic::count_tokens_anthropic(model, context, api_key)
+                .await
+                .map(Some)
+        }
+        "google-generative-ai" => {
+            crate::providers::google::count_tokens_google(model, context, api_key)
+                .await
+                .map(Some)
+        }
+        _ => Ok(None),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn model(api: &str, provider: &str, id: &str) -> Model {
+        let mut model = crate::models::get_models("anthropic")
+            .into_iter()
+            .next()
+            .unwrap();
+        model.api = api.to_string();
+        model.provider = provider.to_string();
+        model.id = id.to_string();
+        model
+    }
+
+    #[test]
+    fn test_bpe_counts_real_tokens() {
+        let counter = BpeCounter::o200k();
+        assert_eq!(counter.count_text(""), 0);
+        assert_eq!(counter.count_text("hello world"), 2);
+        // CJK: the byte heuristic (len/4) badly overestimates.
+        let cjk = "你好，世界。今天天气很好。";
+        let bpe = counter.count_text(cjk);
+        assert!(bpe < (cjk.len() as u64).div_ceil(4) + 10);
+        assert!(bpe >= 5);
+    }
+
+    #[test]
+    fn test_heuristic_counts_cjk_per_char() {
+        let counter = HeuristicCounter;
+        assert_eq!(counter.count_text("hello"), 2);
+        assert_eq!(counter.count_text("你好"), 2);
+        assert_eq!(counter.count_text(&"a".repeat(100)), 25);
+    }
+
+    #[test]
+    fn test_counter_for_model() {
+        assert_eq!(
+            counter_for_model(&model(
+                "anthropic-messages",
+                "anthropic",
+                "claude-sonnet-4-5"
+            ))
+            .name(),
+            "cl100k_base~claude"
+        );
+        assert_eq!(
+            counter_for_model(&model("openai-responses", "openai", "gpt-4o")).name(),
+            "o200k_base"
+        );
+        assert_eq!(
+            counter_for_model(&model("openai-completions", "openai", "gpt-4-turbo")).name(),
+            "cl100k_base"
+        );
+        assert_eq!(
+            counter_for_model(&model("google-generative-ai", "google", "gemini-2.5-pro")).name(),
+            "o200k_base"
+        );
+    }
+
+    #[test]
+    fn test_calibration() {
+        let calibration = Calibration {
+            reported: 1200,
+            estimated: 1000,
+        };
+        assert_eq!(calibration.ratio(), 1.2);
+        assert_eq!(calibration.apply(100), 1320);
+        let wild = Calibration {
+            reported: 10_000,
+            estimated: 10,
+        };
+        assert_eq!(wild.ratio(), 2.0);
+        assert_eq!(
+            Calibration {
+                reported: 5,
+                estimated: 0
+            }
+            .apply(10),
+            15
+        );
+    }
+
+    #[test]
+    fn test_image_tokens_from_dimensions() {
+        // 1x1 PNG header (IHDR only is enough).
+        let mut png = b"\x89PNG\r\n\x1a\n\0\0\0\x0dIHDR".to_vec();
+        png.extend_from_slice(&750u32.to_be_bytes());
+        png.extend_from_slice(&100u32.to_be_bytes());
+        png.extend_from_slice(&[8, 6, 0, 0, 0]);
+        let image = ImageContent {
+            data: base64::engine::general_purpose::STANDARD.encode(&png),
+            mime_type: "image/png".to_string(),
+        };
+        assert_eq!(estimate_image_tokens(&image), 100);
+
+        let unknown = ImageContent {
+            data: "not-an-image".to_string(),
+            mime_type: "image/webp".to_string(),
+        };
+        assert_eq!(estimate_image_tokens(&unknown), IMAGE_TOKENS_FALLBACK);
+    }
+}
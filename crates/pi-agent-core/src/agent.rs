@@ -456,11 +456,20 @@ impl Agent {
             api_key: None,
             cache_retention: None,
             headers: None,
+            tool_choice: None,
+            top_p: None,
+            top_k: None,
+            stop_sequences: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
             convert_to_llm: self.convert_to_llm.clone(),
             transform_context: self.transform_context.clone(),
             get_api_key: self.get_api_key.clone(),
             get_steering_messages: Some(get_steering),
             get_follow_up_messages: Some(get_follow_up),
+            limits: AgentLimits::default(),
+            limit_progress: None,
         };
 
         let result: Result<(), String> = async {
@@ -527,6 +536,7 @@ impl Agent {
                 content: vec![ContentBlock::Text(TextContent {
                     text: String::new(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: self.state.model.api.clone(),
                 provider: self.state.model.provider.clone(),
@@ -539,6 +549,7 @@ impl Agent {
                 },
                 error_message: Some(err.clone()),
                 timestamp: chrono::Utc::now().timestamp_millis(),
+                rate_limit: None,
             };
             self.append_message(error_msg.clone().into());
             self.state.error = Some(err);
@@ -1,4 +1,5 @@
-use std::sync::Arc;
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::Duration;
 
 use futures::StreamExt;
 use tokio_util::sync::CancellationToken;
@@ -112,6 +113,82 @@ fn create_agent_stream() -> EventStream<AgentEvent, Vec<AgentMessage>> {
     )
 }
 
+/// Tracks a run's progress against its [`AgentLimits`].
+struct LimitTracker<'a> {
+    limits: &'a AgentLimits,
+    progress: Arc<Mutex<LimitProgress>>,
+}
+
+impl<'a> LimitTracker<'a> {
+    fn new(limits: &'a AgentLimits, progress: Option<Arc<Mutex<LimitProgress>>>) -> Self {
+        Self {
+            limits,
+            progress: progress.unwrap_or_else(LimitProgress::shared),
+        }
+    }
+
+    fn progress(&self) -> MutexGuard<'_, LimitProgress> {
+        self.progress.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn record_response(&mut self, message: &AssistantMessage) {
+        let mut progress = self.progress();
+        progress.turns += 1;
+        progress.cost += message.usage.cost.total;
+    }
+
+    /// Time left before `max_duration_ms`.
+    fn remaining_duration(&self) -> Option<Duration> {
+        let limit = Duration::from_millis(self.limits.max_duration_ms?);
+        Some(limit.saturating_sub(self.progress().started.elapsed()))
+    }
+
+    fn deadline_passed(&self) -> Option<LimitReason> {
+        let limit_ms = self.limits.max_duration_ms?;
+        self.remaining_duration()?
+            .is_zero()
+            .then_some(LimitReason::MaxDuration { limit_ms })
+    }
+
+    /// A limit that forbids any further request or tool execution.
+    fn spent(&self) -> Option<LimitReason> {
+        let cost = self.progress().cost;
+        if let Some(limit) = self.limits.max_cost
+            && cost >= limit
+        {
+            return Some(LimitReason::MaxCost { limit, spent: cost });
+        }
+        if let Some(limit) = self.limits.max_session_cost {
+            let spent = self.limits.session_cost + cost;
+            if spent >= limit {
+                return Some(LimitReason::MaxSessionCost { limit, spent });
+            }
+        }
+        self.deadline_passed()
+    }
+
+    /// A limit that forbids another assistant response.
+    fn before_turn(&self) -> Option<LimitReason> {
+        if let Some(limit) = self.limits.max_turns
+            && self.progress().turns >= limit
+        {
+            return Some(LimitReason::MaxTurns { limit });
+        }
+        self.spent()
+    }
+
+    /// How many of `requested` tool calls may run.
+    fn allow_tool_calls(&mut self, requested: usize) -> usize {
+        let mut progress = self.progress();
+        let allowed = match self.limits.max_tool_calls {
+            Some(limit) => requested.min(limit.saturating_sub(progress.tool_calls) as usize),
+            None => requested,
+        };
+        progress.tool_calls += allowed as u32;
+        allowed
+    }
+}
+
 /// Main loop logic shared by agent_loop and agent_loop_continue.
 async fn run_loop(
     current_context: &mut AgentContext,
@@ -122,6 +199,22 @@ async fn run_loop(
     stream_fn: Option<&StreamFnBox>,
 ) {
     let mut first_turn = true;
+    // A forced tool choice applies to the first request only; later turns
+    // fall back to `Auto` so the model can finish with a text answer.
+    let mut tool_choice = config.tool_choice.clone();
+    let mut limits = LimitTracker::new(&config.limits, config.limit_progress.clone());
+    let mut limit_reached: Option<LimitReason> = config.limits.exhausted();
+
+    // The wall-clock limit cancels in-flight requests and tools through a
+    // child token, leaving the caller's token untouched.
+    let cancel = cancel.child_token();
+    let deadline = limits.remaining_duration().map(|remaining| {
+        let cancel = cancel.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(remaining).await;
+            cancel.cancel();
+        })
+    });
 
     // Check for steering messages at start
     let mut pending_messages: Vec<AgentMessage> =
@@ -132,14 +225,19 @@ async fn run_loop(
         };
 
     // Outer loop: continues when queued follow-up messages arrive
-    loop {
+    'outer: while limit_reached.is_none() {
         let mut has_more_tool_calls = true;
         let mut steering_after_tools: Option<Vec<AgentMessage>> = None;
 
         // Inner loop: process tool calls and steering messages
         while has_more_tool_calls || !pending_messages.is_empty() {
             if cancel.is_cancelled() {
-                break;
+                limit_reached = limits.deadline_passed();
+                break 'outer;
+            }
+            if let Some(reason) = limits.before_turn() {
+                limit_reached = Some(reason);
+                break 'outer;
             }
 
             if !first_turn {
@@ -166,12 +264,17 @@ async fn run_loop(
             let message = stream_assistant_response(
                 current_context,
                 config,
+                tool_choice.clone(),
                 cancel.clone(),
                 stream,
                 stream_fn,
             )
             .await;
             new_messages.push(message.clone().into());
+            limits.record_response(&message);
+            if tool_choice.as_ref().is_some_and(ToolChoice::is_forced) {
+                tool_choice = Some(ToolChoice::Auto);
+            }
 
             if message.stop_reason == StopReason::Error
                 || message.stop_reason == StopReason::Aborted
@@ -180,11 +283,10 @@ async fn run_loop(
                     message: message.clone().into(),
                     tool_results: vec![],
                 });
-                stream.push(AgentEvent::AgentEnd {
-                    messages: new_messages.clone(),
-                });
-                stream.end(Some(new_messages));
-                return;
+                if message.stop_reason == StopReason::Aborted {
+                    limit_reached = limits.deadline_passed();
+                }
+                break 'outer;
             }
 
             // Check for tool calls
@@ -197,9 +299,25 @@ async fn run_loop(
 
             let mut tool_results: Vec<ToolResultMessage> = Vec::new();
             if has_more_tool_calls {
+                // Every tool call still gets a result so the transcript
+                // stays valid; calls over budget are skipped.
+                limit_reached = limits.spent();
+                let allowed = if limit_reached.is_some() {
+                    0
+                } else {
+                    limits.allow_tool_calls(tool_calls.len())
+                };
+                if allowed < tool_calls.len()
+                    && limit_reached.is_none()
+                    && let Some(limit) = config.limits.max_tool_calls
+                {
+                    limit_reached = Some(LimitReason::MaxToolCalls { limit });
+                }
+
                 let execution = execute_tool_calls(
                     &current_context.tools,
                     &message,
+                    allowed,
                     cancel.clone(),
                     stream,
                     config.get_steering_messages.as_ref(),
@@ -212,6 +330,9 @@ async fn run_loop(
                     current_context.messages.push(result.clone().into());
                     new_messages.push(result.clone().into());
                 }
+                if limit_reached.is_none() {
+                    limit_reached = limits.deadline_passed();
+                }
             }
 
             stream.push(AgentEvent::TurnEnd {
@@ -219,6 +340,10 @@ async fn run_loop(
                 tool_results,
             });
 
+            if limit_reached.is_some() {
+                break 'outer;
+            }
+
             // Get steering messages after turn completes
             if let Some(steering) = steering_after_tools.take() {
                 if !steering.is_empty() {
@@ -249,6 +374,12 @@ async fn run_loop(
         break;
     }
 
+    if let Some(deadline) = deadline {
+        deadline.abort();
+    }
+    if let Some(reason) = limit_reached {
+        stream.push(AgentEvent::LimitReached { reason });
+    }
     stream.push(AgentEvent::AgentEnd {
         messages: new_messages.clone(),
     });
@@ -259,6 +390,7 @@ async fn run_loop(
 async fn stream_assistant_response(
     context: &mut AgentContext,
     config: &AgentLoopConfig,
+    tool_choice: Option<ToolChoice>,
     cancel: CancellationToken,
     stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
     stream_fn: Option<&StreamFnBox>,
@@ -309,6 +441,14 @@ async fn stream_assistant_response(
             session_id: config.session_id.clone(),
             headers: config.headers.clone(),
             max_retry_delay_ms: config.max_retry_delay_ms,
+            response_format: None,
+            tool_choice,
+            top_p: config.top_p,
+            top_k: config.top_k,
+            stop_sequences: config.stop_sequences.clone(),
+            seed: config.seed,
+            frequency_penalty: config.frequency_penalty,
+            presence_penalty: config.presence_penalty,
         },
         reasoning: config.reasoning.clone(),
         thinking_budgets: config.thinking_budgets.clone(),
@@ -333,8 +473,19 @@ async fn stream_assistant_response(
 
     let mut partial_message: Option<AssistantMessage> = None;
     let mut added_partial = false;
+    let mut aborted = false;
 
-    while let Some(event) = response.next().await {
+    loop {
+        let event = tokio::select! {
+            event = response.next() => event,
+            _ = cancel.cancelled() => {
+                aborted = true;
+                None
+            }
+        };
+        let Some(event) = event else {
+            break;
+        };
         match &event {
             AssistantMessageEvent::Start { partial } => {
                 partial_message = Some(partial.clone());
@@ -391,7 +542,12 @@ async fn stream_assistant_response(
 
     // Stream ended without Done/Error event — construct error response
     let mut error_msg = partial_message.unwrap_or_else(|| AssistantMessage::empty(&config.model));
-    error_msg.stop_reason = StopReason::Error;
+    if aborted {
+        error_msg.stop_reason = StopReason::Aborted;
+        error_msg.error_message = Some("Request was aborted".to_string());
+    } else {
+        error_msg.stop_reason = StopReason::Error;
+    }
     if error_msg.error_message.is_none() {
         error_msg.error_message = Some("LLM stream ended without a completion event".to_string());
     }
@@ -435,9 +591,13 @@ struct ToolExecutionResult {
 }
 
 /// Execute tool calls from an assistant message.
+///
+/// Only the first `allowed` calls run; the rest are skipped because a limit
+/// was reached.
 async fn execute_tool_calls(
     tools: &[Arc<dyn AgentTool>],
     assistant_message: &AssistantMessage,
+    allowed: usize,
     cancel: CancellationToken,
     stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
     get_steering_messages: Option<&Arc<MessageQueueFn>>,
@@ -452,6 +612,14 @@ async fn execute_tool_calls(
     let mut steering_messages: Option<Vec<AgentMessage>> = None;
 
     for (index, tool_call) in tool_calls.iter().enumerate() {
+        if index >= allowed {
+            results.push(skip_tool_call(
+                tool_call,
+                "Skipped: agent limit reached.",
+                stream,
+            ));
+            continue;
+        }
         let tool = tools.iter().find(|t| t.name() == tool_call.name);
 
         stream.push(AgentEvent::ToolExecutionStart {
@@ -466,6 +634,7 @@ async fn execute_tool_calls(
                     content: vec![ContentBlock::Text(TextContent {
                         text: format!("Tool {} not found", tool_call.name),
                         text_signature: None,
+                        citations: None,
                     })],
                     details: Some(serde_json::json!({})),
                 };
@@ -481,6 +650,7 @@ async fn execute_tool_calls(
                             content: vec![ContentBlock::Text(TextContent {
                                 text: error_text.clone(),
                                 text_signature: None,
+                                citations: None,
                             })],
                             details: Some(serde_json::json!({})),
                         };
@@ -497,6 +667,7 @@ async fn execute_tool_calls(
                             content: vec![ContentBlock::Text(TextContent {
                                 text: error_text,
                                 text_signature: None,
+                                citations: None,
                             })],
                             details: Some(serde_json::json!({})),
                             is_error: true,
@@ -517,7 +688,11 @@ async fn execute_tool_calls(
                                 steering_messages = Some(steering);
                                 let remaining = &tool_calls[index + 1..];
                                 for skipped in remaining {
-                                    results.push(skip_tool_call(skipped, stream));
+                                    results.push(skip_tool_call(
+                                        skipped,
+                                        SKIPPED_FOR_STEERING,
+                                        stream,
+                                    ));
                                 }
                                 return ToolExecutionResult {
                                     tool_results: results,
@@ -554,6 +729,7 @@ async fn execute_tool_calls(
                             content: vec![ContentBlock::Text(TextContent {
                                 text: e.to_string(),
                                 text_signature: None,
+                                citations: None,
                             })],
                             details: Some(serde_json::json!({})),
                         };
@@ -594,7 +770,7 @@ async fn execute_tool_calls(
                 steering_messages = Some(steering);
                 let remaining = &tool_calls[index + 1..];
                 for skipped in remaining {
-                    results.push(skip_tool_call(skipped, stream));
+                    results.push(skip_tool_call(skipped, SKIPPED_FOR_STEERING, stream));
                 }
                 break;
             }
@@ -607,14 +783,18 @@ async fn execute_tool_calls(
     }
 }
 
+const SKIPPED_FOR_STEERING: &str = "Skipped due to queued user message.";
+
 fn skip_tool_call(
     tool_call: &ToolCall,
+    reason: &str,
     stream: &EventStream<AgentEvent, Vec<AgentMessage>>,
 ) -> ToolResultMessage {
     let result = AgentToolResult {
         content: vec![ContentBlock::Text(TextContent {
-            text: "Skipped due to queued user message.".to_string(),
+            text: reason.to_string(),
             text_signature: None,
+            citations: None,
         })],
         details: Some(serde_json::json!({})),
     };
@@ -649,3 +829,339 @@ fn skip_tool_call(
 
     tool_result_msg
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use async_trait::async_trait;
+    use serde_json::json;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Instant;
+
+    struct SleepTool {
+        definition: Tool,
+        delay: Duration,
+    }
+
+    #[async_trait]
+    impl AgentTool for SleepTool {
+        fn name(&self) -> &str {
+            "sleep"
+        }
+
+        fn label(&self) -> &str {
+            "Sleep"
+        }
+
+        fn definition(&self) -> &Tool {
+            &self.definition
+        }
+
+        async fn execute(
+            &self,
+            _tool_call_id: &str,
+            _params: serde_json::Value,
+            cancel: CancellationToken,
+            _on_update: Option<Box<dyn Fn(AgentToolResult) + Send + Sync>>,
+        ) -> Result<AgentToolResult, Box<dyn std::error::Error + Send + Sync>> {
+            tokio::select! {
+                _ = tokio::time::sleep(self.delay) => {}
+                _ = cancel.cancelled() => return Err("cancelled".into()),
+            }
+            Ok(AgentToolResult {
+                content: vec![ContentBlock::Text(TextContent {
+                    text: "slept".to_string(),
+                    text_signature: None,
+                    citations: None,
+                })],
+                details: None,
+            })
+        }
+    }
+
+    fn test_model() -> Model {
+        Model {
+            id: "test".to_string(),
+            name: "Test".to_string(),
+            api: "test".to_string(),
+            provider: "test".to_string(),
+            base_url: String::new(),
+            reasoning: false,
+            input: vec!["text".to_string()],
+            cost: ModelCost::default(),
+            context_window: 100_000,
+            max_tokens: 1000,
+            headers: None,
+            compat: None,
+        }
+    }
+
+    static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+
+    /// A model that always asks for `calls` sleep tool calls, costing $0.01.
+    fn tool_calling_stream(calls: usize) -> StreamFnBox {
+        Arc::new(move |model, _context, _options| {
+            let stream = crate::event_stream::create_assistant_message_event_stream();
+            let mut message = AssistantMessage::empty(model);
+            message.content = (0..calls)
+                .map(|i| {
+                    ContentBlock::ToolCall(ToolCall {
+                        id: format!("call-{}-{i}", NEXT_ID.fetch_add(1, Ordering::Relaxed)),
+                        name: "sleep".to_string(),
+                        arguments: json!({}),
+                        thought_signature: None,
+                    })
+                })
+                .collect();
+            message.usage.cost.total = 0.01;
+            message.stop_reason = StopReason::ToolUse;
+            stream.push(AssistantMessageEvent::Done {
+                reason: StopReason::ToolUse,
+                message,
+            });
+            stream
+        })
+    }
+
+    async fn run(
+        limits: AgentLimits,
+        calls: usize,
+        delay: Duration,
+    ) -> (Vec<AgentEvent>, Vec<AgentMessage>) {
+        run_with(limits, None, None, tool_calling_stream(calls), delay).await
+    }
+
+    async fn run_with(
+        limits: AgentLimits,
+        limit_progress: Option<Arc<Mutex<LimitProgress>>>,
+        tool_choice: Option<ToolChoice>,
+        stream_fn: StreamFnBox,
+        delay: Duration,
+    ) -> (Vec<AgentEvent>, Vec<AgentMessage>) {
+        let config = AgentLoopConfig {
+            model: test_model(),
+            reasoning: None,
+            thinking_budgets: None,
+            temperature: None,
+            max_tokens: None,
+            api_key: None,
+            cache_retention: None,
+            session_id: None,
+            headers: None,
+            max_retry_delay_ms: None,
+            tool_choice,
+            top_p: None,
+            top_k: None,
+            stop_sequences: None,
+            seed: None,
+            frequency_penalty: None,
+            presence_penalty: None,
+            convert_to_llm: Arc::new(|messages: &[AgentMessage]| {
+                let messages = messages
+                    .iter()
+                    .filter_map(|m| m.as_message().cloned())
+                    .collect();
+                Box::pin(async move { messages })
+            }),
+            transform_context: None,
+            get_api_key: None,
+            get_steering_messages: None,
+            get_follow_up_messages: None,
+            limits,
+            limit_progress,
+        };
+        let tool: Arc<dyn AgentTool> = Arc::new(SleepTool {
+            definition: Tool {
+                name: "sleep".to_string(),
+                description: "Sleep".to_string(),
+                parameters: json!({"type": "object", "properties": {}}),
+            },
+            delay,
+        });
+        let stream = agent_loop(
+            vec![AgentMessage::user("go")],
+            AgentContext {
+                system_prompt: String::new(),
+                messages: Vec::new(),
+                tools: vec![tool],
+            },
+            config,
+            CancellationToken::new(),
+            Some(stream_fn),
+        );
+        let events: Vec<AgentEvent> = stream.clone().collect().await;
+        let messages = stream.result().await.unwrap_or_default();
+        (events, messages)
+    }
+
+    fn limit_reason(events: &[AgentEvent]) -> Option<LimitReason> {
+        let reasons: Vec<_> = events
+            .iter()
+            .filter_map(|e| match e {
+                AgentEvent::LimitReached { reason } => Some(reason.clone()),
+                _ => None,
+            })
+            .collect();
+        assert!(reasons.len() <= 1);
+        assert!(matches!(events.last(), Some(AgentEvent::AgentEnd { .. })));
+        reasons.into_iter().next()
+    }
+
+    fn count_tool_results(messages: &[AgentMessage], skipped: bool) -> usize {
+        messages
+            .iter()
+            .filter(|m| match m {
+                AgentMessage::Llm(Message::ToolResult(r)) => r.is_error == skipped,
+                _ => false,
+            })
+            .count()
+    }
+
+    #[tokio::test]
+    async fn test_max_turns_stops_before_next_request() {
+        let limits = AgentLimits {
+            max_turns: Some(2),
+            ..AgentLimits::default()
+        };
+        let (events, messages) = run(limits, 1, Duration::ZERO).await;
+        assert_eq!(
+            limit_reason(&events),
+            Some(LimitReason::MaxTurns { limit: 2 })
+        );
+        assert_eq!(count_tool_results(&messages, false), 2);
+    }
+
+    #[tokio::test]
+    async fn test_max_tool_calls_skips_remaining_calls() {
+        let limits = AgentLimits {
+            max_tool_calls: Some(3),
+            ..AgentLimits::default()
+        };
+        let (events, messages) = run(limits, 2, Duration::ZERO).await;
+        assert_eq!(
+            limit_reason(&events),
+            Some(LimitReason::MaxToolCalls { limit: 3 })
+        );
+        assert_eq!(count_tool_results(&messages, false), 3);
+        assert_eq!(count_tool_results(&messages, true), 1);
+    }
+
+    #[tokio::test]
+    async fn test_cost_limits() {
+        let limits = AgentLimits {
+            max_cost: Some(0.025),
+            ..AgentLimits::default()
+        };
+        let (events, messages) = run(limits, 1, Duration::ZERO).await;
+        assert!(matches!(
+            limit_reason(&events),
+            Some(LimitReason::MaxCost { .. })
+        ));
+        // The third response crosses the limit; its tool call is skipped.
+        assert_eq!(count_tool_results(&messages, false), 2);
+        assert_eq!(count_tool_results(&messages, true), 1);
+
+        let exhausted = AgentLimits {
+            max_session_cost: Some(1.0),
+            session_cost: 1.5,
+            ..AgentLimits::default()
+        };
+        let (events, messages) = run(exhausted, 1, Duration::ZERO).await;
+        assert!(matches!(
+            limit_reason(&events),
+            Some(LimitReason::MaxSessionCost { .. })
+        ));
+        assert!(
+            !messages
+                .iter()
+                .any(|m| matches!(m, AgentMessage::Llm(Message::Assistant(_))))
+        );
+    }
+
+    #[tokio::test]
+    async fn test_max_duration_cancels_running_tool() {
+        let limits = AgentLimits {
+            max_duration_ms: Some(50),
+            ..AgentLimits::default()
+        };
+        let started = Instant::now();
+        let (events, _) = run(limits, 1, Duration::from_secs(30)).await;
+        assert!(started.elapsed() < Duration::from_secs(5));
+        assert_eq!(
+            limit_reason(&events),
+            Some(LimitReason::MaxDuration { limit_ms: 50 })
+        );
+    }
+
+    #[tokio::test]
+    async fn test_no_limits_runs_until_done() {
+        let (events, _) = run(AgentLimits::default(), 0, Duration::ZERO).await;
+        assert_eq!(limit_reason(&events), None);
+    }
+
+    #[tokio::test]
+    async fn test_forced_tool_choice_applies_to_first_request_only() {
+        let seen = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let inner = tool_calling_stream(1);
+        let recorder = seen.clone();
+        let stream_fn: StreamFnBox = Arc::new(move |model, context, options| {
+            recorder
+                .lock()
+                .unwrap()
+                .push(options.base.tool_choice.clone());
+            inner(model, context, options)
+        });
+        let limits = AgentLimits {
+            max_turns: Some(3),
+            ..AgentLimits::default()
+        };
+        run_with(
+            limits,
+            None,
+            Some(ToolChoice::Required),
+            stream_fn,
+            Duration::ZERO,
+        )
+        .await;
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                Some(ToolChoice::Required),
+                Some(ToolChoice::Auto),
+                Some(ToolChoice::Auto)
+            ]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_limit_progress_carries_across_attempts() {
+        let limits = AgentLimits {
+            max_turns: Some(3),
+            max_tool_calls: Some(3),
+            ..AgentLimits::default()
+        };
+        let progress = LimitProgress::shared();
+        let stream = || tool_calling_stream(1);
+        let (events, _) = run_with(
+            limits.clone(),
+            Some(progress.clone()),
+            None,
+            stream(),
+            Duration::ZERO,
+        )
+        .await;
+        assert_eq!(
+            limit_reason(&events),
+            Some(LimitReason::MaxTurns { limit: 3 })
+        );
+
+        // A retry of the same prompt starts with the budget already spent.
+        let (events, messages) =
+            run_with(limits, Some(progress), None, stream(), Duration::ZERO).await;
+        assert_eq!(
+            limit_reason(&events),
+            Some(LimitReason::MaxTurns { limit: 3 })
+        );
+        assert_eq!(count_tool_results(&messages, false), 0);
+    }
+}
@@ -2,7 +2,8 @@ use std::collections::HashSet;
 use std::fmt;
 use std::future::Future;
 use std::pin::Pin;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
 
 use async_trait::async_trait;
 use serde::{Deserialize, Serialize};
@@ -210,6 +211,11 @@ pub enum AgentEvent {
         result: AgentToolResult,
         is_error: bool,
     },
+    /// The loop stopped because a configured [`AgentLimits`] bound was hit.
+    /// Emitted once, right before `AgentEnd`.
+    LimitReached {
+        reason: LimitReason,
+    },
 }
 
 impl AgentEvent {
@@ -225,6 +231,7 @@ impl AgentEvent {
             AgentEvent::ToolExecutionStart { .. } => "tool_execution_start",
             AgentEvent::ToolExecutionUpdate { .. } => "tool_execution_update",
             AgentEvent::ToolExecutionEnd { .. } => "tool_execution_end",
+            AgentEvent::LimitReached { .. } => "limit_reached",
         }
     }
 }
@@ -239,6 +246,106 @@ pub enum QueueMode {
     OneAtATime,
 }
 
+// ---------- AgentLimits ----------
+
+/// Bounds on a single agent loop run. `None` means unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct AgentLimits {
+    /// Maximum assistant responses.
+    pub max_turns: Option<u32>,
+    /// Maximum tool executions; calls beyond it are skipped.
+    pub max_tool_calls: Option<u32>,
+    /// Wall-clock limit; in-flight requests and tools are cancelled.
+    pub max_duration_ms: Option<u64>,
+    /// Maximum spend in USD for this run.
+    pub max_cost: Option<f64>,
+    /// Maximum spend in USD for the whole session.
+    pub max_session_cost: Option<f64>,
+    /// Session spend before this run, counted against `max_session_cost`.
+    #[serde(default)]
+    pub session_cost: f64,
+}
+
+impl AgentLimits {
+    /// The limit already exhausted before any request is made.
+    pub fn exhausted(&self) -> Option<LimitReason> {
+        match self.max_session_cost {
+            Some(limit) if self.session_cost >= limit => Some(LimitReason::MaxSessionCost {
+                limit,
+                spent: self.session_cost,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// What a run has used of its [`AgentLimits`].
+#[derive(Debug, Clone)]
+pub struct LimitProgress {
+    pub started: Instant,
+    pub turns: u32,
+    pub tool_calls: u32,
+    pub cost: f64,
+}
+
+impl LimitProgress {
+    /// Progress of a run starting now.
+    pub fn new() -> Self {
+        Self {
+            started: Instant::now(),
+            turns: 0,
+            tool_calls: 0,
+            cost: 0.0,
+        }
+    }
+
+    /// New progress to hand to every attempt of one prompt.
+    pub fn shared() -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self::new()))
+    }
+}
+
+impl Default for LimitProgress {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Which [`AgentLimits`] bound stopped the loop.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum LimitReason {
+    MaxTurns { limit: u32 },
+    MaxToolCalls { limit: u32 },
+    MaxDuration { limit_ms: u64 },
+    MaxCost { limit: f64, spent: f64 },
+    MaxSessionCost { limit: f64, spent: f64 },
+}
+
+impl fmt::Display for LimitReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            LimitReason::MaxTurns { limit } => write!(f, "turn limit of {limit} reached"),
+            LimitReason::MaxToolCalls { limit } => {
+                write!(f, "tool call limit of {limit} reached")
+            }
+            LimitReason::MaxDuration { limit_ms } => {
+                write!(f, "time limit of {:.1}s reached", *limit_ms as f64 / 1000.0)
+            }
+            LimitReason::MaxCost { limit, spent } => {
+                write!(f, "cost limit of ${limit:.4} reached (spent ${spent:.4})")
+            }
+            LimitReason::MaxSessionCost { limit, spent } => {
+                write!(
+                    f,
+                    "session cost limit of ${limit:.4} reached (spent ${spent:.4})"
+                )
+            }
+        }
+    }
+}
+
 // ---------- AgentLoopConfig ----------
 
 pub struct AgentLoopConfig {
@@ -252,11 +359,22 @@ pub struct AgentLoopConfig {
     pub session_id: Option<String>,
     pub headers: Option<std::collections::HashMap<String, String>>,
     pub max_retry_delay_ms: Option<u64>,
+    pub tool_choice: Option<ToolChoice>,
+    pub top_p: Option<f64>,
+    pub top_k: Option<u64>,
+    pub stop_sequences: Option<Vec<String>>,
+    pub seed: Option<u64>,
+    pub frequency_penalty: Option<f64>,
+    pub presence_penalty: Option<f64>,
     pub convert_to_llm: Arc<ConvertToLlmFn>,
     pub transform_context: Option<Arc<TransformContextFn>>,
     pub get_api_key: Option<Arc<GetApiKeyFn>>,
     pub get_steering_messages: Option<Arc<MessageQueueFn>>,
     pub get_follow_up_messages: Option<Arc<MessageQueueFn>>,
+    pub limits: AgentLimits,
+    /// Progress against `limits` shared across attempts of one prompt, so a
+    /// retried run continues its budgets; a fresh run's own when unset.
+    pub limit_progress: Option<Arc<Mutex<LimitProgress>>>,
 }
 
 // ---------- StreamFn type ----------
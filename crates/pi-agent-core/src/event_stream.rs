@@ -159,6 +159,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "Hello".to_string(),
                 text_signature: None,
+                citations: None,
             })],
             api: "test".to_string(),
             provider: "test".to_string(),
@@ -167,6 +168,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         }
     }
 
@@ -73,6 +73,7 @@ mod tests {
             stop_reason: StopReason::Error,
             error_message: Some(error.to_string()),
             timestamp: 0,
+            rate_limit: None,
         }
     }
 
@@ -116,6 +117,7 @@ mod tests {
             },
             stop_reason: StopReason::Stop,
             error_message: None,
+            rate_limit: None,
             timestamp: 0,
         };
         assert!(is_context_overflow(&msg, Some(200000)));
@@ -1,4 +1,4 @@
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
 use crate::event_stream::{AssistantMessageEventStream, create_assistant_message_event_stream};
@@ -7,7 +7,9 @@ use crate::types::*;
 
 // ---------- ProxyAssistantMessageEvent ----------
 
-#[derive(Debug, Clone, Deserialize)]
+/// Delta-only form of [`AssistantMessageEvent`]: the `partial` message is
+/// stripped and rebuilt by the receiver with [`ProxyMessageBuilder`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum ProxyAssistantMessageEvent {
     Start,
@@ -69,6 +71,120 @@ pub enum ProxyAssistantMessageEvent {
     },
 }
 
+impl ProxyAssistantMessageEvent {
+    /// Strip the partial message from an event.
+    pub fn from_event(event: &AssistantMessageEvent) -> Self {
+        match event {
+            AssistantMessageEvent::Start { .. } => Self::Start,
+            AssistantMessageEvent::TextStart { content_index, .. } => Self::TextStart {
+                content_index: *content_index,
+            },
+            AssistantMessageEvent::TextDelta {
+                content_index,
+                delta,
+                ..
+            } => Self::TextDelta {
+                content_index: *content_index,
+                delta: delta.clone(),
+            },
+            AssistantMessageEvent::TextEnd {
+                content_index,
+                partial,
+                ..
+            } => Self::TextEnd {
+                content_index: *content_index,
+                content_signature: match partial.content.get(*content_index) {
+                    Some(ContentBlock::Text(t)) => t.text_signature.clone(),
+                    _ => None,
+                },
+            },
+            AssistantMessageEvent::ThinkingStart { content_index, .. } => Self::ThinkingStart {
+                content_index: *content_index,
+            },
+            AssistantMessageEvent::ThinkingDelta {
+                content_index,
+                delta,
+                ..
+            } => Self::ThinkingDelta {
+                content_index: *content_index,
+                delta: delta.clone(),
+            },
+            AssistantMessageEvent::ThinkingEnd {
+                content_index,
+                partial,
+                ..
+            } => Self::ThinkingEnd {
+                content_index: *content_index,
+                content_signature: match partial.content.get(*content_index) {
+                    Some(ContentBlock::Thinking(t)) => t.thinking_signature.clone(),
+                    _ => None,
+                },
+            },
+            AssistantMessageEvent::ToolCallStart {
+                content_index,
+                partial,
+            } => {
+                let (id, tool_name) = match partial.content.get(*content_index) {
+                    Some(ContentBlock::ToolCall(tc)) => (tc.id.clone(), tc.name.clone()),
+                    _ => (String::new(), String::new()),
+                };
+                Self::ToolcallStart {
+                    content_index: *content_index,
+                    id,
+                    tool_name,
+                }
+            }
+            AssistantMessageEvent::ToolCallDelta {
+                content_index,
+                delta,
+                ..
+            } => Self::ToolcallDelta {
+                content_index: *content_index,
+                delta: delta.clone(),
+            },
+            AssistantMessageEvent::ToolCallEnd { content_index, .. } => Self::ToolcallEnd {
+                content_index: *content_index,
+            },
+            AssistantMessageEvent::Done { reason, message } => Self::Done {
+                reason: reason.clone(),
+                usage: message.usage.clone(),
+            },
+            AssistantMessageEvent::Error { reason, error } => Self::Error {
+                reason: reason.clone(),
+                error_message: error.error_message.clone(),
+                usage: error.usage.clone(),
+            },
+        }
+    }
+}
+
+// ---------- ProxyMessageBuilder ----------
+
+/// Rebuilds full [`AssistantMessageEvent`]s from delta-only proxy events.
+pub struct ProxyMessageBuilder {
+    partial: AssistantMessage,
+    partial_json_map: std::collections::HashMap<usize, String>,
+}
+
+impl ProxyMessageBuilder {
+    pub fn new(model: &Model) -> Self {
+        Self {
+            partial: AssistantMessage::empty(model),
+            partial_json_map: std::collections::HashMap::new(),
+        }
+    }
+
+    /// Apply one proxy event, returning the reconstructed event.
+    pub fn apply(&mut self, event: ProxyAssistantMessageEvent) -> Option<AssistantMessageEvent> {
+        process_proxy_event(event, &mut self.partial, &mut self.partial_json_map)
+    }
+
+    /// The message built so far.
+    pub fn partial(&self) -> &AssistantMessage {
+        &self.partial
+    }
+}
+
 // ---------- ProxyStreamOptions ----------
 
 #[derive(Debug, Clone)]
@@ -76,6 +192,10 @@ pub struct ProxyStreamOptions {
     pub base: SimpleStreamOptions,
     pub auth_token: String,
     pub proxy_url: String,
+    /// Client to send the request with; a default client when unset.
+    /// `pi_agent_ai::http_client::proxy_stream_options` fills in the shared,
+    /// configured client.
+    pub client: Option<reqwest::Client>,
 }
 
 /// Stream function that proxies through a server.
@@ -105,7 +225,7 @@ pub fn stream_proxy(
             }
         });
 
-        let client = reqwest::Client::new();
+        let client = options.client.clone().unwrap_or_default();
         let url = format!("{}/api/stream", options.proxy_url);
 
         let response = match client
@@ -222,11 +342,13 @@ fn process_proxy_event(
                 partial.content.push(ContentBlock::Text(TextContent {
                     text: String::new(),
                     text_signature: None,
+                    citations: None,
                 }));
             }
             partial.content[content_index] = ContentBlock::Text(TextContent {
                 text: String::new(),
                 text_signature: None,
+                citations: None,
             });
             Some(AssistantMessageEvent::TextStart {
                 content_index,
@@ -271,6 +393,7 @@ fn process_proxy_event(
                 partial.content.push(ContentBlock::Text(TextContent {
                     text: String::new(),
                     text_signature: None,
+                    citations: None,
                 }));
             }
             partial.content[content_index] = ContentBlock::Thinking(ThinkingContent {
@@ -324,6 +447,7 @@ fn process_proxy_event(
                 partial.content.push(ContentBlock::Text(TextContent {
                     text: String::new(),
                     text_signature: None,
+                    citations: None,
                 }));
             }
             partial.content[content_index] = ContentBlock::ToolCall(ToolCall {
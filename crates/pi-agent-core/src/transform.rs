@@ -54,19 +54,38 @@ pub fn transform_messages(
                             Some(ContentBlock::Text(TextContent {
                                 text: t.thinking.clone(),
                                 text_signature: None,
+                                citations: None,
                             }))
                         }
                         ContentBlock::Text(t) => {
                             if is_same_model {
                                 Some(block.clone())
                             } else {
-                                // Strip textSignature for cross-model
+                                // Strip textSignature and provider-specific citation
+                                // payloads for cross-model
                                 Some(ContentBlock::Text(TextContent {
                                     text: t.text.clone(),
                                     text_signature: None,
+                                    citations: t.citations.as_ref().map(|citations| {
+                                        citations
+                                            .iter()
+                                            .map(|c| Citation {
+                                                provider_data: None,
+                                                ..c.clone()
+                                            })
+                                            .collect()
+                                    }),
                                 }))
                             }
                         }
+                        // Redacted thinking is only meaningful to the model that produced it
+                        ContentBlock::RedactedThinking(_) => {
+                            if is_same_model {
+                                Some(block.clone())
+                            } else {
+                                None
+                            }
+                        }
                         ContentBlock::ToolCall(tc) => {
                             let mut normalized = tc.clone();
 
@@ -118,6 +137,7 @@ pub fn transform_messages(
                                 content: vec![ContentBlock::Text(TextContent {
                                     text: "No result provided".to_string(),
                                     text_signature: None,
+                                    citations: None,
                                 })],
                                 details: None,
                                 is_error: true,
@@ -164,6 +184,7 @@ pub fn transform_messages(
                                 content: vec![ContentBlock::Text(TextContent {
                                     text: "No result provided".to_string(),
                                     text_signature: None,
+                                    citations: None,
                                 })],
                                 details: None,
                                 is_error: true,
@@ -189,6 +210,7 @@ pub fn transform_messages(
                     content: vec![ContentBlock::Text(TextContent {
                         text: "No result provided".to_string(),
                         text_signature: None,
+                        citations: None,
                     })],
                     details: None,
                     is_error: true,
@@ -234,6 +256,7 @@ mod tests {
                 ContentBlock::Text(TextContent {
                     text: "hello".to_string(),
                     text_signature: None,
+                    citations: None,
                 }),
             ],
             api: "anthropic-messages".to_string(),
@@ -243,6 +266,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         })];
 
         let result = transform_messages(&messages, &model, None);
@@ -270,6 +294,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         })];
 
         let result = transform_messages(&messages, &model, None);
@@ -281,6 +306,117 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_mid_session_model_switch_only_keeps_own_signed_thinking() {
+        // A fallback model on the same provider served the second turn; its
+        // signed thinking must not be replayed to the primary model.
+        let model = test_model();
+        let assistant = |model_id: &str, thinking: &str| {
+            Message::Assistant(AssistantMessage {
+                content: vec![ContentBlock::Thinking(ThinkingContent {
+                    thinking: thinking.to_string(),
+                    thinking_signature: Some(format!("{model_id}-sig")),
+                })],
+                api: "anthropic-messages".to_string(),
+                provider: "anthropic".to_string(),
+                model: model_id.to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 0,
+                rate_limit: None,
+            })
+        };
+        let messages = vec![
+            assistant("claude-sonnet-4", "primary turn"),
+            assistant("claude-haiku-4", "fallback turn"),
+        ];
+
+        let result = transform_messages(&messages, &model, None);
+        let Message::Assistant(primary) = &result[0] else {
+            panic!("expected assistant");
+        };
+        assert!(primary.content[0].as_thinking().is_some());
+        let Message::Assistant(fallback) = &result[1] else {
+            panic!("expected assistant");
+        };
+        assert_eq!(fallback.content[0].as_text().unwrap().text, "fallback turn");
+    }
+
+    #[test]
+    fn test_redacted_thinking_kept_only_for_same_model() {
+        let model = test_model();
+        let assistant = |provider: &str, model_id: &str| {
+            Message::Assistant(AssistantMessage {
+                content: vec![
+                    ContentBlock::RedactedThinking(RedactedThinkingContent {
+                        data: "opaque".to_string(),
+                    }),
+                    ContentBlock::Text(TextContent {
+                        text: "answer".to_string(),
+                        text_signature: None,
+                        citations: None,
+                    }),
+                ],
+                api: "anthropic-messages".to_string(),
+                provider: provider.to_string(),
+                model: model_id.to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 0,
+                rate_limit: None,
+            })
+        };
+
+        let same = transform_messages(&[assistant("anthropic", "claude-sonnet-4")], &model, None);
+        let a = same[0].as_assistant().unwrap();
+        assert_eq!(a.content.len(), 2);
+        assert_eq!(a.content[0].as_redacted_thinking().unwrap().data, "opaque");
+
+        let cross = transform_messages(&[assistant("anthropic", "claude-opus-4")], &model, None);
+        let a = cross[0].as_assistant().unwrap();
+        assert_eq!(a.content.len(), 1);
+        assert!(a.content[0].as_text().is_some());
+    }
+
+    #[test]
+    fn test_cross_model_keeps_citations_without_provider_data() {
+        let model = test_model();
+        let messages = vec![Message::Assistant(AssistantMessage {
+            content: vec![ContentBlock::Text(TextContent {
+                text: "Rust 1.0 shipped in 2015.".to_string(),
+                text_signature: Some("msg_1".to_string()),
+                citations: Some(vec![Citation {
+                    url: Some("https://blog.rust-lang.org".to_string()),
+                    title: Some("Rust Blog".to_string()),
+                    provider_data: Some(serde_json::json!({"type": "url_citation"})),
+                    ..Default::default()
+                }]),
+            })],
+            api: "openai-responses".to_string(),
+            provider: "openai".to_string(),
+            model: "gpt-5".to_string(),
+            usage: Usage::default(),
+            stop_reason: StopReason::Stop,
+            error_message: None,
+            rate_limit: None,
+            timestamp: 0,
+        })];
+
+        let result = transform_messages(&messages, &model, None);
+        let text = result[0].as_assistant().unwrap().content[0]
+            .as_text()
+            .unwrap();
+        assert!(text.text_signature.is_none());
+        let citations = text.citations.as_ref().unwrap();
+        assert_eq!(
+            citations[0].url.as_deref(),
+            Some("https://blog.rust-lang.org")
+        );
+        assert!(citations[0].provider_data.is_none());
+    }
+
     #[test]
     fn test_orphaned_tool_calls_get_synthetic_results() {
         let model = test_model();
@@ -299,12 +435,14 @@ mod tests {
                 stop_reason: StopReason::ToolUse,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             }),
             // No tool result, directly another assistant message
             Message::Assistant(AssistantMessage {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "ok".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: "anthropic-messages".to_string(),
                 provider: "anthropic".to_string(),
@@ -313,6 +451,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             }),
         ];
 
@@ -339,6 +478,7 @@ mod tests {
                 stop_reason: StopReason::Error,
                 error_message: Some("rate limit".to_string()),
                 timestamp: 0,
+                rate_limit: None,
             }),
         ];
 
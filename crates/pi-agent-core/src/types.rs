@@ -89,6 +89,50 @@ pub enum CacheRetention {
     Long,
 }
 
+// ---------- ResponseFormat ----------
+
+/// Requested shape of the model's answer. Providers map this to their native
+/// structured-output feature, or force a tool call whose input is the answer.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ResponseFormat {
+    Text,
+    JsonObject,
+    JsonSchema {
+        name: String,
+        schema: Value,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        description: Option<String>,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        strict: Option<bool>,
+    },
+}
+
+// ---------- ToolChoice ----------
+
+/// Provider-neutral tool selection policy.
+///
+/// Serialized as `"auto"`, `"none"`, `"required"` or `{"tool": "<name>"}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ToolChoice {
+    /// The model decides whether to call tools.
+    Auto,
+    /// The model must not call tools.
+    None,
+    /// The model must call at least one tool.
+    Required,
+    /// The model must call the named tool.
+    Tool(String),
+}
+
+impl ToolChoice {
+    /// Whether the model is made to call a tool rather than answer.
+    pub fn is_forced(&self) -> bool {
+        matches!(self, ToolChoice::Required | ToolChoice::Tool(_))
+    }
+}
+
 // ---------- StreamOptions ----------
 
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
@@ -101,6 +145,22 @@ pub struct StreamOptions {
     pub session_id: Option<String>,
     pub headers: Option<HashMap<String, String>>,
     pub max_retry_delay_ms: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub response_format: Option<ResponseFormat>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
 }
 
 // ---------- SimpleStreamOptions ----------
@@ -122,6 +182,32 @@ pub struct TextContent {
     pub text: String,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub text_signature: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub citations: Option<Vec<Citation>>,
+}
+
+/// A source reference attached to a text block, from document citations
+/// or web-search grounding.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Citation {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cited_text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub document_index: Option<u64>,
+    /// Start offset of the cited span (in the source document for document
+    /// citations, in the response text for web grounding).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_index: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_index: Option<u64>,
+    /// Raw provider payload, replayed verbatim to the same provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider_data: Option<Value>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -132,6 +218,14 @@ pub struct ThinkingContent {
     pub thinking_signature: Option<String>,
 }
 
+/// Thinking that the provider encrypted for safety reasons. The opaque
+/// `data` must be sent back unchanged to keep multi-turn tool use valid.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RedactedThinkingContent {
+    pub data: String,
+}
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ImageContent {
@@ -155,6 +249,7 @@ pub struct ToolCall {
 pub enum ContentBlock {
     Text(TextContent),
     Thinking(ThinkingContent),
+    RedactedThinking(RedactedThinkingContent),
     Image(ImageContent),
     ToolCall(ToolCall),
 }
@@ -164,6 +259,7 @@ impl ContentBlock {
         match self {
             ContentBlock::Text(_) => "text",
             ContentBlock::Thinking(_) => "thinking",
+            ContentBlock::RedactedThinking(_) => "redactedThinking",
             ContentBlock::Image(_) => "image",
             ContentBlock::ToolCall(_) => "toolCall",
         }
@@ -183,6 +279,13 @@ impl ContentBlock {
         }
     }
 
+    pub fn as_redacted_thinking(&self) -> Option<&RedactedThinkingContent> {
+        match self {
+            ContentBlock::RedactedThinking(r) => Some(r),
+            _ => None,
+        }
+    }
+
     pub fn as_image(&self) -> Option<&ImageContent> {
         match self {
             ContentBlock::Image(i) => Some(i),
@@ -209,6 +312,9 @@ impl Serialize for ContentBlock {
                 if let Some(sig) = &t.text_signature {
                     map.serialize_entry("textSignature", sig)?;
                 }
+                if let Some(citations) = &t.citations {
+                    map.serialize_entry("citations", citations)?;
+                }
                 map.end()
             }
             ContentBlock::Thinking(t) => {
@@ -220,6 +326,12 @@ impl Serialize for ContentBlock {
                 }
                 map.end()
             }
+            ContentBlock::RedactedThinking(r) => {
+                let mut map = serializer.serialize_map(None)?;
+                map.serialize_entry("type", "redactedThinking")?;
+                map.serialize_entry("data", &r.data)?;
+                map.end()
+            }
             ContentBlock::Image(i) => {
                 let mut map = serializer.serialize_map(None)?;
                 map.serialize_entry("type", "image")?;
@@ -264,9 +376,14 @@ impl<'de> Deserialize<'de> for ContentBlock {
                     .get("textSignature")
                     .and_then(|v| v.as_str())
                     .map(String::from);
+                let citations = obj
+                    .get("citations")
+                    .cloned()
+                    .and_then(|v| serde_json::from_value(v).ok());
                 Ok(ContentBlock::Text(TextContent {
                     text,
                     text_signature,
+                    citations,
                 }))
             }
             "thinking" => {
@@ -284,6 +401,16 @@ impl<'de> Deserialize<'de> for ContentBlock {
                     thinking_signature,
                 }))
             }
+            "redactedThinking" => {
+                let data = obj
+                    .get("data")
+                    .and_then(|v| v.as_str())
+                    .unwrap_or("")
+                    .to_string();
+                Ok(ContentBlock::RedactedThinking(RedactedThinkingContent {
+                    data,
+                }))
+            }
             "image" => {
                 let data = obj
                     .get("data")
@@ -353,6 +480,29 @@ pub struct Usage {
     pub cost: UsageCost,
 }
 
+/// Provider rate-limit state reported in response headers.
+///
+/// Reset times are Unix timestamps in milliseconds.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RateLimitInfo {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_remaining: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub requests_reset_at: Option<i64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_limit: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_remaining: Option<u64>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tokens_reset_at: Option<i64>,
+    /// Server-requested delay before the next request, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub retry_after_ms: Option<u64>,
+}
+
 // ---------- StopReason ----------
 
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -429,6 +579,8 @@ pub struct AssistantMessage {
     pub stop_reason: StopReason,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error_message: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<Box<RateLimitInfo>>,
     pub timestamp: i64,
 }
 
@@ -442,6 +594,7 @@ impl AssistantMessage {
             usage: Usage::default(),
             stop_reason: StopReason::Stop,
             error_message: None,
+            rate_limit: None,
             timestamp: chrono::Utc::now().timestamp_millis(),
         }
     }
@@ -530,6 +683,9 @@ impl Serialize for Message {
                 if let Some(err) = &msg.error_message {
                     map.serialize_entry("errorMessage", err)?;
                 }
+                if let Some(rate_limit) = &msg.rate_limit {
+                    map.serialize_entry("rateLimit", rate_limit)?;
+                }
                 map.serialize_entry("timestamp", &msg.timestamp)?;
                 map.end()
             }
@@ -605,6 +761,10 @@ impl<'de> Deserialize<'de> for Message {
                     .get("errorMessage")
                     .and_then(|v| v.as_str())
                     .map(String::from);
+                let rate_limit = obj
+                    .get("rateLimit")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .map(Box::new);
                 let timestamp = obj.get("timestamp").and_then(|v| v.as_i64()).unwrap_or(0);
                 Ok(Message::Assistant(AssistantMessage {
                     content,
@@ -615,6 +775,7 @@ impl<'de> Deserialize<'de> for Message {
                     stop_reason,
                     error_message,
                     timestamp,
+                    rate_limit,
                 }))
             }
             "toolResult" => {
@@ -862,6 +1023,7 @@ mod tests {
                 ContentBlock::Text(TextContent {
                     text: "Hello, world!".to_string(),
                     text_signature: None,
+                    citations: None,
                 }),
                 ContentBlock::ToolCall(ToolCall {
                     id: "call_1".to_string(),
@@ -890,9 +1052,14 @@ mod tests {
             stop_reason: StopReason::ToolUse,
             error_message: None,
             timestamp: 1700000000000,
+            rate_limit: Some(Box::new(RateLimitInfo {
+                requests_remaining: Some(12),
+                ..Default::default()
+            })),
         });
 
         let json = serde_json::to_string_pretty(&assistant_msg).unwrap();
+        assert!(json.contains("\"rateLimit\""));
         let deserialized: Message = serde_json::from_str(&json).unwrap();
 
         // Verify round-trip
@@ -921,6 +1088,7 @@ mod tests {
                 ContentBlock::Text(TextContent {
                     text: "Look at this".to_string(),
                     text_signature: None,
+                    citations: None,
                 }),
                 ContentBlock::Image(ImageContent {
                     data: "base64data".to_string(),
@@ -942,6 +1110,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "Found 3 results".to_string(),
                 text_signature: None,
+                citations: None,
             })],
             details: Some(serde_json::json!({"count": 3})),
             is_error: false,
@@ -952,6 +1121,49 @@ mod tests {
         assert_eq!(deserialized.role(), "toolResult");
     }
 
+    #[test]
+    fn test_redacted_thinking_and_citations_round_trip() {
+        let blocks = vec![
+            ContentBlock::RedactedThinking(RedactedThinkingContent {
+                data: "EmwKAhgBEgy3va".to_string(),
+            }),
+            ContentBlock::Text(TextContent {
+                text: "The grass is green.".to_string(),
+                text_signature: None,
+                citations: Some(vec![Citation {
+                    cited_text: Some("The grass is green.".to_string()),
+                    title: Some("Example Document".to_string()),
+                    document_index: Some(0),
+                    start_index: Some(0),
+                    end_index: Some(20),
+                    provider_data: Some(serde_json::json!({"type": "char_location"})),
+                    ..Default::default()
+                }]),
+            }),
+        ];
+
+        let json = serde_json::to_value(&blocks).unwrap();
+        assert_eq!(json[0]["type"], "redactedThinking");
+        assert_eq!(json[1]["citations"][0]["citedText"], "The grass is green.");
+
+        let deserialized: Vec<ContentBlock> = serde_json::from_value(json.clone()).unwrap();
+        assert_eq!(
+            deserialized[0].as_redacted_thinking().unwrap().data,
+            "EmwKAhgBEgy3va"
+        );
+        assert_eq!(serde_json::to_value(&deserialized).unwrap(), json);
+    }
+
+    #[test]
+    fn test_tool_choice_serde() {
+        assert_eq!(
+            serde_json::to_string(&ToolChoice::Required).unwrap(),
+            "\"required\""
+        );
+        let named: ToolChoice = serde_json::from_str(r#"{"tool": "bash"}"#).unwrap();
+        assert_eq!(named, ToolChoice::Tool("bash".to_string()));
+    }
+
     #[test]
     fn test_stop_reason_serde() {
         assert_eq!(
@@ -1,5 +1,5 @@
 use pi_agent_core::agent_types::{AgentEvent, AgentMessage};
-use pi_agent_core::types::Model;
+use pi_agent_core::types::{Model, RateLimitInfo};
 
 /// Events emitted by AgentSession, extending AgentEvent with session-level events.
 #[derive(Debug, Clone)]
@@ -45,6 +45,12 @@ pub enum AgentSessionEvent {
     /// A retry attempt has completed.
     RetryEnd { attempt: u32, success: bool },
 
+    /// A provider reported its remaining rate-limit quota.
+    RateLimit {
+        provider: String,
+        info: RateLimitInfo,
+    },
+
     /// Error occurred at session level.
     Error { message: String },
 }
@@ -60,6 +66,7 @@ impl AgentSessionEvent {
             AgentSessionEvent::Forked { .. } => "forked",
             AgentSessionEvent::RetryStart { .. } => "retry_start",
             AgentSessionEvent::RetryEnd { .. } => "retry_end",
+            AgentSessionEvent::RateLimit { .. } => "rate_limit",
             AgentSessionEvent::Error { .. } => "session_error",
         }
     }
@@ -1,3 +1,4 @@
 pub mod events;
 pub mod sdk;
 pub mod session;
+pub mod title;
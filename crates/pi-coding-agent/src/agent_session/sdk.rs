@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::sync::Arc;
 
 use crate::agent_session::session::AgentSession;
@@ -9,7 +10,9 @@ use crate::extensions::{
     ExtensionRunner, create_extension_tools, discover_and_load_extensions,
     load_extensions_from_paths, wrap_tools_with_extensions,
 };
+use crate::model::discovery;
 use crate::model::registry::ModelRegistry;
+use crate::model::router::ModelRouter;
 use crate::resources::loader::{
     DefaultResourceLoader, DefaultResourceLoaderOptions, ResourceLoader,
 };
@@ -18,8 +21,6 @@ use crate::settings::manager::SettingsManager;
 use crate::settings::types::Settings;
 use crate::system_prompt::builder::{SystemPromptOptions, build_system_prompt};
 use crate::tools::{create_all_tools, create_coding_tools};
-use pi_agent_core::types::{AssistantMessage, AssistantMessageEvent, StopReason};
-use tokio_util::sync::CancellationToken;
 
 /// Options for creating an agent session.
 #[derive(Debug, Clone, Default)]
@@ -73,13 +74,101 @@ pub struct CreateSessionResult {
     pub extension_errors: Vec<crate::extensions::ExtensionLoadError>,
 }
 
+/// Apply per-provider `rateLimit` settings to the shared rate limiter.
+pub(crate) fn configure_rate_limits(settings: &Settings) {
+    let limiter = pi_agent_ai::rate_limit::rate_limiter();
+    for (provider, provider_settings) in settings.providers.iter().flatten() {
+        let config = provider_settings.rate_limit.as_ref().and_then(|limit| {
+            Some(pi_agent_ai::rate_limit::RateLimitConfig {
+                requests_per_minute: limit.requests_per_minute?,
+                burst: limit.burst,
+            })
+        });
+        limiter.configure(provider, config);
+    }
+}
+
+/// Per-provider API keys from `models.json` providers and from settings,
+/// which take precedence, still unresolved.
+pub(crate) fn configured_api_keys(
+    settings: &Settings,
+    models: &ModelRegistry,
+) -> HashMap<String, String> {
+    let mut keys = models.provider_api_keys();
+    keys.extend(
+        settings
+            .providers
+            .iter()
+            .flatten()
+            .filter_map(|(provider, provider_settings)| {
+                Some((provider.clone(), provider_settings.api_key.clone()?))
+            }),
+    );
+    keys
+}
+
+/// Apply `http` settings (global and per provider) to the shared HTTP clients.
+pub(crate) fn configure_http_clients(settings: &Settings) {
+    let providers = settings
+        .providers
+        .iter()
+        .flatten()
+        .filter_map(|(provider, provider_settings)| {
+            Some((provider.clone(), provider_settings.http.clone()?))
+        })
+        .collect();
+    if let Err(e) = pi_agent_ai::http_client::http_clients()
+        .configure(settings.http.clone().unwrap_or_default(), providers)
+    {
+        tracing::warn!("Invalid HTTP client settings: {e}");
+    }
+}
+
+/// Build the model router from `fallbackModels` and per-provider `apiKeys`.
+fn build_model_router(
+    session: &AgentSession,
+    registry: Arc<pi_agent_ai::registry::ApiRegistry>,
+    auth_storage: Arc<AuthStorage>,
+) -> ModelRouter {
+    let settings = session.settings_manager().settings();
+
+    let mut fallbacks = Vec::new();
+    for id in settings.fallback_models.iter().flatten() {
+        match session.model_registry().find(id) {
+            Some(model) => fallbacks.push(model.clone()),
+            None => tracing::warn!("Unknown fallback model: {id}"),
+        }
+    }
+
+    let mut router = ModelRouter::new(registry)
+        .with_fallbacks(fallbacks)
+        .with_api_key_resolver(Arc::new(move |provider| auth_storage.get_api_key(provider)));
+    for (provider, provider_settings) in settings.providers.iter().flatten() {
+        if let Some(extra_keys) = &provider_settings.api_keys {
+            let keys: Vec<String> = provider_settings
+                .api_key
+                .iter()
+                .chain(extra_keys)
+                .cloned()
+                .collect();
+            router = router.with_api_keys(provider, keys);
+        }
+    }
+    for (provider, config) in session.model_registry().custom_providers() {
+        if let Some(header) = &config.auth_header {
+            router = router.with_auth_header(provider, header);
+        }
+    }
+    router
+}
+
 /// Create and initialize an AgentSession — the main SDK entry point.
 ///
 /// This function:
 /// 1. Resolves the config directory
 /// 2. Loads and merges settings
-/// 3. Initializes auth storage
-/// 4. Builds the model registry (built-in + custom models)
+/// 3. Builds the model registry (built-in + custom models)
+/// 4. Initializes auth storage
 /// 5. Creates the session manager
 /// 6. Resolves the initial model
 /// 7. Returns a ready-to-use AgentSession
@@ -95,10 +184,7 @@ pub fn create_agent_session(
     let project_base = options.working_dir.join(paths::CONFIG_DIR_NAME);
     settings_manager.load_and_merge(options.project_settings.as_ref(), Some(&project_base))?;
 
-    // 3. Initialize auth storage
-    let auth_storage = AuthStorage::new(&base_dir);
-
-    // 4. Build model registry
+    // 3. Build model registry
     let mut model_registry = ModelRegistry::new();
 
     // Load custom models from models.json
@@ -120,14 +206,31 @@ pub fn create_agent_session(
         model_registry.add_custom_models(custom_models);
     }
 
+    // Add models discovered from provider endpoints, as last cached
+    model_registry.add_discovered_models(discovery::cached_models(
+        &base_dir,
+        settings_manager.settings(),
+    ));
+
+    // 4. Initialize auth storage
+    let auth_storage = Arc::new(AuthStorage::new(&base_dir).with_configured_keys(
+        configured_api_keys(settings_manager.settings(), &model_registry),
+    ));
+
     // 5. Create session manager
-    let session_manager = SessionManager::new(&base_dir);
+    let store_kind = settings_manager
+        .settings()
+        .sessions
+        .as_ref()
+        .and_then(|s| s.store)
+        .unwrap_or_default();
+    let session_manager = SessionManager::with_store(&base_dir, store_kind)?;
 
     // 6. Create the session
     let mut session = AgentSession::new(
         options.working_dir,
         session_manager,
-        Arc::new(auth_storage),
+        auth_storage.clone(),
         Arc::new(model_registry),
         Arc::new(settings_manager),
     );
@@ -157,30 +260,13 @@ pub fn create_agent_session(
     };
     session.set_tools(tools.clone());
 
-    // 8.1 Configure default stream function from built-in AI providers.
+    // 8.1 Configure default stream function from built-in AI providers,
+    // routed through the configured fallback chain and key pools.
     let registry = Arc::new(pi_agent_ai::register::create_default_registry());
-    session.set_stream_fn(Arc::new(move |model, context, options| {
-        match pi_agent_ai::stream::stream_simple(
-            model,
-            context,
-            options,
-            &registry,
-            CancellationToken::new(),
-        ) {
-            Ok(stream) => stream,
-            Err(error) => {
-                let stream = pi_agent_core::event_stream::create_assistant_message_event_stream();
-                let mut message = AssistantMessage::empty(model);
-                message.stop_reason = StopReason::Error;
-                message.error_message = Some(error);
-                stream.push(AssistantMessageEvent::Error {
-                    reason: StopReason::Error,
-                    error: message,
-                });
-                stream
-            }
-        }
-    }));
+    configure_rate_limits(session.settings_manager().settings());
+    configure_http_clients(session.settings_manager().settings());
+    let router = build_model_router(&session, registry, auth_storage);
+    session.set_stream_fn(Arc::new(router).into_stream_fn());
 
     // 9. Build default system prompt with skills and tool list
     if let Some(model) = session.model().cloned() {
@@ -243,11 +329,52 @@ pub fn create_agent_session(
     Ok(session)
 }
 
+/// Fetch discovered models from the sources whose cache has expired, or
+/// from all sources with `force`, using the HTTP and API key settings.
+pub async fn refresh_discovered_models(
+    base_dir: &std::path::Path,
+    settings: &Settings,
+    force: bool,
+) -> Vec<discovery::RefreshOutcome> {
+    configure_http_clients(settings);
+    // Only for the keys of models.json providers; load errors are reported
+    // when the session's registry is built.
+    let mut models = ModelRegistry::new();
+    let _ = models.load_custom_models(&paths::models_file(base_dir));
+    let auth_storage =
+        AuthStorage::new(base_dir).with_configured_keys(configured_api_keys(settings, &models));
+    discovery::refresh(base_dir, settings, &auth_storage, force).await
+}
+
+/// Refresh expired discovered models for later sessions; the current one
+/// uses the cache. Failures are logged and leave the cached models in place.
+async fn refresh_stale_discovered_models(options: CreateSessionOptions) {
+    let base_dir = paths::resolve_base_dir(options.config_dir.as_deref());
+    let mut settings_manager = SettingsManager::new(&base_dir);
+    let project_base = options.working_dir.join(paths::CONFIG_DIR_NAME);
+    if settings_manager
+        .load_and_merge(options.project_settings.as_ref(), Some(&project_base))
+        .is_err()
+    {
+        return;
+    }
+    let settings = settings_manager.settings();
+    if discovery::discovery_sources(settings).is_empty() {
+        return;
+    }
+    for outcome in refresh_discovered_models(&base_dir, settings, false).await {
+        if let Err(e) = outcome.result {
+            tracing::warn!("Model discovery for {} failed: {e}", outcome.provider);
+        }
+    }
+}
+
 /// Async session creation that also loads extension factories and wires tool hooks.
 pub async fn create_agent_session_with_extensions(
     options: CreateSessionWithExtensionsOptions,
 ) -> Result<CreateSessionResult, CodingAgentError> {
     let mut session = create_agent_session(options.base.clone())?;
+    tokio::spawn(refresh_stale_discovered_models(options.base.clone()));
 
     let has_explicit_paths = !options.extension_paths.is_empty();
     if options.extension_factories.is_empty() && !options.discover_extensions && !has_explicit_paths
@@ -6,12 +6,17 @@ use std::sync::Arc;
 use futures::StreamExt;
 use tokio_util::sync::CancellationToken;
 
+use pi_agent_ai::token_counter::{
+    HeuristicCounter, TokenCounter, count_tokens_remote, counter_for_model,
+};
 use pi_agent_core::agent_types::{
-    AgentContext, AgentEvent, AgentLoopConfig, AgentMessage, AgentTool, GetApiKeyFn, StreamFnBox,
+    AgentContext, AgentEvent, AgentLimits, AgentLoopConfig, AgentMessage, AgentTool, GetApiKeyFn,
+    LimitProgress, LimitReason, StreamFnBox,
 };
-use pi_agent_core::types::{Message, Model, StopReason, ThinkingLevel};
+use pi_agent_core::types::{Context, Message, Model, StopReason, ThinkingLevel, Tool};
 
 use crate::agent_session::events::AgentSessionEvent;
+use crate::agent_session::title;
 use crate::auth::storage::AuthStorage;
 use crate::compaction::compaction;
 use crate::error::CodingAgentError;
@@ -21,8 +26,10 @@ use crate::messages::convert::convert_to_llm;
 use crate::model::registry::ModelRegistry;
 use crate::retry::{self, RetryConfig};
 use crate::session::manager::SessionManager;
-use crate::session::types::{SessionEntry, now_iso_timestamp};
+use crate::session::types::{SessionEntry, latest_session_info, now_iso_timestamp};
+use crate::session::usage::{UsageKind, UsageLedger, UsageRecord, UsageRecorder};
 use crate::settings::manager::SettingsManager;
+use crate::settings::types::LimitSettings;
 
 /// Options for prompting the agent.
 #[derive(Debug, Clone, Default)]
@@ -93,6 +100,8 @@ pub struct SessionStats {
     pub message_count: usize,
     pub estimated_tokens: u64,
     pub turn_count: usize,
+    /// Cumulative tokens and cost recorded in this session.
+    pub usage: UsageLedger,
 }
 
 /// Estimated context usage for the active model.
@@ -143,7 +152,7 @@ pub struct AgentSession {
     /// LLM stream function.
     stream_fn: Option<StreamFnBox>,
     /// Session manager for persistence.
-    session_manager: SessionManager,
+    session_manager: Arc<SessionManager>,
     /// Auth storage.
     auth_storage: Arc<AuthStorage>,
     /// Model registry.
@@ -166,6 +175,22 @@ pub struct AgentSession {
     extension_runner: Option<Arc<ExtensionRunner>>,
     /// Default reasoning/thinking level.
     thinking_level: Option<ThinkingLevel>,
+    /// Token counter override; defaults to the current model's tokenizer.
+    token_counter: Option<Arc<dyn TokenCounter>>,
+    /// Usage recorded in the current session.
+    usage: UsageLedger,
+    /// Collects usage from compaction and summary calls.
+    usage_recorder: UsageRecorder,
+    /// Limit overrides on top of `settings.limits`.
+    limit_overrides: LimitSettings,
+    /// Why the last prompt stopped early, if it did.
+    last_limit_reason: Option<LimitReason>,
+    /// Generate titles and summaries (`sessions.autoTitle`).
+    auto_title: bool,
+    /// Whether the current session has, or is generating, a title.
+    titled: bool,
+    /// Background title generation, if one was started.
+    title_task: Option<tokio::task::JoinHandle<()>>,
 }
 
 impl AgentSession {
@@ -177,6 +202,12 @@ impl AgentSession {
         model_registry: Arc<ModelRegistry>,
         settings_manager: Arc<SettingsManager>,
     ) -> Self {
+        let auto_title = settings_manager
+            .settings()
+            .sessions
+            .as_ref()
+            .and_then(|s| s.auto_title)
+            .unwrap_or(true);
         Self {
             session_id: None,
             working_dir,
@@ -185,7 +216,7 @@ impl AgentSession {
             messages: Vec::new(),
             tools: Vec::new(),
             stream_fn: None,
-            session_manager,
+            session_manager: Arc::new(session_manager),
             auth_storage,
             model_registry,
             settings_manager,
@@ -197,6 +228,14 @@ impl AgentSession {
             turn_count: 0,
             extension_runner: None,
             thinking_level: None,
+            token_counter: None,
+            usage: UsageLedger::default(),
+            usage_recorder: UsageRecorder::default(),
+            limit_overrides: LimitSettings::default(),
+            last_limit_reason: None,
+            auto_title,
+            titled: false,
+            title_task: None,
         }
     }
 
@@ -220,6 +259,208 @@ impl AgentSession {
         self.summary_fn = Some(summary_fn);
     }
 
+    /// Override the token counter used for context usage and compaction.
+    pub fn set_token_counter(&mut self, counter: Arc<dyn TokenCounter>) {
+        self.token_counter = Some(counter);
+    }
+
+    /// The token counter for the current model.
+    pub fn token_counter(&self) -> Arc<dyn TokenCounter> {
+        if let Some(counter) = &self.token_counter {
+            return counter.clone();
+        }
+        match &self.model {
+            Some(model) => counter_for_model(model),
+            None => Arc::new(HeuristicCounter),
+        }
+    }
+
+    /// Tokens taken by the system prompt and tool definitions.
+    fn fixed_context_tokens(&self, counter: &dyn TokenCounter) -> u64 {
+        let tools: Vec<Tool> = self
+            .tools
+            .iter()
+            .map(|tool| tool.definition().clone())
+            .collect();
+        counter.count_text(&self.system_prompt) + counter.count_tools(&tools)
+    }
+
+    /// Tokens of the current context by `counter` alone, uncalibrated.
+    fn counted_context_tokens(&self, counter: &dyn TokenCounter) -> u64 {
+        self.fixed_context_tokens(counter)
+            + compaction::count_messages_tokens(counter, &self.messages)
+    }
+
+    /// Estimated tokens of the current context, calibrated against the last
+    /// reported usage.
+    pub fn estimate_context_tokens(&self) -> u64 {
+        let counter = self.token_counter();
+        let fixed = self.fixed_context_tokens(counter.as_ref());
+        compaction::estimate_context_tokens(counter.as_ref(), &self.messages, fixed)
+    }
+
+    /// Exact token count of the current context from the provider's count
+    /// endpoint when `compaction.countEndpoint` is enabled and the API has
+    /// one, otherwise [`Self::estimate_context_tokens`].
+    pub async fn count_context_tokens(&self) -> u64 {
+        let use_endpoint = self
+            .settings_manager
+            .settings()
+            .compaction
+            .as_ref()
+            .and_then(|c| c.count_endpoint)
+            .unwrap_or(false);
+        if use_endpoint
+            && let Some(model) = &self.model
+            && let Some(api_key) = self.auth_storage.resolve_api_key(&model.provider).await
+        {
+            let context = Context {
+                system_prompt: Some(self.system_prompt.clone()),
+                messages: convert_to_llm(&self.messages),
+                tools: Some(
+                    self.tools
+                        .iter()
+                        .map(|tool| tool.definition().clone())
+                        .collect(),
+                ),
+            };
+            match count_tokens_remote(model, &context, &api_key).await {
+                Ok(Some(tokens)) => return tokens,
+                Ok(None) => {}
+                Err(e) => tracing::debug!("Token count endpoint failed: {e}"),
+            }
+        }
+        self.estimate_context_tokens()
+    }
+
+    /// The auto-compaction threshold check: whether the context, counted by
+    /// [`Self::count_context_tokens`], leaves less than `reserve_tokens` of
+    /// the model's context window.
+    pub async fn should_compact(&self, settings: &compaction::CompactionSettings) -> bool {
+        let Some(model) = &self.model else {
+            return false;
+        };
+        if !settings.enabled || model.context_window == 0 {
+            return false;
+        }
+        let tokens = self.count_context_tokens().await;
+        tokens > model.context_window.saturating_sub(settings.reserve_tokens)
+    }
+
+    /// Enable or disable automatic titles and summaries, overriding
+    /// `sessions.autoTitle`.
+    pub fn set_auto_title(&mut self, enabled: bool) {
+        self.auto_title = enabled;
+    }
+
+    /// The model used for titles and summaries: `sessions.titleModel` if it
+    /// resolves, otherwise the session's model.
+    fn title_model(&self) -> Option<Model> {
+        self.settings_manager
+            .settings()
+            .sessions
+            .as_ref()
+            .and_then(|s| s.title_model.as_deref())
+            .and_then(|query| {
+                let model = self.model_registry.find(query);
+                if model.is_none() {
+                    tracing::warn!(
+                        "Unknown sessions.titleModel {query:?}; using the session model"
+                    );
+                }
+                model
+            })
+            .or(self.model.as_ref())
+            .cloned()
+    }
+
+    /// Generate and persist a title and summary in the background.
+    ///
+    /// Does nothing when disabled, when there is no conversation yet, or
+    /// while a previous generation is still running.
+    fn spawn_title_generation(&mut self) {
+        if !self.auto_title
+            || self
+                .title_task
+                .as_ref()
+                .is_some_and(|task| !task.is_finished())
+            || !self
+                .messages
+                .iter()
+                .any(|m| matches!(m, AgentMessage::Llm(Message::Assistant(_))))
+        {
+            return;
+        }
+        let (Some(stream_fn), Some(session_id), Some(model)) = (
+            self.stream_fn.clone(),
+            self.session_id.clone(),
+            self.title_model(),
+        ) else {
+            return;
+        };
+
+        let messages = self.messages.clone();
+        let manager = self.session_manager.clone();
+        let auth = self.auth_storage.clone();
+        let recorder = self.usage_recorder.clone();
+        self.titled = true;
+        self.title_task = Some(tokio::spawn(async move {
+            let api_key = auth.resolve_api_key(&model.provider).await;
+            match title::generate_title(&stream_fn, &model, api_key, &messages, &recorder).await {
+                Ok(generated) => {
+                    if let Err(e) = manager.append_entry(&session_id, &generated.to_entry()) {
+                        tracing::warn!("Failed to persist session title: {e}");
+                    }
+                }
+                Err(e) => tracing::warn!("Failed to generate session title: {e}"),
+            }
+        }));
+    }
+
+    /// Wait for background title generation to finish and persist its usage.
+    /// Call before exiting so the title is not lost.
+    pub async fn wait_for_title(&mut self) {
+        if let Some(task) = self.title_task.take()
+            && let Err(e) = task.await
+        {
+            tracing::warn!("Title generation task failed: {e}");
+        }
+        self.flush_recorded_usage();
+    }
+
+    /// Override limits from settings; fields left unset keep their
+    /// configured value.
+    pub fn set_limits(&mut self, limits: LimitSettings) {
+        self.limit_overrides = limits;
+    }
+
+    /// Limits in effect: `settings.limits` with overrides applied.
+    pub fn limits(&self) -> LimitSettings {
+        self.settings_manager
+            .settings()
+            .limits
+            .clone()
+            .unwrap_or_default()
+            .overlay(&self.limit_overrides)
+    }
+
+    /// Why the last prompt was stopped by a limit, if it was.
+    pub fn last_limit_reason(&self) -> Option<&LimitReason> {
+        self.last_limit_reason.as_ref()
+    }
+
+    fn agent_limits(&self) -> AgentLimits {
+        let limits = self.limits();
+        AgentLimits {
+            max_turns: limits.max_turns,
+            max_tool_calls: limits.max_tool_calls,
+            max_duration_ms: limits.max_duration_secs.map(|secs| secs * 1000),
+            max_cost: limits.max_cost,
+            max_session_cost: limits.max_session_cost,
+            session_cost: self.usage.total.cost,
+        }
+    }
+
     /// Set the retry configuration for transient errors.
     pub fn set_retry_config(&mut self, config: RetryConfig) {
         self.retry_config = config;
@@ -268,7 +509,11 @@ impl AgentSession {
         // Ensure we have a session
         if self.session_id.is_none() {
             let session_id = uuid::Uuid::new_v4().to_string();
-            self.session_manager.create(&session_id, None)?;
+            self.session_manager.create_in(
+                &session_id,
+                None,
+                &self.working_dir.display().to_string(),
+            )?;
             self.session_id = Some(session_id.clone());
             self.emit(AgentSessionEvent::SessionStart {
                 session_id,
@@ -326,18 +571,25 @@ impl AgentSession {
         });
 
         // Wire auth_storage into the get_api_key closure so saved/runtime
-        // credentials flow through to provider requests.
+        // credentials flow through to provider requests, refreshing OAuth
+        // tokens shortly before they expire.
         let auth = self.auth_storage.clone();
         let get_api_key_fn: Arc<GetApiKeyFn> = Arc::new(move |provider: &str| {
             let auth = auth.clone();
             let provider = provider.to_string();
-            Box::pin(async move { auth.get_api_key(&provider) })
+            Box::pin(async move { auth.resolve_api_key(&provider).await })
         });
 
         // Reset retry attempt counter for this prompt
         self.retry_attempt = 0;
+        self.last_limit_reason = None;
         let context_window = model.context_window;
 
+        let settings = self.settings_manager.settings().clone();
+        // Budgets cover the whole prompt, so retries share one tracker.
+        let limits = self.agent_limits();
+        let limit_progress = LimitProgress::shared();
+
         loop {
             let config = AgentLoopConfig {
                 model: model.clone(),
@@ -353,12 +605,21 @@ impl AgentSession {
                 cache_retention: None,
                 session_id: self.session_id.clone(),
                 headers: None,
-                max_retry_delay_ms: None,
+                max_retry_delay_ms: Some(self.retry_config.max_delay_ms),
+                tool_choice: settings.tool_choice.clone(),
+                top_p: settings.top_p,
+                top_k: settings.top_k,
+                stop_sequences: settings.stop_sequences.clone(),
+                seed: settings.seed,
+                frequency_penalty: settings.frequency_penalty,
+                presence_penalty: settings.presence_penalty,
                 convert_to_llm: convert_fn.clone(),
                 transform_context: None,
                 get_api_key: Some(get_api_key_fn.clone()),
                 get_steering_messages: None,
                 get_follow_up_messages: None,
+                limits: limits.clone(),
+                limit_progress: Some(limit_progress.clone()),
             };
 
             // Reset cancellation for this prompt attempt
@@ -384,6 +645,12 @@ impl AgentSession {
                     message: AgentMessage::Llm(Message::Assistant(assistant_msg)),
                 } = &event
                 {
+                    self.usage.record(&UsageRecord::new(
+                        UsageKind::Chat,
+                        &assistant_msg.provider,
+                        &assistant_msg.model,
+                        assistant_msg.usage.clone(),
+                    ));
                     if let Some(session_id) = &self.session_id {
                         let message_value = match serde_json::to_value(assistant_msg) {
                             Ok(val) => val,
@@ -462,7 +729,25 @@ impl AgentSession {
                     }
                 }
 
+                if let AgentEvent::LimitReached { reason } = &event {
+                    tracing::info!("Agent stopped: {reason}");
+                    self.last_limit_reason = Some(reason.clone());
+                }
+
+                let rate_limit = match &event {
+                    AgentEvent::MessageEnd {
+                        message: AgentMessage::Llm(Message::Assistant(assistant_msg)),
+                    } => assistant_msg
+                        .rate_limit
+                        .as_deref()
+                        .map(|info| (assistant_msg.provider.clone(), info.clone())),
+                    _ => None,
+                };
+
                 self.emit(AgentSessionEvent::Agent(event));
+                if let Some((provider, info)) = rate_limit {
+                    self.emit(AgentSessionEvent::RateLimit { provider, info });
+                }
             }
 
             // Get the final messages from the agent loop result
@@ -540,6 +825,10 @@ impl AgentSession {
             break;
         }
 
+        self.flush_recorded_usage();
+        if !self.titled {
+            self.spawn_title_generation();
+        }
         Ok(())
     }
 
@@ -640,10 +929,13 @@ impl AgentSession {
         let default_settings = compaction::CompactionSettings::default();
         let settings = settings.unwrap_or(&default_settings);
 
-        let tokens_before = compaction::estimate_messages_tokens(&self.messages);
+        // Both counts come from the same local counter so they compare.
+        let counter = self.token_counter();
+        let tokens_before = self.counted_context_tokens(counter.as_ref());
         let messages_before = self.messages.len();
 
-        let (to_summarize, to_keep) = compaction::prepare_compaction(&self.messages, settings);
+        let (to_summarize, to_keep) =
+            compaction::prepare_compaction_with(counter.as_ref(), &self.messages, settings);
 
         if to_summarize.is_empty() {
             return Err(CodingAgentError::Compaction(
@@ -656,7 +948,9 @@ impl AgentSession {
 
         // Generate summary — use LLM-based summary_fn if available
         let summary = if let Some(summary_fn) = &self.summary_fn {
-            summary_fn(to_summarize.to_vec(), previous_summary).await?
+            let summary = summary_fn(to_summarize.to_vec(), previous_summary).await;
+            self.flush_recorded_usage();
+            summary?
         } else {
             // Fallback: use structured context extraction (no LLM)
             let summary_context =
@@ -673,7 +967,7 @@ impl AgentSession {
 
         self.messages = compaction::apply_compaction(&summary, to_keep);
 
-        let tokens_after = compaction::estimate_messages_tokens(&self.messages);
+        let tokens_after = self.counted_context_tokens(counter.as_ref());
         let messages_after = self.messages.len();
 
         let result = CompactionResult {
@@ -707,10 +1001,37 @@ impl AgentSession {
                 tracing::warn!("Failed to persist compaction summary: {e}");
             }
         }
+        self.spawn_title_generation();
 
         Ok(result)
     }
 
+    /// Cumulative tokens and cost recorded in the current session.
+    pub fn usage(&self) -> &UsageLedger {
+        &self.usage
+    }
+
+    /// Handle for reporting usage of requests made outside the agent loop,
+    /// such as an LLM-backed [`SummaryFn`]. Records are added to the ledger
+    /// and persisted once the call returns.
+    pub fn usage_recorder(&self) -> UsageRecorder {
+        self.usage_recorder.clone()
+    }
+
+    /// Move recorded auxiliary usage into the ledger and the session file.
+    fn flush_recorded_usage(&mut self) {
+        for record in self.usage_recorder.drain() {
+            self.usage.record(&record);
+            if let Some(session_id) = &self.session_id
+                && let Err(e) = self
+                    .session_manager
+                    .append_entry(session_id, &record.to_entry())
+            {
+                tracing::warn!("Failed to persist usage entry: {e}");
+            }
+        }
+    }
+
     /// Restore a previously-persisted session by loading its JSONL history.
     ///
     /// This rebuilds the in-memory `messages` and `turn_count` from the
@@ -720,6 +1041,8 @@ impl AgentSession {
         let (_header, entries) = self.session_manager.open(session_id)?;
         self.session_id = Some(session_id.to_string());
         self.messages = crate::session::context::build_session_context(&entries);
+        self.usage = UsageLedger::from_entries(&entries);
+        self.titled = latest_session_info(&entries).0.is_some();
         self.turn_count = self
             .messages
             .iter()
@@ -752,6 +1075,8 @@ impl AgentSession {
         self.session_id = Some(header.id);
         // Rebuild context from forked entries
         self.messages = crate::session::context::build_session_context(&entries);
+        self.usage = UsageLedger::from_entries(&entries);
+        self.titled = latest_session_info(&entries).0.is_some();
 
         Ok(ForkResult {
             new_session_id,
@@ -764,16 +1089,18 @@ impl AgentSession {
         SessionStats {
             session_id: self.session_id.clone(),
             message_count: self.messages.len(),
-            estimated_tokens: compaction::estimate_messages_tokens(&self.messages),
+            estimated_tokens: self.estimate_context_tokens(),
             turn_count: self.turn_count,
+            usage: self.usage.clone(),
         }
     }
 
-    /// Estimate current context usage for the active model.
+    /// Current context usage for the active model, counted by
+    /// [`Self::count_context_tokens`].
     ///
     /// After compaction, usage is unknown until we receive a successful assistant
     /// response with usage metrics produced after the latest compaction boundary.
-    pub fn get_context_usage(&self) -> Option<ContextUsage> {
+    pub async fn get_context_usage(&self) -> Option<ContextUsage> {
         let model = self.model.as_ref()?;
         let context_window = model.context_window;
         if context_window == 0 {
@@ -814,7 +1141,7 @@ impl AgentSession {
             }
         }
 
-        let tokens = compaction::estimate_messages_tokens(&self.messages);
+        let tokens = self.count_context_tokens().await;
         let percent = (tokens as f64 / context_window as f64) * 100.0;
         Some(ContextUsage {
             tokens: Some(tokens),
@@ -833,6 +1160,8 @@ impl AgentSession {
         self.session_id = None;
         self.messages.clear();
         self.turn_count = 0;
+        self.usage = UsageLedger::default();
+        self.titled = false;
     }
 
     /// Get the working directory.
@@ -923,8 +1252,8 @@ mod tests {
         (tmp, session)
     }
 
-    #[test]
-    fn test_get_context_usage_unknown_after_compaction_without_post_usage() {
+    #[tokio::test]
+    async fn test_get_context_usage_unknown_after_compaction_without_post_usage() {
         let (_tmp, session) = create_test_session();
         let compaction_entry = SessionEntry::Compaction {
             id: SessionEntry::new_id(),
@@ -941,14 +1270,14 @@ mod tests {
             .append_entry("test-session", &compaction_entry)
             .unwrap();
 
-        let usage = session.get_context_usage().unwrap();
+        let usage = session.get_context_usage().await.unwrap();
         assert_eq!(usage.tokens, None);
         assert_eq!(usage.percent, None);
         assert!(usage.context_window > 0);
     }
 
-    #[test]
-    fn test_get_context_usage_known_with_post_compaction_usage() {
+    #[tokio::test]
+    async fn test_get_context_usage_known_with_post_compaction_usage() {
         let (_tmp, mut session) = create_test_session();
         let compaction_entry = SessionEntry::Compaction {
             id: SessionEntry::new_id(),
@@ -985,10 +1314,225 @@ mod tests {
                 timestamp: chrono::DateTime::parse_from_rfc3339("2026-02-12T00:00:01Z")
                     .unwrap()
                     .timestamp_millis(),
+                rate_limit: None,
             })));
 
-        let usage = session.get_context_usage().unwrap();
+        let usage = session.get_context_usage().await.unwrap();
         assert!(usage.tokens.is_some());
         assert!(usage.percent.is_some());
     }
+
+    #[tokio::test]
+    async fn test_count_endpoint_setting_uses_provider_count() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        let paths = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let seen = paths.clone();
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                let mut request = Vec::new();
+                let mut buf = [0u8; 4096];
+                // Read the head and the whole body before answering.
+                loop {
+                    let n = stream.read(&mut buf).await.unwrap_or(0);
+                    if n == 0 {
+                        break;
+                    }
+                    request.extend_from_slice(&buf[..n]);
+                    let text = String::from_utf8_lossy(&request).to_lowercase();
+                    if let Some(head_end) = text.find("\r\n\r\n") {
+                        let length = text
+                            .lines()
+                            .find_map(|line| line.strip_prefix("content-length:"))
+                            .and_then(|value| value.trim().parse::<usize>().ok())
+                            .unwrap_or(0);
+                        if request.len() >= head_end + 4 + length {
+                            break;
+                        }
+                    }
+                }
+                let request = String::from_utf8_lossy(&request).to_string();
+                let path = request.split(' ').nth(1).unwrap_or_default().to_string();
+                seen.lock().unwrap().push(path);
+                let body = r#"{"input_tokens":150000}"#;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{body}",
+                    body.len()
+                );
+                let _ = stream.write_all(response.as_bytes()).await;
+            }
+        });
+
+        let (tmp, mut session) = create_test_session();
+        let mut model = session.model().unwrap().clone();
+        model.api = "anthropic-messages".to_string();
+        model.provider = "count-test".to_string();
+        model.base_url = format!("http://{addr}");
+        model.context_window = 160_000;
+        session.set_model(model);
+        session.auth_storage.set_runtime_credential(
+            "count-test",
+            crate::auth::credentials::AuthCredential::ApiKey {
+                key: "test-key".to_string(),
+            },
+        );
+        session.messages.push(AgentMessage::user("hello"));
+        let settings = compaction::CompactionSettings::default();
+
+        // Off by default: the local estimate is used and nothing is sent.
+        assert!(!session.should_compact(&settings).await);
+        assert!(paths.lock().unwrap().is_empty());
+
+        let mut settings_manager = SettingsManager::new(tmp.path());
+        settings_manager.settings_mut().compaction =
+            Some(crate::settings::types::CompactionSettings {
+                count_endpoint: Some(true),
+                ..Default::default()
+            });
+        session.settings_manager = Arc::new(settings_manager);
+
+        let usage = session.get_context_usage().await.unwrap();
+        assert_eq!(usage.tokens, Some(150_000));
+        assert!(session.should_compact(&settings).await);
+        assert_eq!(
+            *paths.lock().unwrap(),
+            ["/v1/messages/count_tokens", "/v1/messages/count_tokens"]
+        );
+    }
+
+    #[tokio::test]
+    async fn test_compaction_usage_is_recorded_and_restored() {
+        let (_tmp, mut session) = create_test_session();
+        for i in 0..40 {
+            session.messages.push(AgentMessage::user(format!(
+                "message {i} {}",
+                "x".repeat(200)
+            )));
+        }
+
+        let recorder = session.usage_recorder();
+        session.set_summary_fn(Arc::new(move |_messages, _previous| {
+            recorder.record(UsageRecord::new(
+                UsageKind::Compaction,
+                "openai",
+                "gpt-4o-mini",
+                Usage {
+                    input: 900,
+                    output: 100,
+                    cost: UsageCost {
+                        total: 0.002,
+                        ..UsageCost::default()
+                    },
+                    ..Usage::default()
+                },
+            ));
+            Box::pin(async { Ok("summary".to_string()) })
+        }));
+
+        let settings = compaction::CompactionSettings {
+            enabled: true,
+            reserve_tokens: 0,
+            keep_recent_tokens: 100,
+        };
+        session.compact(Some(&settings)).await.unwrap();
+        assert_eq!(session.get_stats().usage.total.input, 900);
+        assert_eq!(session.usage().by_kind[&UsageKind::Compaction].requests, 1);
+
+        session.reset_session();
+        assert_eq!(session.usage().total.requests, 0);
+        session.restore_session("test-session").unwrap();
+        assert_eq!(session.usage().by_provider["openai"].output, 100);
+    }
+
+    #[tokio::test]
+    async fn test_title_generated_after_first_exchange() {
+        let (_tmp, mut session) = create_test_session();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        session.set_stream_fn(Arc::new(move |model, _context, _options| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let stream = pi_agent_core::event_stream::create_assistant_message_event_stream();
+            let mut message = AssistantMessage::empty(model);
+            message
+                .content
+                .push(pi_agent_core::types::ContentBlock::Text(
+                    pi_agent_core::types::TextContent {
+                        text: "Title: Rename the config loader\nSummary: Renamed it.".to_string(),
+                        text_signature: None,
+                        citations: None,
+                    },
+                ));
+            message.usage.output = 10;
+            stream.push(pi_agent_core::types::AssistantMessageEvent::Done {
+                reason: StopReason::Stop,
+                message,
+            });
+            stream
+        }));
+
+        session
+            .prompt("rename the loader", PromptOptions::default())
+            .await
+            .unwrap();
+        session.wait_for_title().await;
+        session
+            .prompt("thanks", PromptOptions::default())
+            .await
+            .unwrap();
+        session.wait_for_title().await;
+
+        // One title request after the first exchange only.
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 3);
+        assert_eq!(session.usage().by_kind[&UsageKind::Summary].requests, 1);
+        let info = &session.session_manager().list().unwrap()[0];
+        assert_eq!(info.title.as_deref(), Some("Rename the config loader"));
+        assert_eq!(info.summary.as_deref(), Some("Renamed it."));
+
+        session.restore_session("test-session").unwrap();
+        assert!(session.titled);
+        assert_eq!(session.usage().by_kind[&UsageKind::Summary].requests, 1);
+    }
+
+    #[tokio::test]
+    async fn test_session_cost_limit_stops_prompt_without_request() {
+        let (_tmp, mut session) = create_test_session();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        session.set_stream_fn(Arc::new(move |model, _context, _options| {
+            counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            let stream = pi_agent_core::event_stream::create_assistant_message_event_stream();
+            let mut message = AssistantMessage::empty(model);
+            message.usage.cost.total = 0.5;
+            stream.push(pi_agent_core::types::AssistantMessageEvent::Done {
+                reason: StopReason::Stop,
+                message,
+            });
+            stream
+        }));
+        session.set_auto_title(false);
+        session.set_limits(LimitSettings {
+            max_session_cost: Some(0.4),
+            ..LimitSettings::default()
+        });
+        assert_eq!(session.limits().max_session_cost, Some(0.4));
+
+        session
+            .prompt("first", PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(session.last_limit_reason().is_none());
+
+        session
+            .prompt("second", PromptOptions::default())
+            .await
+            .unwrap();
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+        assert!(matches!(
+            session.last_limit_reason(),
+            Some(LimitReason::MaxSessionCost { .. })
+        ));
+    }
 }
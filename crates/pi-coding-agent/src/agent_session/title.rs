@@ -0,0 +1,189 @@
+//! Automatic session titles and summaries.
+//!
+//! After the first exchange, and again after each compaction, the session
+//! asks a model (preferably a cheap one, see `sessions.titleModel`) for a
+//! short title and a one-paragraph summary. The result is persisted as a
+//! `session_info` entry, which listings, search and exports pick up.
+
+use pi_agent_core::agent_types::{AgentMessage, StreamFnBox};
+use pi_agent_core::types::{
+    ContentBlock, Context, Message, Model, SimpleStreamOptions, StopReason, StreamOptions,
+    UserContent, UserMessage,
+};
+
+use crate::compaction::branch_summary::serialize_conversation;
+use crate::error::CodingAgentError;
+use crate::session::types::{SessionEntry, now_iso_timestamp};
+use crate::session::usage::{UsageKind, UsageRecord, UsageRecorder};
+
+/// Transcript characters sent to the model. Longer conversations keep their
+/// beginning and end.
+const MAX_TRANSCRIPT_CHARS: usize = 12_000;
+
+const MAX_TITLE_CHARS: usize = 80;
+
+const MAX_OUTPUT_TOKENS: u64 = 400;
+
+const TITLE_SYSTEM_PROMPT: &str = "You name and summarize conversations between a user and a \
+coding assistant. Reply with exactly two lines and nothing else:
+Title: <specific title of at most 8 words, no quotes, no trailing period>
+Summary: <one paragraph of 2-4 sentences: what the user wanted, what was done, and what is left>";
+
+/// A generated title and summary.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SessionTitle {
+    pub title: String,
+    pub summary: String,
+}
+
+impl SessionTitle {
+    /// The `session_info` entry persisting this title.
+    pub fn to_entry(&self) -> SessionEntry {
+        SessionEntry::SessionInfo {
+            id: SessionEntry::new_id(),
+            parent_id: None,
+            timestamp: now_iso_timestamp(),
+            name: Some(self.title.clone()),
+            summary: Some(self.summary.clone()),
+        }
+    }
+}
+
+fn truncate_transcript(transcript: &str) -> String {
+    let chars: Vec<char> = transcript.chars().collect();
+    if chars.len() <= MAX_TRANSCRIPT_CHARS {
+        return transcript.to_string();
+    }
+    let half = MAX_TRANSCRIPT_CHARS / 2;
+    let head: String = chars[..half].iter().collect();
+    let tail: String = chars[chars.len() - half..].iter().collect();
+    format!("{head}\n\n[...]\n\n{tail}")
+}
+
+/// The request asking for a title and summary of `messages`.
+pub fn title_request(messages: &[AgentMessage]) -> Context {
+    let transcript = truncate_transcript(&serialize_conversation(messages));
+    Context {
+        system_prompt: Some(TITLE_SYSTEM_PROMPT.to_string()),
+        messages: vec![Message::User(UserMessage {
+            content: UserContent::Text(format!("<conversation>\n{transcript}\n</conversation>")),
+            timestamp: chrono::Utc::now().timestamp_millis(),
+        })],
+        tools: None,
+    }
+}
+
+fn clean_line(line: &str) -> &str {
+    line.trim()
+        .trim_matches(|c: char| c == '*' || c == '#' || c.is_whitespace())
+}
+
+/// Parse the `Title:` / `Summary:` reply. The summary may span several
+/// lines; markdown emphasis and quotes around the title are dropped.
+pub fn parse_title_response(text: &str) -> Option<SessionTitle> {
+    let mut title = None;
+    let mut summary: Vec<&str> = Vec::new();
+    let mut in_summary = false;
+    for line in text.lines() {
+        let line = clean_line(line);
+        let lower = line.to_ascii_lowercase();
+        if lower.starts_with("title:") {
+            title = Some(clean_line(&line["title:".len()..]));
+            in_summary = false;
+        } else if lower.starts_with("summary:") {
+            summary.push(clean_line(&line["summary:".len()..]));
+            in_summary = true;
+        } else if in_summary && !line.is_empty() {
+            summary.push(line);
+        }
+    }
+
+    let title = title?
+        .trim_matches(|c| matches!(c, '"' | '\'' | '`' | '“' | '”'))
+        .trim_end_matches('.')
+        .trim();
+    let summary = summary
+        .into_iter()
+        .filter(|s| !s.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ");
+    if title.is_empty() || summary.is_empty() {
+        return None;
+    }
+    Some(SessionTitle {
+        title: title.chars().take(MAX_TITLE_CHARS).collect(),
+        summary,
+    })
+}
+
+/// Ask `model` for a title and summary of `messages`. Usage is reported to
+/// `recorder` as [`UsageKind::Summary`].
+pub async fn generate_title(
+    stream_fn: &StreamFnBox,
+    model: &Model,
+    api_key: Option<String>,
+    messages: &[AgentMessage],
+    recorder: &UsageRecorder,
+) -> Result<SessionTitle, CodingAgentError> {
+    let options = SimpleStreamOptions {
+        base: StreamOptions {
+            max_tokens: Some(MAX_OUTPUT_TOKENS),
+            api_key,
+            ..StreamOptions::default()
+        },
+        ..SimpleStreamOptions::default()
+    };
+    let message = stream_fn(model, &title_request(messages), &options)
+        .result()
+        .await
+        .ok_or_else(|| CodingAgentError::Agent("Title request ended without a result".into()))?;
+
+    recorder.record(UsageRecord::new(
+        UsageKind::Summary,
+        &message.provider,
+        &message.model,
+        message.usage.clone(),
+    ));
+    if matches!(message.stop_reason, StopReason::Error | StopReason::Aborted) {
+        return Err(CodingAgentError::Agent(
+            message
+                .error_message
+                .unwrap_or_else(|| "Title request failed".to_string()),
+        ));
+    }
+
+    let text: Vec<&str> = message
+        .content
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(t) => Some(t.text.as_str()),
+            _ => None,
+        })
+        .collect();
+    parse_title_response(&text.join("\n"))
+        .ok_or_else(|| CodingAgentError::Agent("Model returned no usable title".to_string()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_title_response() {
+        let parsed = parse_title_response(
+            "**Title:** \"Fix flaky retry test.\"\n\nSummary: The user asked why the\nretry test fails.\n",
+        )
+        .unwrap();
+        assert_eq!(parsed.title, "Fix flaky retry test");
+        assert_eq!(parsed.summary, "The user asked why the retry test fails.");
+
+        assert!(parse_title_response("Just some text").is_none());
+        assert!(parse_title_response("Title: Only a title").is_none());
+
+        let long = "x".repeat(200);
+        assert_eq!(
+            truncate_transcript(&long.repeat(100)).chars().count(),
+            MAX_TRANSCRIPT_CHARS + "\n\n[...]\n\n".len()
+        );
+    }
+}
@@ -1,3 +1,4 @@
+use pi_agent_ai::oauth::OAuthCredentials;
 use serde::{Deserialize, Serialize};
 
 /// Represents an authentication credential for an API provider.
@@ -16,9 +17,15 @@ pub enum AuthCredential {
         refresh_token: Option<String>,
         #[serde(skip_serializing_if = "Option::is_none")]
         expires_at: Option<i64>,
+        /// OAuth client the tokens were issued to; required for refresh.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        client_id: Option<String>,
     },
 }
 
+/// Refresh OAuth credentials this many seconds before they expire.
+pub const REFRESH_MARGIN_SECS: i64 = 60;
+
 impl AuthCredential {
     /// Create a new API key credential.
     pub fn api_key(key: impl Into<String>) -> Self {
@@ -31,6 +38,17 @@ impl AuthCredential {
             access_token: access_token.into(),
             refresh_token: None,
             expires_at: None,
+            client_id: None,
+        }
+    }
+
+    /// Create an OAuth credential from a provider token response.
+    pub fn from_oauth(credentials: OAuthCredentials, client_id: Option<String>) -> Self {
+        AuthCredential::OAuth {
+            access_token: credentials.access_token,
+            refresh_token: credentials.refresh_token,
+            expires_at: credentials.expires_at,
+            client_id,
         }
     }
 
@@ -56,4 +74,22 @@ impl AuthCredential {
             }
         }
     }
+
+    /// Whether this is an OAuth credential that is about to expire and can be
+    /// refreshed.
+    pub fn needs_refresh(&self) -> bool {
+        match self {
+            AuthCredential::ApiKey { .. } => false,
+            AuthCredential::OAuth {
+                refresh_token,
+                expires_at,
+                ..
+            } => {
+                refresh_token.is_some()
+                    && expires_at.is_some_and(|exp| {
+                        chrono::Utc::now().timestamp() >= exp - REFRESH_MARGIN_SECS
+                    })
+            }
+        }
+    }
 }
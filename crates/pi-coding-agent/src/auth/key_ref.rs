@@ -0,0 +1,309 @@
+//! API key references.
+//!
+//! Anywhere an API key is configured (`settings.json` `apiKey`/`apiKeys`,
+//! `auth.json`, `--api-key`) it may instead name where to fetch the key:
+//!
+//! - `env:VAR` — the environment variable `VAR`
+//! - `file:/path/to/key` — the trimmed contents of a file (`~/` is expanded)
+//! - `cmd:op read op://vault/item/key` — the trimmed stdout of a shell command
+//!
+//! References are resolved when a request needs the key, never when
+//! settings are loaded, and only the reference is ever persisted. File and
+//! command results are cached for a TTL so a password manager is not
+//! prompted on every request; a command that runs longer than
+//! [`KEY_COMMAND_TIMEOUT`] is killed.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, Instant};
+
+use crate::error::CodingAgentError;
+
+/// How long file and command results are reused.
+pub const DEFAULT_KEY_TTL: Duration = Duration::from_secs(300);
+
+/// Longest a `cmd:` reference may run, e.g. waiting on a password manager.
+pub const KEY_COMMAND_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// A parsed key value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum KeyRef<'a> {
+    /// A plain key.
+    Literal(&'a str),
+    Env(&'a str),
+    File(&'a str),
+    Command(&'a str),
+}
+
+impl<'a> KeyRef<'a> {
+    pub fn parse(value: &'a str) -> Self {
+        if let Some(var) = value.strip_prefix("env:") {
+            KeyRef::Env(var.trim())
+        } else if let Some(path) = value.strip_prefix("file:") {
+            KeyRef::File(path.trim())
+        } else if let Some(command) = value.strip_prefix("cmd:") {
+            KeyRef::Command(command.trim())
+        } else {
+            KeyRef::Literal(value)
+        }
+    }
+
+    /// Whether `value` names a key source rather than being the key itself.
+    pub fn is_reference(value: &str) -> bool {
+        !matches!(KeyRef::parse(value), KeyRef::Literal(_))
+    }
+}
+
+/// Resolves key references, caching file and command results.
+pub struct KeyResolver {
+    ttl: Duration,
+    cache: Mutex<HashMap<String, (String, Instant)>>,
+}
+
+impl Default for KeyResolver {
+    fn default() -> Self {
+        Self::new(DEFAULT_KEY_TTL)
+    }
+}
+
+impl KeyResolver {
+    pub fn new(ttl: Duration) -> Self {
+        Self {
+            ttl,
+            cache: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Resolve `value` to the key it names. Errors never include the key.
+    ///
+    /// An uncached `cmd:` reference blocks the calling thread until the
+    /// command exits, at most [`KEY_COMMAND_TIMEOUT`]; async callers should
+    /// use [`resolve_async`](Self::resolve_async).
+    pub fn resolve(&self, value: &str) -> Result<String, CodingAgentError> {
+        let KeyRef::Command(command) = KeyRef::parse(value) else {
+            return self.resolve_local(value);
+        };
+        if let Some(key) = self.cached(value) {
+            return Ok(key);
+        }
+        // Run on a separate thread so this also works inside a runtime.
+        let command = command.to_string();
+        let key = std::thread::spawn(move || {
+            tokio::runtime::Builder::new_current_thread()
+                .enable_all()
+                .build()
+                .map_err(|e| CodingAgentError::Auth(format!("Failed to run key command: {e}")))?
+                .block_on(run_key_command(&command))
+        })
+        .join()
+        .map_err(|_| CodingAgentError::Auth("Key command panicked".to_string()))??;
+        self.store(value, key)
+    }
+
+    /// Like [`resolve`](Self::resolve), without blocking the runtime on
+    /// `cmd:` references.
+    pub async fn resolve_async(&self, value: &str) -> Result<String, CodingAgentError> {
+        let KeyRef::Command(command) = KeyRef::parse(value) else {
+            return self.resolve_local(value);
+        };
+        if let Some(key) = self.cached(value) {
+            return Ok(key);
+        }
+        let key = run_key_command(command).await?;
+        self.store(value, key)
+    }
+
+    /// Literal, environment and file references.
+    fn resolve_local(&self, value: &str) -> Result<String, CodingAgentError> {
+        match KeyRef::parse(value) {
+            KeyRef::Literal(key) => Ok(key.to_string()),
+            KeyRef::Env(var) => std::env::var(var)
+                .ok()
+                .filter(|v| !v.is_empty())
+                .ok_or_else(|| {
+                    CodingAgentError::Auth(format!("Environment variable {var} is not set"))
+                }),
+            KeyRef::File(path) => match self.cached(value) {
+                Some(key) => Ok(key),
+                None => self.store(value, read_key_file(path)?),
+            },
+            KeyRef::Command(_) => unreachable!(),
+        }
+    }
+
+    fn cached(&self, value: &str) -> Option<String> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .get(value)
+            .filter(|(_, fetched)| fetched.elapsed() < self.ttl)
+            .map(|(key, _)| key.clone())
+    }
+
+    fn store(&self, value: &str, key: String) -> Result<String, CodingAgentError> {
+        if key.is_empty() {
+            return Err(CodingAgentError::Auth(format!(
+                "Key reference {value:?} resolved to an empty key"
+            )));
+        }
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache.insert(value.to_string(), (key.clone(), Instant::now()));
+        Ok(key)
+    }
+
+    /// Drop cached results, e.g. after a key was rotated.
+    pub fn invalidate(&self) {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).clear();
+    }
+}
+
+fn read_key_file(path: &str) -> Result<String, CodingAgentError> {
+    let path = match path.strip_prefix("~/") {
+        Some(rest) => dirs::home_dir()
+            .map(|home| home.join(rest))
+            .unwrap_or_else(|| PathBuf::from(path)),
+        None => PathBuf::from(path),
+    };
+    std::fs::read_to_string(&path)
+        .map(|content| content.trim().to_string())
+        .map_err(|e| {
+            CodingAgentError::Auth(format!("Cannot read key file {}: {e}", path.display()))
+        })
+}
+
+async fn run_key_command(command: &str) -> Result<String, CodingAgentError> {
+    #[cfg(windows)]
+    let mut process = {
+        let mut process = tokio::process::Command::new("cmd");
+        process.args(["/C", command]);
+        process
+    };
+    #[cfg(not(windows))]
+    let mut process = {
+        let mut process = tokio::process::Command::new("sh");
+        process.args(["-c", command]);
+        process
+    };
+    process.kill_on_drop(true);
+
+    let output = tokio::time::timeout(KEY_COMMAND_TIMEOUT, process.output())
+        .await
+        .map_err(|_| {
+            CodingAgentError::Auth(format!(
+                "Key command `{command}` timed out after {}s",
+                KEY_COMMAND_TIMEOUT.as_secs()
+            ))
+        })?
+        .map_err(|e| CodingAgentError::Auth(format!("Failed to run key command: {e}")))?;
+    if !output.status.success() {
+        return Err(CodingAgentError::Auth(format!(
+            "Key command `{command}` exited with {}: {}",
+            output.status,
+            String::from_utf8_lossy(&output.stderr).trim()
+        )));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+/// Process-wide resolver.
+pub fn key_resolver() -> &'static KeyResolver {
+    static RESOLVER: OnceLock<KeyResolver> = OnceLock::new();
+    RESOLVER.get_or_init(KeyResolver::default)
+}
+
+/// Resolve `value` with the process-wide resolver, logging failures.
+pub fn resolve_key(value: &str) -> Option<String> {
+    match key_resolver().resolve(value) {
+        Ok(key) => Some(key),
+        Err(e) => {
+            tracing::warn!("{e}");
+            None
+        }
+    }
+}
+
+/// Async form of [`resolve_key`].
+pub async fn resolve_key_async(value: &str) -> Option<String> {
+    match key_resolver().resolve_async(value).await {
+        Ok(key) => Some(key),
+        Err(e) => {
+            tracing::warn!("{e}");
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_references() {
+        assert_eq!(KeyRef::parse("sk-ant-123"), KeyRef::Literal("sk-ant-123"));
+        assert_eq!(KeyRef::parse("env:MY_KEY"), KeyRef::Env("MY_KEY"));
+        assert_eq!(KeyRef::parse("file:~/.keys/a"), KeyRef::File("~/.keys/a"));
+        assert_eq!(
+            KeyRef::parse("cmd:op read op://v/i/k"),
+            KeyRef::Command("op read op://v/i/k")
+        );
+        assert!(KeyRef::is_reference("cmd:pass show x"));
+        assert!(!KeyRef::is_reference("sk-literal"));
+    }
+
+    #[test]
+    fn test_file_reference() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("key");
+        std::fs::write(&path, "sk-from-file\n").unwrap();
+        let resolver = KeyResolver::default();
+        assert_eq!(
+            resolver
+                .resolve(&format!("file:{}", path.display()))
+                .unwrap(),
+            "sk-from-file"
+        );
+        assert!(resolver.resolve("file:/nonexistent/key").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_command_reference_is_cached_for_ttl() {
+        let dir = tempfile::tempdir().unwrap();
+        let counter = dir.path().join("count");
+        let command = format!(
+            "cmd:echo x >> {0}; echo sk-$(wc -l < {0} | tr -d ' ')",
+            counter.display()
+        );
+
+        let resolver = KeyResolver::default();
+        assert_eq!(resolver.resolve(&command).unwrap(), "sk-1");
+        assert_eq!(resolver.resolve(&command).unwrap(), "sk-1");
+        resolver.invalidate();
+        assert_eq!(resolver.resolve(&command).unwrap(), "sk-2");
+
+        let uncached = KeyResolver::new(Duration::ZERO);
+        assert_eq!(uncached.resolve(&command).unwrap(), "sk-3");
+        assert_eq!(uncached.resolve(&command).unwrap(), "sk-4");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failing_command_reports_stderr() {
+        let error = KeyResolver::default()
+            .resolve("cmd:echo locked >&2; exit 3")
+            .unwrap_err();
+        assert!(error.to_string().contains("locked"));
+    }
+
+    #[cfg(unix)]
+    #[tokio::test]
+    async fn test_async_command_reference_shares_cache() {
+        let resolver = KeyResolver::default();
+        assert_eq!(
+            resolver.resolve_async("cmd:echo sk-async").await.unwrap(),
+            "sk-async"
+        );
+        // The sync path reuses the result instead of running the command.
+        assert_eq!(resolver.resolve("cmd:echo sk-async").unwrap(), "sk-async");
+    }
+}
@@ -0,0 +1,513 @@
+//! Interactive OAuth logins (`pi login`, `/login`).
+//!
+//! Browser flows listen once on the redirect URI's loopback port, check the
+//! returned `state` and redeem the code with the PKCE verifier. Without a
+//! browser the authorization URL is printed and the user pastes the URL they
+//! were redirected to. GitHub Copilot uses the device flow instead.
+
+use std::time::Duration;
+
+use pi_agent_ai::oauth::anthropic::{
+    AnthropicOAuthConfig, exchange_anthropic_code, start_anthropic_oauth,
+};
+use pi_agent_ai::oauth::device_code::DeviceCodeResponse;
+use pi_agent_ai::oauth::github_copilot::{
+    GithubCopilotOAuthConfig, complete_github_copilot_device_flow, start_github_copilot_device_flow,
+};
+use pi_agent_ai::oauth::google_antigravity::{
+    GoogleAntigravityOAuthConfig, exchange_google_antigravity_code, start_google_antigravity_oauth,
+};
+use pi_agent_ai::oauth::google_gemini_cli::{
+    GoogleGeminiCliOAuthConfig, exchange_google_gemini_cli_code, start_google_gemini_cli_oauth,
+};
+use pi_agent_ai::oauth::openai_codex::{
+    OpenAICodexOAuthConfig, exchange_openai_codex_code, start_openai_codex_oauth,
+};
+use pi_agent_ai::oauth::pkce::OAuthFlowStart;
+use pi_agent_ai::oauth::{OAuthCredentials, OAuthProvider};
+use tokio::io::BufReader;
+use tokio::net::TcpListener;
+
+use crate::auth::credentials::AuthCredential;
+use crate::auth::storage::AuthStorage;
+use crate::error::CodingAgentError;
+use crate::server::http;
+
+/// How long to wait for the user to finish authorizing.
+pub const LOGIN_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// How the authorization code gets back to us.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LoginMethod {
+    /// Open a browser and catch the redirect on a loopback listener.
+    Browser,
+    /// Print the URL and read the redirected URL from the user (headless).
+    Manual,
+}
+
+/// User interaction during a login.
+pub trait LoginUi: Send + Sync {
+    /// Present the authorization URL (and open it, if appropriate).
+    fn show_authorization_url(&self, url: &str);
+    /// Present a device code to enter at the verification URL.
+    fn show_device_code(&self, device: &DeviceCodeResponse);
+    /// Read one line of input; `None` if input is unavailable.
+    fn prompt(&self, message: &str) -> Option<String>;
+}
+
+/// Terminal UI: prints to stdout, reads stdin, optionally opens a browser.
+pub struct ConsoleLoginUi {
+    pub open_browser: bool,
+}
+
+impl LoginUi for ConsoleLoginUi {
+    fn show_authorization_url(&self, url: &str) {
+        println!("Open this URL to log in:\n\n  {url}\n");
+        if self.open_browser && open_in_browser(url).is_err() {
+            println!("(Could not open a browser automatically.)");
+        }
+    }
+
+    fn show_device_code(&self, device: &DeviceCodeResponse) {
+        println!(
+            "Go to {} and enter the code: {}",
+            device.verification_uri, device.user_code
+        );
+        if self.open_browser {
+            let _ = open_in_browser(&device.verification_uri);
+        }
+    }
+
+    fn prompt(&self, message: &str) -> Option<String> {
+        use std::io::Write;
+
+        print!("{message}");
+        std::io::stdout().flush().ok()?;
+        let mut line = String::new();
+        match std::io::stdin().read_line(&mut line) {
+            Ok(0) | Err(_) => None,
+            Ok(_) => Some(line.trim().to_string()),
+        }
+    }
+}
+
+fn open_in_browser(url: &str) -> std::io::Result<()> {
+    #[cfg(target_os = "macos")]
+    let mut command = std::process::Command::new("open");
+    #[cfg(target_os = "windows")]
+    let mut command = {
+        let mut command = std::process::Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    };
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut command = std::process::Command::new("xdg-open");
+
+    command
+        .arg(url)
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .map(|_| ())
+}
+
+/// Whether a browser is likely available; used to pick the default method.
+pub fn browser_available() -> bool {
+    if cfg!(any(target_os = "macos", target_os = "windows")) {
+        return std::env::var_os("SSH_CONNECTION").is_none();
+    }
+    std::env::var_os("DISPLAY").is_some() || std::env::var_os("WAYLAND_DISPLAY").is_some()
+}
+
+/// Environment variable holding the OAuth client id for `provider`,
+/// e.g. `PI_GITHUB_COPILOT_OAUTH_CLIENT_ID`.
+pub fn client_id_env_var(provider: &OAuthProvider) -> String {
+    format!(
+        "PI_{}_OAUTH_CLIENT_ID",
+        provider
+            .provider_key()
+            .to_ascii_uppercase()
+            .replace('-', "_")
+    )
+}
+
+fn auth_error(e: impl std::fmt::Display) -> CodingAgentError {
+    CodingAgentError::Auth(e.to_string())
+}
+
+/// Extract and verify the authorization code from a redirect target
+/// (`/callback?code=..&state=..`) or a full redirect URL.
+pub fn parse_redirect(redirect: &str, expected_state: &str) -> Result<String, CodingAgentError> {
+    let query = redirect
+        .split_once('?')
+        .map_or(redirect, |(_, query)| query)
+        .split('#')
+        .next()
+        .unwrap_or_default();
+    let params: Vec<(String, String)> = url::form_urlencoded::parse(query.as_bytes())
+        .into_owned()
+        .collect();
+    let param = |name: &str| {
+        params
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.as_str())
+    };
+
+    if let Some(error) = param("error") {
+        let detail = param("error_description").unwrap_or(error);
+        return Err(auth_error(format!("Authorization failed: {detail}")));
+    }
+    let state = param("state").unwrap_or_default();
+    if !http::constant_time_eq(state, expected_state) {
+        return Err(auth_error("OAuth state mismatch; login aborted"));
+    }
+    param("code")
+        .filter(|code| !code.is_empty())
+        .map(str::to_string)
+        .ok_or_else(|| auth_error("Redirect did not include an authorization code"))
+}
+
+/// Bind the loopback listener for `redirect_uri`.
+pub async fn bind_redirect_listener(redirect_uri: &str) -> Result<TcpListener, CodingAgentError> {
+    let url = url::Url::parse(redirect_uri).map_err(auth_error)?;
+    let host = match url.host_str() {
+        Some("localhost") | None => "127.0.0.1",
+        Some(host) => host,
+    };
+    let port = url.port_or_known_default().unwrap_or(80);
+    TcpListener::bind((host, port)).await.map_err(|e| {
+        auth_error(format!(
+            "Cannot listen on {host}:{port} for the redirect: {e}"
+        ))
+    })
+}
+
+/// Serve the redirect once and return the verified authorization code.
+///
+/// Requests for other paths (a browser's favicon fetch) are answered with
+/// 404 and do not end the wait.
+pub async fn wait_for_authorization_code(
+    listener: &TcpListener,
+    callback_path: &str,
+    expected_state: &str,
+    timeout: Duration,
+) -> Result<String, CodingAgentError> {
+    let accept = async {
+        loop {
+            let (socket, _) = listener.accept().await?;
+            let (read_half, mut writer) = socket.into_split();
+            let mut reader = BufReader::new(read_half);
+            let Ok(request) = http::read_request(&mut reader).await else {
+                continue;
+            };
+            if request.path() != callback_path {
+                http::write_response(&mut writer, 404, "text/plain", b"Not found").await?;
+                continue;
+            }
+            let result = parse_redirect(&request.target, expected_state);
+            let (status, page) = match &result {
+                Ok(_) => (200, "Login complete. You can close this tab."),
+                Err(_) => (400, "Login failed. Return to the terminal for details."),
+            };
+            let body = format!("<!doctype html><html><body><p>{page}</p></body></html>");
+            http::write_response(
+                &mut writer,
+                status,
+                "text/html; charset=utf-8",
+                body.as_bytes(),
+            )
+            .await?;
+            return Ok::<_, std::io::Error>(result);
+        }
+    };
+    match tokio::time::timeout(timeout, accept).await {
+        Ok(Ok(result)) => result,
+        Ok(Err(e)) => Err(CodingAgentError::Io(e)),
+        Err(_) => Err(auth_error("Timed out waiting for the login redirect")),
+    }
+}
+
+/// Run the user-facing half of an authorization-code flow and return the code.
+pub async fn receive_authorization_code(
+    flow: &OAuthFlowStart,
+    redirect_uri: &str,
+    method: LoginMethod,
+    ui: &dyn LoginUi,
+) -> Result<String, CodingAgentError> {
+    match method {
+        LoginMethod::Browser => {
+            let listener = bind_redirect_listener(redirect_uri).await?;
+            let path = url::Url::parse(redirect_uri)
+                .map_err(auth_error)?
+                .path()
+                .to_string();
+            ui.show_authorization_url(&flow.auth_url);
+            wait_for_authorization_code(&listener, &path, &flow.state, LOGIN_TIMEOUT).await
+        }
+        LoginMethod::Manual => {
+            ui.show_authorization_url(&flow.auth_url);
+            let redirect = ui
+                .prompt("Paste the URL you were redirected to: ")
+                .ok_or_else(|| auth_error("Login cancelled"))?;
+            parse_redirect(&redirect, &flow.state)
+        }
+    }
+}
+
+/// Log in to `provider` and return the credential to store.
+pub async fn login(
+    provider: &OAuthProvider,
+    client_id: &str,
+    method: LoginMethod,
+    ui: &dyn LoginUi,
+) -> Result<AuthCredential, CodingAgentError> {
+    let credentials: OAuthCredentials = match provider {
+        OAuthProvider::Anthropic => {
+            let config = AnthropicOAuthConfig::new(client_id);
+            let flow = start_anthropic_oauth(&config).map_err(auth_error)?;
+            let code = receive_authorization_code(&flow, &config.redirect_uri, method, ui).await?;
+            exchange_anthropic_code(&config, &code, &flow.pkce.code_verifier)
+                .await
+                .map_err(auth_error)?
+        }
+        OAuthProvider::OpenaiCodex => {
+            let config = OpenAICodexOAuthConfig::new(client_id);
+            let flow = start_openai_codex_oauth(&config).map_err(auth_error)?;
+            let code = receive_authorization_code(&flow, &config.redirect_uri, method, ui).await?;
+            exchange_openai_codex_code(&config, &code, &flow.pkce.code_verifier)
+                .await
+                .map_err(auth_error)?
+        }
+        OAuthProvider::GoogleGeminiCli => {
+            let config = GoogleGeminiCliOAuthConfig::new(client_id);
+            let flow = start_google_gemini_cli_oauth(&config).map_err(auth_error)?;
+            let code = receive_authorization_code(&flow, &config.redirect_uri, method, ui).await?;
+            exchange_google_gemini_cli_code(&config, &code, &flow.pkce.code_verifier)
+                .await
+                .map_err(auth_error)?
+        }
+        OAuthProvider::GoogleAntigravity => {
+            let config = GoogleAntigravityOAuthConfig::new(client_id);
+            let flow = start_google_antigravity_oauth(&config).map_err(auth_error)?;
+            let code = receive_authorization_code(&flow, &config.redirect_uri, method, ui).await?;
+            exchange_google_antigravity_code(&config, &code, &flow.pkce.code_verifier)
+                .await
+                .map_err(auth_error)?
+        }
+        OAuthProvider::GithubCopilot => {
+            let config = GithubCopilotOAuthConfig::new(client_id);
+            let device = start_github_copilot_device_flow(&config)
+                .await
+                .map_err(auth_error)?;
+            ui.show_device_code(&device);
+            complete_github_copilot_device_flow(&config, &device)
+                .await
+                .map_err(auth_error)?
+        }
+    };
+    Ok(AuthCredential::from_oauth(
+        credentials,
+        Some(client_id.to_string()),
+    ))
+}
+
+/// Log in to the provider with key `provider_key` and save the credential
+/// to auth.json. Returns the provider's display name.
+pub async fn login_and_save(
+    auth_storage: &AuthStorage,
+    provider_key: &str,
+    method: LoginMethod,
+    ui: &dyn LoginUi,
+) -> Result<String, CodingAgentError> {
+    let provider = OAuthProvider::from_provider_key(provider_key).ok_or_else(|| {
+        auth_error(format!(
+            "Unknown OAuth provider: {provider_key} (available: {})",
+            oauth_provider_keys().join(", ")
+        ))
+    })?;
+    let env_var = client_id_env_var(&provider);
+    let client_id = std::env::var(&env_var)
+        .ok()
+        .filter(|id| !id.is_empty())
+        .ok_or_else(|| {
+            auth_error(format!(
+                "No OAuth client id for {}; set {env_var}",
+                provider.display_name()
+            ))
+        })?;
+
+    let credential = login(&provider, &client_id, method, ui).await?;
+    auth_storage.save_credential(provider_key, &credential)?;
+    Ok(provider.display_name().to_string())
+}
+
+/// Provider keys accepted by `pi login`.
+pub fn oauth_provider_keys() -> Vec<String> {
+    pi_agent_ai::oauth::get_oauth_providers()
+        .iter()
+        .map(|p| p.provider_key().to_string())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Mutex;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    /// Acts as the browser: follows the authorization URL straight to the
+    /// redirect, echoing `state` (or a forged one).
+    struct FakeBrowser {
+        redirect_uri: String,
+        forge_state: bool,
+        requests: Mutex<Vec<tokio::task::JoinHandle<()>>>,
+    }
+
+    impl LoginUi for FakeBrowser {
+        fn show_authorization_url(&self, url: &str) {
+            let url = url::Url::parse(url).unwrap();
+            let state = url
+                .query_pairs()
+                .find(|(k, _)| k == "state")
+                .map(|(_, v)| v.into_owned())
+                .unwrap();
+            let state = if self.forge_state {
+                "forged".to_string()
+            } else {
+                state
+            };
+            let target = format!("{}?code=auth-code&state={state}", self.redirect_uri);
+            self.requests.lock().unwrap().push(tokio::spawn(async move {
+                let url = url::Url::parse(&target).unwrap();
+                let addr = format!("{}:{}", url.host_str().unwrap(), url.port().unwrap());
+                let mut socket = tokio::net::TcpStream::connect(addr).await.unwrap();
+                let request = format!(
+                    "GET {}?{} HTTP/1.1\r\nHost: localhost\r\n\r\n",
+                    url.path(),
+                    url.query().unwrap()
+                );
+                socket.write_all(request.as_bytes()).await.unwrap();
+                let mut response = String::new();
+                let _ = socket.read_to_string(&mut response).await;
+            }));
+        }
+
+        fn show_device_code(&self, _device: &DeviceCodeResponse) {}
+
+        fn prompt(&self, _message: &str) -> Option<String> {
+            None
+        }
+    }
+
+    /// Stub token endpoint that checks the PKCE verifier was sent.
+    async fn stub_token_endpoint() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let endpoint = format!("http://{}/token", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            let (read_half, mut writer) = socket.into_split();
+            let request = http::read_request(&mut BufReader::new(read_half))
+                .await
+                .unwrap();
+            let form = String::from_utf8(request.body).unwrap();
+            let (status, body) =
+                if form.contains("code=auth-code") && form.contains("code_verifier=") {
+                    (
+                        200,
+                        serde_json::json!({
+                            "access_token": "access-1",
+                            "refresh_token": "refresh-1",
+                            "expires_in": 3600,
+                        }),
+                    )
+                } else {
+                    (400, serde_json::json!({ "error": "invalid_grant" }))
+                };
+            http::write_json(&mut writer, status, &body).await.unwrap();
+        });
+        endpoint
+    }
+
+    async fn free_redirect_uri() -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        format!(
+            "http://127.0.0.1:{}/oauth/callback",
+            listener.local_addr().unwrap().port()
+        )
+    }
+
+    #[tokio::test]
+    async fn test_browser_login_end_to_end() {
+        let redirect_uri = free_redirect_uri().await;
+        let config = AnthropicOAuthConfig {
+            client_id: "client".to_string(),
+            auth_url: "https://auth.example.com/authorize".to_string(),
+            token_url: stub_token_endpoint().await,
+            redirect_uri: redirect_uri.clone(),
+        };
+        let ui = FakeBrowser {
+            redirect_uri,
+            forge_state: false,
+            requests: Mutex::new(Vec::new()),
+        };
+
+        let flow = start_anthropic_oauth(&config).unwrap();
+        let code =
+            receive_authorization_code(&flow, &config.redirect_uri, LoginMethod::Browser, &ui)
+                .await
+                .unwrap();
+        assert_eq!(code, "auth-code");
+        let credentials = exchange_anthropic_code(&config, &code, &flow.pkce.code_verifier)
+            .await
+            .unwrap();
+
+        let tmp = tempfile::tempdir().unwrap();
+        let storage = AuthStorage::new(tmp.path());
+        storage
+            .save_credential(
+                "anthropic",
+                &AuthCredential::from_oauth(credentials, Some("client".to_string())),
+            )
+            .unwrap();
+        assert_eq!(
+            storage.get_api_key("anthropic").as_deref(),
+            Some("access-1")
+        );
+    }
+
+    #[tokio::test]
+    async fn test_browser_login_rejects_forged_state() {
+        let redirect_uri = free_redirect_uri().await;
+        let config = AnthropicOAuthConfig {
+            client_id: "client".to_string(),
+            redirect_uri: redirect_uri.clone(),
+            ..Default::default()
+        };
+        let ui = FakeBrowser {
+            redirect_uri,
+            forge_state: true,
+            requests: Mutex::new(Vec::new()),
+        };
+        let flow = start_anthropic_oauth(&config).unwrap();
+        let error =
+            receive_authorization_code(&flow, &config.redirect_uri, LoginMethod::Browser, &ui)
+                .await
+                .unwrap_err();
+        assert!(error.to_string().contains("state mismatch"));
+    }
+
+    #[test]
+    fn test_parse_pasted_redirect() {
+        let url = "http://localhost:8787/oauth/callback?code=abc%2F1&state=s1";
+        assert_eq!(parse_redirect(url, "s1").unwrap(), "abc/1");
+        assert!(parse_redirect(url, "s2").is_err());
+        let denied = "http://localhost/cb?error=access_denied&state=s1";
+        assert!(
+            parse_redirect(denied, "s1")
+                .unwrap_err()
+                .to_string()
+                .contains("access_denied")
+        );
+    }
+}
@@ -1,4 +1,6 @@
 pub mod credentials;
+pub mod key_ref;
+pub mod login;
 pub mod storage;
 
 pub use credentials::*;
@@ -1,10 +1,14 @@
 use std::collections::HashMap;
+use std::future::Future;
 use std::path::{Path, PathBuf};
+use std::pin::Pin;
 use std::sync::{Arc, RwLock};
 
+use pi_agent_ai::oauth::{OAuthProvider, refresh_oauth_credentials};
 use serde::{Deserialize, Serialize};
 
 use crate::auth::credentials::AuthCredential;
+use crate::auth::key_ref::{resolve_key, resolve_key_async};
 use crate::config::paths;
 use crate::error::CodingAgentError;
 
@@ -17,13 +21,26 @@ pub struct AuthFile {
     pub credentials: HashMap<String, AuthCredential>,
 }
 
+/// Exchanges an expiring credential for a fresh one: `(provider, credential)`.
+pub type CredentialRefreshFn = Arc<
+    dyn Fn(
+            String,
+            AuthCredential,
+        ) -> Pin<Box<dyn Future<Output = Result<AuthCredential, String>> + Send>>
+        + Send
+        + Sync,
+>;
+
 /// Multi-layer auth credential storage.
 ///
 /// Resolution order:
 /// 1. Runtime overrides (set programmatically)
 /// 2. auth.json file on disk
-/// 3. Environment variables
-/// 4. Fallback (if configured)
+/// 3. `providers.<name>.apiKey` from settings
+/// 4. Environment variables
+/// 5. Fallback (if configured)
+///
+/// API keys from any layer may be key references (see [`super::key_ref`]).
 pub struct AuthStorage {
     base_dir: PathBuf,
     /// Runtime overrides, highest priority.
@@ -32,6 +49,12 @@ pub struct AuthStorage {
     file_cache: Arc<RwLock<Option<AuthFile>>>,
     /// Environment variable name mappings: provider -> env var name.
     env_mappings: HashMap<String, String>,
+    /// Keys (or key references) from the `providers` section of settings.
+    configured_keys: HashMap<String, String>,
+    /// Renews expiring OAuth credentials.
+    refresher: CredentialRefreshFn,
+    /// Serializes refreshes within this process.
+    refresh_lock: Arc<tokio::sync::Mutex<()>>,
 }
 
 impl AuthStorage {
@@ -72,9 +95,25 @@ impl AuthStorage {
             runtime: Arc::new(RwLock::new(HashMap::new())),
             file_cache: Arc::new(RwLock::new(None)),
             env_mappings,
+            configured_keys: HashMap::new(),
+            refresher: oauth_refresher(),
+            refresh_lock: Arc::new(tokio::sync::Mutex::new(())),
         }
     }
 
+    /// Replace the OAuth refresher (used by tests and embedders).
+    pub fn with_refresher(mut self, refresher: CredentialRefreshFn) -> Self {
+        self.refresher = refresher;
+        self
+    }
+
+    /// Keys configured per provider in settings, consulted after auth.json
+    /// and before environment variables. Values may be key references.
+    pub fn with_configured_keys(mut self, keys: HashMap<String, String>) -> Self {
+        self.configured_keys = keys;
+        self
+    }
+
     /// Set a runtime credential override (highest priority).
     pub fn set_runtime_credential(&self, provider: &str, credential: AuthCredential) {
         let mut rt = self.runtime.write().unwrap_or_else(|e| e.into_inner());
@@ -88,8 +127,74 @@ impl AuthStorage {
     }
 
     /// Get the API key for a provider, following the resolution order.
+    /// API keys given as references (`env:`, `file:`, `cmd:`) are resolved.
     pub fn get_api_key(&self, provider: &str) -> Option<String> {
-        self.get_credential(provider).map(|c| c.token().to_string())
+        match self.get_credential(provider)? {
+            AuthCredential::ApiKey { key } => resolve_key(&key),
+            oauth => Some(oauth.token().to_string()),
+        }
+    }
+
+    /// Like [`get_api_key`](Self::get_api_key), but first refreshes an
+    /// auth.json OAuth credential that is about to expire. A failed refresh
+    /// is logged and resolution falls through to the remaining layers.
+    pub async fn resolve_api_key(&self, provider: &str) -> Option<String> {
+        let has_runtime = {
+            let rt = self.runtime.read().unwrap_or_else(|e| e.into_inner());
+            rt.get(provider).is_some_and(|c| !c.is_expired())
+        };
+        if !has_runtime
+            && self
+                .get_from_file(provider)
+                .is_some_and(|c| c.needs_refresh())
+            && let Err(e) = self.refresh_credential(provider).await
+        {
+            tracing::warn!("Failed to refresh {provider} credentials: {e}");
+        }
+        match self.get_credential(provider)? {
+            AuthCredential::ApiKey { key } => resolve_key_async(&key).await,
+            oauth => Some(oauth.token().to_string()),
+        }
+    }
+
+    /// Refresh the auth.json credential for `provider` and persist it.
+    ///
+    /// Refreshes are serialized within the process and, through a lock on
+    /// `auth.json.lock`, across processes. The file is re-read once the lock
+    /// is held, so a refresh already completed elsewhere is reused instead of
+    /// redeeming a possibly rotated refresh token a second time.
+    pub async fn refresh_credential(
+        &self,
+        provider: &str,
+    ) -> Result<AuthCredential, CodingAgentError> {
+        let _guard = self.refresh_lock.lock().await;
+        paths::ensure_dir(&self.base_dir)?;
+        let lock_path = paths::auth_lock_file(&self.base_dir);
+        let _file_lock = tokio::task::spawn_blocking(move || {
+            let file = std::fs::OpenOptions::new()
+                .create(true)
+                .truncate(false)
+                .write(true)
+                .open(&lock_path)?;
+            file.lock()?;
+            Ok::<_, std::io::Error>(file)
+        })
+        .await
+        .map_err(|e| CodingAgentError::Auth(e.to_string()))??;
+
+        self.invalidate_cache();
+        let credential = self.get_from_file(provider).ok_or_else(|| {
+            CodingAgentError::Auth(format!("No stored credential for {provider}"))
+        })?;
+        if !credential.needs_refresh() {
+            return Ok(credential);
+        }
+
+        let refreshed = (self.refresher)(provider.to_string(), credential)
+            .await
+            .map_err(CodingAgentError::Auth)?;
+        self.save_credential(provider, &refreshed)?;
+        Ok(refreshed)
     }
 
     /// Get the credential for a provider, following the resolution order.
@@ -111,7 +216,12 @@ impl AuthStorage {
             }
         }
 
-        // 3. Environment variable
+        // 3. Settings
+        if let Some(key) = self.configured_keys.get(provider) {
+            return Some(AuthCredential::api_key(key.clone()));
+        }
+
+        // 4. Environment variable
         if let Some(env_var) = self.env_mappings.get(provider) {
             if let Ok(val) = std::env::var(env_var) {
                 if !val.is_empty() {
@@ -295,6 +405,32 @@ impl AuthStorage {
     }
 }
 
+/// Refresher backed by the provider OAuth token endpoints.
+fn oauth_refresher() -> CredentialRefreshFn {
+    Arc::new(|provider, credential| {
+        Box::pin(async move {
+            let AuthCredential::OAuth {
+                refresh_token: Some(refresh_token),
+                client_id,
+                ..
+            } = credential
+            else {
+                return Err(format!("{provider} credential has no refresh token"));
+            };
+            let oauth_provider = OAuthProvider::from_provider_key(&provider)
+                .ok_or_else(|| format!("{provider} does not support token refresh"))?;
+            let refreshed = refresh_oauth_credentials(
+                &oauth_provider,
+                client_id.as_deref().unwrap_or_default(),
+                &refresh_token,
+            )
+            .await
+            .map_err(|e| e.to_string())?;
+            Ok(AuthCredential::from_oauth(refreshed, client_id))
+        })
+    })
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -343,6 +479,7 @@ mod tests {
             access_token: "access-123".to_string(),
             refresh_token: Some("refresh-456".to_string()),
             expires_at: Some(chrono::Utc::now().timestamp() + 3600),
+            client_id: None,
         };
         assert_eq!(oauth.token(), "access-123");
         assert!(!oauth.is_expired());
@@ -351,6 +488,7 @@ mod tests {
             access_token: "old-token".to_string(),
             refresh_token: None,
             expires_at: Some(1000000000), // Way in the past
+            client_id: None,
         };
         assert!(expired.is_expired());
     }
@@ -378,4 +516,106 @@ mod tests {
             Some("runtime-key".to_string())
         );
     }
+
+    #[test]
+    fn test_configured_key_references_are_resolved() {
+        let tmp = tempfile::tempdir().unwrap();
+        let key_file = tmp.path().join("openai.key");
+        std::fs::write(&key_file, "sk-from-file\n").unwrap();
+        let reference = format!("file:{}", key_file.display());
+        let storage = AuthStorage::new(tmp.path())
+            .with_configured_keys(HashMap::from([("openai".to_string(), reference.clone())]));
+
+        assert_eq!(
+            storage.get_api_key("openai"),
+            Some("sk-from-file".to_string())
+        );
+        // The credential keeps the reference, so it is what gets persisted.
+        assert_eq!(
+            storage.get_credential("openai").unwrap().token(),
+            reference.as_str()
+        );
+
+        // auth.json still takes precedence over settings.
+        storage
+            .save_credential("openai", &AuthCredential::api_key("sk-auth-json"))
+            .unwrap();
+        assert_eq!(
+            storage.get_api_key("openai"),
+            Some("sk-auth-json".to_string())
+        );
+    }
+
+    fn expiring_oauth(access_token: &str) -> AuthCredential {
+        AuthCredential::OAuth {
+            access_token: access_token.to_string(),
+            refresh_token: Some("refresh-1".to_string()),
+            expires_at: Some(chrono::Utc::now().timestamp() + 10),
+            client_id: Some("client".to_string()),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_resolve_api_key_refreshes_once() {
+        let tmp = tempfile::tempdir().unwrap();
+        let calls = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let counter = calls.clone();
+        let storage = Arc::new(AuthStorage::new(tmp.path()).with_refresher(Arc::new(
+            move |_provider, credential| {
+                let counter = counter.clone();
+                Box::pin(async move {
+                    counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                    tokio::time::sleep(std::time::Duration::from_millis(20)).await;
+                    let AuthCredential::OAuth { client_id, .. } = credential else {
+                        unreachable!()
+                    };
+                    Ok(AuthCredential::OAuth {
+                        access_token: "fresh".to_string(),
+                        refresh_token: Some("refresh-2".to_string()),
+                        expires_at: Some(chrono::Utc::now().timestamp() + 3600),
+                        client_id,
+                    })
+                })
+            },
+        )));
+        storage
+            .save_credential("anthropic", &expiring_oauth("stale"))
+            .unwrap();
+
+        let (a, b) = tokio::join!(
+            storage.resolve_api_key("anthropic"),
+            storage.resolve_api_key("anthropic")
+        );
+        assert_eq!(a.as_deref(), Some("fresh"));
+        assert_eq!(b.as_deref(), Some("fresh"));
+        assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 1);
+
+        // Persisted for other processes, client id preserved.
+        let reloaded = AuthStorage::new(tmp.path()).get_credential("anthropic");
+        let Some(AuthCredential::OAuth {
+            access_token,
+            client_id,
+            ..
+        }) = reloaded
+        else {
+            panic!("expected oauth credential");
+        };
+        assert_eq!(access_token, "fresh");
+        assert_eq!(client_id.as_deref(), Some("client"));
+    }
+
+    #[tokio::test]
+    async fn test_failed_refresh_keeps_unexpired_token() {
+        let tmp = tempfile::tempdir().unwrap();
+        let storage = AuthStorage::new(tmp.path()).with_refresher(Arc::new(|_, _| {
+            Box::pin(async { Err("denied".to_string()) })
+        }));
+        storage
+            .save_credential("anthropic", &expiring_oauth("still-valid"))
+            .unwrap();
+        assert_eq!(
+            storage.resolve_api_key("anthropic").await.as_deref(),
+            Some("still-valid")
+        );
+    }
 }
@@ -2,6 +2,8 @@ use std::collections::HashMap;
 
 use pi_agent_core::types::ThinkingLevel;
 
+use crate::settings::types::LimitSettings;
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Mode {
     Text,
@@ -19,6 +21,7 @@ pub struct Args {
     pub thinking: Option<String>,
     pub continue_session: bool,
     pub resume: bool,
+    pub all_projects: bool,
     pub help: bool,
     pub version: bool,
     pub mode: Option<Mode>,
@@ -32,6 +35,13 @@ pub struct Args {
     pub no_extensions: bool,
     pub print: bool,
     pub export: Option<String>,
+    pub export_output: Option<String>,
+    pub export_format: Option<String>,
+    pub export_branch: Option<String>,
+    pub export_since: Option<String>,
+    pub export_until: Option<String>,
+    pub export_redact: bool,
+    pub export_theme: Option<String>,
     pub no_skills: bool,
     pub skills: Vec<String>,
     pub prompt_templates: Vec<String>,
@@ -40,9 +50,12 @@ pub struct Args {
     pub no_themes: bool,
     pub list_models: Option<String>,
     pub verbose: bool,
+    pub limits: LimitSettings,
     pub messages: Vec<String>,
     pub file_args: Vec<String>,
     pub unknown_flags: HashMap<String, String>,
+    /// Flag values that failed to parse.
+    pub errors: Vec<String>,
 }
 
 pub fn is_valid_thinking_level(level: &str) -> bool {
@@ -64,6 +77,21 @@ pub fn parse_thinking_level(level: &str) -> Option<Option<ThinkingLevel>> {
     }
 }
 
+/// Parse a numeric limit, recording an error instead of dropping the limit.
+fn parse_limit<T: std::str::FromStr>(
+    flag: &str,
+    value: &str,
+    errors: &mut Vec<String>,
+) -> Option<T> {
+    match value.parse() {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            errors.push(format!("Invalid value {value:?} for {flag}"));
+            None
+        }
+    }
+}
+
 pub fn parse_args(args: &[String], extension_flags: Option<&HashMap<String, String>>) -> Args {
     let mut result = Args::default();
     let mut i = 0;
@@ -85,6 +113,7 @@ pub fn parse_args(args: &[String], extension_flags: Option<&HashMap<String, Stri
             }
             "--continue" | "-c" => result.continue_session = true,
             "--resume" | "-r" => result.resume = true,
+            "--all-projects" => result.all_projects = true,
             "--provider" if i + 1 < args.len() => {
                 i += 1;
                 result.provider = Some(args[i].clone());
@@ -175,8 +204,67 @@ pub fn parse_args(args: &[String], extension_flags: Option<&HashMap<String, Stri
             "--export" if i + 1 < args.len() => {
                 i += 1;
                 result.export = Some(args[i].clone());
+                // `-` here means stdout rather than a prompt.
+                if let Some(output) = args
+                    .get(i + 1)
+                    .filter(|value| *value == "-" || !value.starts_with('-'))
+                {
+                    i += 1;
+                    result.export_output = Some(output.clone());
+                }
+            }
+            "--export-format" if i + 1 < args.len() => {
+                i += 1;
+                result.export_format = Some(args[i].clone());
+            }
+            "--export-branch" if i + 1 < args.len() => {
+                i += 1;
+                result.export_branch = Some(args[i].clone());
+            }
+            "--export-since" if i + 1 < args.len() => {
+                i += 1;
+                result.export_since = Some(args[i].clone());
+            }
+            "--export-until" if i + 1 < args.len() => {
+                i += 1;
+                result.export_until = Some(args[i].clone());
+            }
+            "--export-redact" => result.export_redact = true,
+            "--export-theme" if i + 1 < args.len() => {
+                i += 1;
+                result.export_theme = Some(args[i].clone());
             }
             "--verbose" => result.verbose = true,
+            "--max-turns" if i + 1 < args.len() => {
+                i += 1;
+                result.limits.max_turns = parse_limit("--max-turns", &args[i], &mut result.errors);
+            }
+            "--max-tool-calls" if i + 1 < args.len() => {
+                i += 1;
+                result.limits.max_tool_calls =
+                    parse_limit("--max-tool-calls", &args[i], &mut result.errors);
+            }
+            "--max-duration" if i + 1 < args.len() => {
+                i += 1;
+                result.limits.max_duration_secs =
+                    parse_limit("--max-duration", &args[i], &mut result.errors);
+            }
+            "--max-cost" if i + 1 < args.len() => {
+                i += 1;
+                result.limits.max_cost = parse_limit(
+                    "--max-cost",
+                    args[i].trim_start_matches('$'),
+                    &mut result.errors,
+                );
+            }
+            "--max-session-cost" if i + 1 < args.len() => {
+                i += 1;
+                result.limits.max_session_cost = parse_limit(
+                    "--max-session-cost",
+                    args[i].trim_start_matches('$'),
+                    &mut result.errors,
+                );
+            }
             v if v.starts_with('@') => {
                 result.file_args.push(v.trim_start_matches('@').to_string());
             }
@@ -194,7 +282,7 @@ pub fn parse_args(args: &[String], extension_flags: Option<&HashMap<String, Stri
                     }
                 }
             }
-            v if !v.starts_with('-') => result.messages.push(v.to_string()),
+            v if v == "-" || !v.starts_with('-') => result.messages.push(v.to_string()),
             _ => {}
         }
 
@@ -208,7 +296,29 @@ pub fn print_help(bin_name: &str) {
     println!(
         "{bin_name} - AI coding assistant\n\n\
          Usage:\n  {bin_name} [options] [@files...] [messages...]\n\n\
-         Commands:\n  {bin_name} install <source> [-l]\n  {bin_name} remove <source> [-l]\n  {bin_name} update [source]\n  {bin_name} list\n  {bin_name} config\n\n\
-         Options:\n  --mode <text|json|rpc>\n  --continue, -c\n  --resume, -r\n  --provider <name>\n  --model <pattern>\n  --api-key <key>\n  --system-prompt <text>\n  --append-system-prompt <text>\n  --thinking <off|minimal|low|medium|high|xhigh>\n  --no-session\n  --session <id>\n  --session-dir <dir>\n  --models <patterns>\n  --no-tools\n  --tools <read,bash,...>\n  --extension, -e <path>\n  --no-extensions\n  --skill <path>\n  --no-skills\n  --prompt-template <path>\n  --no-prompt-templates\n  --theme <path>\n  --no-themes\n  --export <file>\n  --list-models [search]\n  --print, -p\n  --verbose\n  --help, -h\n  --version, -v"
+         Commands:\n  {bin_name} install <source> [-l]\n  {bin_name} remove <source> [-l]\n  {bin_name} update [source]\n  {bin_name} list\n  {bin_name} config\n  {bin_name} login <provider> [--no-browser]\n  {bin_name} logout <provider>\n  {bin_name} usage [day|week|project|model|provider] [--days <n>] [--format table|csv|json]\n  {bin_name} sessions list [--all-projects]\n  {bin_name} sessions search <query...> [--since <date>] [--until <date>] [--project <path>] [--model <pattern>] [--json]\n  {bin_name} sessions prune [--max-age <days>] [--max-count <n>] [--max-size <MB>] [--dry-run]\n  {bin_name} sessions gc [--dry-run]\n  {bin_name} sessions pin|unpin <id>\n  {bin_name} sessions import|export <jsonl|sqlite>\n  {bin_name} models refresh [--provider <name>]\n  {bin_name} proxy serve [--host <addr>] [--port <port>] [--token <token>]...\n  {bin_name} gateway serve [--host <addr>] [--port <port>] [--token <key>]...\n\n\
+         Options:\n  --mode <text|json|rpc>\n  --continue, -c\n  --resume, -r\n  --all-projects\n  --provider <name>\n  --model <pattern>\n  --api-key <key>\n  --system-prompt <text>\n  --append-system-prompt <text>\n  --thinking <off|minimal|low|medium|high|xhigh>\n  --no-session\n  --session <id>\n  --session-dir <dir>\n  --models <patterns>\n  --no-tools\n  --tools <read,bash,...>\n  --extension, -e <path>\n  --no-extensions\n  --skill <path>\n  --no-skills\n  --prompt-template <path>\n  --no-prompt-templates\n  --theme <path>\n  --no-themes\n  --export <file|session-id> [output|-]\n  --export-format <html|md|json>\n  --export-branch <entry-id|latest>\n  --export-since <YYYY-MM-DD>\n  --export-until <YYYY-MM-DD>\n  --export-redact\n  --export-theme <name>\n  --list-models [search]\n  --print, -p\n  --max-turns <n>\n  --max-tool-calls <n>\n  --max-duration <seconds>\n  --max-cost <usd>\n  --max-session-cost <usd>\n  --verbose\n  --help, -h\n  --version, -v"
     );
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Args {
+        let args: Vec<String> = args.iter().map(|arg| arg.to_string()).collect();
+        parse_args(&args, None)
+    }
+
+    #[test]
+    fn test_dash_is_export_output_only_after_export() {
+        let args = parse(&["-"]);
+        assert_eq!(args.messages, ["-"]);
+        assert_eq!(args.export_output, None);
+
+        let args = parse(&["--export", "session.jsonl", "-", "--export-format", "md"]);
+        assert_eq!(args.export.as_deref(), Some("session.jsonl"));
+        assert_eq!(args.export_output.as_deref(), Some("-"));
+        assert!(args.messages.is_empty());
+    }
+}
@@ -226,6 +226,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "I'll look at main.rs.".to_string(),
                 text_signature: None,
+                citations: None,
             })],
             api: "test".to_string(),
             provider: "test".to_string(),
@@ -234,6 +235,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         }))];
         let context = serialize_conversation(&messages);
         assert!(context.contains("[Assistant]: I'll look at main.rs."));
@@ -250,6 +252,7 @@ mod tests {
                 ContentBlock::Text(TextContent {
                     text: "Here's what I found.".to_string(),
                     text_signature: None,
+                    citations: None,
                 }),
             ],
             api: "test".to_string(),
@@ -259,6 +262,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         }))];
         let context = serialize_conversation(&messages);
         assert!(context.contains("[Assistant thinking]: Let me analyze this..."));
@@ -281,6 +285,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: 0,
+            rate_limit: None,
         }))];
         let context = serialize_conversation(&messages);
         assert!(context.contains("[Assistant tool calls]: read(path=test.rs)"));
@@ -294,6 +299,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "file contents here".into(),
                 text_signature: None,
+                citations: None,
             })],
             details: None,
             is_error: false,
@@ -311,6 +317,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "I'll look at main.rs.".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: "test".to_string(),
                 provider: "test".to_string(),
@@ -319,6 +326,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })),
         ];
 
@@ -369,6 +377,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: long_content,
                 text_signature: None,
+                citations: None,
             })],
             details: None,
             is_error: false,
@@ -1,3 +1,6 @@
+use pi_agent_ai::token_counter::{
+    Calibration, HeuristicCounter, TokenCounter, usage_context_tokens,
+};
 use pi_agent_core::agent_types::AgentMessage;
 use pi_agent_core::types::*;
 
@@ -34,67 +37,76 @@ impl Default for CompactionSettings {
 
 /// Estimate the number of tokens in a string.
 ///
-/// Uses a simple heuristic: ~4 characters per token for English text.
-/// This matches the TS implementation's `estimateTokens()`.
+/// Uses [`HeuristicCounter`]: ~4 characters per token for ASCII text and
+/// one token per non-ASCII character. Prefer a model's [`TokenCounter`]
+/// (see [`pi_agent_ai::token_counter::counter_for_model`]) where one is
+/// available.
 pub fn estimate_tokens(text: &str) -> u64 {
-    (text.len() as f64 / 4.0).ceil() as u64
+    HeuristicCounter.count_text(text)
 }
 
-/// Estimate tokens for a single agent message.
-///
-/// Mirrors pi-mono's per-message `estimateTokens()` which handles each
-/// message role separately.
+/// Estimate tokens for a single agent message with [`HeuristicCounter`].
 pub fn estimate_message_tokens(msg: &AgentMessage) -> u64 {
+    count_message_tokens(&HeuristicCounter, msg)
+}
+
+/// Estimate total tokens for a list of agent messages with [`HeuristicCounter`].
+pub fn estimate_messages_tokens(messages: &[AgentMessage]) -> u64 {
+    count_messages_tokens(&HeuristicCounter, messages)
+}
+
+/// Count tokens for a single agent message with `counter`.
+pub fn count_message_tokens(counter: &dyn TokenCounter, msg: &AgentMessage) -> u64 {
     match msg {
-        AgentMessage::Llm(Message::User(m)) => {
-            let chars: usize = match &m.content {
-                UserContent::Text(t) => t.len(),
-                UserContent::Blocks(blocks) => blocks
-                    .iter()
-                    .map(|b| match b {
-                        ContentBlock::Text(t) => t.text.len(),
-                        ContentBlock::Thinking(t) => t.thinking.len(),
-                        ContentBlock::Image(_) => 4800, // match pi-mono: images ≈ 1200 tokens
-                        ContentBlock::ToolCall(tc) => {
-                            tc.name.len() + tc.arguments.to_string().len()
-                        }
-                    })
-                    .sum(),
-            };
-            (chars as f64 / 4.0).ceil() as u64
-        }
-        AgentMessage::Llm(Message::Assistant(m)) => {
-            let mut chars = 0usize;
-            for block in &m.content {
-                match block {
-                    ContentBlock::Text(t) => chars += t.text.len(),
-                    ContentBlock::Thinking(t) => chars += t.thinking.len(),
-                    ContentBlock::ToolCall(tc) => {
-                        chars += tc.name.len() + tc.arguments.to_string().len()
-                    }
-                    ContentBlock::Image(_) => chars += 4800,
-                }
-            }
-            (chars as f64 / 4.0).ceil() as u64
-        }
-        AgentMessage::Llm(Message::ToolResult(m)) => {
-            let mut chars = 0usize;
-            for block in &m.content {
-                match block {
-                    ContentBlock::Text(t) => chars += t.text.len(),
-                    ContentBlock::Image(_) => chars += 4800,
-                    _ => {}
-                }
-            }
-            (chars as f64 / 4.0).ceil() as u64
-        }
-        AgentMessage::Custom(v) => estimate_tokens(&v.to_string()),
+        AgentMessage::Llm(message) => counter.count_message(message),
+        AgentMessage::Custom(v) => counter.count_text(&v.to_string()),
     }
 }
 
-/// Estimate total tokens for a list of agent messages.
-pub fn estimate_messages_tokens(messages: &[AgentMessage]) -> u64 {
-    messages.iter().map(estimate_message_tokens).sum()
+/// Count tokens for a list of agent messages with `counter`.
+pub fn count_messages_tokens(counter: &dyn TokenCounter, messages: &[AgentMessage]) -> u64 {
+    messages
+        .iter()
+        .map(|m| count_message_tokens(counter, m))
+        .sum()
+}
+
+/// Estimate the context size of `messages` plus `fixed_tokens` (system
+/// prompt and tool definitions).
+///
+/// When a successful assistant response reported usage, that count anchors
+/// the estimate: only messages after it are counted locally, scaled by the
+/// [`Calibration`] between the reported and locally estimated prefix.
+pub fn estimate_context_tokens(
+    counter: &dyn TokenCounter,
+    messages: &[AgentMessage],
+    fixed_tokens: u64,
+) -> u64 {
+    let anchor = messages
+        .iter()
+        .enumerate()
+        .rev()
+        .find_map(|(i, m)| match m {
+            AgentMessage::Llm(Message::Assistant(a))
+                if a.stop_reason != StopReason::Aborted
+                    && a.stop_reason != StopReason::Error
+                    && usage_context_tokens(&a.usage) > 0 =>
+            {
+                Some((i, usage_context_tokens(&a.usage)))
+            }
+            _ => None,
+        });
+
+    match anchor {
+        Some((index, reported)) => {
+            let calibration = Calibration {
+                reported,
+                estimated: fixed_tokens + count_messages_tokens(counter, &messages[..=index]),
+            };
+            calibration.apply(count_messages_tokens(counter, &messages[index + 1..]))
+        }
+        None => fixed_tokens + count_messages_tokens(counter, messages),
+    }
 }
 
 // ============================================================================
@@ -108,11 +120,22 @@ pub fn should_compact(
     messages: &[AgentMessage],
     context_window: u64,
     settings: &CompactionSettings,
+) -> bool {
+    should_compact_with(&HeuristicCounter, messages, context_window, settings)
+}
+
+/// [`should_compact`] with the context size measured by `counter`,
+/// calibrated against reported usage.
+pub fn should_compact_with(
+    counter: &dyn TokenCounter,
+    messages: &[AgentMessage],
+    context_window: u64,
+    settings: &CompactionSettings,
 ) -> bool {
     if !settings.enabled {
         return false;
     }
-    let estimated = estimate_messages_tokens(messages);
+    let estimated = estimate_context_tokens(counter, messages, 0);
     estimated > context_window.saturating_sub(settings.reserve_tokens)
 }
 
@@ -140,7 +163,11 @@ fn is_valid_cut_point(msg: &AgentMessage) -> bool {
 /// Only cuts at valid cut points (never at tool results).
 ///
 /// Returns the index of the first message to keep.
-fn find_cut_point(messages: &[AgentMessage], keep_recent_tokens: u64) -> usize {
+fn find_cut_point(
+    counter: &dyn TokenCounter,
+    messages: &[AgentMessage],
+    keep_recent_tokens: u64,
+) -> usize {
     if messages.is_empty() {
         return 0;
     }
@@ -149,7 +176,7 @@ fn find_cut_point(messages: &[AgentMessage], keep_recent_tokens: u64) -> usize {
     let mut cut_index = 0; // default: keep everything
 
     for i in (0..messages.len()).rev() {
-        accumulated += estimate_message_tokens(&messages[i]);
+        accumulated += count_message_tokens(counter, &messages[i]);
 
         if accumulated >= keep_recent_tokens {
             // Find the closest valid cut point at or after this index
@@ -188,7 +215,16 @@ pub fn prepare_compaction(
     messages: &[AgentMessage],
     settings: &CompactionSettings,
 ) -> (Vec<AgentMessage>, Vec<AgentMessage>) {
-    let cut_index = find_cut_point(messages, settings.keep_recent_tokens);
+    prepare_compaction_with(&HeuristicCounter, messages, settings)
+}
+
+/// [`prepare_compaction`] with message sizes measured by `counter`.
+pub fn prepare_compaction_with(
+    counter: &dyn TokenCounter,
+    messages: &[AgentMessage],
+    settings: &CompactionSettings,
+) -> (Vec<AgentMessage>, Vec<AgentMessage>) {
+    let cut_index = find_cut_point(counter, messages, settings.keep_recent_tokens);
 
     if cut_index == 0 {
         // Nothing to summarize (or everything fits within budget)
@@ -246,6 +282,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "I'm doing well!".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: "test".to_string(),
                 provider: "test".to_string(),
@@ -254,6 +291,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })),
         ];
 
@@ -261,6 +299,100 @@ mod tests {
         assert!(tokens > 0);
     }
 
+    fn assistant_with_usage(text: &str, input: u64, stop_reason: StopReason) -> AgentMessage {
+        AgentMessage::Llm(Message::Assistant(AssistantMessage {
+            content: vec![ContentBlock::Text(TextContent {
+                text: text.to_string(),
+                text_signature: None,
+                citations: None,
+            })],
+            api: "test".to_string(),
+            provider: "test".to_string(),
+            model: "test".to_string(),
+            usage: Usage {
+                input,
+                ..Usage::default()
+            },
+            stop_reason,
+            error_message: None,
+            timestamp: 0,
+            rate_limit: None,
+        }))
+    }
+
+    #[test]
+    fn test_heuristic_counts_cjk_per_character() {
+        // 12 bytes of UTF-8 but four characters, each roughly a token.
+        assert_eq!(estimate_tokens("你好世界"), 4);
+    }
+
+    #[test]
+    fn test_estimate_context_tokens_without_usage_counts_everything() {
+        let messages = vec![AgentMessage::user("Hello, how are you?")];
+        assert_eq!(
+            estimate_context_tokens(&HeuristicCounter, &messages, 10),
+            10 + estimate_messages_tokens(&messages)
+        );
+    }
+
+    #[test]
+    fn test_estimate_context_tokens_calibrates_from_reported_usage() {
+        let prefix = vec![
+            AgentMessage::user("a".repeat(400)),
+            assistant_with_usage("ok", 0, StopReason::Stop),
+        ];
+        let estimated_prefix = estimate_messages_tokens(&prefix);
+
+        // The provider reported twice what we estimated for the prefix, so
+        // the tail is scaled by the same ratio.
+        let mut messages = vec![prefix[0].clone()];
+        messages.push(assistant_with_usage(
+            "ok",
+            estimated_prefix * 2,
+            StopReason::Stop,
+        ));
+        messages.push(AgentMessage::user("b".repeat(40)));
+        assert_eq!(
+            estimate_context_tokens(&HeuristicCounter, &messages, 0),
+            estimated_prefix * 2 + 20
+        );
+
+        // Errored responses are never used as an anchor.
+        let last = messages.len() - 1;
+        messages.insert(last, assistant_with_usage("x", 1, StopReason::Error));
+        assert!(estimate_context_tokens(&HeuristicCounter, &messages, 0) > estimated_prefix * 2);
+    }
+
+    #[test]
+    fn test_should_compact_with_bpe_counter() {
+        let counter = pi_agent_ai::token_counter::BpeCounter::o200k();
+        let messages: Vec<AgentMessage> = (0..50)
+            .map(|i| AgentMessage::user(format!("这是第{i}条消息，内容比较长，用来测试分词。")))
+            .collect();
+        let settings = CompactionSettings {
+            enabled: true,
+            reserve_tokens: 0,
+            keep_recent_tokens: 100,
+        };
+        let counted = count_messages_tokens(&counter, &messages);
+        assert!(should_compact_with(
+            &counter,
+            &messages,
+            counted - 1,
+            &settings
+        ));
+        assert!(!should_compact_with(
+            &counter,
+            &messages,
+            counted + 100,
+            &settings
+        ));
+
+        let (to_summarize, to_keep) = prepare_compaction_with(&counter, &messages, &settings);
+        assert!(!to_summarize.is_empty());
+        assert!(count_messages_tokens(&counter, &to_keep) >= 100);
+    }
+
     #[test]
     fn test_should_compact_enabled() {
         let messages: Vec<AgentMessage> = (0..100)
@@ -384,6 +516,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })),
             AgentMessage::Llm(Message::ToolResult(ToolResultMessage {
                 tool_call_id: "tc1".into(),
@@ -391,6 +524,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "file contents here".into(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: None,
                 is_error: false,
@@ -9,8 +9,15 @@ pub const CONFIG_DIR_NAME: &str = ".pi";
 pub const SESSIONS_DIR_NAME: &str = "sessions";
 pub const MODELS_FILE_NAME: &str = "models.json";
 pub const AUTH_FILE_NAME: &str = "auth.json";
+pub const AUTH_LOCK_FILE_NAME: &str = "auth.json.lock";
 pub const SETTINGS_FILE_NAME: &str = "settings.json";
 pub const SKILLS_DIR_NAME: &str = "skills";
+pub const GATEWAY_USAGE_FILE_NAME: &str = "gateway-usage.jsonl";
+pub const SESSION_INDEX_FILE_NAME: &str = "session-index.json";
+pub const SESSIONS_DB_FILE_NAME: &str = "sessions.db";
+pub const SESSION_FILES_DIR_NAME: &str = "session-files";
+pub const PINNED_SESSIONS_FILE_NAME: &str = "pinned-sessions.json";
+pub const MODEL_CACHE_FILE_NAME: &str = "model-cache.json";
 
 /// Default base directory: ~/.pi/agent/
 pub static DEFAULT_BASE_DIR: Lazy<PathBuf> = Lazy::new(|| {
@@ -42,11 +49,47 @@ pub fn auth_file(base: &Path) -> PathBuf {
     base.join(AUTH_FILE_NAME)
 }
 
+/// Get the lock file that serializes auth.json refreshes across processes.
+pub fn auth_lock_file(base: &Path) -> PathBuf {
+    base.join(AUTH_LOCK_FILE_NAME)
+}
+
 /// Get the settings.json file path.
 pub fn settings_file(base: &Path) -> PathBuf {
     base.join(SETTINGS_FILE_NAME)
 }
 
+/// Get the gateway usage log path.
+pub fn gateway_usage_file(base: &Path) -> PathBuf {
+    base.join(GATEWAY_USAGE_FILE_NAME)
+}
+
+/// Get the session search index path.
+pub fn session_index_file(base: &Path) -> PathBuf {
+    base.join(SESSION_INDEX_FILE_NAME)
+}
+
+/// Get the SQLite session database path.
+pub fn sessions_db_file(base: &Path) -> PathBuf {
+    base.join(SESSIONS_DB_FILE_NAME)
+}
+
+/// Get the directory holding per-session files (spilled tool output,
+/// attachments), one subdirectory per session id.
+pub fn session_files_dir(base: &Path) -> PathBuf {
+    base.join(SESSION_FILES_DIR_NAME)
+}
+
+/// Get the pinned sessions list path.
+pub fn pinned_sessions_file(base: &Path) -> PathBuf {
+    base.join(PINNED_SESSIONS_FILE_NAME)
+}
+
+/// Get the discovered model cache path.
+pub fn model_cache_file(base: &Path) -> PathBuf {
+    base.join(MODEL_CACHE_FILE_NAME)
+}
+
 /// Get the skills directory path.
 pub fn skills_dir(base: &Path) -> PathBuf {
     base.join(SKILLS_DIR_NAME)
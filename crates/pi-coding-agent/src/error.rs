@@ -11,6 +11,9 @@ pub enum CodingAgentError {
     #[error("Session error: {0}")]
     Session(String),
 
+    #[error("Database error: {0}")]
+    Database(#[from] rusqlite::Error),
+
     #[error("Tool error: {0}")]
     Tool(String),
 
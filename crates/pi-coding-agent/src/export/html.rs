@@ -0,0 +1,520 @@
+//! Self-contained HTML session viewer.
+//!
+//! The page embeds its styles and a small script: tool calls and outputs
+//! are collapsible, edit diffs are colored, thinking can be hidden, and
+//! sessions with several branches get a branch picker plus previous/next
+//! controls at each fork. Without the script every entry is shown.
+
+use std::path::{Path, PathBuf};
+
+use pi_agent_core::types::{Citation, ContentBlock, Message};
+
+use crate::error::CodingAgentError;
+use crate::export::{
+    ExportDocument, ExportEntry, ExportItem, ExportOptions, blocks_text, export_session,
+    format_time, read_session_file, split_diff, user_text,
+};
+use crate::resources::themes::Theme;
+
+#[derive(Debug, Clone, Default)]
+pub struct ExportHtmlOptions {
+    pub output_path: Option<PathBuf>,
+}
+
+/// Tool outputs longer than this many lines start collapsed.
+const COLLAPSE_OUTPUT_LINES: usize = 20;
+
+const STYLE: &str = r#"
+:root {
+  color-scheme: light dark;
+  --bg: #0f172a; --panel: #111827; --fg: #e2e8f0; --muted: #94a3b8; --border: #334155;
+  --accent: #93c5fd; --user: #0ea5e9; --assistant: #22c55e; --tool: #f59e0b;
+  --system: #a78bfa; --add: #4ade80; --del: #f87171;
+}
+body { margin: 0; font-family: ui-monospace, Menlo, Consolas, monospace; background: var(--bg); color: var(--fg); }
+.wrap { max-width: 1080px; margin: 0 auto; padding: 24px; }
+h1 { margin: 0 0 6px 0; font-size: 24px; }
+.sub { margin-bottom: 12px; color: var(--muted); }
+.summary { margin: 0 0 16px 0; line-height: 1.5; }
+.toolbar { display: flex; flex-wrap: wrap; gap: 12px; align-items: center; margin-bottom: 20px; color: var(--muted); }
+.toolbar select, .toolbar button, .branch-nav button { background: var(--panel); color: var(--fg); border: 1px solid var(--border); border-radius: 6px; padding: 2px 8px; font: inherit; }
+.entry { border: 1px solid var(--border); border-radius: 10px; padding: 14px; margin-bottom: 12px; background: var(--panel); }
+.entry.user { border-color: var(--user); }
+.entry.assistant { border-color: var(--assistant); }
+.entry.tool-result { border-color: var(--tool); }
+.entry.tool-result.error { border-color: var(--del); }
+.entry.system { border-color: var(--system); }
+h3 { margin: 0 0 8px 0; font-size: 14px; }
+h3 .time { float: right; font-weight: normal; color: var(--muted); }
+.meta { font-size: 12px; color: var(--muted); margin-bottom: 8px; }
+.block { margin: 8px 0; white-space: pre-wrap; word-break: break-word; }
+pre { margin: 0; white-space: pre-wrap; word-break: break-word; }
+details > summary { cursor: pointer; color: var(--accent); }
+.args { color: var(--muted); }
+.diff .add { color: var(--add); }
+.diff .del { color: var(--del); }
+.diff .hunk { color: var(--accent); }
+.hide-thinking .thinking { display: none; }
+.branch-nav { font-size: 12px; color: var(--muted); margin-bottom: 8px; }
+.citations { font-size: 12px; color: var(--muted); white-space: normal; }
+.citations a { color: var(--accent); }
+"#;
+
+const SCRIPT: &str = r#"
+(function () {
+  var entries = Array.prototype.slice.call(document.querySelectorAll('.entry[data-id]'));
+  var byId = {}, children = {};
+  entries.forEach(function (e) {
+    byId[e.dataset.id] = e;
+    var parent = e.dataset.parent || '';
+    (children[parent] = children[parent] || []).push(e.dataset.id);
+  });
+  function leafUnder(id) {
+    var best = null, stack = [id];
+    while (stack.length) {
+      var current = stack.pop(), kids = children[current] || [];
+      if (kids.length) { stack.push.apply(stack, kids); continue; }
+      if (best === null || +byId[current].dataset.ts > +byId[best].dataset.ts) best = current;
+    }
+    return best;
+  }
+  var select = document.getElementById('branch');
+  function show(leaf) {
+    var path = {}, current = leaf;
+    while (current && byId[current]) { path[current] = true; current = byId[current].dataset.parent; }
+    entries.forEach(function (e) { e.hidden = !path[e.dataset.id]; });
+    document.querySelectorAll('.branch-nav').forEach(function (n) { n.remove(); });
+    Object.keys(path).forEach(function (id) {
+      var siblings = children[byId[id].dataset.parent || ''] || [];
+      if (siblings.length < 2) return;
+      var index = siblings.indexOf(id), nav = document.createElement('div');
+      nav.className = 'branch-nav';
+      function button(label, target) {
+        var b = document.createElement('button');
+        b.textContent = label;
+        b.disabled = target < 0 || target >= siblings.length;
+        b.onclick = function () { show(leafUnder(siblings[target])); };
+        return b;
+      }
+      nav.appendChild(button('◀', index - 1));
+      nav.appendChild(document.createTextNode(' branch ' + (index + 1) + '/' + siblings.length + ' '));
+      nav.appendChild(button('▶', index + 1));
+      byId[id].insertBefore(nav, byId[id].firstChild);
+    });
+    if (select) select.value = leaf;
+  }
+  var leaves = JSON.parse(document.getElementById('leaves').textContent);
+  if (select) select.onchange = function () { show(select.value); };
+  if (leaves.length > 1) show(leaves[0]);
+  document.getElementById('show-thinking').onchange = function (e) {
+    document.body.classList.toggle('hide-thinking', !e.target.checked);
+  };
+  function setTools(open) {
+    document.querySelectorAll('details.tool').forEach(function (d) { d.open = open; });
+  }
+  document.getElementById('expand-tools').onclick = function () { setTools(true); };
+  document.getElementById('collapse-tools').onclick = function () { setTools(false); };
+})();
+"#;
+
+/// Theme color keys mapped onto the viewer's CSS variables, in order of
+/// preference.
+const THEME_VARIABLES: &[(&str, &[&str])] = &[
+    ("bg", &["bg", "background"]),
+    ("panel", &["panel", "surface", "card"]),
+    ("fg", &["fg", "text", "foreground"]),
+    ("muted", &["muted", "dim"]),
+    ("border", &["border"]),
+    ("accent", &["accent", "primary"]),
+    ("user", &["user"]),
+    ("assistant", &["assistant"]),
+    ("tool", &["tool", "warning"]),
+    ("system", &["system"]),
+    ("add", &["add", "success", "toolDiffAdded"]),
+    ("del", &["del", "error", "toolDiffRemoved"]),
+];
+
+fn escape_html(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&#39;")
+}
+
+/// Only plain color values reach the stylesheet.
+fn is_css_color(value: &str) -> bool {
+    !value.is_empty()
+        && value.len() <= 64
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "#(),.% ".contains(c))
+}
+
+fn theme_css(theme: &Theme) -> String {
+    let declarations: Vec<String> = THEME_VARIABLES
+        .iter()
+        .filter_map(|(variable, keys)| {
+            let value = keys
+                .iter()
+                .filter_map(|key| theme.colors.get(*key))
+                .find(|value| is_css_color(value))?;
+            Some(format!("--{variable}: {value};"))
+        })
+        .collect();
+    if declarations.is_empty() {
+        return String::new();
+    }
+    format!(":root {{ {} }}", declarations.join(" "))
+}
+
+fn render_citations(citations: &[Citation]) -> String {
+    let items: String = citations
+        .iter()
+        .map(|citation| {
+            let label = citation
+                .title
+                .as_deref()
+                .or(citation.url.as_deref())
+                .or(citation.cited_text.as_deref())
+                .unwrap_or("source");
+            match citation.url.as_deref().filter(|url| is_web_url(url)) {
+                Some(url) => format!(
+                    "<li><a href=\"{}\">{}</a></li>",
+                    escape_html(url),
+                    escape_html(label)
+                ),
+                None => format!("<li>{}</li>", escape_html(label)),
+            }
+        })
+        .collect();
+    format!("<ol class=\"block citations\">{items}</ol>")
+}
+
+/// Only web links are clickable; `javascript:` and other schemes stay text.
+fn is_web_url(url: &str) -> bool {
+    let url = url.trim_start().to_ascii_lowercase();
+    url.starts_with("http://") || url.starts_with("https://")
+}
+
+fn render_diff(diff: &str) -> String {
+    let lines: String = diff
+        .lines()
+        .map(|line| {
+            let class = if line.starts_with("@@") {
+                "hunk"
+            } else if line.starts_with('+') {
+                "add"
+            } else if line.starts_with('-') {
+                "del"
+            } else {
+                "ctx"
+            };
+            format!("<span class=\"{class}\">{}</span>\n", escape_html(line))
+        })
+        .collect();
+    format!("<pre class=\"block diff\">{lines}</pre>")
+}
+
+fn render_content_blocks(blocks: &[ContentBlock]) -> String {
+    let mut rendered = String::new();
+    for block in blocks {
+        match block {
+            ContentBlock::Text(text) => {
+                rendered.push_str(&format!(
+                    "<div class=\"block text\">{}</div>",
+                    escape_html(&text.text)
+                ));
+                if let Some(citations) = text.citations.as_ref().filter(|c| !c.is_empty()) {
+                    rendered.push_str(&render_citations(citations));
+                }
+            }
+            ContentBlock::Thinking(thinking) => {
+                rendered.push_str(&format!(
+                    "<details class=\"block thinking\"><summary>Thinking</summary><pre>{}</pre></details>",
+                    escape_html(&thinking.thinking)
+                ));
+            }
+            ContentBlock::RedactedThinking(_) => {
+                rendered
+                    .push_str("<div class=\"block thinking redacted\">[redacted thinking]</div>");
+            }
+            ContentBlock::ToolCall(call) => {
+                let arguments = serde_json::to_string_pretty(&call.arguments)
+                    .unwrap_or_else(|_| call.arguments.to_string());
+                let preview: String = call.arguments.to_string().chars().take(80).collect();
+                rendered.push_str(&format!(
+                    "<details class=\"block tool\"><summary>{} <span class=\"args\">{}</span></summary><pre>{}</pre></details>",
+                    escape_html(&call.name),
+                    escape_html(&preview),
+                    escape_html(&arguments)
+                ));
+            }
+            ContentBlock::Image(image) => {
+                rendered.push_str(&format!(
+                    "<div class=\"block image\">[image: {}; {} bytes]</div>",
+                    escape_html(&image.mime_type),
+                    image.data.len()
+                ));
+            }
+        }
+    }
+    rendered
+}
+
+fn render_tool_output(blocks: &[ContentBlock]) -> String {
+    let text = blocks_text(blocks);
+    let (prefix, body) = match split_diff(&text) {
+        Some((prefix, diff)) => (prefix, render_diff(diff)),
+        None => ("", format!("<pre>{}</pre>", escape_html(&text))),
+    };
+    let open = if text.lines().count() > COLLAPSE_OUTPUT_LINES {
+        ""
+    } else {
+        " open"
+    };
+    let summary = if prefix.is_empty() {
+        "Output".to_string()
+    } else {
+        escape_html(prefix)
+    };
+    let images: String = blocks
+        .iter()
+        .filter(|block| matches!(block, ContentBlock::Image(_)))
+        .map(|block| render_content_blocks(std::slice::from_ref(block)))
+        .collect();
+    format!(
+        "<details class=\"block tool\"{open}><summary>{summary}</summary>{body}</details>{images}"
+    )
+}
+
+fn render_item(item: &ExportItem) -> (&'static str, String, String) {
+    match item {
+        ExportItem::Message(Message::User(user)) => (
+            "user",
+            "User".to_string(),
+            match &user.content {
+                pi_agent_core::types::UserContent::Blocks(blocks) => render_content_blocks(blocks),
+                content => format!(
+                    "<div class=\"block text\">{}</div>",
+                    escape_html(&user_text(content))
+                ),
+            },
+        ),
+        ExportItem::Message(Message::Assistant(assistant)) => {
+            let meta = format!(
+                "<div class=\"meta\">{}/{}</div>",
+                escape_html(&assistant.provider),
+                escape_html(&assistant.model)
+            );
+            (
+                "assistant",
+                "Assistant".to_string(),
+                format!("{meta}{}", render_content_blocks(&assistant.content)),
+            )
+        }
+        ExportItem::Message(Message::ToolResult(tool)) => (
+            if tool.is_error {
+                "tool-result error"
+            } else {
+                "tool-result"
+            },
+            format!(
+                "Tool Result: {} ({})",
+                escape_html(&tool.tool_name),
+                if tool.is_error { "error" } else { "ok" }
+            ),
+            render_tool_output(&tool.content),
+        ),
+        ExportItem::Compaction { summary } => (
+            "system",
+            "Compaction".to_string(),
+            format!("<pre>{}</pre>", escape_html(summary)),
+        ),
+        ExportItem::BranchSummary { summary } => (
+            "system",
+            "Branch Summary".to_string(),
+            format!("<pre>{}</pre>", escape_html(summary)),
+        ),
+        ExportItem::ModelChange { model } => (
+            "system",
+            "Model Change".to_string(),
+            format!("<div class=\"meta\">{}</div>", escape_html(model)),
+        ),
+        ExportItem::ThinkingLevel { level } => (
+            "system",
+            "Thinking Level".to_string(),
+            format!("<div class=\"meta\">{}</div>", escape_html(level)),
+        ),
+    }
+}
+
+fn render_entry(entry: &ExportEntry) -> String {
+    let (class, heading, content) = render_item(&entry.item);
+    format!(
+        "<div class=\"entry {class}\" data-id=\"{}\" data-parent=\"{}\" data-ts=\"{}\"><h3>{heading}<span class=\"time\">{}</span></h3>{content}</div>\n",
+        escape_html(&entry.id),
+        escape_html(entry.parent_id.as_deref().unwrap_or("")),
+        entry.timestamp,
+        format_time(entry.timestamp),
+    )
+}
+
+/// Label for a branch in the picker: its last user message.
+fn branch_label(document: &ExportDocument, leaf: &str) -> String {
+    let by_id: std::collections::HashMap<&str, &ExportEntry> = document
+        .entries
+        .iter()
+        .map(|e| (e.id.as_str(), e))
+        .collect();
+    let mut current = Some(leaf);
+    while let Some(entry) = current.and_then(|id| by_id.get(id)) {
+        if let ExportItem::Message(Message::User(user)) = &entry.item {
+            let text = user_text(&user.content);
+            let text: String = text.split_whitespace().collect::<Vec<_>>().join(" ");
+            return format!(
+                "{} {}",
+                format_time(entry.timestamp),
+                text.chars().take(60).collect::<String>()
+            );
+        }
+        current = entry.parent_id.as_deref();
+    }
+    leaf.to_string()
+}
+
+/// Render `document` as a standalone HTML page.
+pub fn render(document: &ExportDocument, theme: Option<&Theme>) -> String {
+    let header = &document.header;
+    let mut html = String::new();
+    html.push_str("<!doctype html>\n<html lang=\"en\">\n<head>\n  <meta charset=\"utf-8\" />\n");
+    html.push_str("  <meta name=\"viewport\" content=\"width=device-width, initial-scale=1\" />\n");
+    html.push_str(&format!(
+        "  <title>{}</title>\n  <style>{STYLE}{}</style>\n</head>\n<body>\n  <div class=\"wrap\">\n",
+        escape_html(&document.title),
+        theme.map(theme_css).unwrap_or_default(),
+    ));
+    html.push_str(&format!(
+        "    <h1>{}</h1>\n    <div class=\"sub\">sessionId: {} | created: {}",
+        escape_html(&document.title),
+        escape_html(&header.id),
+        escape_html(&header.timestamp),
+    ));
+    if !header.cwd.is_empty() {
+        html.push_str(&format!(" | cwd: {}", escape_html(&header.cwd)));
+    }
+    if document.usage.requests > 0 {
+        html.push_str(&format!(
+            " | {} tokens, ${:.4}",
+            document.usage.total_tokens(),
+            document.usage.cost
+        ));
+    }
+    html.push_str("</div>\n");
+    if let Some(summary) = &document.summary {
+        html.push_str(&format!(
+            "    <p class=\"summary\">{}</p>\n",
+            escape_html(summary)
+        ));
+    }
+
+    html.push_str("    <div class=\"toolbar\">\n");
+    if document.leaves.len() > 1 {
+        html.push_str("      <label>Branch <select id=\"branch\">");
+        for leaf in &document.leaves {
+            html.push_str(&format!(
+                "<option value=\"{}\">{}</option>",
+                escape_html(leaf),
+                escape_html(&branch_label(document, leaf))
+            ));
+        }
+        html.push_str("</select></label>\n");
+    }
+    html.push_str(
+        "      <label><input type=\"checkbox\" id=\"show-thinking\" checked /> Thinking</label>\n",
+    );
+    html.push_str("      <button id=\"expand-tools\">Expand tools</button>\n");
+    html.push_str("      <button id=\"collapse-tools\">Collapse tools</button>\n    </div>\n");
+
+    for entry in &document.entries {
+        html.push_str(&render_entry(entry));
+    }
+
+    let leaves = serde_json::to_string(&document.leaves)
+        .unwrap_or_else(|_| "[]".to_string())
+        .replace('<', "\\u003c");
+    html.push_str(&format!(
+        "  </div>\n  <script type=\"application/json\" id=\"leaves\">{leaves}</script>\n  <script>{SCRIPT}</script>\n</body>\n</html>\n"
+    ));
+    html
+}
+
+/// Export a JSONL session file as HTML with default options.
+pub fn export_session_to_html(
+    input_path: &Path,
+    options: ExportHtmlOptions,
+) -> Result<PathBuf, CodingAgentError> {
+    if !input_path.exists() {
+        return Err(CodingAgentError::Session(format!(
+            "Session file not found: {}",
+            input_path.display()
+        )));
+    }
+
+    let (header, entries) = read_session_file(input_path)?;
+    let html = export_session(header, &entries, &ExportOptions::default())?;
+    let output = options.output_path.unwrap_or_else(|| {
+        let stem = input_path
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .unwrap_or("session");
+        PathBuf::from(format!("pi-agent-session-{stem}.html"))
+    });
+    std::fs::write(&output, html)?;
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFilter;
+    use crate::export::tests::sample_session;
+
+    #[test]
+    fn test_render_html_viewer() {
+        let (header, entries) = sample_session();
+        let document = ExportDocument::build(header, &entries, &ExportFilter::default()).unwrap();
+        let theme = Theme {
+            name: "paper".to_string(),
+            colors: [
+                ("background".to_string(), "#fdfdfd".to_string()),
+                ("accent".to_string(), "red;}</style>".to_string()),
+            ]
+            .into_iter()
+            .collect(),
+            source: String::new(),
+        };
+        let html = render(&document, Some(&theme));
+        assert!(html.contains("<title>Bug fix</title>"));
+        assert!(html.contains("--bg: #fdfdfd;"));
+        assert!(!html.contains("red;}"));
+        assert!(html.contains("<span class=\"add\">+new</span>"));
+        assert!(html.contains("<select id=\"branch\">"));
+        assert!(html.contains("data-parent=\"u1\""));
+    }
+
+    #[test]
+    fn test_render_citations_links_only_web_urls() {
+        let citation = |url: &str| Citation {
+            url: Some(url.to_string()),
+            ..Default::default()
+        };
+        let html = render_citations(&[
+            citation("https://example.com/a"),
+            citation("javascript:alert(1)"),
+        ]);
+        assert!(html.contains("<a href=\"https://example.com/a\">"));
+        assert!(html.contains("<li>javascript:alert(1)</li>"));
+    }
+}
@@ -0,0 +1,213 @@
+//! Normalized JSON transcript for analytics pipelines.
+//!
+//! Every entry becomes a flat object with a `kind` and camelCase fields,
+//! whatever session format it was read from. `parentId` links entries so
+//! consumers can rebuild branches; `leaves` lists the branch tips.
+
+use pi_agent_core::types::{ContentBlock, Message};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::CodingAgentError;
+use crate::export::{ExportDocument, ExportEntry, ExportItem, blocks_text, user_text};
+use crate::session::usage::UsageTotals;
+
+/// Bumped when the shape of the export changes incompatibly.
+pub const JSON_EXPORT_VERSION: u32 = 1;
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonExport<'a> {
+    version: u32,
+    session: JsonSession<'a>,
+    usage: &'a UsageTotals,
+    leaves: &'a [String],
+    entries: Vec<JsonEntry<'a>>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonSession<'a> {
+    id: &'a str,
+    title: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    summary: Option<&'a str>,
+    cwd: &'a str,
+    created_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    parent_session: Option<&'a str>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonToolCall<'a> {
+    id: &'a str,
+    name: &'a str,
+    arguments: &'a Value,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonUsage {
+    input: u64,
+    output: u64,
+    cache_read: u64,
+    cache_write: u64,
+    cost: f64,
+}
+
+#[derive(Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct JsonEntry<'a> {
+    id: &'a str,
+    parent_id: Option<&'a str>,
+    timestamp: i64,
+    kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    thinking: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tool_calls: Vec<JsonToolCall<'a>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_call_id: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    tool_name: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    is_error: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    provider: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    model: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    usage: Option<JsonUsage>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stop_reason: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error_message: Option<&'a str>,
+}
+
+fn json_entry(entry: &ExportEntry) -> JsonEntry<'_> {
+    let base = JsonEntry {
+        id: &entry.id,
+        parent_id: entry.parent_id.as_deref(),
+        timestamp: entry.timestamp,
+        ..JsonEntry::default()
+    };
+    match &entry.item {
+        ExportItem::Message(Message::User(user)) => JsonEntry {
+            kind: "user",
+            text: Some(user_text(&user.content)),
+            ..base
+        },
+        ExportItem::Message(Message::Assistant(assistant)) => {
+            let thinking: Vec<&str> = assistant
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::Thinking(t) => Some(t.thinking.as_str()),
+                    _ => None,
+                })
+                .collect();
+            let tool_calls = assistant
+                .content
+                .iter()
+                .filter_map(|block| match block {
+                    ContentBlock::ToolCall(call) => Some(JsonToolCall {
+                        id: &call.id,
+                        name: &call.name,
+                        arguments: &call.arguments,
+                    }),
+                    _ => None,
+                })
+                .collect();
+            let usage = &assistant.usage;
+            JsonEntry {
+                kind: "assistant",
+                text: Some(blocks_text(&assistant.content)).filter(|t| !t.is_empty()),
+                thinking: Some(thinking.join("\n\n")).filter(|t| !t.is_empty()),
+                tool_calls,
+                provider: Some(&assistant.provider),
+                model: Some(&assistant.model),
+                usage: Some(JsonUsage {
+                    input: usage.input,
+                    output: usage.output,
+                    cache_read: usage.cache_read,
+                    cache_write: usage.cache_write,
+                    cost: usage.cost.total,
+                }),
+                stop_reason: Some(assistant.stop_reason.to_string()),
+                error_message: assistant.error_message.as_deref(),
+                ..base
+            }
+        }
+        ExportItem::Message(Message::ToolResult(tool)) => JsonEntry {
+            kind: "toolResult",
+            text: Some(blocks_text(&tool.content)),
+            tool_call_id: Some(&tool.tool_call_id),
+            tool_name: Some(&tool.tool_name),
+            is_error: Some(tool.is_error),
+            ..base
+        },
+        ExportItem::Compaction { summary } => JsonEntry {
+            kind: "compaction",
+            text: Some(summary.clone()),
+            ..base
+        },
+        ExportItem::BranchSummary { summary } => JsonEntry {
+            kind: "branchSummary",
+            text: Some(summary.clone()),
+            ..base
+        },
+        ExportItem::ModelChange { model } => JsonEntry {
+            kind: "modelChange",
+            model: Some(model),
+            ..base
+        },
+        ExportItem::ThinkingLevel { level } => JsonEntry {
+            kind: "thinkingLevel",
+            text: Some(level.clone()),
+            ..base
+        },
+    }
+}
+
+/// Render `document` as pretty-printed JSON.
+pub fn render(document: &ExportDocument) -> Result<String, CodingAgentError> {
+    let header = &document.header;
+    let export = JsonExport {
+        version: JSON_EXPORT_VERSION,
+        session: JsonSession {
+            id: &header.id,
+            title: &document.title,
+            summary: document.summary.as_deref(),
+            cwd: &header.cwd,
+            created_at: header.timestamp_ms(),
+            parent_session: header.parent_session.as_deref(),
+        },
+        usage: &document.usage,
+        leaves: &document.leaves,
+        entries: document.entries.iter().map(json_entry).collect(),
+    };
+    Ok(serde_json::to_string_pretty(&export)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFilter;
+    use crate::export::tests::sample_session;
+
+    #[test]
+    fn test_render_json() {
+        let (header, entries) = sample_session();
+        let document = ExportDocument::build(header, &entries, &ExportFilter::default()).unwrap();
+        let value: Value = serde_json::from_str(&render(&document).unwrap()).unwrap();
+        assert_eq!(value["version"], 1);
+        assert_eq!(value["session"]["summary"], "Fixed it.");
+        assert_eq!(value["entries"][1]["kind"], "toolResult");
+        assert_eq!(value["entries"][1]["parentId"], "u1");
+        assert_eq!(value["entries"][0]["parentId"], Value::Null);
+        assert_eq!(value["leaves"][0], "u2");
+    }
+}
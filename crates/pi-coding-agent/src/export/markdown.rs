@@ -0,0 +1,184 @@
+//! Markdown transcript, suitable for PR descriptions and docs.
+//!
+//! Markdown has no way to show alternatives, so only the latest branch is
+//! rendered; use a branch filter to pick another one.
+
+use pi_agent_core::types::{ContentBlock, Message};
+
+use crate::export::{
+    ExportDocument, ExportEntry, ExportItem, blocks_text, format_time, split_diff, user_text,
+};
+
+/// `content` in a code fence longer than any backtick run inside it.
+fn fence(content: &str, language: &str) -> String {
+    let mut longest = 0;
+    let mut run = 0;
+    for c in content.chars() {
+        run = if c == '`' { run + 1 } else { 0 };
+        longest = longest.max(run);
+    }
+    let ticks = "`".repeat((longest + 1).max(3));
+    format!(
+        "{ticks}{language}\n{}\n{ticks}\n",
+        content.trim_end_matches('\n')
+    )
+}
+
+fn quote(text: &str) -> String {
+    text.lines()
+        .map(|line| format!("> {line}").trim_end().to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `name` inside inline HTML, where Markdown escapes do not apply.
+fn html_code(name: &str) -> String {
+    format!(
+        "<code>{}</code>",
+        name.replace('&', "&amp;").replace('<', "&lt;")
+    )
+}
+
+fn collapsible(summary: &str, body: &str) -> String {
+    format!("<details>\n<summary>{summary}</summary>\n\n{body}\n</details>\n")
+}
+
+fn render_blocks(blocks: &[ContentBlock], out: &mut String) {
+    for block in blocks {
+        match block {
+            ContentBlock::Text(text) => {
+                out.push_str(text.text.trim_end());
+                out.push_str("\n\n");
+            }
+            ContentBlock::Thinking(thinking) => {
+                out.push_str(&collapsible("Thinking", &quote(&thinking.thinking)));
+                out.push('\n');
+            }
+            ContentBlock::RedactedThinking(_) => out.push_str("_[redacted thinking]_\n\n"),
+            ContentBlock::ToolCall(call) => {
+                let arguments = serde_json::to_string_pretty(&call.arguments)
+                    .unwrap_or_else(|_| call.arguments.to_string());
+                out.push_str(&collapsible(
+                    &format!("Tool call: {}", html_code(&call.name)),
+                    &fence(&arguments, "json"),
+                ));
+                out.push('\n');
+            }
+            ContentBlock::Image(image) => {
+                out.push_str(&format!("_[image: {}]_\n\n", image.mime_type));
+            }
+        }
+    }
+}
+
+fn render_entry(entry: &ExportEntry, out: &mut String) {
+    let time = format_time(entry.timestamp);
+    match &entry.item {
+        ExportItem::Message(Message::User(user)) => {
+            out.push_str(&format!("## User · {time}\n\n"));
+            out.push_str(user_text(&user.content).trim_end());
+            out.push_str("\n\n");
+        }
+        ExportItem::Message(Message::Assistant(assistant)) => {
+            out.push_str(&format!("## Assistant · {time}\n\n"));
+            if !assistant.model.is_empty() {
+                out.push_str(&format!("_{}/{}_\n\n", assistant.provider, assistant.model));
+            }
+            render_blocks(&assistant.content, out);
+        }
+        ExportItem::Message(Message::ToolResult(tool)) => {
+            let status = if tool.is_error { "error" } else { "ok" };
+            let text = blocks_text(&tool.content);
+            let body = match split_diff(&text) {
+                Some(("", diff)) => fence(diff, "diff"),
+                Some((prefix, diff)) => format!("{prefix}\n\n{}", fence(diff, "diff")),
+                None => fence(&text, ""),
+            };
+            out.push_str(&collapsible(
+                &format!("Tool result: {} ({status})", html_code(&tool.tool_name)),
+                &body,
+            ));
+            out.push('\n');
+        }
+        ExportItem::Compaction { summary } => {
+            out.push_str(&format!("> **Compacted history** · {time}\n>\n"));
+            out.push_str(&quote(summary));
+            out.push_str("\n\n");
+        }
+        ExportItem::BranchSummary { summary } => {
+            out.push_str("> **Branch summary**\n>\n");
+            out.push_str(&quote(summary));
+            out.push_str("\n\n");
+        }
+        ExportItem::ModelChange { model } => {
+            out.push_str(&format!("_Model changed to `{model}`._\n\n"));
+        }
+        ExportItem::ThinkingLevel { level } => {
+            out.push_str(&format!("_Thinking level set to `{level}`._\n\n"));
+        }
+    }
+}
+
+/// Render `document` as Markdown.
+pub fn render(document: &ExportDocument) -> String {
+    let header = &document.header;
+    let mut out = format!("# {}\n\n", document.title);
+    if let Some(summary) = &document.summary {
+        out.push_str(&quote(summary));
+        out.push_str("\n\n");
+    }
+    out.push_str(&format!("- Session: `{}`\n", header.id));
+    out.push_str(&format!("- Created: {}\n", header.timestamp));
+    if !header.cwd.is_empty() {
+        out.push_str(&format!("- Working directory: `{}`\n", header.cwd));
+    }
+    if document.usage.requests > 0 {
+        out.push_str(&format!(
+            "- Usage: {} tokens, ${:.4}\n",
+            document.usage.total_tokens(),
+            document.usage.cost
+        ));
+    }
+    if document.leaves.len() > 1 {
+        out.push_str(&format!(
+            "- Showing the latest of {} branches\n",
+            document.leaves.len()
+        ));
+    }
+    out.push('\n');
+
+    for entry in document.latest_branch() {
+        render_entry(entry, &mut out);
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::export::ExportFilter;
+    use crate::export::tests::sample_session;
+
+    #[test]
+    fn test_render_markdown() {
+        let (header, entries) = sample_session();
+        let document = ExportDocument::build(header, &entries, &ExportFilter::default()).unwrap();
+        let markdown = render(&document);
+        assert!(markdown.starts_with("# Bug fix\n\n> Fixed it.\n"));
+        assert!(markdown.contains("Showing the latest of 2 branches"));
+        assert!(markdown.contains("Try again"));
+        assert!(!markdown.contains("```diff"));
+
+        let branch = ExportFilter {
+            branch: Some("t1".to_string()),
+            ..ExportFilter::default()
+        };
+        let (header, entries) = sample_session();
+        let markdown = render(&ExportDocument::build(header, &entries, &branch).unwrap());
+        assert!(markdown.contains("Replaced 1 occurrence(s) in a.rs\n\n```diff\n@@ -1 +1 @@"));
+        assert_eq!(fence("a ``` b", ""), "````\na ``` b\n````\n");
+        assert_eq!(html_code("a</code>&b"), "<code>a&lt;/code>&amp;b</code>");
+    }
+}
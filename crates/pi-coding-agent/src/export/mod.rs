@@ -0,0 +1,527 @@
+//! Session transcript export: a self-contained HTML viewer, Markdown and
+//! normalized JSON.
+//!
+//! Entries in any supported session format are first normalized into an
+//! [`ExportDocument`], filtered by branch, date range and tool-output
+//! redaction, and then rendered by the format's module.
+
+pub mod html;
+pub mod json;
+pub mod markdown;
+
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, BufReader};
+use std::path::Path;
+
+use pi_agent_core::agent_types::AgentMessage;
+use pi_agent_core::types::{ContentBlock, Message, TextContent, UserContent};
+
+use crate::error::CodingAgentError;
+use crate::resources::themes::Theme;
+use crate::session::context::build_session_context;
+use crate::session::tree::SessionTree;
+use crate::session::types::{SessionEntry, SessionHeader, latest_session_info};
+use crate::session::usage::{UsageLedger, UsageTotals};
+
+pub use html::{ExportHtmlOptions, export_session_to_html};
+
+/// Output format of an export.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ExportFormat {
+    #[default]
+    Html,
+    Markdown,
+    Json,
+}
+
+impl ExportFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "html" | "htm" => Some(Self::Html),
+            "md" | "markdown" => Some(Self::Markdown),
+            "json" => Some(Self::Json),
+            _ => None,
+        }
+    }
+
+    /// The format implied by a file extension, if any.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        Self::parse(path.extension()?.to_str()?)
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Self::Html => "html",
+            Self::Markdown => "md",
+            Self::Json => "json",
+        }
+    }
+}
+
+/// Which parts of a session to export.
+#[derive(Debug, Clone, Default)]
+pub struct ExportFilter {
+    /// Only the branch through this entry ID (or unique prefix), followed
+    /// to its latest leaf. `"latest"` selects the most recent branch.
+    pub branch: Option<String>,
+    /// Only entries at or after this Unix millisecond timestamp.
+    pub since: Option<i64>,
+    /// Only entries at or before this Unix millisecond timestamp.
+    pub until: Option<i64>,
+    /// Replace tool results with a placeholder noting their size.
+    pub redact_tool_output: bool,
+}
+
+/// Options for [`export_session`].
+#[derive(Debug, Clone, Default)]
+pub struct ExportOptions {
+    pub format: ExportFormat,
+    pub filter: ExportFilter,
+    /// Colors for the HTML viewer; other formats ignore it.
+    pub theme: Option<Theme>,
+}
+
+/// A rendered entry's content.
+#[derive(Debug, Clone)]
+pub enum ExportItem {
+    Message(Message),
+    Compaction {
+        summary: String,
+    },
+    BranchSummary {
+        summary: String,
+    },
+    /// Switch to `model`, as "provider/id" where the provider is known.
+    ModelChange {
+        model: String,
+    },
+    ThinkingLevel {
+        level: String,
+    },
+}
+
+/// One exported entry.
+#[derive(Debug, Clone)]
+pub struct ExportEntry {
+    pub id: String,
+    /// Nearest exported ancestor.
+    pub parent_id: Option<String>,
+    /// Unix milliseconds.
+    pub timestamp: i64,
+    pub item: ExportItem,
+}
+
+/// A session normalized for rendering.
+#[derive(Debug, Clone)]
+pub struct ExportDocument {
+    pub header: SessionHeader,
+    pub title: String,
+    pub summary: Option<String>,
+    /// Exported entries in session order.
+    pub entries: Vec<ExportEntry>,
+    /// Tips of the exported branches, latest first.
+    pub leaves: Vec<String>,
+    /// Usage of the exported entries.
+    pub usage: UsageTotals,
+}
+
+impl ExportDocument {
+    /// Normalize `entries` and apply `filter`.
+    pub fn build(
+        header: SessionHeader,
+        entries: &[SessionEntry],
+        filter: &ExportFilter,
+    ) -> Result<Self, CodingAgentError> {
+        let tree = SessionTree::from_entries_chained(entries);
+        let on_branch: Option<HashSet<&str>> = match &filter.branch {
+            Some(branch) => {
+                let leaf = if branch == "latest" {
+                    tree.latest_leaf()
+                } else {
+                    tree.latest_leaf_under(resolve_entry_id(entries, branch)?)
+                };
+                Some(
+                    leaf.map(|leaf| tree.path_to(leaf.id()))
+                        .unwrap_or_default()
+                        .into_iter()
+                        .map(SessionEntry::id)
+                        .collect(),
+                )
+            }
+            None => None,
+        };
+
+        let selected: Vec<&SessionEntry> = entries
+            .iter()
+            .filter(|entry| {
+                on_branch
+                    .as_ref()
+                    .is_none_or(|ids| ids.contains(entry.id()))
+            })
+            .filter(|entry| {
+                let timestamp = entry.timestamp();
+                filter.since.is_none_or(|since| timestamp >= since)
+                    && filter.until.is_none_or(|until| timestamp <= until)
+            })
+            .collect();
+
+        let mut exported = Vec::new();
+        for entry in &selected {
+            if let Some(mut item) = normalize(entry) {
+                if filter.redact_tool_output {
+                    redact(&mut item);
+                }
+                exported.push(ExportEntry {
+                    id: entry.id().to_string(),
+                    parent_id: None,
+                    timestamp: entry.timestamp(),
+                    item,
+                });
+            }
+        }
+
+        // Link each entry to its nearest exported ancestor.
+        let exported_ids: HashSet<String> = exported.iter().map(|e| e.id.clone()).collect();
+        for entry in &mut exported {
+            let mut parent = tree.get(&entry.id).and_then(|node| node.parent.clone());
+            while let Some(id) = parent.take() {
+                if exported_ids.contains(&id) {
+                    parent = Some(id);
+                    break;
+                }
+                parent = tree.get(&id).and_then(|node| node.parent.clone());
+            }
+            entry.parent_id = parent;
+        }
+
+        let parents: HashSet<&str> = exported
+            .iter()
+            .filter_map(|e| e.parent_id.as_deref())
+            .collect();
+        let mut leaves: Vec<&ExportEntry> = exported
+            .iter()
+            .filter(|e| !parents.contains(e.id.as_str()))
+            .collect();
+        leaves.sort_by_key(|e| std::cmp::Reverse(e.timestamp));
+        let leaves = leaves.into_iter().map(|e| e.id.clone()).collect();
+
+        let owned: Vec<SessionEntry> = selected.into_iter().cloned().collect();
+        let usage = UsageLedger::from_entries(&owned).total;
+        let (generated_title, summary) = latest_session_info(entries);
+        let title = generated_title
+            .or_else(|| header.title.clone())
+            .unwrap_or_else(|| format!("Session {}", header.id));
+
+        Ok(Self {
+            header,
+            title,
+            summary,
+            entries: exported,
+            leaves,
+            usage,
+        })
+    }
+
+    /// Entries from the root to the latest leaf.
+    pub fn latest_branch(&self) -> Vec<&ExportEntry> {
+        let by_id: HashMap<&str, &ExportEntry> =
+            self.entries.iter().map(|e| (e.id.as_str(), e)).collect();
+        let mut path = Vec::new();
+        let mut current = self.leaves.first().map(String::as_str);
+        while let Some(entry) = current.and_then(|id| by_id.get(id)) {
+            path.push(*entry);
+            current = entry.parent_id.as_deref();
+        }
+        path.reverse();
+        path
+    }
+}
+
+fn resolve_entry_id<'a>(
+    entries: &'a [SessionEntry],
+    query: &str,
+) -> Result<&'a str, CodingAgentError> {
+    if let Some(entry) = entries.iter().find(|e| e.id() == query) {
+        return Ok(entry.id());
+    }
+    let matches: Vec<&str> = entries
+        .iter()
+        .map(SessionEntry::id)
+        .filter(|id| id.starts_with(query))
+        .collect();
+    match matches.as_slice() {
+        [id] => Ok(id),
+        [] => Err(CodingAgentError::Session(format!(
+            "Entry not found: {query}"
+        ))),
+        _ => Err(CodingAgentError::Session(format!(
+            "Entry ID prefix {query} is ambiguous"
+        ))),
+    }
+}
+
+fn normalize(entry: &SessionEntry) -> Option<ExportItem> {
+    match entry {
+        SessionEntry::Message { message, .. } => Some(ExportItem::Message(message.clone())),
+        SessionEntry::Compaction { summary, .. } | SessionEntry::LegacySummary { summary, .. } => {
+            Some(ExportItem::Compaction {
+                summary: summary.clone(),
+            })
+        }
+        SessionEntry::BranchSummary { summary, .. } => Some(ExportItem::BranchSummary {
+            summary: summary.clone(),
+        }),
+        SessionEntry::ModelChange {
+            provider, model_id, ..
+        } => Some(ExportItem::ModelChange {
+            model: format!("{provider}/{model_id}"),
+        }),
+        SessionEntry::LegacyModelSwitch { to_model, .. } => Some(ExportItem::ModelChange {
+            model: to_model.clone(),
+        }),
+        SessionEntry::ThinkingLevelChange { thinking_level, .. } => {
+            Some(ExportItem::ThinkingLevel {
+                level: thinking_level.clone(),
+            })
+        }
+        SessionEntry::LegacyUser { .. }
+        | SessionEntry::LegacyAssistant { .. }
+        | SessionEntry::LegacyToolResult { .. } => {
+            match build_session_context(std::slice::from_ref(entry)).pop()? {
+                AgentMessage::Llm(message) => Some(ExportItem::Message(message)),
+                _ => None,
+            }
+        }
+        _ => None,
+    }
+}
+
+fn redact(item: &mut ExportItem) {
+    if let ExportItem::Message(Message::ToolResult(result)) = item {
+        let chars: usize = result
+            .content
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text(t) => t.text.chars().count(),
+                _ => 0,
+            })
+            .sum();
+        result.content = vec![ContentBlock::Text(TextContent {
+            text: format!("[tool output redacted: {chars} characters]"),
+            text_signature: None,
+            citations: None,
+        })];
+        result.details = None;
+    }
+}
+
+/// Text of a user message's content.
+pub(crate) fn user_text(content: &UserContent) -> String {
+    match content {
+        UserContent::Text(text) => text.clone(),
+        UserContent::Blocks(blocks) => blocks_text(blocks),
+    }
+}
+
+/// Text blocks of `blocks`, joined by blank lines.
+pub(crate) fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|block| match block {
+            ContentBlock::Text(t) => Some(t.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n")
+}
+
+/// Split tool output into the text before a unified diff and the diff,
+/// as produced by the edit tool.
+pub(crate) fn split_diff(text: &str) -> Option<(&str, &str)> {
+    let mut offset = 0;
+    for line in text.split_inclusive('\n') {
+        if line.starts_with("@@ -")
+            || (line.starts_with("--- ") && text[offset..].contains("\n+++ "))
+        {
+            return Some((text[..offset].trim_end(), &text[offset..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Local time of a Unix millisecond timestamp, for display.
+pub(crate) fn format_time(timestamp_ms: i64) -> String {
+    use chrono::TimeZone;
+    chrono::Local
+        .timestamp_millis_opt(timestamp_ms)
+        .single()
+        .map(|time| time.format("%Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default()
+}
+
+/// Read a session from a JSONL file.
+pub fn read_session_file(
+    path: &Path,
+) -> Result<(SessionHeader, Vec<SessionEntry>), CodingAgentError> {
+    let file = std::fs::File::open(path)?;
+    let mut lines = BufReader::new(file).lines();
+
+    let header_line = lines
+        .next()
+        .ok_or_else(|| CodingAgentError::Session("Empty session file".to_string()))??;
+    let header = serde_json::from_str::<SessionHeader>(&header_line)?;
+
+    let mut entries = Vec::new();
+    for line in lines {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        if let Ok(entry) = serde_json::from_str::<SessionEntry>(&line) {
+            entries.push(entry);
+        }
+    }
+
+    Ok((header, entries))
+}
+
+/// Render a session in the requested format.
+pub fn export_session(
+    header: SessionHeader,
+    entries: &[SessionEntry],
+    options: &ExportOptions,
+) -> Result<String, CodingAgentError> {
+    let document = ExportDocument::build(header, entries, &options.filter)?;
+    Ok(match options.format {
+        ExportFormat::Html => html::render(&document, options.theme.as_ref()),
+        ExportFormat::Markdown => markdown::render(&document),
+        ExportFormat::Json => json::render(&document)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::types::CURRENT_SESSION_VERSION;
+    use pi_agent_core::types::{ToolResultMessage, UserMessage};
+
+    pub(super) fn sample_session() -> (SessionHeader, Vec<SessionEntry>) {
+        let header = SessionHeader {
+            entry_type: "session".to_string(),
+            version: Some(CURRENT_SESSION_VERSION),
+            id: "s1".to_string(),
+            timestamp: "2026-03-01T10:00:00.000Z".to_string(),
+            cwd: "/work".to_string(),
+            parent_session: None,
+            title: None,
+        };
+        let message =
+            |id: &str, parent: Option<&str>, ts: &str, message: Message| SessionEntry::Message {
+                id: id.to_string(),
+                parent_id: parent.map(str::to_string),
+                timestamp: ts.to_string(),
+                message,
+            };
+        let user = |text: &str| {
+            Message::User(UserMessage {
+                content: UserContent::Text(text.to_string()),
+                timestamp: 0,
+            })
+        };
+        let entries = vec![
+            message("u1", None, "2026-03-01T10:00:01.000Z", user("Fix the bug")),
+            message(
+                "t1",
+                Some("u1"),
+                "2026-03-01T10:00:02.000Z",
+                Message::ToolResult(ToolResultMessage {
+                    tool_call_id: "call".to_string(),
+                    tool_name: "edit".to_string(),
+                    content: vec![ContentBlock::Text(TextContent {
+                        text: "Replaced 1 occurrence(s) in a.rs\n\n@@ -1 +1 @@\n-old\n+new\n"
+                            .to_string(),
+                        text_signature: None,
+                        citations: None,
+                    })],
+                    details: None,
+                    is_error: false,
+                    timestamp: 0,
+                }),
+            ),
+            message(
+                "u2",
+                Some("u1"),
+                "2026-03-02T10:00:00.000Z",
+                user("Try again"),
+            ),
+            SessionEntry::SessionInfo {
+                id: "i1".to_string(),
+                parent_id: None,
+                timestamp: "2026-03-02T10:00:01.000Z".to_string(),
+                name: Some("Bug fix".to_string()),
+                summary: Some("Fixed it.".to_string()),
+            },
+        ];
+        (header, entries)
+    }
+
+    #[test]
+    fn test_build_document_filters() {
+        let (header, entries) = sample_session();
+        let doc =
+            ExportDocument::build(header.clone(), &entries, &ExportFilter::default()).unwrap();
+        assert_eq!(doc.title, "Bug fix");
+        assert_eq!(doc.entries.len(), 3);
+        assert_eq!(doc.leaves, ["u2", "t1"]);
+        let path: Vec<_> = doc.latest_branch().iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(path, ["u1", "u2"]);
+
+        let branch = ExportFilter {
+            branch: Some("t".to_string()),
+            redact_tool_output: true,
+            ..ExportFilter::default()
+        };
+        let doc = ExportDocument::build(header.clone(), &entries, &branch).unwrap();
+        let ids: Vec<_> = doc.entries.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["u1", "t1"]);
+        let ExportItem::Message(Message::ToolResult(result)) = &doc.entries[1].item else {
+            panic!("expected tool result");
+        };
+        assert_eq!(
+            blocks_text(&result.content),
+            "[tool output redacted: 56 characters]"
+        );
+
+        let since = ExportFilter {
+            since: Some(
+                chrono::DateTime::parse_from_rfc3339("2026-03-02T00:00:00Z")
+                    .unwrap()
+                    .timestamp_millis(),
+            ),
+            ..ExportFilter::default()
+        };
+        let doc = ExportDocument::build(header.clone(), &entries, &since).unwrap();
+        assert_eq!(doc.entries.len(), 1);
+        assert_eq!(doc.entries[0].parent_id, None);
+
+        let missing = ExportFilter {
+            branch: Some("zz".to_string()),
+            ..ExportFilter::default()
+        };
+        assert!(ExportDocument::build(header, &entries, &missing).is_err());
+    }
+
+    #[test]
+    fn test_split_diff_and_format() {
+        let (prefix, diff) = split_diff("Replaced 1\n\n@@ -1 +1 @@\n-a\n+b\n").unwrap();
+        assert_eq!(prefix, "Replaced 1");
+        assert!(diff.starts_with("@@"));
+        assert!(split_diff("no diff here").is_none());
+        assert_eq!(
+            ExportFormat::from_path(Path::new("out.MD")),
+            Some(ExportFormat::Markdown)
+        );
+        assert_eq!(ExportFormat::parse("pdf"), None);
+    }
+}
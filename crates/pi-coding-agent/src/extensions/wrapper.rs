@@ -14,6 +14,7 @@ fn text_result(text: impl Into<String>) -> AgentToolResult {
         content: vec![ContentBlock::Text(TextContent {
             text: text.into(),
             text_signature: None,
+            citations: None,
         })],
         details: None,
     }
@@ -4,6 +4,7 @@ pub mod cli;
 pub mod compaction;
 pub mod config;
 pub mod error;
+pub mod export;
 pub mod export_html;
 pub mod extensions;
 pub mod keybindings;
@@ -12,6 +13,7 @@ pub mod model;
 pub mod modes;
 pub mod resources;
 pub mod retry;
+pub mod server;
 pub mod session;
 pub mod settings;
 pub mod slash_commands;
@@ -23,7 +25,10 @@ pub mod tools;
 
 // Error
 pub use error::CodingAgentError;
-pub use export_html::{ExportHtmlOptions, export_session_to_html};
+pub use export::{
+    ExportFilter, ExportFormat, ExportHtmlOptions, ExportOptions, export_session,
+    export_session_to_html,
+};
 
 // Config
 pub use config::paths;
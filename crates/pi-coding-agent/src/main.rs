@@ -1,30 +1,51 @@
+use std::collections::HashMap;
 use std::io::{IsTerminal, Read, Write};
 use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use pi_agent_core::agent_types::AgentMessage;
 use pi_agent_core::types::{ContentBlock, Message, Model};
 use pi_coding_agent::agent_session::sdk::{
     CreateSessionOptions, CreateSessionWithExtensionsOptions, create_agent_session_with_extensions,
+    refresh_discovered_models,
 };
 use pi_coding_agent::agent_session::session::PromptOptions;
 use pi_coding_agent::auth::credentials::AuthCredential;
+use pi_coding_agent::auth::login::{self, ConsoleLoginUi, LoginMethod};
+use pi_coding_agent::auth::storage::AuthStorage;
 use pi_coding_agent::cli::args::{Args, Mode, is_valid_thinking_level, parse_args, print_help};
 use pi_coding_agent::config::paths::{self, APP_NAME, CONFIG_DIR_NAME};
-use pi_coding_agent::export_html::{ExportHtmlOptions, export_session_to_html};
+use pi_coding_agent::export::{
+    ExportFilter, ExportFormat, ExportOptions, export_session, read_session_file,
+};
+use pi_coding_agent::model::discovery;
 use pi_coding_agent::model::registry::ModelRegistry;
 use pi_coding_agent::model::resolver::{parse_model_pattern, resolve_cli_model};
 use pi_coding_agent::modes::{
     InteractiveMode, InteractiveModeOptions, PrintModeOptions, PrintOutputMode, ScopedModelConfig,
     run_print_mode, run_rpc_mode,
 };
+use pi_coding_agent::resources::loader::{
+    DefaultResourceLoader, DefaultResourceLoaderOptions, ResourceLoader,
+};
 use pi_coding_agent::resources::package_manager::PackageManager;
 use pi_coding_agent::resources::patterns::{apply_patterns, to_posix_string};
 use pi_coding_agent::resources::source_identity::{
     normalize_source_for_scope, source_match_key_for_input, source_match_key_for_scope,
 };
+use pi_coding_agent::resources::themes::Theme;
+use pi_coding_agent::server::gateway::{DEFAULT_GATEWAY_ADDR, GatewayServer};
+use pi_coding_agent::server::proxy::{DEFAULT_PROXY_ADDR, ProxyServer};
 use pi_coding_agent::session::manager::SessionManager;
+use pi_coding_agent::session::project::{project_of, project_root};
+use pi_coding_agent::session::retention::{PinnedSessions, auto_prune, gc, prune};
+use pi_coding_agent::session::search::SearchQuery;
+use pi_coding_agent::session::store::{SessionFilter, SessionStoreKind, copy_sessions};
+use pi_coding_agent::session::usage::{UsageGrouping, UsageReport, collect_usage};
 use pi_coding_agent::settings::manager::SettingsManager;
-use pi_coding_agent::settings::types::{PackageSource, PackageSourceFilter};
+use pi_coding_agent::settings::types::{
+    PackageSource, PackageSourceFilter, RetentionSettings, SessionSettings,
+};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 enum PackageCommand {
@@ -1082,8 +1103,802 @@ fn resolve_session_id(manager: &SessionManager, input: &str) -> String {
     input.to_string()
 }
 
-fn print_models(search: Option<&str>) {
-    let registry = ModelRegistry::new();
+/// `pi proxy serve`: run the stream proxy server until interrupted.
+async fn handle_auth_command(raw_args: &[String], base_dir: &Path) -> Option<i32> {
+    let command = raw_args.first().map(String::as_str)?;
+    if command != "login" && command != "logout" {
+        return None;
+    }
+    let usage = format!(
+        "Usage:\n  {APP_NAME} login <provider> [--no-browser]\n  {APP_NAME} logout <provider>\n\n\
+         Providers: {}",
+        login::oauth_provider_keys().join(", ")
+    );
+    let mut provider = None;
+    let mut no_browser = false;
+    for arg in &raw_args[1..] {
+        match arg.as_str() {
+            "--no-browser" if command == "login" => no_browser = true,
+            "-h" | "--help" => {
+                println!("{usage}");
+                return Some(0);
+            }
+            value if !value.starts_with('-') && provider.is_none() => provider = Some(value),
+            _ => {
+                eprintln!("Unknown option {arg} for command {command}");
+                eprintln!("{usage}");
+                return Some(1);
+            }
+        }
+    }
+    let Some(provider) = provider else {
+        eprintln!("{usage}");
+        return Some(1);
+    };
+
+    let auth_storage = AuthStorage::new(base_dir);
+    if command == "logout" {
+        return match auth_storage.remove_credential(provider) {
+            Ok(()) => {
+                println!("Logged out of {provider}");
+                Some(0)
+            }
+            Err(e) => {
+                eprintln!("{e}");
+                Some(1)
+            }
+        };
+    }
+
+    let method = if no_browser || !login::browser_available() {
+        LoginMethod::Manual
+    } else {
+        LoginMethod::Browser
+    };
+    let ui = ConsoleLoginUi {
+        open_browser: method == LoginMethod::Browser,
+    };
+    match login::login_and_save(&auth_storage, provider, method, &ui).await {
+        Ok(name) => {
+            println!("Logged in to {name}");
+            Some(0)
+        }
+        Err(e) => {
+            eprintln!("{e}");
+            Some(1)
+        }
+    }
+}
+
+/// `pi usage`: token and cost totals across stored sessions.
+fn handle_usage_command(raw_args: &[String], base_dir: &Path) -> Option<i32> {
+    if raw_args.first().map(String::as_str) != Some("usage") {
+        return None;
+    }
+    let usage = format!(
+        "Usage:\n  {APP_NAME} usage [day|week|project|model|provider] [--days <n> | --since <YYYY-MM-DD>]\n  \
+         [--project <path>] [--format table|csv|json]"
+    );
+    let mut grouping = UsageGrouping::Day;
+    let mut since = 0i64;
+    let mut project: Option<String> = None;
+    let mut format = "table".to_string();
+    let mut iter = raw_args[1..].iter();
+    while let Some(arg) = iter.next() {
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{usage}");
+                return Some(0);
+            }
+            "--by" | "--days" | "--since" | "--project" | "--format" => {
+                let Some(value) = iter.next() else {
+                    eprintln!("Missing value for {arg}");
+                    return Some(1);
+                };
+                let parsed = match arg.as_str() {
+                    "--by" => UsageGrouping::parse(value).map(|g| grouping = g),
+                    "--days" => value.parse::<i64>().ok().map(|days| {
+                        since = chrono::Utc::now().timestamp_millis() - days * 86_400_000;
+                    }),
+                    "--since" => parse_local_date(value, false).map(|t| since = t),
+                    "--project" => {
+                        let path = std::path::absolute(value).unwrap_or_else(|_| value.into());
+                        project = Some(path.display().to_string());
+                        Some(())
+                    }
+                    _ => matches!(value.as_str(), "table" | "csv" | "json")
+                        .then(|| format = value.clone()),
+                };
+                if parsed.is_none() {
+                    eprintln!("Invalid value {value:?} for {arg}");
+                    eprintln!("{usage}");
+                    return Some(1);
+                }
+            }
+            "--json" => format = "json".to_string(),
+            "--csv" => format = "csv".to_string(),
+            value => match UsageGrouping::parse(value) {
+                Some(g) => grouping = g,
+                None => {
+                    eprintln!("Unknown option {arg} for command usage");
+                    eprintln!("{usage}");
+                    return Some(1);
+                }
+            },
+        }
+    }
+
+    let manager = match open_session_manager(base_dir) {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("Failed to open session store: {e}");
+            return Some(1);
+        }
+    };
+    let mut records = collect_usage(&manager);
+    if let Some(project) = &project {
+        records.retain(|r| &r.project == project);
+    }
+    let report = UsageReport::build(&records, grouping, since);
+    match format.as_str() {
+        "json" => println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        ),
+        "csv" => print!("{}", report.to_csv()),
+        _ if report.rows.is_empty() => println!("No usage recorded."),
+        _ => print!("{}", report.to_table()),
+    }
+    Some(0)
+}
+
+/// Parse `YYYY-MM-DD` as local midnight, or the last millisecond of that
+/// day when `end_of_day` is set, in Unix ms.
+fn parse_local_date(value: &str, end_of_day: bool) -> Option<i64> {
+    let date = chrono::NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+    let time = if end_of_day {
+        date.and_hms_milli_opt(23, 59, 59, 999)
+    } else {
+        date.and_hms_opt(0, 0, 0)
+    }?;
+    let time = time.and_local_timezone(chrono::Local).earliest()?;
+    Some(time.timestamp_millis())
+}
+
+/// Session settings from the user settings file.
+fn load_session_settings(base_dir: &Path) -> Result<SessionSettings, String> {
+    Ok(load_settings_manager_at(base_dir)?
+        .settings()
+        .sessions
+        .clone()
+        .unwrap_or_default())
+}
+
+/// Open the session manager for the storage backend configured in user
+/// settings.
+fn open_session_manager(base_dir: &Path) -> Result<SessionManager, String> {
+    let kind = load_session_settings(base_dir)?.store.unwrap_or_default();
+    SessionManager::with_store(base_dir, kind).map_err(|e| e.to_string())
+}
+
+fn format_local_time(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+fn format_size(bytes: u64) -> String {
+    match bytes {
+        b if b >= 1024 * 1024 => format!("{:.1} MB", b as f64 / (1024.0 * 1024.0)),
+        b if b >= 1024 => format!("{:.1} KB", b as f64 / 1024.0),
+        b => format!("{b} B"),
+    }
+}
+
+/// Load the theme named `name` from the theme directories and `--theme`
+/// paths.
+fn find_theme(args: &Args, cwd: &Path, base_dir: &Path, name: &str) -> Result<Theme, String> {
+    let mut loader = DefaultResourceLoader::new(DefaultResourceLoaderOptions {
+        cwd: cwd.to_path_buf(),
+        agent_dir: Some(base_dir.to_path_buf()),
+        additional_theme_paths: args.themes.iter().map(PathBuf::from).collect(),
+        no_skills: true,
+        no_prompt_templates: true,
+        ..DefaultResourceLoaderOptions::default()
+    });
+    loader.reload().map_err(|e| e.to_string())?;
+    let (themes, _) = loader.get_themes();
+    if let Some(theme) = themes.iter().find(|theme| theme.name == name) {
+        return Ok(theme.clone());
+    }
+    let available: Vec<&str> = themes.iter().map(|theme| theme.name.as_str()).collect();
+    Err(if available.is_empty() {
+        format!("Theme not found: {name} (no themes installed)")
+    } else {
+        format!(
+            "Theme not found: {name} (available: {})",
+            available.join(", ")
+        )
+    })
+}
+
+/// `--export <file|session-id> [output|-]`: write a session transcript as
+/// HTML, Markdown or JSON.
+fn run_export(args: &Args, cwd: &Path, base_dir: &Path) -> Result<(), String> {
+    let source = args.export.as_deref().unwrap_or_default();
+    let output = args
+        .export_output
+        .as_deref()
+        .or(args.messages.first().map(String::as_str));
+    let (header, entries) = if Path::new(source).is_file() {
+        read_session_file(Path::new(source)).map_err(|e| e.to_string())?
+    } else {
+        let manager = open_session_manager(base_dir)?;
+        let session_id = resolve_session_id(&manager, source);
+        manager.open(&session_id).map_err(|e| e.to_string())?
+    };
+
+    let format = match (&args.export_format, output) {
+        (Some(format), _) => ExportFormat::parse(format)
+            .ok_or_else(|| format!("Unknown export format: {format} (use html, md or json)"))?,
+        (None, Some(path)) if path != "-" => {
+            ExportFormat::from_path(Path::new(path)).unwrap_or_default()
+        }
+        _ => ExportFormat::default(),
+    };
+    let date = |value: &Option<String>, end_of_day: bool| {
+        value
+            .as_deref()
+            .map(|value| {
+                parse_local_date(value, end_of_day)
+                    .ok_or_else(|| format!("Invalid date: {value} (expected YYYY-MM-DD)"))
+            })
+            .transpose()
+    };
+    let theme = match &args.export_theme {
+        Some(name) => Some(find_theme(args, cwd, base_dir, name)?),
+        None => None,
+    };
+    let options = ExportOptions {
+        format,
+        filter: ExportFilter {
+            branch: args.export_branch.clone(),
+            since: date(&args.export_since, false)?,
+            until: date(&args.export_until, true)?,
+            redact_tool_output: args.export_redact,
+        },
+        theme,
+    };
+
+    let session_id = header.id.clone();
+    let content = export_session(header, &entries, &options).map_err(|e| e.to_string())?;
+    if output == Some("-") {
+        print!("{content}");
+        return Ok(());
+    }
+    let path = output.map(PathBuf::from).unwrap_or_else(|| {
+        PathBuf::from(format!(
+            "pi-agent-session-{session_id}.{}",
+            format.extension()
+        ))
+    });
+    std::fs::write(&path, content).map_err(|e| format!("{}: {e}", path.display()))?;
+    println!("Exported to: {}", path.display());
+    Ok(())
+}
+
+fn handle_sessions_command(raw_args: &[String], base_dir: &Path) -> Option<i32> {
+    if raw_args.first().map(String::as_str) != Some("sessions") {
+        return None;
+    }
+    let usage = format!(
+        "Usage:\n  {APP_NAME} sessions list [--all-projects] [--limit <n>] [--json]\n  \
+         {APP_NAME} sessions search <query...> [--since <YYYY-MM-DD>] [--until <YYYY-MM-DD>]\n    \
+         [--project <path>] [--model <pattern>] [--limit <n>] [--json]\n  \
+         {APP_NAME} sessions prune [--max-age <days>] [--max-count <n>] [--max-size <MB>] [--dry-run]\n  \
+         {APP_NAME} sessions gc [--dry-run]\n  \
+         {APP_NAME} sessions pin|unpin <id>\n  \
+         {APP_NAME} sessions import|export <jsonl|sqlite>"
+    );
+    let subcommand = raw_args.get(1).map(String::as_str);
+    if matches!(subcommand, None | Some("-h" | "--help")) {
+        println!("{usage}");
+        return Some(if subcommand.is_none() { 1 } else { 0 });
+    }
+    let manager = match open_session_manager(base_dir) {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("Failed to open session store: {e}");
+            return Some(1);
+        }
+    };
+    let args = &raw_args[2..];
+    if args.iter().any(|a| a == "-h" || a == "--help") {
+        println!("{usage}");
+        return Some(0);
+    }
+    let result = match subcommand {
+        Some("list") => sessions_list(&manager, args),
+        Some("search") => sessions_search(&manager, args),
+        Some("prune") => sessions_prune(&manager, args),
+        Some("gc") => sessions_gc(&manager, args),
+        Some(action @ ("pin" | "unpin")) => sessions_pin(&manager, args, action == "pin"),
+        Some(direction @ ("import" | "export")) => {
+            sessions_copy(&manager, args, direction == "import")
+        }
+        Some(other) => Err(format!("Unknown sessions command: {other}")),
+        None => unreachable!(),
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("{e}");
+            eprintln!("{usage}");
+            Some(1)
+        }
+    }
+}
+
+/// Split `args` into flag values and positional arguments. `value_flags`
+/// take a value; `switches` do not.
+fn parse_subcommand_args<'a>(
+    args: &'a [String],
+    value_flags: &[&str],
+    switches: &[&str],
+) -> Result<(HashMap<&'a str, &'a str>, Vec<&'a str>), String> {
+    let mut flags = HashMap::new();
+    let mut positional = Vec::new();
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        let arg = arg.as_str();
+        if value_flags.contains(&arg) {
+            let value = iter
+                .next()
+                .ok_or_else(|| format!("Missing value for {arg}"))?;
+            flags.insert(arg, value.as_str());
+        } else if switches.contains(&arg) {
+            flags.insert(arg, "");
+        } else if arg.starts_with("--") {
+            return Err(format!("Unknown option {arg}"));
+        } else {
+            positional.push(arg);
+        }
+    }
+    Ok((flags, positional))
+}
+
+fn parse_flag<T: std::str::FromStr>(
+    flags: &HashMap<&str, &str>,
+    flag: &str,
+) -> Result<Option<T>, String> {
+    flags
+        .get(flag)
+        .map(|value| {
+            value
+                .parse()
+                .map_err(|_| format!("Invalid value {value:?} for {flag}"))
+        })
+        .transpose()
+}
+
+fn current_project() -> String {
+    let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
+    project_root(&cwd).display().to_string()
+}
+
+fn sessions_list(manager: &SessionManager, args: &[String]) -> Result<(), String> {
+    let (flags, _) = parse_subcommand_args(args, &["--limit"], &["--all-projects", "--json"])?;
+    let filter = SessionFilter {
+        project: (!flags.contains_key("--all-projects")).then(current_project),
+        limit: parse_flag(&flags, "--limit")?,
+        ..SessionFilter::default()
+    };
+    let sessions = manager.list_filtered(&filter).map_err(|e| e.to_string())?;
+    if flags.contains_key("--json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&sessions).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    if sessions.is_empty() {
+        println!("No sessions.");
+        return Ok(());
+    }
+    let pinned = PinnedSessions::load(manager.base_dir());
+    for info in &sessions {
+        let pin = if pinned.contains(&info.session_id) {
+            " [pinned]"
+        } else {
+            ""
+        };
+        println!(
+            "{}  {}  {:>4} entries  {:>8}  {}{pin}",
+            info.session_id,
+            format_local_time(info.updated_at),
+            info.entry_count,
+            format_size(info.size_bytes),
+            info.title.as_deref().unwrap_or("(untitled)"),
+        );
+        if filter.project.is_none() && !info.cwd.is_empty() {
+            println!("    {}", info.cwd);
+        }
+        if let Some(summary) = &info.summary {
+            println!("    {summary}");
+        }
+    }
+    Ok(())
+}
+
+fn sessions_search(manager: &SessionManager, args: &[String]) -> Result<(), String> {
+    let (flags, terms) = parse_subcommand_args(
+        args,
+        &["--since", "--until", "--project", "--model", "--limit"],
+        &["--json"],
+    )?;
+    let date = |flag: &str, end_of_day: bool| {
+        flags
+            .get(flag)
+            .map(|value| {
+                parse_local_date(value, end_of_day)
+                    .ok_or_else(|| format!("Invalid value {value:?} for {flag}"))
+            })
+            .transpose()
+    };
+    let query = SearchQuery {
+        text: terms.join(" "),
+        since: date("--since", false)?,
+        until: date("--until", true)?,
+        project: flags.get("--project").map(|value| {
+            std::path::absolute(value)
+                .unwrap_or_else(|_| PathBuf::from(value))
+                .display()
+                .to_string()
+        }),
+        model: flags.get("--model").map(|value| value.to_string()),
+        limit: parse_flag(&flags, "--limit")?,
+    };
+
+    let hits = manager
+        .search(&query)
+        .map_err(|e| format!("Session search failed: {e}"))?;
+    if flags.contains_key("--json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&hits).unwrap_or_default()
+        );
+    } else if hits.is_empty() {
+        println!("No matching sessions.");
+    } else {
+        for hit in &hits {
+            let title = hit.title.as_deref().unwrap_or("(untitled)");
+            println!(
+                "{}  {}  {title}",
+                hit.session_id,
+                format_local_time(hit.updated_at)
+            );
+            if !hit.cwd.is_empty() {
+                println!("    {}", hit.cwd);
+            }
+            if !hit.snippet.is_empty() {
+                println!("    {}", hit.snippet);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn sessions_prune(manager: &SessionManager, args: &[String]) -> Result<(), String> {
+    let (flags, _) = parse_subcommand_args(
+        args,
+        &["--max-age", "--max-count", "--max-size"],
+        &["--dry-run", "--json"],
+    )?;
+    let overrides = RetentionSettings {
+        max_age_days: parse_flag(&flags, "--max-age")?,
+        max_count: parse_flag(&flags, "--max-count")?,
+        max_total_mb: parse_flag(&flags, "--max-size")?,
+    };
+    let policy = load_session_settings(manager.base_dir())?
+        .retention
+        .unwrap_or_default()
+        .overlay(&overrides);
+    if !policy.is_enabled() {
+        return Err(
+            "No retention policy: set sessions.retention in settings or pass --max-age, --max-count or --max-size."
+                .to_string(),
+        );
+    }
+    let dry_run = flags.contains_key("--dry-run");
+    let report = prune(manager, &policy, &[], dry_run).map_err(|e| e.to_string())?;
+    if flags.contains_key("--json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    for session_id in &report.deleted {
+        println!("{session_id}");
+    }
+    println!(
+        "{} {} session(s), {}; {} remaining.",
+        if dry_run { "Would delete" } else { "Deleted" },
+        report.deleted.len(),
+        format_size(report.freed_bytes),
+        report.remaining
+    );
+    Ok(())
+}
+
+fn sessions_gc(manager: &SessionManager, args: &[String]) -> Result<(), String> {
+    let (flags, _) = parse_subcommand_args(args, &[], &["--dry-run", "--json"])?;
+    let dry_run = flags.contains_key("--dry-run");
+    let report = gc(manager, dry_run).map_err(|e| e.to_string())?;
+    if flags.contains_key("--json") {
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&report).unwrap_or_default()
+        );
+        return Ok(());
+    }
+    println!(
+        "{} {} orphaned session file director{} ({}) and {} stale pin(s).",
+        if dry_run { "Would remove" } else { "Removed" },
+        report.removed_file_dirs.len(),
+        if report.removed_file_dirs.len() == 1 {
+            "y"
+        } else {
+            "ies"
+        },
+        format_size(report.freed_bytes),
+        report.removed_pins
+    );
+    Ok(())
+}
+
+fn sessions_pin(manager: &SessionManager, args: &[String], pin: bool) -> Result<(), String> {
+    let (_, ids) = parse_subcommand_args(args, &[], &[])?;
+    if ids.is_empty() {
+        return Err("Missing session id".to_string());
+    }
+    let mut pinned = PinnedSessions::load(manager.base_dir());
+    for input in ids {
+        let session_id = resolve_session_id(manager, input);
+        if pin {
+            if !manager.exists(&session_id) {
+                return Err(format!("Session not found: {input}"));
+            }
+            pinned.pin(&session_id);
+            println!("Pinned {session_id}");
+        } else if pinned.unpin(&session_id) {
+            println!("Unpinned {session_id}");
+        } else {
+            println!("{session_id} was not pinned");
+        }
+    }
+    pinned.save().map_err(|e| e.to_string())
+}
+
+fn sessions_copy(manager: &SessionManager, args: &[String], import: bool) -> Result<(), String> {
+    let (_, positional) = parse_subcommand_args(args, &[], &[])?;
+    let kind = positional
+        .first()
+        .and_then(|v| SessionStoreKind::parse(v))
+        .ok_or_else(|| "Expected a backend: jsonl or sqlite".to_string())?;
+    if kind == manager.store().kind() {
+        return Err(format!("Sessions are already stored as {}.", kind.as_str()));
+    }
+    let other = kind
+        .open(manager.base_dir())
+        .map_err(|e| format!("Failed to open {} session store: {e}", kind.as_str()))?;
+    let result = if import {
+        copy_sessions(other.as_ref(), manager.store())
+    } else {
+        copy_sessions(manager.store(), other.as_ref())
+    };
+    let report = result.map_err(|e| {
+        let direction = if import { "import" } else { "export" };
+        format!("Session {direction} failed: {e}")
+    })?;
+    println!(
+        "{} {} session(s), skipped {} already present.",
+        if import { "Imported" } else { "Exported" },
+        report.copied,
+        report.skipped
+    );
+    Ok(())
+}
+
+async fn handle_server_command(raw_args: &[String], base_dir: &Path) -> Option<i32> {
+    let command = raw_args.first().map(String::as_str)?;
+    let (default_addr, token_env) = match command {
+        "proxy" => (DEFAULT_PROXY_ADDR, "PI_PROXY_TOKENS"),
+        "gateway" => (DEFAULT_GATEWAY_ADDR, "PI_GATEWAY_KEYS"),
+        _ => return None,
+    };
+    let usage = format!(
+        "Usage:\n  {APP_NAME} {command} serve [--host <addr>] [--port <port>] [--token <token>]...\n\n\
+         Tokens may also be given as a comma-separated list in {token_env}."
+    );
+    if raw_args.get(1).map(String::as_str) != Some("serve") {
+        eprintln!("{usage}");
+        return Some(1);
+    }
+
+    let (default_host, default_port) = default_addr
+        .rsplit_once(':')
+        .expect("default server addresses include a port");
+    let mut host = default_host.to_string();
+    let mut port = default_port.to_string();
+    let mut tokens: Vec<String> = std::env::var(token_env)
+        .unwrap_or_default()
+        .split(',')
+        .map(str::trim)
+        .filter(|t| !t.is_empty())
+        .map(str::to_string)
+        .collect();
+    let mut rest = raw_args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        match (arg.as_str(), rest.next()) {
+            ("--host", Some(value)) => host = value.clone(),
+            ("--port", Some(value)) => port = value.clone(),
+            ("--token", Some(value)) => tokens.push(value.clone()),
+            ("-h" | "--help", _) => {
+                println!("{usage}");
+                return Some(0);
+            }
+            _ => {
+                eprintln!("Unknown option {arg} for command {command} serve");
+                eprintln!("{usage}");
+                return Some(1);
+            }
+        }
+    }
+
+    let addr = format!("{host}:{port}");
+    let bind = |addr: String| async move {
+        tokio::net::TcpListener::bind(&addr)
+            .await
+            .map_err(|e| format!("Failed to listen on {addr}: {e}"))
+    };
+    let result = if command == "proxy" {
+        if tokens.is_empty() {
+            eprintln!("At least one client token is required (--token or {token_env})");
+            return Some(1);
+        }
+        match ProxyServer::from_config_dir(base_dir, tokens) {
+            Ok(server) => match bind(addr.clone()).await {
+                Ok(listener) => {
+                    println!("Proxy listening on http://{addr}/api/stream");
+                    Arc::new(server)
+                        .serve(listener)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.to_string()),
+        }
+    } else {
+        match GatewayServer::from_config_dir(base_dir, tokens) {
+            Ok(server) if !server.has_keys() => Err(format!(
+                "At least one client key is required (gateway.keys in settings, --token or {token_env})"
+            )),
+            Ok(server) => match bind(addr.clone()).await {
+                Ok(listener) => {
+                    println!("Gateway listening on http://{addr}/v1");
+                    Arc::new(server)
+                        .serve(listener)
+                        .await
+                        .map_err(|e| e.to_string())
+                }
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e.to_string()),
+        }
+    };
+    match result {
+        Ok(()) => Some(0),
+        Err(e) => {
+            eprintln!("{e}");
+            Some(1)
+        }
+    }
+}
+
+/// `pi models refresh`: fetch the models of the `modelDiscovery` sources.
+async fn handle_models_command(raw_args: &[String], base_dir: &Path) -> Option<i32> {
+    if raw_args.first().map(String::as_str) != Some("models") {
+        return None;
+    }
+    let usage = format!("Usage:\n  {APP_NAME} models refresh [--provider <name>]");
+    if raw_args.get(1).map(String::as_str) != Some("refresh") {
+        eprintln!("{usage}");
+        return Some(1);
+    }
+    let mut provider = None;
+    let mut rest = raw_args.iter().skip(2);
+    while let Some(arg) = rest.next() {
+        match (arg.as_str(), rest.next()) {
+            ("--provider", Some(value)) => provider = Some(value.clone()),
+            ("-h" | "--help", _) => {
+                println!("{usage}");
+                return Some(0);
+            }
+            _ => {
+                eprintln!("Unknown option {arg} for command models refresh");
+                eprintln!("{usage}");
+                return Some(1);
+            }
+        }
+    }
+
+    let settings_manager = match load_settings_manager_at(base_dir) {
+        Ok(manager) => manager,
+        Err(e) => {
+            eprintln!("Failed to load settings: {e}");
+            return Some(1);
+        }
+    };
+    let mut settings = settings_manager.settings().clone();
+    if let Some(provider) = &provider
+        && let Some(sources) = settings
+            .model_discovery
+            .as_mut()
+            .and_then(|d| d.sources.as_mut())
+    {
+        sources.retain(|source| &source.provider == provider);
+    }
+    if discovery::discovery_sources(&settings).is_empty() {
+        eprintln!(
+            "No model discovery sources configured{}. Add them to modelDiscovery.sources in {}",
+            provider.map(|p| format!(" for {p}")).unwrap_or_default(),
+            paths::settings_file(base_dir).display()
+        );
+        return Some(1);
+    }
+
+    let mut failed = false;
+    for outcome in refresh_discovered_models(base_dir, &settings, true).await {
+        match outcome.result {
+            Ok(count) => println!("{}: {count} models", outcome.provider),
+            Err(e) => {
+                failed = true;
+                eprintln!("{}: {e}", outcome.provider);
+            }
+        }
+    }
+    Some(if failed { 1 } else { 0 })
+}
+
+/// Built-in models plus those from `models.json`, settings and the
+/// discovered model cache.
+fn load_model_registry(base_dir: &Path) -> ModelRegistry {
+    let mut registry = ModelRegistry::new();
+    let models_path = paths::models_file(base_dir);
+    if let Err(e) = registry.load_custom_models(&models_path) {
+        eprintln!(
+            "Warning: failed to load custom models from {}: {e}",
+            models_path.display()
+        );
+    }
+    if let Ok(settings_manager) = load_settings_manager_at(base_dir) {
+        let settings = settings_manager.settings();
+        if let Some(custom_models) = &settings.custom_models {
+            registry.add_custom_models(custom_models);
+        }
+        registry.add_discovered_models(discovery::cached_models(base_dir, settings));
+    }
+    registry
+}
+
+fn print_models(base_dir: &Path, search: Option<&str>) {
+    let registry = load_model_registry(base_dir);
     let query = search.unwrap_or("").trim().to_lowercase();
     for model in registry.all_models() {
         let key = format!("{}/{} {}", model.provider, model.id, model.name).to_lowercase();
@@ -1374,6 +2189,37 @@ async fn main() {
     let cwd = std::env::current_dir().unwrap_or_else(|_| PathBuf::from("."));
     let base_dir = paths::resolve_base_dir(None);
 
+    if let Some(exit_code) = handle_auth_command(&raw_args, &base_dir).await {
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+        return;
+    }
+
+    if let Some(exit_code) = handle_sessions_command(&raw_args, &base_dir) {
+        std::process::exit(exit_code);
+    }
+    if let Some(exit_code) = handle_usage_command(&raw_args, &base_dir) {
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+        return;
+    }
+
+    if let Some(exit_code) = handle_models_command(&raw_args, &base_dir).await {
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+        return;
+    }
+
+    if let Some(exit_code) = handle_server_command(&raw_args, &base_dir).await {
+        if exit_code != 0 {
+            std::process::exit(exit_code);
+        }
+        return;
+    }
+
     if let Some(exit_code) = handle_package_command(&raw_args, &cwd, &base_dir) {
         if exit_code != 0 {
             std::process::exit(exit_code);
@@ -1387,6 +2233,12 @@ async fn main() {
         print_help(APP_NAME);
         return;
     }
+    if !args.errors.is_empty() {
+        for error in &args.errors {
+            eprintln!("{error}");
+        }
+        std::process::exit(1);
+    }
     if args.version {
         println!("{}", env!("CARGO_PKG_VERSION"));
         return;
@@ -1397,7 +2249,7 @@ async fn main() {
         } else {
             Some(search.as_str())
         };
-        print_models(search);
+        print_models(&base_dir, search);
         return;
     }
 
@@ -1420,19 +2272,11 @@ async fn main() {
         std::process::exit(1);
     }
 
-    if let Some(export_source) = args.export.as_deref() {
-        let output = args.messages.first().map(PathBuf::from);
-        match export_session_to_html(
-            Path::new(export_source),
-            ExportHtmlOptions {
-                output_path: output,
-            },
-        ) {
-            Ok(path) => println!("Exported to: {}", path.display()),
-            Err(e) => {
-                eprintln!("{e}");
-                std::process::exit(1);
-            }
+    if args.export.is_some() {
+        let sessions_dir = paths::resolve_base_dir(args.session_dir.as_deref().map(Path::new));
+        if let Err(e) = run_export(&args, &cwd, &sessions_dir) {
+            eprintln!("{e}");
+            std::process::exit(1);
         }
         return;
     }
@@ -1577,6 +2421,8 @@ async fn main() {
         }
     }
 
+    session.set_limits(args.limits.clone());
+
     if let Some(session_input) = &args.session {
         let session_id = resolve_session_id(session.session_manager(), session_input);
         if let Err(e) = session.restore_session(&session_id) {
@@ -1584,7 +2430,11 @@ async fn main() {
             std::process::exit(1);
         }
     } else if args.continue_session || args.resume {
-        match session.session_manager().continue_recent() {
+        let project = (!args.all_projects).then(|| project_of(&cwd.display().to_string()));
+        match session
+            .session_manager()
+            .continue_recent_in(project.as_deref())
+        {
             Ok(Some((header, _entries))) => {
                 if let Err(e) = session.restore_session(&header.id) {
                     eprintln!("恢复最近会话失败 ({}): {e}", header.id);
@@ -1603,6 +2453,27 @@ async fn main() {
         }
     }
 
+    if !args.no_session
+        && let Some(policy) = session
+            .settings_manager()
+            .settings()
+            .sessions
+            .as_ref()
+            .and_then(|s| s.retention.clone())
+            .filter(RetentionSettings::is_enabled)
+    {
+        let protected: Vec<&str> = session.session_id().into_iter().collect();
+        match auto_prune(session.session_manager(), &policy, &protected) {
+            Ok(report) if !report.deleted.is_empty() => tracing::info!(
+                "Retention removed {} session(s) ({} bytes)",
+                report.deleted.len(),
+                report.freed_bytes
+            ),
+            Ok(_) => {}
+            Err(e) => tracing::warn!("Session retention failed: {e}"),
+        }
+    }
+
     let is_interactive = !args.print && args.mode.is_none();
     let mode = args.mode.unwrap_or(Mode::Text);
     if is_interactive && args.verbose && !scoped_models.is_empty() {
@@ -53,6 +53,7 @@ mod tests {
                 content: vec![ContentBlock::Text(TextContent {
                     text: "Hi!".to_string(),
                     text_signature: None,
+                    citations: None,
                 })],
                 api: "test".to_string(),
                 provider: "test".to_string(),
@@ -61,6 +62,7 @@ mod tests {
                 stop_reason: StopReason::Stop,
                 error_message: None,
                 timestamp: 0,
+                rate_limit: None,
             })),
         ];
 
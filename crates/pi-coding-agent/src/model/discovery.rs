@@ -0,0 +1,331 @@
+//! Models discovered from the endpoints in `modelDiscovery`, cached in
+//! `model-cache.json`.
+//!
+//! Startup only reads the cache, so a server that is down never blocks it;
+//! expired sources are fetched again by [`refresh`], in the background once
+//! a session is created and by `pi models refresh`. Refreshed models show up
+//! in the next session. A source that fails keeps its cached models and is
+//! not fetched again in the background for [`DISCOVERY_RETRY_BACKOFF`].
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+use pi_agent_ai::discovery::{DiscoveryKind, DiscoverySource, discover_models};
+use pi_agent_core::types::Model;
+use serde::{Deserialize, Serialize};
+
+use crate::auth::storage::AuthStorage;
+use crate::config::paths;
+use crate::error::CodingAgentError;
+use crate::settings::types::Settings;
+
+/// Default time discovered models stay fresh.
+pub const DEFAULT_DISCOVERY_TTL: Duration = Duration::from_secs(86_400);
+
+/// Time before a failed source is fetched again, unless forced. Capped at
+/// the TTL.
+pub const DISCOVERY_RETRY_BACKOFF: Duration = Duration::from_secs(600);
+
+/// Models last fetched from one source.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedSource {
+    pub kind: DiscoveryKind,
+    pub base_url: String,
+    /// Unix milliseconds; 0 if never fetched.
+    pub fetched_at: i64,
+    /// Unix milliseconds of the last failed fetch, if it failed.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub failed_at: Option<i64>,
+    pub models: Vec<Model>,
+}
+
+impl CachedSource {
+    fn matches(&self, source: &DiscoverySource) -> bool {
+        self.kind == source.kind && self.base_url == source.base_url
+    }
+}
+
+/// Contents of `model-cache.json`, keyed by provider.
+#[derive(Debug, Default)]
+pub struct ModelCache {
+    path: PathBuf,
+    sources: BTreeMap<String, CachedSource>,
+}
+
+impl ModelCache {
+    pub fn load(base_dir: &Path) -> Self {
+        let path = paths::model_cache_file(base_dir);
+        let sources = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, sources }
+    }
+
+    pub fn save(&self) -> Result<(), CodingAgentError> {
+        if let Some(parent) = self.path.parent() {
+            paths::ensure_dir(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.sources)?)?;
+        Ok(())
+    }
+
+    /// Cached models of `sources`, however old.
+    pub fn models(&self, sources: &[DiscoverySource]) -> Vec<Model> {
+        sources
+            .iter()
+            .filter_map(|source| {
+                self.sources
+                    .get(&source.provider)
+                    .filter(|c| c.matches(source))
+            })
+            .flat_map(|cached| cached.models.iter().cloned())
+            .collect()
+    }
+
+    /// Whether `source` was fetched less than `ttl` before `now_ms`.
+    pub fn is_fresh(&self, source: &DiscoverySource, ttl: Duration, now_ms: i64) -> bool {
+        self.sources.get(&source.provider).is_some_and(|cached| {
+            cached.matches(source)
+                && now_ms.saturating_sub(cached.fetched_at) < ttl.as_millis() as i64
+        })
+    }
+
+    /// Whether the last fetch of `source` failed less than the retry
+    /// backoff before `now_ms`.
+    pub fn is_backing_off(&self, source: &DiscoverySource, ttl: Duration, now_ms: i64) -> bool {
+        let backoff = ttl.min(DISCOVERY_RETRY_BACKOFF).as_millis() as i64;
+        self.sources.get(&source.provider).is_some_and(|cached| {
+            cached.matches(source)
+                && cached
+                    .failed_at
+                    .is_some_and(|failed_at| now_ms.saturating_sub(failed_at) < backoff)
+        })
+    }
+
+    pub fn insert(&mut self, source: &DiscoverySource, models: Vec<Model>, now_ms: i64) {
+        self.sources.insert(
+            source.provider.clone(),
+            CachedSource {
+                kind: source.kind,
+                base_url: source.base_url.clone(),
+                fetched_at: now_ms,
+                failed_at: None,
+                models,
+            },
+        );
+    }
+
+    /// Record a failed fetch of `source`, keeping its cached models.
+    pub fn record_failure(&mut self, source: &DiscoverySource, now_ms: i64) {
+        match self.sources.get_mut(&source.provider) {
+            Some(cached) if cached.matches(source) => cached.failed_at = Some(now_ms),
+            _ => {
+                self.sources.insert(
+                    source.provider.clone(),
+                    CachedSource {
+                        kind: source.kind,
+                        base_url: source.base_url.clone(),
+                        fetched_at: 0,
+                        failed_at: Some(now_ms),
+                        models: Vec::new(),
+                    },
+                );
+            }
+        }
+    }
+}
+
+/// Sources configured in `modelDiscovery`, without API keys. Sources with
+/// no URL are skipped with a warning.
+pub fn discovery_sources(settings: &Settings) -> Vec<DiscoverySource> {
+    let configs = settings
+        .model_discovery
+        .as_ref()
+        .and_then(|d| d.sources.as_ref());
+    configs
+        .into_iter()
+        .flatten()
+        .filter_map(|config| {
+            let kind = config
+                .kind
+                .unwrap_or_else(|| DiscoveryKind::for_provider(&config.provider));
+            let provider_settings = settings
+                .providers
+                .as_ref()
+                .and_then(|providers| providers.get(&config.provider));
+            let base_url = config
+                .base_url
+                .clone()
+                .or_else(|| provider_settings.and_then(|p| p.base_url.clone()))
+                .or_else(|| kind.default_base_url().map(str::to_string));
+            let Some(base_url) = base_url else {
+                tracing::warn!(
+                    "Model discovery for {} needs a baseUrl; skipping",
+                    config.provider
+                );
+                return None;
+            };
+            Some(DiscoverySource {
+                provider: config.provider.clone(),
+                kind,
+                base_url,
+                api_key: None,
+                headers: provider_settings
+                    .and_then(|p| p.headers.clone())
+                    .unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+/// How long discovered models stay fresh.
+pub fn discovery_ttl(settings: &Settings) -> Duration {
+    settings
+        .model_discovery
+        .as_ref()
+        .and_then(|d| d.ttl_secs)
+        .map(Duration::from_secs)
+        .unwrap_or(DEFAULT_DISCOVERY_TTL)
+}
+
+/// Cached models of the configured sources, for the model registry.
+pub fn cached_models(base_dir: &Path, settings: &Settings) -> Vec<Model> {
+    let sources = discovery_sources(settings);
+    if sources.is_empty() {
+        return Vec::new();
+    }
+    ModelCache::load(base_dir).models(&sources)
+}
+
+/// Result of fetching one source.
+#[derive(Debug)]
+pub struct RefreshOutcome {
+    pub provider: String,
+    /// Number of models found, or why the fetch failed.
+    pub result: Result<usize, String>,
+}
+
+/// Fetch the configured sources whose cache has expired and that did not
+/// fail recently, or all of them with `force`, and update the cache. Skipped
+/// sources are not reported.
+pub async fn refresh(
+    base_dir: &Path,
+    settings: &Settings,
+    auth_storage: &AuthStorage,
+    force: bool,
+) -> Vec<RefreshOutcome> {
+    let sources = discovery_sources(settings);
+    let ttl = discovery_ttl(settings);
+    let mut cache = ModelCache::load(base_dir);
+    let now_ms = chrono::Utc::now().timestamp_millis();
+
+    let mut outcomes = Vec::new();
+    for mut source in sources {
+        if !force
+            && (cache.is_fresh(&source, ttl, now_ms) || cache.is_backing_off(&source, ttl, now_ms))
+        {
+            continue;
+        }
+        source.api_key = auth_storage.resolve_api_key(&source.provider).await;
+        let result = match discover_models(&source).await {
+            Ok(models) => {
+                let count = models.len();
+                cache.insert(&source, models, now_ms);
+                Ok(count)
+            }
+            Err(e) => {
+                cache.record_failure(&source, now_ms);
+                Err(e)
+            }
+        };
+        outcomes.push(RefreshOutcome {
+            provider: source.provider,
+            result,
+        });
+    }
+
+    if !outcomes.is_empty()
+        && let Err(e) = cache.save()
+    {
+        tracing::warn!("Failed to save the model cache: {e}");
+    }
+    outcomes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::settings::types::{DiscoverySourceSettings, ModelDiscoverySettings};
+
+    fn model(provider: &str, id: &str) -> Model {
+        Model {
+            id: id.to_string(),
+            name: id.to_string(),
+            api: "openai-completions".to_string(),
+            provider: provider.to_string(),
+            base_url: "http://localhost:11434/v1".to_string(),
+            reasoning: false,
+            input: vec!["text".to_string()],
+            cost: Default::default(),
+            context_window: 8192,
+            max_tokens: 4096,
+            headers: None,
+            compat: None,
+        }
+    }
+
+    #[test]
+    fn test_cache_freshness_and_sources() {
+        let dir = tempfile::tempdir().unwrap();
+        let settings = Settings {
+            model_discovery: Some(ModelDiscoverySettings {
+                ttl_secs: Some(60),
+                sources: Some(vec![
+                    DiscoverySourceSettings {
+                        provider: "ollama".to_string(),
+                        ..Default::default()
+                    },
+                    DiscoverySourceSettings {
+                        provider: "lmstudio".to_string(),
+                        ..Default::default()
+                    },
+                ]),
+            }),
+            ..Default::default()
+        };
+        // lmstudio has no URL and is skipped; ollama gets its default kind and URL.
+        let sources = discovery_sources(&settings);
+        assert_eq!(sources.len(), 1);
+        assert_eq!(sources[0].kind, DiscoveryKind::Ollama);
+        assert_eq!(sources[0].base_url, "http://localhost:11434");
+
+        let mut cache = ModelCache::load(dir.path());
+        assert!(!cache.is_fresh(&sources[0], discovery_ttl(&settings), 0));
+        cache.insert(&sources[0], vec![model("ollama", "llama3.2:latest")], 1_000);
+        cache.save().unwrap();
+
+        let cache = ModelCache::load(dir.path());
+        let ttl = discovery_ttl(&settings);
+        assert!(cache.is_fresh(&sources[0], ttl, 30_000));
+        assert!(!cache.is_fresh(&sources[0], ttl, 61_000));
+        // Stale entries are still served until they are replaced.
+        assert_eq!(cached_models(dir.path(), &settings).len(), 1);
+
+        // A failure keeps the cached models and backs off, within the TTL.
+        let mut cache = cache;
+        cache.record_failure(&sources[0], 70_000);
+        assert_eq!(cache.models(&sources).len(), 1);
+        assert!(cache.is_backing_off(&sources[0], ttl, 100_000));
+        assert!(!cache.is_backing_off(&sources[0], ttl, 130_000));
+        cache.insert(&sources[0], Vec::new(), 100_000);
+        assert!(!cache.is_backing_off(&sources[0], ttl, 100_000));
+
+        // A different server for the same provider does not reuse the cache.
+        let mut moved = sources[0].clone();
+        moved.base_url = "http://gpu-box:11434".to_string();
+        assert!(cache.models(&[moved]).is_empty());
+    }
+}
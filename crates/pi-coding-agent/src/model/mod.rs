@@ -1,2 +1,4 @@
+pub mod discovery;
 pub mod registry;
 pub mod resolver;
+pub mod router;
@@ -1,10 +1,14 @@
 use std::collections::HashMap;
 use std::path::Path;
 
-use pi_agent_core::types::Model;
+use pi_agent_core::types::{Model, OpenAICompletionsCompat, StreamOptions};
+use serde_json::Value;
 
 use crate::error::CodingAgentError;
-use crate::settings::types::CustomModelConfig;
+use crate::settings::types::{CustomModelConfig, CustomProviderConfig, ModelsConfig};
+
+/// The only API with compat settings.
+const OPENAI_COMPLETIONS_API: &str = "openai-completions";
 
 /// Registry for managing available models (built-in + custom).
 pub struct ModelRegistry {
@@ -12,6 +16,8 @@ pub struct ModelRegistry {
     builtin: HashMap<String, Model>,
     /// Custom models from models.json and settings.
     custom: Vec<CustomModelConfig>,
+    /// Providers defined or overridden in models.json.
+    providers: HashMap<String, CustomProviderConfig>,
     /// All models combined for lookup.
     all_models: Vec<Model>,
 }
@@ -32,22 +38,192 @@ impl ModelRegistry {
         Self {
             builtin,
             custom: Vec::new(),
+            providers: HashMap::new(),
             all_models: builtin_models,
         }
     }
 
-    /// Load custom models from a models.json file.
+    /// Load custom providers and models from a models.json file.
     pub fn load_custom_models(&mut self, path: &Path) -> Result<(), CodingAgentError> {
         if !path.exists() {
             return Ok(());
         }
 
         let content = std::fs::read_to_string(path)?;
-        let configs: Vec<CustomModelConfig> = serde_json::from_str(&content)?;
-        self.add_custom_models(&configs);
+        let value: Value = serde_json::from_str(&content)?;
+        let config = if value.is_array() {
+            ModelsConfig {
+                models: Some(serde_json::from_value(value)?),
+                ..Default::default()
+            }
+        } else {
+            serde_json::from_value::<ModelsConfig>(value)?
+        };
+        if let Some(providers) = &config.providers {
+            self.add_providers(providers)?;
+        }
+        if let Some(models) = &config.models {
+            self.add_custom_models(models);
+        }
+        Ok(())
+    }
+
+    /// Register custom providers and apply overrides to built-in ones.
+    /// Nothing is applied unless every provider is valid.
+    pub fn add_providers(
+        &mut self,
+        providers: &HashMap<String, CustomProviderConfig>,
+    ) -> Result<(), CodingAgentError> {
+        let mut names: Vec<&String> = providers.keys().collect();
+        names.sort();
+        for name in &names {
+            self.validate_provider(name, &providers[*name])
+                .map_err(|e| {
+                    CodingAgentError::Config(format!("models.json: provider \"{name}\": {e}"))
+                })?;
+        }
+        for name in names {
+            let config = &providers[name];
+            self.apply_provider(name, config);
+            self.providers.insert(name.clone(), config.clone());
+        }
         Ok(())
     }
 
+    /// Providers defined or overridden in models.json.
+    pub fn custom_providers(&self) -> &HashMap<String, CustomProviderConfig> {
+        &self.providers
+    }
+
+    /// API keys (or key references) of the models.json providers.
+    pub fn provider_api_keys(&self) -> HashMap<String, String> {
+        self.providers
+            .iter()
+            .filter_map(|(name, config)| {
+                let key = config
+                    .api_key
+                    .clone()
+                    .or_else(|| config.api_key_env.as_ref().map(|var| format!("env:{var}")))?;
+                Some((name.clone(), key))
+            })
+            .collect()
+    }
+
+    /// Extra header that carries the provider's API key, from models.json.
+    pub fn auth_header(&self, provider: &str) -> Option<&str> {
+        self.providers.get(provider)?.auth_header.as_deref()
+    }
+
+    /// The API of a provider: its own setting, else that of its built-in
+    /// models.
+    fn provider_api(&self, name: &str, config: &CustomProviderConfig) -> Option<String> {
+        config.api.clone().or_else(|| {
+            self.all_models
+                .iter()
+                .find(|m| m.provider == name)
+                .map(|m| m.api.clone())
+        })
+    }
+
+    fn validate_provider(&self, name: &str, config: &CustomProviderConfig) -> Result<(), String> {
+        let apis = known_apis();
+        let check_api = |api: &str| {
+            if apis.iter().any(|known| known == api) {
+                Ok(())
+            } else {
+                Err(format!(
+                    "unknown api \"{api}\" (known: {})",
+                    apis.join(", ")
+                ))
+            }
+        };
+        let Some(api) = self.provider_api(name, config) else {
+            return Err("\"api\" is required for a new provider".to_string());
+        };
+        check_api(&api)?;
+        if let Some(compat) = &config.compat {
+            validate_compat(&api, compat)?;
+        }
+
+        let builtin = self.all_models.iter().any(|m| m.provider == name);
+        for model in config.models.iter().flatten() {
+            let model_api = model.api.as_deref().unwrap_or(&api);
+            check_api(model_api).map_err(|e| format!("model \"{}\": {e}", model.id))?;
+            if let Some(compat) = &model.compat {
+                validate_compat(model_api, compat)
+                    .map_err(|e| format!("model \"{}\": {e}", model.id))?;
+            }
+            if !builtin && config.base_url.is_none() && model.base_url.is_none() {
+                return Err(format!(
+                    "model \"{}\" has no baseUrl; set one on the provider or the model",
+                    model.id
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    fn apply_provider(&mut self, name: &str, config: &CustomProviderConfig) {
+        let api = self.provider_api(name, config).unwrap_or_default();
+        let base_url = config.base_url.clone().or_else(|| {
+            self.all_models
+                .iter()
+                .find(|m| m.provider == name)
+                .map(|m| m.base_url.clone())
+        });
+
+        let overrides = |model: &mut Model| {
+            if let Some(base_url) = &config.base_url {
+                model.base_url = base_url.clone();
+            }
+            if let Some(headers) = &config.headers {
+                model
+                    .headers
+                    .get_or_insert_with(HashMap::new)
+                    .extend(headers.clone());
+            }
+            if model.api == OPENAI_COMPLETIONS_API {
+                model.compat = merge_objects(model.compat.as_ref(), config.compat.as_ref());
+            }
+        };
+        self.all_models
+            .iter_mut()
+            .filter(|m| m.provider == name)
+            .for_each(overrides);
+        self.builtin
+            .values_mut()
+            .filter(|m| m.provider == name)
+            .for_each(overrides);
+
+        for model_config in config.models.iter().flatten() {
+            let headers = match (&config.headers, &model_config.headers) {
+                (Some(provider), Some(model)) => {
+                    Some(provider.clone().into_iter().chain(model.clone()).collect())
+                }
+                (provider, model) => model.clone().or_else(|| provider.clone()),
+            };
+            let filled = CustomModelConfig {
+                api: Some(model_config.api.clone().unwrap_or_else(|| api.clone())),
+                provider: Some(name.to_string()),
+                base_url: model_config.base_url.clone().or_else(|| base_url.clone()),
+                headers,
+                compat: merge_objects(config.compat.as_ref(), model_config.compat.as_ref()),
+                ..model_config.clone()
+            };
+            let Some(model) = custom_config_to_model(&filled) else {
+                continue;
+            };
+            match self
+                .all_models
+                .iter_mut()
+                .find(|m| m.provider == model.provider && m.id == model.id)
+            {
+                Some(existing) => *existing = model,
+                None => self.all_models.push(model),
+            }
+        }
+    }
+
     /// Add custom model configurations.
     pub fn add_custom_models(&mut self, configs: &[CustomModelConfig]) {
         for config in configs {
@@ -60,6 +236,16 @@ impl ModelRegistry {
         }
     }
 
+    /// Add discovered models. Built-in and custom models with the same
+    /// provider and ID take precedence.
+    pub fn add_discovered_models(&mut self, models: Vec<Model>) {
+        for model in models {
+            if self.find_by_provider(&model.provider, &model.id).is_none() {
+                self.all_models.push(model);
+            }
+        }
+    }
+
     /// Find a model by ID (exact match or glob pattern).
     pub fn find(&self, query: &str) -> Option<&Model> {
         // Exact match by ID
@@ -127,8 +313,13 @@ impl ModelRegistry {
     }
 
     fn load_builtin_models() -> Vec<Model> {
-        // Delegate to pi-agent-ai's model list
-        pi_agent_ai::models::get_all_models()
+        // Delegate to pi-agent-ai's model list, plus the scripted test model
+        // when a faux script is configured
+        let mut models = pi_agent_ai::models::get_all_models();
+        if pi_agent_ai::providers::faux::faux_enabled() {
+            models.push(pi_agent_ai::providers::faux::faux_model());
+        }
+        models
     }
 }
 
@@ -150,15 +341,87 @@ fn custom_config_to_model(config: &CustomModelConfig) -> Option<Model> {
         provider,
         base_url: config.base_url.clone().unwrap_or_default(),
         reasoning: config.reasoning.unwrap_or(false),
-        input: vec!["text".to_string()],
-        cost: pi_agent_core::types::ModelCost::default(),
+        input: config
+            .input
+            .clone()
+            .unwrap_or_else(|| vec!["text".to_string()]),
+        cost: config.cost.clone().unwrap_or_default(),
         context_window: config.context_window.unwrap_or(128_000),
         max_tokens: config.max_tokens.unwrap_or(4096),
         headers: config.headers.clone(),
-        compat: None,
+        compat: config.compat.clone(),
     })
 }
 
+/// Also send the request's API key in `header`.
+pub fn add_auth_header(options: &mut StreamOptions, header: &str) {
+    if let Some(api_key) = &options.api_key {
+        options
+            .headers
+            .get_or_insert_with(HashMap::new)
+            .insert(header.to_string(), api_key.clone());
+    }
+}
+
+/// Names of the APIs that have a stream implementation.
+fn known_apis() -> Vec<String> {
+    let registry = pi_agent_ai::register::create_default_registry();
+    let mut apis: Vec<String> = registry
+        .providers()
+        .iter()
+        .map(|provider| provider.api().to_string())
+        .collect();
+    apis.sort();
+    apis
+}
+
+/// `compat` must be an object of `OpenAICompletionsCompat` fields, on the
+/// OpenAI completions API.
+fn validate_compat(api: &str, compat: &Value) -> Result<(), String> {
+    if api != OPENAI_COMPLETIONS_API {
+        return Err(format!(
+            "compat is only supported for the {OPENAI_COMPLETIONS_API} api, not \"{api}\""
+        ));
+    }
+    let Some(fields) = compat.as_object() else {
+        return Err("compat must be an object".to_string());
+    };
+    let known = serde_json::to_value(OpenAICompletionsCompat::default()).unwrap_or_default();
+    let known: Vec<&String> = known
+        .as_object()
+        .into_iter()
+        .flat_map(|o| o.keys())
+        .collect();
+    let unknown: Vec<&str> = fields
+        .keys()
+        .filter(|key| !known.contains(key))
+        .map(String::as_str)
+        .collect();
+    if !unknown.is_empty() {
+        let known: Vec<&str> = known.iter().map(|key| key.as_str()).collect();
+        return Err(format!(
+            "unknown compat field(s) {} (known: {})",
+            unknown.join(", "),
+            known.join(", ")
+        ));
+    }
+    serde_json::from_value::<OpenAICompletionsCompat>(compat.clone())
+        .map(|_| ())
+        .map_err(|e| format!("invalid compat: {e}"))
+}
+
+/// `overlay`'s fields layered over `base`'s.
+fn merge_objects(base: Option<&Value>, overlay: Option<&Value>) -> Option<Value> {
+    match (base, overlay) {
+        (Some(Value::Object(base)), Some(Value::Object(overlay))) => {
+            let mut merged = base.clone();
+            merged.extend(overlay.clone());
+            Some(Value::Object(merged))
+        }
+        (base, overlay) => overlay.or(base).cloned(),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -196,6 +459,7 @@ mod tests {
             context_window: Some(32_000),
             max_tokens: Some(4096),
             headers: None,
+            ..Default::default()
         }]);
 
         assert_eq!(registry.all_models().len(), initial_count + 1);
@@ -205,6 +469,137 @@ mod tests {
         assert_eq!(found.unwrap().name, "My Custom Model");
     }
 
+    #[test]
+    fn test_add_discovered_models() {
+        let mut registry = ModelRegistry::new();
+        let builtin = registry.all_models()[0].clone();
+        let initial_count = registry.all_models().len();
+
+        let mut shadowed = builtin.clone();
+        shadowed.name = "Discovered".to_string();
+        let mut local = builtin.clone();
+        local.provider = "ollama".to_string();
+        local.id = "llama3.2:latest".to_string();
+        registry.add_discovered_models(vec![shadowed, local]);
+
+        assert_eq!(registry.all_models().len(), initial_count + 1);
+        assert_eq!(
+            registry
+                .find_by_provider(&builtin.provider, &builtin.id)
+                .unwrap()
+                .name,
+            builtin.name
+        );
+        assert!(registry.find("llama3.2:latest").is_some());
+    }
+
+    #[test]
+    fn test_load_custom_providers() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("models.json");
+        std::fs::write(
+            &path,
+            r#"{
+                "providers": {
+                    "my-gateway": {
+                        "api": "openai-completions",
+                        "baseUrl": "https://gateway.example.com/v1",
+                        "apiKeyEnv": "MY_GATEWAY_KEY",
+                        "authHeader": "api-key",
+                        "headers": { "x-team": "core" },
+                        "compat": { "supportsDeveloperRole": false },
+                        "models": [
+                            { "id": "fast", "contextWindow": 64000, "input": ["text", "image"] },
+                            { "id": "smart", "reasoning": true, "compat": { "maxTokensField": "max_tokens" } }
+                        ]
+                    },
+                    "anthropic": { "baseUrl": "https://gateway.example.com/anthropic" }
+                },
+                "models": [{ "id": "solo", "api": "openai-completions", "provider": "solo-co" }]
+            }"#,
+        )
+        .unwrap();
+        let mut registry = ModelRegistry::new();
+        registry.load_custom_models(&path).unwrap();
+
+        let fast = registry.find_by_provider("my-gateway", "fast").unwrap();
+        assert_eq!(fast.base_url, "https://gateway.example.com/v1");
+        assert_eq!(fast.context_window, 64000);
+        assert_eq!(fast.input, vec!["text", "image"]);
+        assert_eq!(fast.headers.as_ref().unwrap()["x-team"], "core");
+        assert_eq!(
+            fast.compat,
+            Some(serde_json::json!({ "supportsDeveloperRole": false }))
+        );
+        let smart = registry.find_by_provider("my-gateway", "smart").unwrap();
+        assert_eq!(
+            smart.compat.as_ref().unwrap()["maxTokensField"],
+            "max_tokens"
+        );
+        assert_eq!(
+            smart.compat.as_ref().unwrap()["supportsDeveloperRole"],
+            false
+        );
+
+        assert!(
+            registry
+                .models_for_provider("anthropic")
+                .iter()
+                .all(|m| m.base_url == "https://gateway.example.com/anthropic")
+        );
+        assert!(registry.find_by_provider("solo-co", "solo").is_some());
+        assert_eq!(
+            registry.provider_api_keys()["my-gateway"],
+            "env:MY_GATEWAY_KEY"
+        );
+        assert_eq!(registry.auth_header("my-gateway"), Some("api-key"));
+
+        let resolved =
+            crate::model::resolver::resolve_cli_model(Some("my-gateway"), Some("smart"), &registry);
+        assert_eq!(resolved.model.unwrap().provider, "my-gateway");
+    }
+
+    #[test]
+    fn test_invalid_custom_providers() {
+        let invalid = |json: serde_json::Value| {
+            let providers: HashMap<String, CustomProviderConfig> =
+                serde_json::from_value(json).unwrap();
+            let mut registry = ModelRegistry::new();
+            let count = registry.all_models().len();
+            let error = registry.add_providers(&providers).unwrap_err().to_string();
+            assert_eq!(registry.all_models().len(), count);
+            error
+        };
+        let base = "https://x.example.com/v1";
+
+        assert!(
+            invalid(serde_json::json!({ "x": { "baseUrl": base } }))
+                .contains("\"api\" is required")
+        );
+        assert!(
+            invalid(serde_json::json!({ "x": { "api": "telepathy", "baseUrl": base } }))
+                .contains("unknown api \"telepathy\"")
+        );
+        assert!(
+            invalid(serde_json::json!({
+                "x": { "api": "openai-completions", "baseUrl": base, "compat": { "supportsTelepathy": true } }
+            }))
+            .contains("unknown compat field(s) supportsTelepathy")
+        );
+        assert!(
+            invalid(serde_json::json!({
+                "x": { "api": "anthropic-messages", "baseUrl": base, "compat": {} }
+            }))
+            .contains("only supported for the openai-completions api")
+        );
+        assert!(
+            invalid(serde_json::json!({
+                "x": { "api": "openai-completions", "models": [{ "id": "m" }] }
+            }))
+            .contains("model \"m\" has no baseUrl")
+        );
+    }
+
     #[test]
     fn test_find_nonexistent_model() {
         let registry = ModelRegistry::new();
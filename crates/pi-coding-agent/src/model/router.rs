@@ -0,0 +1,427 @@
+//! Provider fallback chains and API key load balancing.
+//!
+//! [`ModelRouter`] sits between the agent loop and the [`ApiRegistry`]: each
+//! request goes to the requested model first and fails over to the next model
+//! in the fallback list when the error is classified as transient by
+//! [`retry::is_retryable_error`]. Failover only happens before any content
+//! has been streamed, so a partially delivered response is never replaced.
+//!
+//! The serving model is recorded on every `AssistantMessage` through its
+//! `provider`/`model` fields; providers run `transform_messages` against the
+//! model actually called, which strips thinking blocks from other models.
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use futures::StreamExt;
+use pi_agent_ai::registry::ApiRegistry;
+use pi_agent_core::agent_types::StreamFnBox;
+use pi_agent_core::event_stream::{
+    AssistantMessageEventStream, create_assistant_message_event_stream,
+};
+use pi_agent_core::types::*;
+use tokio_util::sync::CancellationToken;
+
+use crate::auth::key_ref::resolve_key_async;
+use crate::model::registry::add_auth_header;
+use crate::retry;
+
+/// Resolves an API key for a provider (auth storage, env, ...).
+pub type ApiKeyResolver = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// A set of API keys (or key references) for one provider, handed out
+/// round-robin.
+struct KeyPool {
+    keys: Vec<String>,
+    next: AtomicUsize,
+}
+
+impl KeyPool {
+    async fn next_key(&self) -> Option<String> {
+        if self.keys.is_empty() {
+            return None;
+        }
+        let index = self.next.fetch_add(1, Ordering::Relaxed) % self.keys.len();
+        resolve_key_async(&self.keys[index]).await
+    }
+}
+
+/// Routes stream requests across an ordered fallback chain of models.
+pub struct ModelRouter {
+    registry: Arc<ApiRegistry>,
+    fallbacks: Vec<Model>,
+    key_pools: HashMap<String, KeyPool>,
+    get_api_key: Option<ApiKeyResolver>,
+    auth_headers: HashMap<String, String>,
+}
+
+impl ModelRouter {
+    pub fn new(registry: Arc<ApiRegistry>) -> Self {
+        Self {
+            registry,
+            fallbacks: Vec::new(),
+            key_pools: HashMap::new(),
+            get_api_key: None,
+            auth_headers: HashMap::new(),
+        }
+    }
+
+    /// Models tried, in order, after the requested model fails.
+    pub fn with_fallbacks(mut self, fallbacks: Vec<Model>) -> Self {
+        self.fallbacks = fallbacks;
+        self
+    }
+
+    /// Rotate requests to `provider` across `keys`.
+    pub fn with_api_keys(mut self, provider: &str, keys: Vec<String>) -> Self {
+        if !keys.is_empty() {
+            self.key_pools.insert(
+                provider.to_string(),
+                KeyPool {
+                    keys,
+                    next: AtomicUsize::new(0),
+                },
+            );
+        }
+        self
+    }
+
+    /// Key lookup for fallback models on a different provider than the
+    /// requested one.
+    pub fn with_api_key_resolver(mut self, resolver: ApiKeyResolver) -> Self {
+        self.get_api_key = Some(resolver);
+        self
+    }
+
+    /// Also send `provider`'s API key in `header`.
+    pub fn with_auth_header(mut self, provider: &str, header: &str) -> Self {
+        self.auth_headers
+            .insert(provider.to_string(), header.to_string());
+        self
+    }
+
+    /// The requested model followed by its fallbacks, without duplicates.
+    fn candidates(&self, model: &Model) -> Vec<Model> {
+        let mut candidates = vec![model.clone()];
+        for fallback in &self.fallbacks {
+            if !candidates
+                .iter()
+                .any(|m| m.provider == fallback.provider && m.id == fallback.id)
+            {
+                candidates.push(fallback.clone());
+            }
+        }
+        candidates
+    }
+
+    /// Options for one attempt: picks the next pooled key for the provider,
+    /// and resolves a fresh key when failing over to another provider.
+    async fn options_for(
+        &self,
+        requested: &Model,
+        candidate: &Model,
+        options: &SimpleStreamOptions,
+    ) -> SimpleStreamOptions {
+        let mut options = options.clone();
+        let pooled_key = match self.key_pools.get(&candidate.provider) {
+            Some(pool) => pool.next_key().await,
+            None => None,
+        };
+        if let Some(key) = pooled_key {
+            options.base.api_key = Some(key);
+        } else if candidate.provider != requested.provider {
+            options.base.api_key = self
+                .get_api_key
+                .as_ref()
+                .and_then(|resolve| resolve(&candidate.provider));
+        }
+        if let Some(header) = self.auth_headers.get(&candidate.provider) {
+            add_auth_header(&mut options.base, header);
+        }
+        options
+    }
+
+    /// Stream a response, failing over along the fallback chain.
+    pub fn stream(
+        self: &Arc<Self>,
+        model: &Model,
+        context: &Context,
+        options: &SimpleStreamOptions,
+        cancel: CancellationToken,
+    ) -> AssistantMessageEventStream {
+        let out = create_assistant_message_event_stream();
+        let router = self.clone();
+        let requested = model.clone();
+        let context = context.clone();
+        let options = options.clone();
+        let out_clone = out.clone();
+
+        tokio::spawn(async move {
+            let candidates = router.candidates(&requested);
+            let last = candidates.len() - 1;
+
+            for (index, candidate) in candidates.iter().enumerate() {
+                let attempt_options = router.options_for(&requested, candidate, &options).await;
+                let Some(provider) = router.registry.get(&candidate.api) else {
+                    if index == last {
+                        push_error(
+                            &out_clone,
+                            candidate,
+                            format!("No API provider registered for api: {}", candidate.api),
+                        );
+                        return;
+                    }
+                    continue;
+                };
+
+                let mut inner =
+                    provider.stream_simple(candidate, &context, &attempt_options, cancel.clone());
+
+                // Hold back `Start` until content arrives, so a failed attempt
+                // leaves no trace in the consumer's message list.
+                let mut held_start: Option<AssistantMessageEvent> = None;
+                let mut committed = false;
+
+                while let Some(event) = inner.next().await {
+                    match event {
+                        AssistantMessageEvent::Start { .. } if !committed => {
+                            held_start = Some(event);
+                        }
+                        AssistantMessageEvent::Error { reason, error }
+                            if !committed
+                                && index < last
+                                && reason == StopReason::Error
+                                && !cancel.is_cancelled()
+                                && error.error_message.as_deref().is_some_and(|msg| {
+                                    retry::is_retryable_error(msg, candidate.context_window)
+                                }) =>
+                        {
+                            let next = &candidates[index + 1];
+                            tracing::warn!(
+                                "{}/{} failed ({}); falling back to {}/{}",
+                                candidate.provider,
+                                candidate.id,
+                                error.error_message.as_deref().unwrap_or_default(),
+                                next.provider,
+                                next.id
+                            );
+                            break;
+                        }
+                        event => {
+                            if let Some(start) = held_start.take() {
+                                out_clone.push(start);
+                            }
+                            committed = true;
+                            let finished = matches!(
+                                event,
+                                AssistantMessageEvent::Done { .. }
+                                    | AssistantMessageEvent::Error { .. }
+                            );
+                            out_clone.push(event);
+                            if finished {
+                                if index > 0 {
+                                    tracing::info!(
+                                        "Response served by fallback model {}/{}",
+                                        candidate.provider,
+                                        candidate.id
+                                    );
+                                }
+                                return;
+                            }
+                        }
+                    }
+                }
+
+                if committed || index == last {
+                    // Inner stream ended without a terminal event.
+                    out_clone.end(None);
+                    return;
+                }
+            }
+        });
+
+        out
+    }
+
+    /// Wrap the router as a stream function for `AgentSession`.
+    pub fn into_stream_fn(self: Arc<Self>) -> StreamFnBox {
+        Arc::new(move |model, context, options| {
+            self.stream(model, context, options, CancellationToken::new())
+        })
+    }
+}
+
+fn push_error(stream: &AssistantMessageEventStream, model: &Model, message: String) {
+    let mut error = AssistantMessage::empty(model);
+    error.stop_reason = StopReason::Error;
+    error.error_message = Some(message);
+    stream.push(AssistantMessageEvent::Error {
+        reason: StopReason::Error,
+        error,
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pi_agent_ai::registry::ApiProvider;
+
+    /// Fails with `error` for model ids listed in `failing`, answers otherwise.
+    struct ScriptedProvider {
+        failing: Vec<String>,
+        error: String,
+    }
+
+    impl ApiProvider for ScriptedProvider {
+        fn api(&self) -> &str {
+            "scripted"
+        }
+
+        fn stream(
+            &self,
+            model: &Model,
+            context: &Context,
+            options: &StreamOptions,
+            cancel: CancellationToken,
+        ) -> AssistantMessageEventStream {
+            let simple = SimpleStreamOptions {
+                base: options.clone(),
+                reasoning: None,
+                thinking_budgets: None,
+            };
+            self.stream_simple(model, context, &simple, cancel)
+        }
+
+        fn stream_simple(
+            &self,
+            model: &Model,
+            _context: &Context,
+            options: &SimpleStreamOptions,
+            _cancel: CancellationToken,
+        ) -> AssistantMessageEventStream {
+            let stream = create_assistant_message_event_stream();
+            let mut message = AssistantMessage::empty(model);
+            stream.push(AssistantMessageEvent::Start {
+                partial: message.clone(),
+            });
+            if self.failing.contains(&model.id) {
+                message.stop_reason = StopReason::Error;
+                message.error_message = Some(self.error.clone());
+                stream.push(AssistantMessageEvent::Error {
+                    reason: StopReason::Error,
+                    error: message,
+                });
+            } else {
+                message.content.push(ContentBlock::Text(TextContent {
+                    text: options.base.api_key.clone().unwrap_or_default(),
+                    text_signature: None,
+                    citations: None,
+                }));
+                stream.push(AssistantMessageEvent::Done {
+                    reason: StopReason::Stop,
+                    message,
+                });
+            }
+            stream
+        }
+    }
+
+    fn model(provider: &str, id: &str) -> Model {
+        Model {
+            id: id.to_string(),
+            name: id.to_string(),
+            api: "scripted".to_string(),
+            provider: provider.to_string(),
+            base_url: String::new(),
+            reasoning: false,
+            input: vec!["text".to_string()],
+            cost: ModelCost::default(),
+            context_window: 128000,
+            max_tokens: 4096,
+            headers: None,
+            compat: None,
+        }
+    }
+
+    fn router(failing: &[&str], error: &str) -> ModelRouter {
+        let mut registry = ApiRegistry::new();
+        registry.register(Arc::new(ScriptedProvider {
+            failing: failing.iter().map(|s| s.to_string()).collect(),
+            error: error.to_string(),
+        }));
+        ModelRouter::new(Arc::new(registry))
+    }
+
+    fn context() -> Context {
+        Context {
+            system_prompt: None,
+            messages: vec![],
+            tools: None,
+        }
+    }
+
+    async fn run(router: Arc<ModelRouter>, model: &Model) -> (usize, AssistantMessage) {
+        let stream = router.stream(
+            model,
+            &context(),
+            &SimpleStreamOptions::default(),
+            CancellationToken::new(),
+        );
+        let events: Vec<_> = stream.clone().collect().await;
+        let starts = events
+            .iter()
+            .filter(|e| matches!(e, AssistantMessageEvent::Start { .. }))
+            .count();
+        (starts, stream.result().await.unwrap())
+    }
+
+    #[tokio::test]
+    async fn test_fails_over_on_retryable_error() {
+        let router = router(&["primary"], "HTTP 529 overloaded")
+            .with_fallbacks(vec![model("backup-co", "backup")])
+            .with_api_key_resolver(Arc::new(|provider| Some(format!("{provider}-key"))));
+        let (starts, message) = run(Arc::new(router), &model("main-co", "primary")).await;
+        assert_eq!(starts, 1);
+        assert_eq!(message.stop_reason, StopReason::Stop);
+        assert_eq!(message.provider, "backup-co");
+        assert_eq!(message.model, "backup");
+        assert_eq!(message.content[0].as_text().unwrap().text, "backup-co-key");
+    }
+
+    #[tokio::test]
+    async fn test_does_not_fail_over_on_context_overflow() {
+        let router = router(&["primary"], "prompt is too long")
+            .with_fallbacks(vec![model("backup-co", "backup")]);
+        let (_, message) = run(Arc::new(router), &model("main-co", "primary")).await;
+        assert_eq!(message.stop_reason, StopReason::Error);
+        assert_eq!(message.model, "primary");
+    }
+
+    #[tokio::test]
+    async fn test_round_robin_api_keys() {
+        let router = Arc::new(
+            router(&[], "").with_api_keys("main-co", vec!["k1".to_string(), "k2".to_string()]),
+        );
+        let primary = model("main-co", "primary");
+        let mut served = Vec::new();
+        for _ in 0..3 {
+            let (_, message) = run(router.clone(), &primary).await;
+            served.push(message.content[0].as_text().unwrap().text.clone());
+        }
+        assert_eq!(served, vec!["k1", "k2", "k1"]);
+    }
+
+    #[tokio::test]
+    async fn test_auth_header_carries_key() {
+        let router = router(&[], "")
+            .with_api_keys("gateway", vec!["k1".to_string()])
+            .with_auth_header("gateway", "api-key");
+        let model = model("gateway", "m");
+        let options = router
+            .options_for(&model, &model, &SimpleStreamOptions::default())
+            .await;
+        assert_eq!(options.base.api_key.as_deref(), Some("k1"));
+        assert_eq!(options.base.headers.unwrap()["api-key"], "k1");
+    }
+}
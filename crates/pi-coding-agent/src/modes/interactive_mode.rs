@@ -1,8 +1,13 @@
 use std::io::{self, Write};
 
 use crate::agent_session::session::{AgentSession, PromptOptions};
+use crate::auth::login::{self, ConsoleLoginUi, LoginMethod};
 use crate::compaction::compaction::CompactionSettings;
 use crate::error::CodingAgentError;
+use crate::export::{ExportFormat, ExportOptions, export_session};
+use crate::session::project::project_root;
+use crate::session::search::SearchQuery;
+use crate::session::store::SessionFilter;
 use crate::slash_commands::builtin_slash_commands;
 use pi_agent_core::agent_types::AgentMessage;
 use pi_agent_core::types::{ContentBlock, Message};
@@ -14,6 +19,34 @@ pub struct ScopedModelConfig {
     pub thinking_level: Option<String>,
 }
 
+/// `/export [file]`: write the current session, in the format implied by
+/// the file extension (HTML by default).
+fn export_current(session: &AgentSession, target: Option<&str>) {
+    let Some(session_id) = session.session_id() else {
+        println!("当前没有可导出的会话。");
+        return;
+    };
+    let format = target
+        .and_then(|path| ExportFormat::from_path(std::path::Path::new(path)))
+        .unwrap_or_default();
+    let path = target
+        .map(str::to_string)
+        .unwrap_or_else(|| format!("pi-agent-session-{session_id}.{}", format.extension()));
+    let options = ExportOptions {
+        format,
+        ..ExportOptions::default()
+    };
+    let result = session
+        .session_manager()
+        .open(session_id)
+        .and_then(|(header, entries)| export_session(header, &entries, &options))
+        .and_then(|content| Ok(std::fs::write(&path, content)?));
+    match result {
+        Ok(()) => println!("已导出: {path}"),
+        Err(e) => println!("导出失败: {e}"),
+    }
+}
+
 fn find_scoped_model<'a>(
     scoped_models: &'a [ScopedModelConfig],
     query: &str,
@@ -55,6 +88,121 @@ fn print_last_assistant(messages: &[AgentMessage]) {
     }
 }
 
+/// Number of sessions offered by `/resume`.
+const RESUME_PICKER_LIMIT: usize = 10;
+
+fn format_local_time(ms: i64) -> String {
+    chrono::DateTime::from_timestamp_millis(ms)
+        .map(|t| {
+            t.with_timezone(&chrono::Local)
+                .format("%Y-%m-%d %H:%M")
+                .to_string()
+        })
+        .unwrap_or_default()
+}
+
+/// List recent sessions, or sessions matching `query`, and restore the one
+/// the user picks by number. Only sessions of the current project are
+/// offered unless `all_projects` is set.
+fn resume_picker(
+    session: &mut AgentSession,
+    query: &str,
+    all_projects: bool,
+) -> Result<(), CodingAgentError> {
+    let project =
+        (!all_projects).then(|| project_root(session.working_dir()).display().to_string());
+    let manager = session.session_manager();
+    let candidates: Vec<(String, String, String)> = if query.is_empty() {
+        let filter = SessionFilter {
+            project: project.clone(),
+            limit: Some(RESUME_PICKER_LIMIT),
+            ..SessionFilter::default()
+        };
+        match manager.list_filtered(&filter) {
+            Ok(sessions) => sessions
+                .into_iter()
+                .map(|info| {
+                    let detail = info
+                        .summary
+                        .clone()
+                        .unwrap_or_else(|| format!("{} 条记录", info.entry_count));
+                    let label = format!(
+                        "{}  {}",
+                        format_local_time(info.updated_at),
+                        info.title.as_deref().unwrap_or(&info.session_id)
+                    );
+                    (info.session_id, label, detail)
+                })
+                .collect(),
+            Err(e) => {
+                println!("读取会话列表失败: {e}");
+                return Ok(());
+            }
+        }
+    } else {
+        let search = SearchQuery {
+            text: query.to_string(),
+            project,
+            limit: Some(RESUME_PICKER_LIMIT),
+            ..SearchQuery::default()
+        };
+        match manager.search(&search) {
+            Ok(hits) => hits
+                .into_iter()
+                .map(|hit| {
+                    let label = format!(
+                        "{}  {}",
+                        format_local_time(hit.updated_at),
+                        hit.title.as_deref().unwrap_or(&hit.session_id)
+                    );
+                    (hit.session_id, label, hit.snippet)
+                })
+                .collect(),
+            Err(e) => {
+                println!("搜索会话失败: {e}");
+                return Ok(());
+            }
+        }
+    };
+
+    if candidates.is_empty() {
+        println!("没有找到会话。");
+        return Ok(());
+    }
+    for (i, (_, label, detail)) in candidates.iter().enumerate() {
+        println!("  {}. {label}", i + 1);
+        if !detail.is_empty() {
+            println!("     {detail}");
+        }
+    }
+    print!("输入编号恢复会话（回车取消）: ");
+    io::stdout()
+        .flush()
+        .map_err(|e| CodingAgentError::Other(e.to_string()))?;
+    let mut choice = String::new();
+    io::stdin()
+        .read_line(&mut choice)
+        .map_err(|e| CodingAgentError::Other(e.to_string()))?;
+    let choice = choice.trim();
+    if choice.is_empty() {
+        return Ok(());
+    }
+    let Some((session_id, _, _)) = choice
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_sub(1))
+        .and_then(|i| candidates.get(i))
+    else {
+        println!("无效编号: {choice}");
+        return Ok(());
+    };
+    match session.restore_session(session_id) {
+        Ok(()) => println!("已恢复会话: {session_id}"),
+        Err(e) => println!("恢复会话失败: {e}"),
+    }
+    Ok(())
+}
+
 #[derive(Debug, Clone)]
 pub struct InteractiveModeOptions {
     pub prompt: String,
@@ -115,7 +263,7 @@ impl InteractiveMode {
                     }
                     "/session" => {
                         let stats = session.get_stats();
-                        let context_usage = session.get_context_usage();
+                        let context_usage = session.get_context_usage().await;
                         let context_str = match context_usage {
                             Some(usage) => match usage.tokens {
                                 Some(tokens) => format!(
@@ -136,6 +284,23 @@ impl InteractiveMode {
                             stats.estimated_tokens,
                             context_str
                         );
+                        let usage = &stats.usage.total;
+                        println!(
+                            "usage: input={}, output={}, cache_read={}, cache_write={}, cost=${:.4}",
+                            usage.input,
+                            usage.output,
+                            usage.cache_read,
+                            usage.cache_write,
+                            usage.cost
+                        );
+                        for (model, totals) in &stats.usage.by_model {
+                            println!(
+                                "  {model}: 请求 {} 次, {} tokens, ${:.4}",
+                                totals.requests,
+                                totals.total_tokens(),
+                                totals.cost
+                            );
+                        }
                         continue;
                     }
                     "/model" => {
@@ -244,6 +409,50 @@ impl InteractiveMode {
                         }
                         continue;
                     }
+                    "/login" => {
+                        let Some(provider) = parts.next() else {
+                            println!("用法: /login <provider>");
+                            println!("可用提供商: {}", login::oauth_provider_keys().join(", "));
+                            continue;
+                        };
+                        let method = if login::browser_available() {
+                            LoginMethod::Browser
+                        } else {
+                            LoginMethod::Manual
+                        };
+                        let ui = ConsoleLoginUi {
+                            open_browser: method == LoginMethod::Browser,
+                        };
+                        match login::login_and_save(session.auth_storage(), provider, method, &ui)
+                            .await
+                        {
+                            Ok(name) => println!("已登录: {name}"),
+                            Err(e) => println!("登录失败: {e}"),
+                        }
+                        continue;
+                    }
+                    "/logout" => {
+                        let Some(provider) = parts.next() else {
+                            println!("用法: /logout <provider>");
+                            continue;
+                        };
+                        session.auth_storage().remove_runtime_credential(provider);
+                        match session.auth_storage().remove_credential(provider) {
+                            Ok(()) => println!("已登出: {provider}"),
+                            Err(e) => println!("登出失败: {e}"),
+                        }
+                        continue;
+                    }
+                    "/resume" => {
+                        let (all, terms): (Vec<&str>, Vec<&str>) =
+                            parts.partition(|part| *part == "--all");
+                        resume_picker(session, &terms.join(" "), !all.is_empty())?;
+                        continue;
+                    }
+                    "/export" => {
+                        export_current(session, parts.next());
+                        continue;
+                    }
                     "/new" => {
                         session.reset_session();
                         println!("已创建新会话上下文。");
@@ -277,8 +486,12 @@ impl InteractiveMode {
 
             session.prompt(input, PromptOptions::default()).await?;
             print_last_assistant(session.messages());
+            if let Some(reason) = session.last_limit_reason() {
+                println!("已停止: {reason}");
+            }
         }
 
+        session.wait_for_title().await;
         Ok(())
     }
 }
@@ -55,6 +55,10 @@ fn assistant_text(messages: &[AgentMessage]) -> Option<String> {
     Some(text)
 }
 
+/// Run the prompts non-interactively.
+///
+/// Returns an error when a configured limit stopped the agent, so unattended
+/// runs exit non-zero.
 pub async fn run_print_mode(
     session: &mut AgentSession,
     options: PrintModeOptions,
@@ -72,6 +76,9 @@ pub async fn run_print_mode(
             session.prompt(initial, PromptOptions::default()).await?;
         }
         for message in &options.messages {
+            if session.last_limit_reason().is_some() {
+                break;
+            }
             session.prompt(message, PromptOptions::default()).await?;
         }
 
@@ -80,13 +87,17 @@ pub async fn run_print_mode(
                 println!("{}", serde_json::to_string(event).unwrap_or_default());
             }
         }
-        return Ok(());
+        session.wait_for_title().await;
+        return limit_result(session);
     }
 
     if let Some(initial) = &options.initial_message {
         session.prompt(initial, PromptOptions::default()).await?;
     }
     for message in &options.messages {
+        if session.last_limit_reason().is_some() {
+            break;
+        }
         session.prompt(message, PromptOptions::default()).await?;
     }
 
@@ -94,5 +105,15 @@ pub async fn run_print_mode(
         println!("{text}");
     }
 
-    Ok(())
+    session.wait_for_title().await;
+    limit_result(session)
+}
+
+fn limit_result(session: &AgentSession) -> Result<(), crate::error::CodingAgentError> {
+    match session.last_limit_reason() {
+        Some(reason) => Err(crate::error::CodingAgentError::Agent(format!(
+            "Agent stopped: {reason}"
+        ))),
+        None => Ok(()),
+    }
 }
@@ -5,18 +5,50 @@ use serde::{Deserialize, Serialize};
 use crate::agent_session::session::{AgentSession, PromptOptions};
 use crate::compaction::compaction::CompactionSettings;
 use crate::error::CodingAgentError;
+use crate::session::search::SearchQuery;
+use crate::settings::types::LimitSettings;
 
 #[derive(Debug, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
 pub enum RpcCommand {
-    Prompt { id: Option<String>, message: String },
-    Abort { id: Option<String> },
-    Stats { id: Option<String> },
-    SetModel { id: Option<String>, model: String },
-    Models { id: Option<String> },
-    NewSession { id: Option<String> },
-    Compact { id: Option<String> },
-    Shutdown { id: Option<String> },
+    Prompt {
+        id: Option<String>,
+        message: String,
+    },
+    Abort {
+        id: Option<String>,
+    },
+    Stats {
+        id: Option<String>,
+    },
+    SetModel {
+        id: Option<String>,
+        model: String,
+    },
+    Models {
+        id: Option<String>,
+    },
+    NewSession {
+        id: Option<String>,
+    },
+    Compact {
+        id: Option<String>,
+    },
+    GetLimits {
+        id: Option<String>,
+    },
+    SetLimits {
+        id: Option<String>,
+        limits: LimitSettings,
+    },
+    SearchSessions {
+        id: Option<String>,
+        #[serde(flatten)]
+        query: SearchQuery,
+    },
+    Shutdown {
+        id: Option<String>,
+    },
 }
 
 #[derive(Debug, Serialize)]
@@ -91,7 +123,15 @@ pub async fn run_rpc_mode(session: &mut AgentSession) -> Result<(), CodingAgentE
         let response = match cmd {
             RpcCommand::Prompt { id, message } => {
                 match session.prompt(&message, PromptOptions::default()).await {
-                    Ok(_) => ok(id, None),
+                    Ok(_) => ok(
+                        id,
+                        session.last_limit_reason().map(|reason| {
+                            serde_json::json!({
+                                "limitReached": reason,
+                                "message": reason.to_string(),
+                            })
+                        }),
+                    ),
                     Err(e) => err(id, e.to_string()),
                 }
             }
@@ -101,7 +141,7 @@ pub async fn run_rpc_mode(session: &mut AgentSession) -> Result<(), CodingAgentE
             }
             RpcCommand::Stats { id } => {
                 let stats = session.get_stats();
-                let context_usage = session.get_context_usage();
+                let context_usage = session.get_context_usage().await;
                 ok(
                     id,
                     Some(serde_json::json!({
@@ -109,6 +149,10 @@ pub async fn run_rpc_mode(session: &mut AgentSession) -> Result<(), CodingAgentE
                         "messageCount": stats.message_count,
                         "turnCount": stats.turn_count,
                         "estimatedTokens": stats.estimated_tokens,
+                        "usage": stats.usage,
+                        "limits": session.limits(),
+                        "lastLimitReason": session.last_limit_reason(),
+                        "cost": stats.usage.total.cost,
                         "contextUsage": context_usage.as_ref().map(|usage| serde_json::json!({
                             "tokens": usage.tokens,
                             "contextWindow": usage.context_window,
@@ -153,6 +197,17 @@ pub async fn run_rpc_mode(session: &mut AgentSession) -> Result<(), CodingAgentE
                 session.reset_session();
                 ok(id, None)
             }
+            RpcCommand::GetLimits { id } => ok(id, serde_json::to_value(session.limits()).ok()),
+            RpcCommand::SetLimits { id, limits } => {
+                session.set_limits(limits);
+                ok(id, serde_json::to_value(session.limits()).ok())
+            }
+            RpcCommand::SearchSessions { id, query } => {
+                match session.session_manager().search(&query) {
+                    Ok(hits) => ok(id, Some(serde_json::json!({ "results": hits }))),
+                    Err(e) => err(id, e.to_string()),
+                }
+            }
             RpcCommand::Compact { id } => {
                 match session.compact(Some(&CompactionSettings::default())).await {
                     Ok(result) => ok(
@@ -177,5 +232,6 @@ pub async fn run_rpc_mode(session: &mut AgentSession) -> Result<(), CodingAgentE
         write_json(&serde_json::to_value(response).unwrap_or_default());
     }
 
+    session.wait_for_title().await;
     Ok(())
 }
@@ -0,0 +1,454 @@
+//! `/v1/chat/completions` request and response mapping.
+
+use std::collections::HashMap;
+
+use pi_agent_core::types::*;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::{finish_reason, parse_data_url, parse_reasoning_effort, parse_tool_choice};
+
+#[derive(Debug, Deserialize)]
+pub struct ChatCompletionRequest {
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    #[serde(default)]
+    pub tools: Option<Vec<ChatTool>>,
+    #[serde(default)]
+    pub tool_choice: Option<Value>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub stream_options: Option<ChatStreamOptions>,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub max_tokens: Option<u64>,
+    #[serde(default)]
+    pub max_completion_tokens: Option<u64>,
+    #[serde(default)]
+    pub stop: Option<Value>,
+    #[serde(default)]
+    pub seed: Option<u64>,
+    #[serde(default)]
+    pub frequency_penalty: Option<f64>,
+    #[serde(default)]
+    pub presence_penalty: Option<f64>,
+    #[serde(default)]
+    pub reasoning_effort: Option<String>,
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct ChatStreamOptions {
+    #[serde(default)]
+    pub include_usage: bool,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatMessage {
+    pub role: String,
+    #[serde(default)]
+    pub content: Option<Value>,
+    #[serde(default)]
+    pub tool_calls: Option<Vec<ChatToolCall>>,
+    #[serde(default)]
+    pub tool_call_id: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatToolCall {
+    pub id: String,
+    pub function: ChatFunctionCall,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatFunctionCall {
+    pub name: String,
+    #[serde(default)]
+    pub arguments: String,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatTool {
+    pub function: ChatFunction,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ChatFunction {
+    pub name: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parameters: Option<Value>,
+}
+
+/// Text of a `content` field: a string or an array of text parts.
+fn content_text(content: Option<&Value>) -> String {
+    match content {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|p| p.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        _ => String::new(),
+    }
+}
+
+fn user_content(content: Option<&Value>) -> UserContent {
+    let Some(Value::Array(parts)) = content else {
+        return UserContent::Text(content_text(content));
+    };
+    let blocks = parts
+        .iter()
+        .filter_map(|part| match part.get("type").and_then(Value::as_str) {
+            Some("text") => Some(ContentBlock::Text(TextContent {
+                text: part.get("text")?.as_str()?.to_string(),
+                text_signature: None,
+                citations: None,
+            })),
+            Some("image_url") => {
+                let url = part.pointer("/image_url/url")?.as_str()?;
+                parse_data_url(url).map(ContentBlock::Image)
+            }
+            _ => None,
+        })
+        .collect();
+    UserContent::Blocks(blocks)
+}
+
+impl ChatCompletionRequest {
+    /// Convert to a neutral context for `model`.
+    pub fn to_context(&self, model: &Model) -> Context {
+        let mut system = Vec::new();
+        let mut messages = Vec::new();
+        let mut tool_names: HashMap<String, String> = HashMap::new();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        for message in &self.messages {
+            match message.role.as_str() {
+                "system" | "developer" => system.push(content_text(message.content.as_ref())),
+                "user" => messages.push(Message::User(UserMessage {
+                    content: user_content(message.content.as_ref()),
+                    timestamp: now,
+                })),
+                "assistant" => {
+                    let mut assistant = AssistantMessage::empty(model);
+                    assistant.timestamp = now;
+                    let text = content_text(message.content.as_ref());
+                    if !text.is_empty() {
+                        assistant.content.push(ContentBlock::Text(TextContent {
+                            text,
+                            text_signature: None,
+                            citations: None,
+                        }));
+                    }
+                    for call in message.tool_calls.iter().flatten() {
+                        tool_names.insert(call.id.clone(), call.function.name.clone());
+                        assistant.content.push(ContentBlock::ToolCall(ToolCall {
+                            id: call.id.clone(),
+                            name: call.function.name.clone(),
+                            arguments: serde_json::from_str(&call.function.arguments)
+                                .unwrap_or_else(|_| json!({})),
+                            thought_signature: None,
+                        }));
+                    }
+                    if !assistant.content.is_empty() {
+                        assistant.stop_reason = if message.tool_calls.is_some() {
+                            StopReason::ToolUse
+                        } else {
+                            StopReason::Stop
+                        };
+                        messages.push(Message::Assistant(assistant));
+                    }
+                }
+                "tool" => {
+                    let id = message.tool_call_id.clone().unwrap_or_default();
+                    messages.push(Message::ToolResult(ToolResultMessage {
+                        tool_name: tool_names.get(&id).cloned().unwrap_or_default(),
+                        tool_call_id: id,
+                        content: vec![ContentBlock::Text(TextContent {
+                            text: content_text(message.content.as_ref()),
+                            text_signature: None,
+                            citations: None,
+                        })],
+                        details: None,
+                        is_error: false,
+                        timestamp: now,
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        let tools = self.tools.as_ref().map(|tools| {
+            tools
+                .iter()
+                .map(|tool| Tool {
+                    name: tool.function.name.clone(),
+                    description: tool.function.description.clone().unwrap_or_default(),
+                    parameters: tool
+                        .function
+                        .parameters
+                        .clone()
+                        .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                })
+                .collect()
+        });
+
+        Context {
+            system_prompt: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages,
+            tools,
+        }
+    }
+
+    pub fn to_options(&self) -> SimpleStreamOptions {
+        let stop_sequences = match &self.stop {
+            Some(Value::String(stop)) => Some(vec![stop.clone()]),
+            Some(Value::Array(stops)) => Some(
+                stops
+                    .iter()
+                    .filter_map(|s| s.as_str().map(str::to_string))
+                    .collect(),
+            ),
+            _ => None,
+        };
+        SimpleStreamOptions {
+            base: StreamOptions {
+                temperature: self.temperature,
+                max_tokens: self.max_completion_tokens.or(self.max_tokens),
+                tool_choice: self.tool_choice.as_ref().and_then(parse_tool_choice),
+                top_p: self.top_p,
+                stop_sequences,
+                seed: self.seed,
+                frequency_penalty: self.frequency_penalty,
+                presence_penalty: self.presence_penalty,
+                ..Default::default()
+            },
+            reasoning: self
+                .reasoning_effort
+                .as_deref()
+                .and_then(parse_reasoning_effort),
+            thinking_budgets: None,
+        }
+    }
+}
+
+fn usage_json(usage: &Usage) -> Value {
+    json!({
+        "prompt_tokens": usage.input + usage.cache_read + usage.cache_write,
+        "completion_tokens": usage.output,
+        "total_tokens": usage.input + usage.cache_read + usage.cache_write + usage.output,
+        "prompt_tokens_details": { "cached_tokens": usage.cache_read },
+    })
+}
+
+fn tool_call_json(call: &ToolCall) -> Value {
+    json!({
+        "id": call.id,
+        "type": "function",
+        "function": { "name": call.name, "arguments": call.arguments.to_string() },
+    })
+}
+
+/// Non-streaming `chat.completion` body.
+pub fn completion_response(
+    id: &str,
+    model: &str,
+    created: i64,
+    message: &AssistantMessage,
+) -> Value {
+    let text: String = message
+        .content
+        .iter()
+        .filter_map(|b| b.as_text().map(|t| t.text.as_str()))
+        .collect();
+    let tool_calls: Vec<Value> = message
+        .content
+        .iter()
+        .filter_map(|b| match b {
+            ContentBlock::ToolCall(call) => Some(tool_call_json(call)),
+            _ => None,
+        })
+        .collect();
+    let mut reply = json!({
+        "role": "assistant",
+        "content": if text.is_empty() && !tool_calls.is_empty() { Value::Null } else { json!(text) },
+    });
+    if !tool_calls.is_empty() {
+        reply["tool_calls"] = json!(tool_calls);
+    }
+    json!({
+        "id": id,
+        "object": "chat.completion",
+        "created": created,
+        "model": model,
+        "choices": [{
+            "index": 0,
+            "message": reply,
+            "finish_reason": finish_reason(&message.stop_reason),
+        }],
+        "usage": usage_json(&message.usage),
+    })
+}
+
+/// Translates agent events into `chat.completion.chunk` payloads.
+pub struct ChunkEncoder {
+    id: String,
+    model: String,
+    created: i64,
+    include_usage: bool,
+    /// content_index -> position in the `tool_calls` array.
+    tool_indices: HashMap<usize, usize>,
+}
+
+impl ChunkEncoder {
+    pub fn new(id: &str, model: &str, created: i64, include_usage: bool) -> Self {
+        Self {
+            id: id.to_string(),
+            model: model.to_string(),
+            created,
+            include_usage,
+            tool_indices: HashMap::new(),
+        }
+    }
+
+    fn chunk(&self, delta: Value, finish_reason: Value) -> Value {
+        json!({
+            "id": self.id,
+            "object": "chat.completion.chunk",
+            "created": self.created,
+            "model": self.model,
+            "choices": [{ "index": 0, "delta": delta, "finish_reason": finish_reason }],
+        })
+    }
+
+    /// Chunks for one event; empty for events with no wire equivalent.
+    pub fn encode(&mut self, event: &AssistantMessageEvent) -> Vec<Value> {
+        match event {
+            AssistantMessageEvent::Start { .. } => {
+                vec![self.chunk(json!({"role": "assistant", "content": ""}), Value::Null)]
+            }
+            AssistantMessageEvent::TextDelta { delta, .. } => {
+                vec![self.chunk(json!({"content": delta}), Value::Null)]
+            }
+            AssistantMessageEvent::ToolCallStart {
+                content_index,
+                partial,
+            } => {
+                let index = self.tool_indices.len();
+                self.tool_indices.insert(*content_index, index);
+                let (id, name) = match partial.content.get(*content_index) {
+                    Some(ContentBlock::ToolCall(call)) => (call.id.clone(), call.name.clone()),
+                    _ => (String::new(), String::new()),
+                };
+                vec![self.chunk(
+                    json!({"tool_calls": [{
+                        "index": index,
+                        "id": id,
+                        "type": "function",
+                        "function": { "name": name, "arguments": "" },
+                    }]}),
+                    Value::Null,
+                )]
+            }
+            AssistantMessageEvent::ToolCallDelta {
+                content_index,
+                delta,
+                ..
+            } => {
+                let index = self.tool_indices.get(content_index).copied().unwrap_or(0);
+                vec![self.chunk(
+                    json!({"tool_calls": [{ "index": index, "function": { "arguments": delta } }]}),
+                    Value::Null,
+                )]
+            }
+            AssistantMessageEvent::Done { reason, message } => {
+                let mut chunks = vec![self.chunk(json!({}), json!(finish_reason(reason)))];
+                if self.include_usage {
+                    let mut usage = self.chunk(json!({}), Value::Null);
+                    usage["choices"] = json!([]);
+                    usage["usage"] = usage_json(&message.usage);
+                    chunks.push(usage);
+                }
+                chunks
+            }
+            AssistantMessageEvent::Error { error, .. } => vec![json!({
+                "error": {
+                    "message": error.error_message.clone().unwrap_or_default(),
+                    "type": "api_error",
+                },
+            })],
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_to_context_maps_tool_round_trip() {
+        let request: ChatCompletionRequest = serde_json::from_value(json!({
+            "model": "m",
+            "messages": [
+                {"role": "system", "content": "Be brief"},
+                {"role": "user", "content": "List files"},
+                {"role": "assistant", "content": null, "tool_calls": [
+                    {"id": "call_1", "type": "function",
+                     "function": {"name": "ls", "arguments": "{\"path\":\".\"}"}}
+                ]},
+                {"role": "tool", "tool_call_id": "call_1", "content": "a.rs"}
+            ],
+            "tools": [{"type": "function", "function": {"name": "ls", "parameters": {"type": "object"}}}],
+            "tool_choice": {"type": "function", "function": {"name": "ls"}},
+            "stop": "END"
+        }))
+        .unwrap();
+        let model = pi_agent_ai::providers::faux::faux_model();
+        let context = request.to_context(&model);
+        assert_eq!(context.system_prompt.as_deref(), Some("Be brief"));
+        assert_eq!(context.messages.len(), 3);
+        let Message::Assistant(assistant) = &context.messages[1] else {
+            panic!("expected assistant");
+        };
+        let ContentBlock::ToolCall(call) = &assistant.content[0] else {
+            panic!("expected tool call");
+        };
+        assert_eq!(call.arguments["path"], ".");
+        let Message::ToolResult(result) = &context.messages[2] else {
+            panic!("expected tool result");
+        };
+        assert_eq!(result.tool_name, "ls");
+
+        let options = request.to_options();
+        assert_eq!(
+            options.base.tool_choice,
+            Some(ToolChoice::Tool("ls".to_string()))
+        );
+        assert_eq!(options.base.stop_sequences, Some(vec!["END".to_string()]));
+    }
+
+    #[test]
+    fn test_completion_response_with_tool_calls() {
+        let mut message = AssistantMessage::empty(&pi_agent_ai::providers::faux::faux_model());
+        message.stop_reason = StopReason::ToolUse;
+        message.content.push(ContentBlock::ToolCall(ToolCall {
+            id: "call_1".to_string(),
+            name: "ls".to_string(),
+            arguments: json!({"path": "."}),
+            thought_signature: None,
+        }));
+        let body = completion_response("chatcmpl-1", "faux", 0, &message);
+        assert_eq!(body["choices"][0]["finish_reason"], "tool_calls");
+        assert!(body["choices"][0]["message"]["content"].is_null());
+        assert_eq!(
+            body["choices"][0]["message"]["tool_calls"][0]["function"]["arguments"],
+            "{\"path\":\".\"}"
+        );
+    }
+}
@@ -0,0 +1,711 @@
+//! OpenAI-compatible HTTP gateway (`pi gateway serve`).
+//!
+//! Exposes `GET /v1/models`, `POST /v1/chat/completions` and
+//! `POST /v1/responses` over every provider in the [`ApiRegistry`], so any
+//! OpenAI client can reach Anthropic, Google, Bedrock and the rest through
+//! the credentials in `auth.json`. Requests are translated to a neutral
+//! [`Context`] and streamed through `stream_simple`; responses are mapped
+//! back, including tool calls in both directions.
+//!
+//! Clients authenticate with a bearer key from `gateway.keys`; each request's
+//! usage and cost is appended to `gateway-usage.jsonl` under the key's name.
+//! Models are addressed as `provider/model-id`, a bare id, or a
+//! `gateway.aliases` entry.
+
+mod chat;
+mod responses;
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use base64::Engine;
+use futures::StreamExt;
+use pi_agent_ai::registry::ApiRegistry;
+use pi_agent_core::event_stream::AssistantMessageEventStream;
+use pi_agent_core::types::*;
+use serde_json::{Value, json};
+use tokio::io::{AsyncWrite, AsyncWriteExt, BufReader};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio_util::sync::CancellationToken;
+
+use crate::agent_session::sdk::{
+    configure_http_clients, configure_rate_limits, configured_api_keys,
+};
+use crate::auth::storage::AuthStorage;
+use crate::config::paths;
+use crate::error::CodingAgentError;
+use crate::model::discovery;
+use crate::model::registry::{ModelRegistry, add_auth_header};
+use crate::server::http::{self, HttpRequest};
+use crate::settings::manager::SettingsManager;
+
+use chat::{ChatCompletionRequest, ChunkEncoder};
+use responses::{EventEncoder, ResponsesRequest};
+
+/// Default listen address for `pi gateway serve`.
+pub const DEFAULT_GATEWAY_ADDR: &str = "127.0.0.1:8788";
+
+/// Name recorded in the usage log for keys given on the command line.
+const CLI_KEY_NAME: &str = "cli";
+
+/// Serves the OpenAI-compatible API.
+pub struct GatewayServer {
+    registry: Arc<ApiRegistry>,
+    models: Arc<ModelRegistry>,
+    auth_storage: Arc<AuthStorage>,
+    /// `(name, key)` pairs.
+    keys: Vec<(String, String)>,
+    aliases: HashMap<String, String>,
+    usage_log: Option<PathBuf>,
+    usage_lock: Mutex<()>,
+}
+
+impl GatewayServer {
+    /// `keys` maps key names to the bearer keys clients may present; it must
+    /// not be empty.
+    pub fn new(
+        registry: Arc<ApiRegistry>,
+        models: Arc<ModelRegistry>,
+        auth_storage: Arc<AuthStorage>,
+        keys: HashMap<String, String>,
+    ) -> Self {
+        let mut keys: Vec<_> = keys.into_iter().collect();
+        keys.sort();
+        Self {
+            registry,
+            models,
+            auth_storage,
+            keys,
+            aliases: HashMap::new(),
+            usage_log: None,
+            usage_lock: Mutex::new(()),
+        }
+    }
+
+    /// Model aliases: alias -> `provider/model-id`.
+    pub fn with_aliases(mut self, aliases: HashMap<String, String>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Append per-request usage records to `path`.
+    pub fn with_usage_log(mut self, path: Option<PathBuf>) -> Self {
+        self.usage_log = path;
+        self
+    }
+
+    /// Gateway backed by the user's config directory. `extra_keys` (from the
+    /// command line) are added to `gateway.keys` under the name `cli`.
+    pub fn from_config_dir(
+        base_dir: &std::path::Path,
+        extra_keys: Vec<String>,
+    ) -> Result<Self, CodingAgentError> {
+        let mut settings_manager = SettingsManager::new(base_dir);
+        settings_manager.load()?;
+        let settings = settings_manager.settings();
+        configure_rate_limits(settings);
+        configure_http_clients(settings);
+
+        let mut models = ModelRegistry::new();
+        models.load_custom_models(&paths::models_file(base_dir))?;
+        if let Some(custom_models) = &settings.custom_models {
+            models.add_custom_models(custom_models);
+        }
+        models.add_discovered_models(discovery::cached_models(base_dir, settings));
+        let auth_storage =
+            AuthStorage::new(base_dir).with_configured_keys(configured_api_keys(settings, &models));
+
+        let gateway = settings.gateway.clone().unwrap_or_default();
+        let mut keys = gateway.keys.unwrap_or_default();
+        for (i, key) in extra_keys.into_iter().enumerate() {
+            let name = if i == 0 {
+                CLI_KEY_NAME.to_string()
+            } else {
+                format!("{CLI_KEY_NAME}-{i}")
+            };
+            keys.insert(name, key);
+        }
+        let usage_log = gateway
+            .usage_log
+            .unwrap_or(true)
+            .then(|| paths::gateway_usage_file(base_dir));
+
+        Ok(Self::new(
+            Arc::new(pi_agent_ai::register::create_default_registry()),
+            Arc::new(models),
+            Arc::new(auth_storage),
+            keys,
+        )
+        .with_aliases(gateway.aliases.unwrap_or_default())
+        .with_usage_log(usage_log))
+    }
+
+    /// Whether any client key is configured.
+    pub fn has_keys(&self) -> bool {
+        !self.keys.is_empty()
+    }
+
+    /// Accept connections until the listener fails.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> std::io::Result<()> {
+        loop {
+            let (socket, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(socket).await {
+                    tracing::debug!("Gateway connection from {peer} failed: {e}");
+                }
+            });
+        }
+    }
+
+    /// Name of the key presented by `request`, if it is valid.
+    fn key_name(&self, request: &HttpRequest) -> Option<&str> {
+        let token = request.bearer_token()?;
+        self.keys
+            .iter()
+            .find(|(_, key)| http::constant_time_eq(key, token))
+            .map(|(name, _)| name.as_str())
+    }
+
+    /// Resolve an alias, `provider/model-id`, or bare model id.
+    fn resolve_model(&self, name: &str) -> Option<Model> {
+        let name = self.aliases.get(name).map(String::as_str).unwrap_or(name);
+        if let Some((provider, id)) = name.split_once('/')
+            && let Some(model) = self.models.find_by_provider(provider, id)
+        {
+            return Some(model.clone());
+        }
+        self.models.find(name).cloned()
+    }
+
+    fn models_body(&self) -> Value {
+        let mut data: Vec<Value> = self
+            .models
+            .all_models()
+            .iter()
+            .map(|model| {
+                json!({
+                    "id": format!("{}/{}", model.provider, model.id),
+                    "object": "model",
+                    "created": 0,
+                    "owned_by": model.provider,
+                })
+            })
+            .collect();
+        let mut aliases: Vec<_> = self.aliases.iter().collect();
+        aliases.sort();
+        for (alias, target) in aliases {
+            let owner = target.split_once('/').map_or("", |(provider, _)| provider);
+            data.push(json!({
+                "id": alias,
+                "object": "model",
+                "created": 0,
+                "owned_by": owner,
+            }));
+        }
+        json!({ "object": "list", "data": data })
+    }
+
+    async fn handle_connection(&self, socket: TcpStream) -> std::io::Result<()> {
+        let (read_half, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(read_half);
+        let mut request = match http::read_head(&mut reader).await {
+            Ok(request) => request,
+            Err(e) => return write_error(&mut writer, 400, "invalid_request_error", &e).await,
+        };
+
+        let route = match (request.method.as_str(), request.path()) {
+            ("GET", "/v1/models") => Route::Models,
+            ("POST", "/v1/chat/completions") => Route::ChatCompletions,
+            ("POST", "/v1/responses") => Route::Responses,
+            (_, "/v1/models" | "/v1/chat/completions" | "/v1/responses") => {
+                return write_error(
+                    &mut writer,
+                    405,
+                    "invalid_request_error",
+                    "Method not allowed",
+                )
+                .await;
+            }
+            _ => return write_error(&mut writer, 404, "not_found", "Not found").await,
+        };
+        let Some(key_name) = self.key_name(&request).map(str::to_string) else {
+            return write_error(&mut writer, 401, "authentication_error", "Invalid API key").await;
+        };
+        if let Err(e) = http::read_body(&mut reader, &mut request).await {
+            let status = if e.contains("too large") { 413 } else { 400 };
+            return write_error(&mut writer, status, "invalid_request_error", &e).await;
+        }
+
+        match route {
+            Route::Models => http::write_json(&mut writer, 200, &self.models_body()).await,
+            Route::ChatCompletions => match serde_json::from_slice(&request.body) {
+                Ok(body) => self.chat_completions(&mut writer, &key_name, body).await,
+                Err(e) => {
+                    write_error(&mut writer, 400, "invalid_request_error", &e.to_string()).await
+                }
+            },
+            Route::Responses => match serde_json::from_slice(&request.body) {
+                Ok(body) => self.responses(&mut writer, &key_name, body).await,
+                Err(e) => {
+                    write_error(&mut writer, 400, "invalid_request_error", &e.to_string()).await
+                }
+            },
+        }
+    }
+
+    /// Resolve the model and start the upstream stream, or describe why not.
+    async fn start(
+        &self,
+        model_name: &str,
+        context: impl FnOnce(&Model) -> Context,
+        mut options: SimpleStreamOptions,
+        cancel: CancellationToken,
+    ) -> Result<(Model, AssistantMessageEventStream), String> {
+        let model = self
+            .resolve_model(model_name)
+            .ok_or_else(|| format!("Unknown model: {model_name}"))?;
+        let provider = self
+            .registry
+            .get(&model.api)
+            .ok_or_else(|| format!("No API provider registered for api: {}", model.api))?;
+        options.base.api_key = self.auth_storage.resolve_api_key(&model.provider).await;
+        if let Some(header) = self.models.auth_header(&model.provider) {
+            add_auth_header(&mut options.base, header);
+        }
+        let events = provider.stream_simple(&model, &context(&model), &options, cancel);
+        Ok((model, events))
+    }
+
+    async fn chat_completions<W>(
+        &self,
+        writer: &mut W,
+        key_name: &str,
+        request: ChatCompletionRequest,
+    ) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let cancel = CancellationToken::new();
+        let (model, mut events) = match self
+            .start(
+                &request.model,
+                |model| request.to_context(model),
+                request.to_options(),
+                cancel.clone(),
+            )
+            .await
+        {
+            Ok(started) => started,
+            Err(e) => return write_error(writer, 400, "invalid_request_error", &e).await,
+        };
+        let id = format!("chatcmpl-{}", uuid::Uuid::new_v4().simple());
+        let created = chrono::Utc::now().timestamp();
+
+        if !request.stream {
+            let message = collect(&model, &mut events).await;
+            self.record_usage(key_name, "chat.completions", &model, &message)
+                .await;
+            if message.stop_reason == StopReason::Error {
+                let error = message.error_message.unwrap_or_default();
+                return write_error(writer, 502, "api_error", &error).await;
+            }
+            let body = chat::completion_response(&id, &request.model, created, &message);
+            return http::write_json(writer, 200, &body).await;
+        }
+
+        let include_usage = request
+            .stream_options
+            .as_ref()
+            .is_some_and(|o| o.include_usage);
+        let mut encoder = ChunkEncoder::new(&id, &request.model, created, include_usage);
+        http::start_sse(writer).await?;
+        while let Some(event) = events.next().await {
+            for chunk in encoder.encode(&event) {
+                if let Err(e) = http::write_sse_data(writer, &chunk.to_string()).await {
+                    cancel.cancel();
+                    return Err(e);
+                }
+            }
+            if let Some(message) = final_message(&event) {
+                self.record_usage(key_name, "chat.completions", &model, message)
+                    .await;
+                break;
+            }
+        }
+        http::write_sse_data(writer, "[DONE]").await
+    }
+
+    async fn responses<W>(
+        &self,
+        writer: &mut W,
+        key_name: &str,
+        request: ResponsesRequest,
+    ) -> std::io::Result<()>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let cancel = CancellationToken::new();
+        let (model, mut events) = match self
+            .start(
+                &request.model,
+                |model| request.to_context(model),
+                request.to_options(),
+                cancel.clone(),
+            )
+            .await
+        {
+            Ok(started) => started,
+            Err(e) => return write_error(writer, 400, "invalid_request_error", &e).await,
+        };
+        let id = format!("resp_{}", uuid::Uuid::new_v4().simple());
+        let created = chrono::Utc::now().timestamp();
+
+        if !request.stream {
+            let message = collect(&model, &mut events).await;
+            self.record_usage(key_name, "responses", &model, &message)
+                .await;
+            if message.stop_reason == StopReason::Error {
+                let error = message.error_message.unwrap_or_default();
+                return write_error(writer, 502, "api_error", &error).await;
+            }
+            let body = responses::response_object(&id, &request.model, created, Some(&message));
+            return http::write_json(writer, 200, &body).await;
+        }
+
+        let mut encoder = EventEncoder::new(&id, &request.model, created);
+        http::start_sse(writer).await?;
+        while let Some(event) = events.next().await {
+            for (name, data) in encoder.encode(&event) {
+                if let Err(e) = http::write_sse_event(writer, name, &data.to_string()).await {
+                    cancel.cancel();
+                    return Err(e);
+                }
+            }
+            if let Some(message) = final_message(&event) {
+                self.record_usage(key_name, "responses", &model, message)
+                    .await;
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    /// Append one usage record; failures are logged and otherwise ignored.
+    async fn record_usage(
+        &self,
+        key_name: &str,
+        endpoint: &str,
+        model: &Model,
+        message: &AssistantMessage,
+    ) {
+        let Some(path) = &self.usage_log else {
+            return;
+        };
+        let mut usage = message.usage.clone();
+        pi_agent_ai::models::calculate_cost(model, &mut usage);
+        let record = json!({
+            "timestamp": chrono::Utc::now().to_rfc3339(),
+            "key": key_name,
+            "endpoint": endpoint,
+            "provider": model.provider,
+            "model": model.id,
+            "input": usage.input,
+            "output": usage.output,
+            "cacheRead": usage.cache_read,
+            "cacheWrite": usage.cache_write,
+            "cost": usage.cost.total,
+            "stopReason": message.stop_reason.to_string(),
+        });
+
+        let _guard = self.usage_lock.lock().await;
+        let result = async {
+            let mut file = tokio::fs::OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(path)
+                .await?;
+            file.write_all(format!("{record}\n").as_bytes()).await?;
+            file.flush().await
+        }
+        .await;
+        if let Err(e) = result {
+            tracing::warn!("Failed to write gateway usage log {}: {e}", path.display());
+        }
+    }
+}
+
+enum Route {
+    Models,
+    ChatCompletions,
+    Responses,
+}
+
+/// The finished message carried by a terminal event.
+fn final_message(event: &AssistantMessageEvent) -> Option<&AssistantMessage> {
+    match event {
+        AssistantMessageEvent::Done { message, .. } => Some(message),
+        AssistantMessageEvent::Error { error, .. } => Some(error),
+        _ => None,
+    }
+}
+
+/// Drain `events` and return the final message.
+async fn collect(model: &Model, events: &mut AssistantMessageEventStream) -> AssistantMessage {
+    while let Some(event) = events.next().await {
+        if let Some(message) = final_message(&event) {
+            return message.clone();
+        }
+    }
+    events.result().await.unwrap_or_else(|| {
+        let mut message = AssistantMessage::empty(model);
+        message.stop_reason = StopReason::Error;
+        message.error_message = Some("Upstream stream ended without a result".to_string());
+        message
+    })
+}
+
+async fn write_error<W>(
+    writer: &mut W,
+    status: u16,
+    kind: &str,
+    message: &str,
+) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let body = json!({ "error": { "message": message, "type": kind, "code": Value::Null } });
+    http::write_json(writer, status, &body).await
+}
+
+/// OpenAI `finish_reason` for a stop reason.
+fn finish_reason(reason: &StopReason) -> &'static str {
+    match reason {
+        StopReason::Length => "length",
+        StopReason::ToolUse => "tool_calls",
+        _ => "stop",
+    }
+}
+
+/// `tool_choice` in either the Chat Completions or Responses shape.
+fn parse_tool_choice(value: &Value) -> Option<ToolChoice> {
+    match value {
+        Value::String(choice) => match choice.as_str() {
+            "auto" => Some(ToolChoice::Auto),
+            "none" => Some(ToolChoice::None),
+            "required" => Some(ToolChoice::Required),
+            _ => None,
+        },
+        Value::Object(_) => value
+            .pointer("/function/name")
+            .or_else(|| value.get("name"))
+            .and_then(Value::as_str)
+            .map(|name| ToolChoice::Tool(name.to_string())),
+        _ => None,
+    }
+}
+
+fn parse_reasoning_effort(effort: &str) -> Option<ThinkingLevel> {
+    match effort {
+        "minimal" => Some(ThinkingLevel::Minimal),
+        "low" => Some(ThinkingLevel::Low),
+        "medium" => Some(ThinkingLevel::Medium),
+        "high" => Some(ThinkingLevel::High),
+        "xhigh" => Some(ThinkingLevel::Xhigh),
+        _ => None,
+    }
+}
+
+/// Inline image from a base64 `data:` URL; remote URLs are not fetched.
+fn parse_data_url(url: &str) -> Option<ImageContent> {
+    let (header, data) = url.strip_prefix("data:")?.split_once(',')?;
+    let mime_type = header.strip_suffix(";base64")?;
+    base64::engine::general_purpose::STANDARD
+        .decode(data)
+        .ok()?;
+    Some(ImageContent {
+        data: data.to_string(),
+        mime_type: mime_type.to_string(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pi_agent_ai::providers::faux::{
+        FauxProvider, FauxTurn, faux_model, register_faux_provider,
+    };
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    async fn start(turns: Vec<FauxTurn>, usage_log: Option<PathBuf>) -> String {
+        let mut registry = ApiRegistry::new();
+        register_faux_provider(&mut registry, Arc::new(FauxProvider::new(turns)));
+        let mut models = ModelRegistry::new();
+        models.add_discovered_models(vec![faux_model()]);
+        let dir = tempfile::tempdir().unwrap();
+        let server = GatewayServer::new(
+            Arc::new(registry),
+            Arc::new(models),
+            Arc::new(AuthStorage::new(dir.path())),
+            HashMap::from([("team".to_string(), "secret".to_string())]),
+        )
+        .with_aliases(HashMap::from([(
+            "cheap".to_string(),
+            "faux/faux-1".to_string(),
+        )]))
+        .with_usage_log(usage_log);
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(Arc::new(server).serve(listener));
+        addr.to_string()
+    }
+
+    /// Send one request and return `(status, body)`.
+    async fn send(addr: &str, method: &str, path: &str, key: &str, body: Value) -> (u16, String) {
+        let body = body.to_string();
+        let mut socket = TcpStream::connect(addr).await.unwrap();
+        let request = format!(
+            "{method} {path} HTTP/1.1\r\nHost: {addr}\r\nAuthorization: Bearer {key}\r\nContent-Length: {}\r\n\r\n{body}",
+            body.len()
+        );
+        socket.write_all(request.as_bytes()).await.unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        let (head, body) = response.split_once("\r\n\r\n").unwrap();
+        let status = head.split_whitespace().nth(1).unwrap().parse().unwrap();
+        (status, body.to_string())
+    }
+
+    fn sse_data(body: &str) -> Vec<String> {
+        body.lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(str::to_string)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_chat_completion_with_usage_log() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("usage.jsonl");
+        let addr = start(vec![FauxTurn::text("hello")], Some(log.clone())).await;
+        let (status, body) = send(
+            &addr,
+            "POST",
+            "/v1/chat/completions",
+            "secret",
+            json!({"model": "cheap", "messages": [{"role": "user", "content": "hi"}]}),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(body["model"], "cheap");
+        assert_eq!(body["choices"][0]["message"]["content"], "hello");
+        assert_eq!(body["choices"][0]["finish_reason"], "stop");
+
+        let record: Value =
+            serde_json::from_str(std::fs::read_to_string(&log).unwrap().trim()).unwrap();
+        assert_eq!(record["key"], "team");
+        assert_eq!(record["model"], "faux-1");
+    }
+
+    #[tokio::test]
+    async fn test_streaming_chat_tool_call() {
+        let addr = start(vec![FauxTurn::tool_call("ls", json!({"path": "."}))], None).await;
+        let (status, body) = send(
+            &addr,
+            "POST",
+            "/v1/chat/completions",
+            "secret",
+            json!({
+                "model": "faux/faux-1",
+                "stream": true,
+                "stream_options": {"include_usage": true},
+                "messages": [{"role": "user", "content": "hi"}],
+                "tools": [{"type": "function", "function": {"name": "ls"}}]
+            }),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let data = sse_data(&body);
+        assert_eq!(data.last().unwrap(), "[DONE]");
+        let chunks: Vec<Value> = data[..data.len() - 1]
+            .iter()
+            .map(|d| serde_json::from_str(d).unwrap())
+            .collect();
+        let arguments: String = chunks
+            .iter()
+            .filter_map(|c| {
+                c["choices"][0]["delta"]["tool_calls"][0]["function"]["arguments"].as_str()
+            })
+            .collect();
+        assert_eq!(
+            serde_json::from_str::<Value>(&arguments).unwrap()["path"],
+            "."
+        );
+        assert!(
+            chunks
+                .iter()
+                .any(|c| c["choices"][0]["finish_reason"] == "tool_calls")
+        );
+        assert!(chunks.last().unwrap()["usage"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_streaming_responses() {
+        let addr = start(vec![FauxTurn::text("hello")], None).await;
+        let (status, body) = send(
+            &addr,
+            "POST",
+            "/v1/responses",
+            "secret",
+            json!({"model": "faux-1", "input": "hi", "stream": true}),
+        )
+        .await;
+        assert_eq!(status, 200);
+        let events: Vec<&str> = body
+            .lines()
+            .filter_map(|line| line.strip_prefix("event: "))
+            .collect();
+        assert_eq!(events.first(), Some(&"response.created"));
+        assert_eq!(events.last(), Some(&"response.completed"));
+        assert!(events.contains(&"response.output_text.delta"));
+        let completed: Value = serde_json::from_str(sse_data(&body).last().unwrap()).unwrap();
+        assert_eq!(
+            completed["response"]["output"][0]["content"][0]["text"],
+            "hello"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_models_and_auth() {
+        let addr = start(vec![], None).await;
+        let (status, _) = send(&addr, "GET", "/v1/models", "wrong", json!({})).await;
+        assert_eq!(status, 401);
+        let (status, body) = send(&addr, "GET", "/v1/models", "secret", json!({})).await;
+        assert_eq!(status, 200);
+        let body: Value = serde_json::from_str(&body).unwrap();
+        let ids: Vec<&str> = body["data"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .filter_map(|m| m["id"].as_str())
+            .collect();
+        assert!(ids.contains(&"faux/faux-1"));
+        assert!(ids.contains(&"cheap"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_key_before_reading_body() {
+        let addr = start(vec![], None).await;
+        let mut socket = TcpStream::connect(&addr).await.unwrap();
+        socket
+            .write_all(b"POST /v1/chat/completions HTTP/1.1\r\nAuthorization: Bearer wrong\r\nContent-Length: 1000000\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 401"));
+    }
+}
@@ -0,0 +1,571 @@
+//! `/v1/responses` request and response mapping.
+
+use std::collections::HashMap;
+
+use pi_agent_core::types::*;
+use serde::Deserialize;
+use serde_json::{Value, json};
+
+use super::{parse_data_url, parse_reasoning_effort, parse_tool_choice};
+
+#[derive(Debug, Deserialize)]
+pub struct ResponsesRequest {
+    pub model: String,
+    pub input: Value,
+    #[serde(default)]
+    pub instructions: Option<String>,
+    #[serde(default)]
+    pub tools: Option<Vec<ResponsesTool>>,
+    #[serde(default)]
+    pub tool_choice: Option<Value>,
+    #[serde(default)]
+    pub stream: bool,
+    #[serde(default)]
+    pub temperature: Option<f64>,
+    #[serde(default)]
+    pub top_p: Option<f64>,
+    #[serde(default)]
+    pub max_output_tokens: Option<u64>,
+    #[serde(default)]
+    pub reasoning: Option<ResponsesReasoning>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResponsesTool {
+    #[serde(rename = "type")]
+    pub kind: String,
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub parameters: Option<Value>,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct ResponsesReasoning {
+    #[serde(default)]
+    pub effort: Option<String>,
+}
+
+fn text_block(text: String) -> ContentBlock {
+    ContentBlock::Text(TextContent {
+        text,
+        text_signature: None,
+        citations: None,
+    })
+}
+
+/// Content parts of an input message (`input_text`, `output_text`, `input_image`).
+fn content_blocks(content: Option<&Value>) -> Vec<ContentBlock> {
+    match content {
+        Some(Value::String(text)) => vec![text_block(text.clone())],
+        Some(Value::Array(parts)) => parts
+            .iter()
+            .filter_map(|part| match part.get("type").and_then(Value::as_str) {
+                Some("input_text" | "output_text" | "text") => {
+                    Some(text_block(part.get("text")?.as_str()?.to_string()))
+                }
+                Some("input_image") => {
+                    parse_data_url(part.get("image_url")?.as_str()?).map(ContentBlock::Image)
+                }
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn blocks_text(blocks: &[ContentBlock]) -> String {
+    blocks
+        .iter()
+        .filter_map(|b| b.as_text().map(|t| t.text.as_str()))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+impl ResponsesRequest {
+    /// Convert to a neutral context for `model`. Consecutive assistant items
+    /// (text and function calls) become one assistant message.
+    pub fn to_context(&self, model: &Model) -> Context {
+        let mut system: Vec<String> = self.instructions.iter().cloned().collect();
+        let mut messages: Vec<Message> = Vec::new();
+        let mut tool_names: HashMap<String, String> = HashMap::new();
+        let now = chrono::Utc::now().timestamp_millis();
+
+        let push_assistant = |messages: &mut Vec<Message>, block: ContentBlock| {
+            if let Some(Message::Assistant(last)) = messages.last_mut() {
+                last.content.push(block);
+                return;
+            }
+            let mut assistant = AssistantMessage::empty(model);
+            assistant.timestamp = now;
+            assistant.content.push(block);
+            messages.push(Message::Assistant(assistant));
+        };
+
+        let items = match &self.input {
+            Value::String(text) => vec![json!({"role": "user", "content": text})],
+            Value::Array(items) => items.clone(),
+            _ => Vec::new(),
+        };
+        for item in &items {
+            let kind = item
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("message");
+            match kind {
+                "message" => {
+                    let blocks = content_blocks(item.get("content"));
+                    match item.get("role").and_then(Value::as_str) {
+                        Some("system" | "developer") => system.push(blocks_text(&blocks)),
+                        Some("assistant") => {
+                            for block in blocks {
+                                push_assistant(&mut messages, block);
+                            }
+                        }
+                        _ => messages.push(Message::User(UserMessage {
+                            content: UserContent::Blocks(blocks),
+                            timestamp: now,
+                        })),
+                    }
+                }
+                "function_call" => {
+                    let id = item
+                        .get("call_id")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    let name = item
+                        .get("name")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    let arguments = item
+                        .get("arguments")
+                        .and_then(Value::as_str)
+                        .and_then(|a| serde_json::from_str(a).ok())
+                        .unwrap_or_else(|| json!({}));
+                    tool_names.insert(id.clone(), name.clone());
+                    push_assistant(
+                        &mut messages,
+                        ContentBlock::ToolCall(ToolCall {
+                            id,
+                            name,
+                            arguments,
+                            thought_signature: None,
+                        }),
+                    );
+                }
+                "function_call_output" => {
+                    let id = item
+                        .get("call_id")
+                        .and_then(Value::as_str)
+                        .unwrap_or_default()
+                        .to_string();
+                    let output = match item.get("output") {
+                        Some(Value::String(text)) => text.clone(),
+                        Some(other) => blocks_text(&content_blocks(Some(other))),
+                        None => String::new(),
+                    };
+                    messages.push(Message::ToolResult(ToolResultMessage {
+                        tool_name: tool_names.get(&id).cloned().unwrap_or_default(),
+                        tool_call_id: id,
+                        content: vec![text_block(output)],
+                        details: None,
+                        is_error: false,
+                        timestamp: now,
+                    }));
+                }
+                _ => {}
+            }
+        }
+
+        for message in &mut messages {
+            if let Message::Assistant(assistant) = message {
+                let has_calls = assistant
+                    .content
+                    .iter()
+                    .any(|b| matches!(b, ContentBlock::ToolCall(_)));
+                assistant.stop_reason = if has_calls {
+                    StopReason::ToolUse
+                } else {
+                    StopReason::Stop
+                };
+            }
+        }
+
+        let tools = self.tools.as_ref().map(|tools| {
+            tools
+                .iter()
+                .filter(|tool| tool.kind == "function")
+                .filter_map(|tool| {
+                    Some(Tool {
+                        name: tool.name.clone()?,
+                        description: tool.description.clone().unwrap_or_default(),
+                        parameters: tool
+                            .parameters
+                            .clone()
+                            .unwrap_or_else(|| json!({"type": "object", "properties": {}})),
+                    })
+                })
+                .collect()
+        });
+
+        Context {
+            system_prompt: (!system.is_empty()).then(|| system.join("\n\n")),
+            messages,
+            tools,
+        }
+    }
+
+    pub fn to_options(&self) -> SimpleStreamOptions {
+        SimpleStreamOptions {
+            base: StreamOptions {
+                temperature: self.temperature,
+                max_tokens: self.max_output_tokens,
+                tool_choice: self.tool_choice.as_ref().and_then(parse_tool_choice),
+                top_p: self.top_p,
+                ..Default::default()
+            },
+            reasoning: self
+                .reasoning
+                .as_ref()
+                .and_then(|r| r.effort.as_deref())
+                .and_then(parse_reasoning_effort),
+            thinking_budgets: None,
+        }
+    }
+}
+
+fn usage_json(usage: &Usage) -> Value {
+    let input = usage.input + usage.cache_read + usage.cache_write;
+    json!({
+        "input_tokens": input,
+        "input_tokens_details": { "cached_tokens": usage.cache_read },
+        "output_tokens": usage.output,
+        "total_tokens": input + usage.output,
+    })
+}
+
+fn message_item(id: &str, text: &str, status: &str) -> Value {
+    json!({
+        "type": "message",
+        "id": id,
+        "status": status,
+        "role": "assistant",
+        "content": [{ "type": "output_text", "text": text, "annotations": [] }],
+    })
+}
+
+fn function_call_item(call: &ToolCall, arguments: &str, status: &str) -> Value {
+    json!({
+        "type": "function_call",
+        "id": format!("fc_{}", call.id),
+        "call_id": call.id,
+        "name": call.name,
+        "arguments": arguments,
+        "status": status,
+    })
+}
+
+/// Output items for a finished message; text blocks are merged into one
+/// `message` item in content order.
+fn output_items(id: &str, message: &AssistantMessage) -> Vec<Value> {
+    message
+        .content
+        .iter()
+        .enumerate()
+        .filter_map(|(index, block)| match block {
+            ContentBlock::Text(text) => Some(message_item(
+                &format!("msg_{id}_{index}"),
+                &text.text,
+                "completed",
+            )),
+            ContentBlock::ToolCall(call) => Some(function_call_item(
+                call,
+                &call.arguments.to_string(),
+                "completed",
+            )),
+            _ => None,
+        })
+        .collect()
+}
+
+/// A `response` object. `message` is `None` while the response is in progress.
+pub fn response_object(
+    id: &str,
+    model: &str,
+    created: i64,
+    message: Option<&AssistantMessage>,
+) -> Value {
+    let Some(message) = message else {
+        return json!({
+            "id": id,
+            "object": "response",
+            "created_at": created,
+            "status": "in_progress",
+            "model": model,
+            "output": [],
+        });
+    };
+    let (status, error, incomplete) = match message.stop_reason {
+        StopReason::Error | StopReason::Aborted => (
+            "failed",
+            json!({
+                "code": "server_error",
+                "message": message.error_message.clone().unwrap_or_default(),
+            }),
+            Value::Null,
+        ),
+        StopReason::Length => (
+            "incomplete",
+            Value::Null,
+            json!({ "reason": "max_output_tokens" }),
+        ),
+        _ => ("completed", Value::Null, Value::Null),
+    };
+    json!({
+        "id": id,
+        "object": "response",
+        "created_at": created,
+        "status": status,
+        "error": error,
+        "incomplete_details": incomplete,
+        "model": model,
+        "output": output_items(id, message),
+        "usage": usage_json(&message.usage),
+    })
+}
+
+/// Translates agent events into named Responses API stream events.
+pub struct EventEncoder {
+    id: String,
+    model: String,
+    created: i64,
+    sequence: u64,
+    /// content_index -> output_index.
+    output_indices: HashMap<usize, usize>,
+}
+
+impl EventEncoder {
+    pub fn new(id: &str, model: &str, created: i64) -> Self {
+        Self {
+            id: id.to_string(),
+            model: model.to_string(),
+            created,
+            sequence: 0,
+            output_indices: HashMap::new(),
+        }
+    }
+
+    fn event(&mut self, name: &'static str, mut data: Value) -> (&'static str, Value) {
+        data["type"] = json!(name);
+        data["sequence_number"] = json!(self.sequence);
+        self.sequence += 1;
+        (name, data)
+    }
+
+    fn output_index(&mut self, content_index: usize) -> usize {
+        let next = self.output_indices.len();
+        *self.output_indices.entry(content_index).or_insert(next)
+    }
+
+    fn item_id(&self, content_index: usize) -> String {
+        format!("msg_{}_{content_index}", self.id)
+    }
+
+    /// `(event name, data)` pairs for one event.
+    pub fn encode(&mut self, event: &AssistantMessageEvent) -> Vec<(&'static str, Value)> {
+        match event {
+            AssistantMessageEvent::Start { .. } => {
+                let response = response_object(&self.id, &self.model, self.created, None);
+                vec![
+                    self.event("response.created", json!({ "response": response })),
+                    self.event("response.in_progress", json!({ "response": response })),
+                ]
+            }
+            AssistantMessageEvent::TextStart { content_index, .. } => {
+                let output_index = self.output_index(*content_index);
+                let item_id = self.item_id(*content_index);
+                let mut item = message_item(&item_id, "", "in_progress");
+                item["content"] = json!([]);
+                vec![
+                    self.event(
+                        "response.output_item.added",
+                        json!({ "output_index": output_index, "item": item }),
+                    ),
+                    self.event(
+                        "response.content_part.added",
+                        json!({
+                            "item_id": item_id,
+                            "output_index": output_index,
+                            "content_index": 0,
+                            "part": { "type": "output_text", "text": "", "annotations": [] },
+                        }),
+                    ),
+                ]
+            }
+            AssistantMessageEvent::TextDelta {
+                content_index,
+                delta,
+                ..
+            } => {
+                let output_index = self.output_index(*content_index);
+                let item_id = self.item_id(*content_index);
+                vec![self.event(
+                    "response.output_text.delta",
+                    json!({
+                        "item_id": item_id,
+                        "output_index": output_index,
+                        "content_index": 0,
+                        "delta": delta,
+                    }),
+                )]
+            }
+            AssistantMessageEvent::TextEnd {
+                content_index,
+                content,
+                ..
+            } => {
+                let output_index = self.output_index(*content_index);
+                let item_id = self.item_id(*content_index);
+                vec![
+                    self.event(
+                        "response.output_text.done",
+                        json!({
+                            "item_id": item_id,
+                            "output_index": output_index,
+                            "content_index": 0,
+                            "text": content,
+                        }),
+                    ),
+                    self.event(
+                        "response.content_part.done",
+                        json!({
+                            "item_id": item_id,
+                            "output_index": output_index,
+                            "content_index": 0,
+                            "part": { "type": "output_text", "text": content, "annotations": [] },
+                        }),
+                    ),
+                    self.event(
+                        "response.output_item.done",
+                        json!({
+                            "output_index": output_index,
+                            "item": message_item(&item_id, content, "completed"),
+                        }),
+                    ),
+                ]
+            }
+            AssistantMessageEvent::ToolCallStart {
+                content_index,
+                partial,
+            } => {
+                let output_index = self.output_index(*content_index);
+                let Some(ContentBlock::ToolCall(call)) = partial.content.get(*content_index) else {
+                    return Vec::new();
+                };
+                let item = function_call_item(call, "", "in_progress");
+                vec![self.event(
+                    "response.output_item.added",
+                    json!({ "output_index": output_index, "item": item }),
+                )]
+            }
+            AssistantMessageEvent::ToolCallDelta {
+                content_index,
+                delta,
+                partial,
+            } => {
+                let output_index = self.output_index(*content_index);
+                let item_id = match partial.content.get(*content_index) {
+                    Some(ContentBlock::ToolCall(call)) => format!("fc_{}", call.id),
+                    _ => String::new(),
+                };
+                vec![self.event(
+                    "response.function_call_arguments.delta",
+                    json!({ "item_id": item_id, "output_index": output_index, "delta": delta }),
+                )]
+            }
+            AssistantMessageEvent::ToolCallEnd {
+                content_index,
+                tool_call,
+                ..
+            } => {
+                let output_index = self.output_index(*content_index);
+                let arguments = tool_call.arguments.to_string();
+                vec![
+                    self.event(
+                        "response.function_call_arguments.done",
+                        json!({
+                            "item_id": format!("fc_{}", tool_call.id),
+                            "output_index": output_index,
+                            "arguments": arguments,
+                        }),
+                    ),
+                    self.event(
+                        "response.output_item.done",
+                        json!({
+                            "output_index": output_index,
+                            "item": function_call_item(tool_call, &arguments, "completed"),
+                        }),
+                    ),
+                ]
+            }
+            AssistantMessageEvent::Done { message, .. } => {
+                let response = response_object(&self.id, &self.model, self.created, Some(message));
+                let name = if message.stop_reason == StopReason::Length {
+                    "response.incomplete"
+                } else {
+                    "response.completed"
+                };
+                vec![self.event(name, json!({ "response": response }))]
+            }
+            AssistantMessageEvent::Error { error, .. } => {
+                let response = response_object(&self.id, &self.model, self.created, Some(error));
+                vec![self.event("response.failed", json!({ "response": response }))]
+            }
+            _ => Vec::new(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_input_items_merge_assistant_turns() {
+        let request: ResponsesRequest = serde_json::from_value(json!({
+            "model": "m",
+            "instructions": "Be brief",
+            "input": [
+                {"role": "user", "content": [{"type": "input_text", "text": "List files"}]},
+                {"type": "message", "role": "assistant",
+                 "content": [{"type": "output_text", "text": "Listing"}]},
+                {"type": "function_call", "call_id": "call_1", "name": "ls", "arguments": "{}"},
+                {"type": "function_call_output", "call_id": "call_1", "output": "a.rs"}
+            ],
+            "tools": [{"type": "function", "name": "ls", "parameters": {"type": "object"}}],
+            "tool_choice": {"type": "function", "name": "ls"}
+        }))
+        .unwrap();
+        let context = request.to_context(&pi_agent_ai::providers::faux::faux_model());
+        assert_eq!(context.system_prompt.as_deref(), Some("Be brief"));
+        assert_eq!(context.messages.len(), 3);
+        let Message::Assistant(assistant) = &context.messages[1] else {
+            panic!("expected assistant");
+        };
+        assert_eq!(assistant.content.len(), 2);
+        assert_eq!(assistant.stop_reason, StopReason::ToolUse);
+        let Message::ToolResult(result) = &context.messages[2] else {
+            panic!("expected tool result");
+        };
+        assert_eq!(result.tool_name, "ls");
+        assert_eq!(context.tools.unwrap()[0].name, "ls");
+        assert_eq!(
+            request.to_options().base.tool_choice,
+            Some(ToolChoice::Tool("ls".to_string()))
+        );
+    }
+}
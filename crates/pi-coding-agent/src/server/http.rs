@@ -0,0 +1,309 @@
+//! Minimal HTTP/1.1 request parsing and response writing over tokio.
+//!
+//! Just enough for the local servers: one request per connection, bodies
+//! delimited by `Content-Length`, and responses closed after writing
+//! (including server-sent event streams). Heads and bodies are read
+//! separately so a server can reject unauthenticated requests before
+//! buffering a body; both reads are size-capped and time out.
+
+use std::time::Duration;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, BufReader};
+
+/// Largest accepted request body.
+pub const MAX_BODY_BYTES: usize = 32 * 1024 * 1024;
+
+/// Largest accepted request head (request line and headers).
+const MAX_HEAD_BYTES: usize = 64 * 1024;
+
+/// Largest accepted request line or header line.
+const MAX_LINE_BYTES: usize = 8 * 1024;
+
+/// Time allowed for reading the request head, and again for the body.
+pub const READ_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A parsed request.
+#[derive(Debug, Clone)]
+pub struct HttpRequest {
+    pub method: String,
+    /// Request target, including any query string.
+    pub target: String,
+    /// Header names are lowercased.
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpRequest {
+    pub fn header(&self, name: &str) -> Option<&str> {
+        let name = name.to_ascii_lowercase();
+        self.headers
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, v)| v.as_str())
+    }
+
+    /// Path without the query string.
+    pub fn path(&self) -> &str {
+        self.target.split('?').next().unwrap_or_default()
+    }
+
+    /// Decoded query parameter.
+    pub fn query_param(&self, name: &str) -> Option<String> {
+        let query = self.target.split_once('?')?.1;
+        url::form_urlencoded::parse(query.as_bytes())
+            .find(|(k, _)| k == name)
+            .map(|(_, v)| v.into_owned())
+    }
+
+    /// Token from an `Authorization: Bearer` header.
+    pub fn bearer_token(&self) -> Option<&str> {
+        let value = self.header("authorization")?;
+        let (scheme, token) = value.split_once(' ')?;
+        scheme.eq_ignore_ascii_case("bearer").then(|| token.trim())
+    }
+}
+
+/// Read the request line and headers from `reader`, leaving the body
+/// unread so callers can authenticate first.
+pub async fn read_head<R>(reader: &mut BufReader<R>) -> Result<HttpRequest, String>
+where
+    R: AsyncRead + Unpin,
+{
+    tokio::time::timeout(READ_TIMEOUT, read_head_inner(reader))
+        .await
+        .map_err(|_| "Timed out reading request head".to_string())?
+}
+
+async fn read_head_inner<R>(reader: &mut BufReader<R>) -> Result<HttpRequest, String>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut line = String::new();
+    let mut head_bytes = read_line(reader, &mut line).await?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(target)) = (parts.next(), parts.next()) else {
+        return Err("Malformed request line".to_string());
+    };
+    let method = method.to_string();
+    let target = target.to_string();
+
+    let mut headers = Vec::new();
+    loop {
+        line.clear();
+        let read = read_line(reader, &mut line).await?;
+        head_bytes += read;
+        if head_bytes > MAX_HEAD_BYTES {
+            return Err("Request head too large".to_string());
+        }
+        let trimmed = line.trim_end_matches(['\r', '\n']);
+        if read == 0 || trimmed.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = trimmed.split_once(':') {
+            headers.push((name.trim().to_ascii_lowercase(), value.trim().to_string()));
+        }
+    }
+
+    Ok(HttpRequest {
+        method,
+        target,
+        headers,
+        body: Vec::new(),
+    })
+}
+
+/// Read one line of at most `MAX_LINE_BYTES`, returning the bytes read.
+async fn read_line<R>(reader: &mut BufReader<R>, line: &mut String) -> Result<usize, String>
+where
+    R: AsyncRead + Unpin,
+{
+    let read = reader
+        .take(MAX_LINE_BYTES as u64)
+        .read_line(line)
+        .await
+        .map_err(|e| e.to_string())?;
+    if read == MAX_LINE_BYTES && !line.ends_with('\n') {
+        return Err("Request line too long".to_string());
+    }
+    Ok(read)
+}
+
+/// Read the `Content-Length` body of a request returned by [`read_head`].
+pub async fn read_body<R>(
+    reader: &mut BufReader<R>,
+    request: &mut HttpRequest,
+) -> Result<(), String>
+where
+    R: AsyncRead + Unpin,
+{
+    let length = request
+        .header("content-length")
+        .map(|v| v.parse::<usize>().map_err(|_| "Invalid Content-Length"))
+        .transpose()?
+        .unwrap_or(0);
+    if length > MAX_BODY_BYTES {
+        return Err("Request body too large".to_string());
+    }
+    let mut body = vec![0; length];
+    tokio::time::timeout(READ_TIMEOUT, reader.read_exact(&mut body))
+        .await
+        .map_err(|_| "Timed out reading request body".to_string())?
+        .map_err(|e| e.to_string())?;
+    request.body = body;
+    Ok(())
+}
+
+/// Read one request, head and body, from `reader`.
+pub async fn read_request<R>(reader: &mut BufReader<R>) -> Result<HttpRequest, String>
+where
+    R: AsyncRead + Unpin,
+{
+    let mut request = read_head(reader).await?;
+    read_body(reader, &mut request).await?;
+    Ok(request)
+}
+
+fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        413 => "Payload Too Large",
+        429 => "Too Many Requests",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        _ => "",
+    }
+}
+
+/// Write a complete response and close the exchange.
+pub async fn write_response<W>(
+    writer: &mut W,
+    status: u16,
+    content_type: &str,
+    body: &[u8],
+) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    let head = format!(
+        "HTTP/1.1 {status} {}\r\nContent-Type: {content_type}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        reason_phrase(status),
+        body.len()
+    );
+    writer.write_all(head.as_bytes()).await?;
+    writer.write_all(body).await?;
+    writer.flush().await
+}
+
+pub async fn write_json<W>(
+    writer: &mut W,
+    status: u16,
+    value: &serde_json::Value,
+) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    write_response(
+        writer,
+        status,
+        "application/json",
+        value.to_string().as_bytes(),
+    )
+    .await
+}
+
+/// Start a server-sent event stream; the body ends when the connection closes.
+pub async fn start_sse<W>(writer: &mut W) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer
+        .write_all(
+            b"HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nCache-Control: no-cache\r\nConnection: close\r\n\r\n",
+        )
+        .await?;
+    writer.flush().await
+}
+
+/// Write one `data:` event.
+pub async fn write_sse_data<W>(writer: &mut W, data: &str) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer
+        .write_all(format!("data: {data}\n\n").as_bytes())
+        .await?;
+    writer.flush().await
+}
+
+/// Write one named event.
+pub async fn write_sse_event<W>(writer: &mut W, event: &str, data: &str) -> std::io::Result<()>
+where
+    W: AsyncWrite + Unpin,
+{
+    writer
+        .write_all(format!("event: {event}\ndata: {data}\n\n").as_bytes())
+        .await?;
+    writer.flush().await
+}
+
+/// Compare secrets without short-circuiting on the first differing byte.
+pub fn constant_time_eq(a: &str, b: &str) -> bool {
+    a.len() == b.len()
+        && a.bytes()
+            .zip(b.bytes())
+            .fold(0u8, |acc, (x, y)| acc | (x ^ y))
+            == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_read_request() {
+        let raw = b"POST /api/stream?x=a%20b HTTP/1.1\r\nHost: localhost\r\nAuthorization: Bearer secret\r\nContent-Length: 4\r\n\r\nbody";
+        let mut reader = BufReader::new(&raw[..]);
+        let request = read_request(&mut reader).await.unwrap();
+        assert_eq!(request.method, "POST");
+        assert_eq!(request.path(), "/api/stream");
+        assert_eq!(request.query_param("x").as_deref(), Some("a b"));
+        assert_eq!(request.bearer_token(), Some("secret"));
+        assert_eq!(request.body, b"body");
+    }
+
+    #[tokio::test]
+    async fn test_read_head_limits() {
+        let long_line = format!("GET /{} HTTP/1.1\r\n\r\n", "a".repeat(MAX_LINE_BYTES));
+        let mut reader = BufReader::new(long_line.as_bytes());
+        assert!(
+            read_head(&mut reader)
+                .await
+                .unwrap_err()
+                .contains("too long")
+        );
+
+        // The body is left unread until asked for.
+        let raw = b"POST / HTTP/1.1\r\nContent-Length: 999999999\r\n\r\n";
+        let mut reader = BufReader::new(&raw[..]);
+        let mut request = read_head(&mut reader).await.unwrap();
+        assert!(request.body.is_empty());
+        assert!(
+            read_body(&mut reader, &mut request)
+                .await
+                .unwrap_err()
+                .contains("too large")
+        );
+    }
+
+    #[test]
+    fn test_constant_time_eq() {
+        assert!(constant_time_eq("token", "token"));
+        assert!(!constant_time_eq("token", "tokem"));
+        assert!(!constant_time_eq("token", "token2"));
+    }
+}
@@ -0,0 +1,295 @@
+//! Server side of the stream proxy protocol (`pi proxy serve`).
+//!
+//! Accepts `POST /api/stream` with the body sent by
+//! `pi_agent_core::proxy::stream_proxy`, checks the bearer token, resolves
+//! the provider credential from [`AuthStorage`] and streams the response as
+//! delta-only [`ProxyAssistantMessageEvent`]s over SSE. API keys never leave
+//! the server.
+//!
+//! The requested model is looked up in the server's [`ModelRegistry`] by
+//! provider and id; the client's copy (base URL, headers) is ignored so a
+//! client cannot redirect server-held credentials to another host.
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use pi_agent_ai::registry::ApiRegistry;
+use pi_agent_core::proxy::ProxyAssistantMessageEvent;
+use pi_agent_core::types::*;
+use serde::Deserialize;
+use tokio::io::BufReader;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_util::sync::CancellationToken;
+
+use crate::agent_session::sdk::{
+    configure_http_clients, configure_rate_limits, configured_api_keys,
+};
+use crate::auth::storage::AuthStorage;
+use crate::config::paths;
+use crate::error::CodingAgentError;
+use crate::model::discovery;
+use crate::model::registry::{ModelRegistry, add_auth_header};
+use crate::server::http::{self, HttpRequest};
+use crate::settings::manager::SettingsManager;
+
+/// Default listen address for `pi proxy serve`.
+/// 8787 is the OAuth login callback port and 8788 the gateway's.
+pub const DEFAULT_PROXY_ADDR: &str = "127.0.0.1:8789";
+
+/// Request body of `POST /api/stream`.
+#[derive(Debug, Deserialize)]
+struct StreamRequest {
+    model: Model,
+    context: Context,
+    #[serde(default)]
+    options: StreamRequestOptions,
+}
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct StreamRequestOptions {
+    temperature: Option<f64>,
+    max_tokens: Option<u64>,
+    reasoning: Option<ThinkingLevel>,
+}
+
+/// Serves the stream proxy protocol.
+pub struct ProxyServer {
+    registry: Arc<ApiRegistry>,
+    models: Arc<ModelRegistry>,
+    auth_storage: Arc<AuthStorage>,
+    tokens: Vec<String>,
+}
+
+impl ProxyServer {
+    /// `tokens` are the bearer tokens clients may present; it must not be empty.
+    pub fn new(
+        registry: Arc<ApiRegistry>,
+        models: Arc<ModelRegistry>,
+        auth_storage: Arc<AuthStorage>,
+        tokens: Vec<String>,
+    ) -> Self {
+        Self {
+            registry,
+            models,
+            auth_storage,
+            tokens,
+        }
+    }
+
+    /// Server backed by the user's config directory: settings, `models.json`
+    /// and `auth.json`, with the built-in providers.
+    pub fn from_config_dir(
+        base_dir: &std::path::Path,
+        tokens: Vec<String>,
+    ) -> Result<Self, CodingAgentError> {
+        let mut settings_manager = SettingsManager::new(base_dir);
+        settings_manager.load()?;
+        let settings = settings_manager.settings();
+        configure_rate_limits(settings);
+        configure_http_clients(settings);
+
+        let mut models = ModelRegistry::new();
+        models.load_custom_models(&paths::models_file(base_dir))?;
+        if let Some(custom_models) = &settings.custom_models {
+            models.add_custom_models(custom_models);
+        }
+        models.add_discovered_models(discovery::cached_models(base_dir, settings));
+        let auth_storage =
+            AuthStorage::new(base_dir).with_configured_keys(configured_api_keys(settings, &models));
+
+        Ok(Self::new(
+            Arc::new(pi_agent_ai::register::create_default_registry()),
+            Arc::new(models),
+            Arc::new(auth_storage),
+            tokens,
+        ))
+    }
+
+    /// Accept connections until the listener fails.
+    pub async fn serve(self: Arc<Self>, listener: TcpListener) -> std::io::Result<()> {
+        loop {
+            let (socket, peer) = listener.accept().await?;
+            let server = self.clone();
+            tokio::spawn(async move {
+                if let Err(e) = server.handle_connection(socket).await {
+                    tracing::debug!("Proxy connection from {peer} failed: {e}");
+                }
+            });
+        }
+    }
+
+    fn authorized(&self, request: &HttpRequest) -> bool {
+        request.bearer_token().is_some_and(|token| {
+            self.tokens
+                .iter()
+                .any(|allowed| http::constant_time_eq(allowed, token))
+        })
+    }
+
+    async fn handle_connection(&self, socket: TcpStream) -> std::io::Result<()> {
+        let (read_half, mut writer) = socket.into_split();
+        let mut reader = BufReader::new(read_half);
+        let mut request = match http::read_head(&mut reader).await {
+            Ok(request) => request,
+            Err(e) => return http::write_json(&mut writer, 400, &error_body(&e)).await,
+        };
+
+        if request.path() != "/api/stream" {
+            return http::write_json(&mut writer, 404, &error_body("Not found")).await;
+        }
+        if request.method != "POST" {
+            return http::write_json(&mut writer, 405, &error_body("Method not allowed")).await;
+        }
+        if !self.authorized(&request) {
+            return http::write_json(&mut writer, 401, &error_body("Unauthorized")).await;
+        }
+        if let Err(e) = http::read_body(&mut reader, &mut request).await {
+            let status = if e.contains("too large") { 413 } else { 400 };
+            return http::write_json(&mut writer, status, &error_body(&e)).await;
+        }
+        let body: StreamRequest = match serde_json::from_slice(&request.body) {
+            Ok(body) => body,
+            Err(e) => {
+                return http::write_json(&mut writer, 400, &error_body(&e.to_string())).await;
+            }
+        };
+        let Some(model) = self
+            .models
+            .find_by_provider(&body.model.provider, &body.model.id)
+            .cloned()
+        else {
+            let message = format!("Unknown model {}/{}", body.model.provider, body.model.id);
+            return http::write_json(&mut writer, 400, &error_body(&message)).await;
+        };
+        let Some(provider) = self.registry.get(&model.api) else {
+            let message = format!("No API provider registered for api: {}", model.api);
+            return http::write_json(&mut writer, 400, &error_body(&message)).await;
+        };
+
+        let mut options = SimpleStreamOptions {
+            base: StreamOptions {
+                temperature: body.options.temperature,
+                max_tokens: body.options.max_tokens,
+                api_key: self.auth_storage.resolve_api_key(&model.provider).await,
+                ..Default::default()
+            },
+            reasoning: body.options.reasoning,
+            thinking_budgets: None,
+        };
+        if let Some(header) = self.models.auth_header(&model.provider) {
+            add_auth_header(&mut options.base, header);
+        }
+        let cancel = CancellationToken::new();
+        let mut events = provider.stream_simple(&model, &body.context, &options, cancel.clone());
+
+        http::start_sse(&mut writer).await?;
+        while let Some(event) = events.next().await {
+            let data = serde_json::to_string(&ProxyAssistantMessageEvent::from_event(&event))
+                .unwrap_or_default();
+            if let Err(e) = http::write_sse_data(&mut writer, &data).await {
+                // Client went away: stop the upstream request.
+                cancel.cancel();
+                return Err(e);
+            }
+            if event.is_complete() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+fn error_body(message: &str) -> serde_json::Value {
+    serde_json::json!({ "error": message })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pi_agent_ai::http_client::proxy_stream_options;
+    use pi_agent_ai::providers::faux::{
+        FauxProvider, FauxTurn, faux_model, register_faux_provider,
+    };
+    use pi_agent_core::proxy::stream_proxy;
+
+    async fn start(turns: Vec<FauxTurn>) -> String {
+        let mut registry = ApiRegistry::new();
+        register_faux_provider(&mut registry, Arc::new(FauxProvider::new(turns)));
+        let mut models = ModelRegistry::new();
+        models.add_discovered_models(vec![faux_model()]);
+        let dir = tempfile::tempdir().unwrap();
+        let server = Arc::new(ProxyServer::new(
+            Arc::new(registry),
+            Arc::new(models),
+            Arc::new(AuthStorage::new(dir.path())),
+            vec!["secret".to_string()],
+        ));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        tokio::spawn(server.serve(listener));
+        format!("http://{addr}")
+    }
+
+    fn context() -> Context {
+        Context {
+            system_prompt: None,
+            messages: vec![Message::User(UserMessage {
+                content: UserContent::Text("hi".to_string()),
+                timestamp: 0,
+            })],
+            tools: None,
+        }
+    }
+
+    async fn run(proxy_url: String, token: &str) -> AssistantMessage {
+        let stream = stream_proxy(
+            &faux_model(),
+            &context(),
+            proxy_stream_options(SimpleStreamOptions::default(), token.to_string(), proxy_url),
+        );
+        let _: Vec<_> = stream.clone().collect().await;
+        stream.result().await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn test_round_trip_through_stream_proxy() {
+        let url = start(vec![
+            FauxTurn::text("hello").then_tool_call("read", serde_json::json!({"path": "a"})),
+        ])
+        .await;
+        let message = run(url, "secret").await;
+        assert_eq!(message.stop_reason, StopReason::ToolUse);
+        assert_eq!(message.content[0].as_text().unwrap().text, "hello");
+        let ContentBlock::ToolCall(call) = &message.content[1] else {
+            panic!("expected tool call");
+        };
+        assert_eq!(call.arguments["path"], "a");
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_token() {
+        let url = start(vec![]).await;
+        let message = run(url, "wrong").await;
+        assert_eq!(message.stop_reason, StopReason::Error);
+        assert!(message.error_message.unwrap().contains("401"));
+    }
+
+    #[tokio::test]
+    async fn test_rejects_bad_token_before_reading_body() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+        let url = start(vec![]).await;
+        let mut socket = TcpStream::connect(url.trim_start_matches("http://"))
+            .await
+            .unwrap();
+        // The body is never sent; the server must answer from the head alone.
+        socket
+            .write_all(b"POST /api/stream HTTP/1.1\r\nContent-Length: 1000000\r\n\r\n")
+            .await
+            .unwrap();
+        let mut response = String::new();
+        socket.read_to_string(&mut response).await.unwrap();
+        assert!(response.starts_with("HTTP/1.1 401"));
+    }
+}
@@ -210,6 +210,7 @@ fn parse_assistant_message(value: &Value, default_timestamp: i64) -> Option<Assi
         stop_reason,
         error_message,
         timestamp,
+        rate_limit: None,
     })
 }
 
@@ -220,10 +221,12 @@ fn parse_content_blocks(value: &Value) -> Vec<ContentBlock> {
         Value::String(s) => vec![ContentBlock::Text(TextContent {
             text: s.clone(),
             text_signature: None,
+            citations: None,
         })],
         _ => vec![ContentBlock::Text(TextContent {
             text: value.to_string(),
             text_signature: None,
+            citations: None,
         })],
     }
 }
@@ -252,6 +255,7 @@ mod tests {
                     content: vec![ContentBlock::Text(TextContent {
                         text: "Hi there!".to_string(),
                         text_signature: None,
+                        citations: None,
                     })],
                     api: "anthropic-messages".to_string(),
                     provider: "anthropic".to_string(),
@@ -260,6 +264,7 @@ mod tests {
                     stop_reason: StopReason::Stop,
                     error_message: None,
                     timestamp: 1001,
+                    rate_limit: None,
                 }),
             },
         ];
@@ -1,55 +1,44 @@
-use std::io::{BufRead, BufReader, Write};
 use std::path::{Path, PathBuf};
 
-use crate::config::paths;
 use crate::error::CodingAgentError;
+use crate::session::search::{self, SearchHit, SearchQuery};
+use crate::session::store::{JsonlStore, SessionFilter, SessionStore, SessionStoreKind};
 use crate::session::types::*;
 
-/// Create a new file with restrictive permissions on Unix (0600).
-fn create_new_restricted(path: &Path) -> std::io::Result<std::fs::File> {
-    let mut opts = std::fs::OpenOptions::new();
-    opts.write(true).create_new(true);
-
-    #[cfg(unix)]
-    {
-        use std::os::unix::fs::OpenOptionsExt;
-        opts.mode(0o600);
-    }
-
-    opts.open(path)
-}
-
-fn parse_timestamp_value(value: &serde_json::Value) -> Option<i64> {
-    match value {
-        serde_json::Value::Number(n) => n.as_i64(),
-        serde_json::Value::String(s) => {
-            if let Ok(ms) = s.parse::<i64>() {
-                return Some(ms);
-            }
-            chrono::DateTime::parse_from_rfc3339(s)
-                .ok()
-                .map(|dt| dt.timestamp_millis())
-        }
-        _ => None,
-    }
-}
-
-/// Manages session files in JSONL format.
+/// Manages sessions on top of a [`SessionStore`] backend.
 pub struct SessionManager {
     base_dir: PathBuf,
+    store: Box<dyn SessionStore>,
 }
 
 impl SessionManager {
-    /// Create a new SessionManager with given base directory.
+    /// Create a new SessionManager with given base directory, storing
+    /// sessions as JSONL files.
     pub fn new(base_dir: &Path) -> Self {
         Self {
             base_dir: base_dir.to_path_buf(),
+            store: Box::new(JsonlStore::new(&crate::config::paths::sessions_dir(
+                base_dir,
+            ))),
         }
     }
 
-    /// Get the sessions directory.
-    fn sessions_dir(&self) -> PathBuf {
-        paths::sessions_dir(&self.base_dir)
+    /// Create a SessionManager using the given storage backend.
+    pub fn with_store(base_dir: &Path, kind: SessionStoreKind) -> Result<Self, CodingAgentError> {
+        Ok(Self {
+            base_dir: base_dir.to_path_buf(),
+            store: kind.open(base_dir)?,
+        })
+    }
+
+    /// The agent directory sessions belong to.
+    pub fn base_dir(&self) -> &Path {
+        &self.base_dir
+    }
+
+    /// The storage backend in use.
+    pub fn store(&self) -> &dyn SessionStore {
+        self.store.as_ref()
     }
 
     /// Validate that a session ID is safe (no path traversal).
@@ -72,42 +61,33 @@ impl SessionManager {
         Ok(())
     }
 
-    /// Get path for a session file.
-    fn session_path(&self, session_id: &str) -> PathBuf {
-        self.sessions_dir().join(format!("{session_id}.jsonl"))
-    }
-
     /// Create a new session, writing the header to disk.
     pub fn create(
         &self,
         session_id: &str,
         title: Option<&str>,
     ) -> Result<SessionHeader, CodingAgentError> {
-        Self::validate_session_id(session_id)?;
-        let dir = self.sessions_dir();
-        paths::ensure_dir(&dir)?;
+        self.create_in(session_id, title, "")
+    }
 
+    /// Create a new session started in working directory `cwd`.
+    pub fn create_in(
+        &self,
+        session_id: &str,
+        title: Option<&str>,
+        cwd: &str,
+    ) -> Result<SessionHeader, CodingAgentError> {
+        Self::validate_session_id(session_id)?;
         let header = SessionHeader {
             entry_type: "session".to_string(),
             version: Some(CURRENT_SESSION_VERSION),
             id: session_id.to_string(),
             timestamp: now_iso_timestamp(),
-            cwd: String::new(),
+            cwd: cwd.to_string(),
             parent_session: None,
             title: title.map(ToString::to_string),
         };
-
-        let path = self.session_path(session_id);
-        let mut file = create_new_restricted(&path).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::AlreadyExists {
-                CodingAgentError::Session(format!("Session already exists: {session_id}"))
-            } else {
-                CodingAgentError::Io(e)
-            }
-        })?;
-        let line = serde_json::to_string(&header)?;
-        writeln!(file, "{line}")?;
-
+        self.store.create(&header, &[])?;
         Ok(header)
     }
 
@@ -117,42 +97,7 @@ impl SessionManager {
         session_id: &str,
     ) -> Result<(SessionHeader, Vec<SessionEntry>), CodingAgentError> {
         Self::validate_session_id(session_id)?;
-        let path = self.session_path(session_id);
-        if !path.exists() {
-            return Err(CodingAgentError::Session(format!(
-                "Session not found: {session_id}"
-            )));
-        }
-
-        let file = std::fs::File::open(&path)?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
-
-        let header_line = lines
-            .next()
-            .ok_or_else(|| CodingAgentError::Session("Empty session file".to_string()))??;
-        let header: SessionHeader = serde_json::from_str(&header_line)?;
-
-        if header.entry_type != "session" {
-            return Err(CodingAgentError::Session(format!(
-                "Invalid session header in {}",
-                path.display()
-            )));
-        }
-
-        let mut entries = Vec::new();
-        for line in lines {
-            let line = line?;
-            if line.trim().is_empty() {
-                continue;
-            }
-            match serde_json::from_str::<SessionEntry>(&line) {
-                Ok(entry) => entries.push(entry),
-                Err(e) => tracing::warn!("Skipping malformed session entry: {e}"),
-            }
-        }
-
-        Ok((header, entries))
+        self.store.load(session_id)
     }
 
     /// Append an entry to an existing session file.
@@ -161,19 +106,7 @@ impl SessionManager {
         session_id: &str,
         entry: &SessionEntry,
     ) -> Result<(), CodingAgentError> {
-        Self::validate_session_id(session_id)?;
-        let path = self.session_path(session_id);
-        if !path.exists() {
-            return Err(CodingAgentError::Session(format!(
-                "Session not found: {session_id}"
-            )));
-        }
-
-        let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
-        let line = serde_json::to_string(entry)?;
-        writeln!(file, "{line}")?;
-
-        Ok(())
+        self.append_entries(session_id, std::slice::from_ref(entry))
     }
 
     /// Append multiple entries.
@@ -183,28 +116,27 @@ impl SessionManager {
         entries: &[SessionEntry],
     ) -> Result<(), CodingAgentError> {
         Self::validate_session_id(session_id)?;
-        let path = self.session_path(session_id);
-        if !path.exists() {
-            return Err(CodingAgentError::Session(format!(
-                "Session not found: {session_id}"
-            )));
-        }
-
-        let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
-        for entry in entries {
-            let line = serde_json::to_string(entry)?;
-            writeln!(file, "{line}")?;
-        }
-
-        Ok(())
+        self.store.append(session_id, entries)
     }
 
     /// Continue the most recent session.
     pub fn continue_recent(
         &self,
     ) -> Result<Option<(SessionHeader, Vec<SessionEntry>)>, CodingAgentError> {
-        let sessions = self.list()?;
-        if let Some(info) = sessions.first() {
+        self.continue_recent_in(None)
+    }
+
+    /// Continue the most recent session of `project`, or of any project.
+    pub fn continue_recent_in(
+        &self,
+        project: Option<&str>,
+    ) -> Result<Option<(SessionHeader, Vec<SessionEntry>)>, CodingAgentError> {
+        let recent = self.store.list(&SessionFilter {
+            project: project.map(str::to_string),
+            limit: Some(1),
+            ..SessionFilter::default()
+        })?;
+        if let Some(info) = recent.first() {
             Ok(Some(self.open(&info.session_id)?))
         } else {
             Ok(None)
@@ -236,9 +168,6 @@ impl SessionManager {
             }
         }
 
-        let dir = self.sessions_dir();
-        paths::ensure_dir(&dir)?;
-
         let header = SessionHeader {
             entry_type: "session".to_string(),
             version: Some(CURRENT_SESSION_VERSION),
@@ -249,136 +178,62 @@ impl SessionManager {
             title: source_header.title,
         };
 
-        let path = self.session_path(new_session_id);
-        let mut file = create_new_restricted(&path).map_err(|e| {
-            if e.kind() == std::io::ErrorKind::AlreadyExists {
-                CodingAgentError::Session(format!(
-                    "Fork target session already exists: {new_session_id}"
-                ))
-            } else {
-                CodingAgentError::Io(e)
-            }
-        })?;
-
-        let header_line = serde_json::to_string(&header)?;
-        writeln!(file, "{header_line}")?;
-
-        for entry in &forked_entries {
-            let line = serde_json::to_string(entry)?;
-            writeln!(file, "{line}")?;
-        }
-
-        let fork_entry = SessionEntry::LegacyFork {
+        forked_entries.push(SessionEntry::LegacyFork {
             id: SessionEntry::new_id(),
             parent_id: Some(source_entry_id.to_string()),
             timestamp: chrono::Utc::now().timestamp_millis(),
             source_session_id: source_session_id.to_string(),
             source_entry_id: source_entry_id.to_string(),
-        };
+        });
 
-        let fork_line = serde_json::to_string(&fork_entry)?;
-        writeln!(file, "{fork_line}")?;
-        forked_entries.push(fork_entry);
+        if self.store.exists(new_session_id) {
+            return Err(CodingAgentError::Session(format!(
+                "Fork target session already exists: {new_session_id}"
+            )));
+        }
+        self.store.create(&header, &forked_entries)?;
 
         Ok((header, forked_entries))
     }
 
     /// List all sessions sorted by updated_at descending.
     pub fn list(&self) -> Result<Vec<SessionInfo>, CodingAgentError> {
-        let dir = self.sessions_dir();
-        if !dir.exists() {
-            return Ok(Vec::new());
-        }
-
-        let mut sessions = Vec::new();
-        for entry in std::fs::read_dir(&dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().is_some_and(|ext| ext == "jsonl") {
-                if let Some(info) = self.read_session_info(&path) {
-                    sessions.push(info);
-                }
-            }
-        }
+        self.store.list(&SessionFilter::default())
+    }
 
-        sessions.sort_by(|a, b| b.updated_at.cmp(&a.updated_at));
-        Ok(sessions)
+    /// List sessions matching `filter`, sorted by updated_at descending.
+    pub fn list_filtered(
+        &self,
+        filter: &SessionFilter,
+    ) -> Result<Vec<SessionInfo>, CodingAgentError> {
+        self.store.list(filter)
     }
 
     /// List all session IDs.
     pub fn list_all(&self) -> Result<Vec<String>, CodingAgentError> {
-        let dir = self.sessions_dir();
-        if !dir.exists() {
-            return Ok(Vec::new());
-        }
-
-        let mut ids = Vec::new();
-        for entry in std::fs::read_dir(&dir)? {
-            let entry = entry?;
-            let path = entry.path();
-            if path.extension().is_some_and(|ext| ext == "jsonl") {
-                if let Some(stem) = path.file_stem() {
-                    ids.push(stem.to_string_lossy().to_string());
-                }
-            }
-        }
+        self.store.ids()
+    }
 
-        Ok(ids)
+    /// Search saved sessions, updating the on-disk search index first.
+    pub fn search(&self, query: &SearchQuery) -> Result<Vec<SearchHit>, CodingAgentError> {
+        search::search_sessions(&self.base_dir, self.store(), query)
     }
 
     /// Check if a session exists.
     pub fn exists(&self, session_id: &str) -> bool {
-        Self::validate_session_id(session_id).is_ok() && self.session_path(session_id).exists()
+        Self::validate_session_id(session_id).is_ok() && self.store.exists(session_id)
     }
 
-    /// Delete a session file.
+    /// Delete a session and its spill and attachment files.
     pub fn delete(&self, session_id: &str) -> Result<(), CodingAgentError> {
         Self::validate_session_id(session_id)?;
-        let path = self.session_path(session_id);
-        if path.exists() {
-            std::fs::remove_file(&path)?;
+        self.store.delete(session_id)?;
+        let files = crate::config::paths::session_files_dir(&self.base_dir).join(session_id);
+        if files.exists() {
+            std::fs::remove_dir_all(&files)?;
         }
         Ok(())
     }
-
-    /// Read lightweight session info from file.
-    fn read_session_info(&self, path: &Path) -> Option<SessionInfo> {
-        let file = std::fs::File::open(path).ok()?;
-        let reader = BufReader::new(file);
-        let mut lines = reader.lines();
-
-        let header_line = lines.next()?.ok()?;
-        let header: SessionHeader = serde_json::from_str(&header_line).ok()?;
-        if header.entry_type != "session" {
-            return None;
-        }
-
-        let mut entry_count = 0usize;
-        let mut last_timestamp = header.timestamp_ms();
-
-        for line in lines.map_while(Result::ok) {
-            if line.trim().is_empty() {
-                continue;
-            }
-
-            entry_count += 1;
-            if let Ok(val) = serde_json::from_str::<serde_json::Value>(&line) {
-                if let Some(ts) = val.get("timestamp").and_then(parse_timestamp_value) {
-                    last_timestamp = last_timestamp.max(ts);
-                }
-            }
-        }
-
-        let created_at = header.timestamp_ms();
-        Some(SessionInfo {
-            session_id: header.id,
-            title: header.title,
-            created_at,
-            updated_at: last_timestamp,
-            entry_count,
-            parent_session_id: header.parent_session,
-        })
-    }
 }
 
 #[cfg(test)]
@@ -394,6 +249,7 @@ mod tests {
             content: vec![ContentBlock::Text(TextContent {
                 text: "Hello!".to_string(),
                 text_signature: None,
+                citations: None,
             })],
             api: "anthropic-messages".to_string(),
             provider: "anthropic".to_string(),
@@ -402,6 +258,7 @@ mod tests {
             stop_reason: StopReason::Stop,
             error_message: None,
             timestamp: chrono::Utc::now().timestamp_millis(),
+            rate_limit: None,
         })
     }
 
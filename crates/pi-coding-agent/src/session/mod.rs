@@ -1,4 +1,9 @@
 pub mod context;
 pub mod manager;
+pub mod project;
+pub mod retention;
+pub mod search;
+pub mod store;
 pub mod tree;
 pub mod types;
+pub mod usage;
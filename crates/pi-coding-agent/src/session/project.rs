@@ -0,0 +1,63 @@
+//! Grouping sessions by the project they were started in.
+//!
+//! A session's project is the nearest ancestor of its working directory
+//! that contains a `.git` entry, or the working directory itself outside a
+//! repository. JSONL sessions are stored in one subdirectory per project.
+
+use std::path::{Path, PathBuf};
+
+/// The project root for working directory `cwd`.
+pub fn project_root(cwd: &Path) -> PathBuf {
+    cwd.ancestors()
+        .find(|dir| dir.join(".git").exists())
+        .unwrap_or(cwd)
+        .to_path_buf()
+}
+
+/// The project root for `cwd` as a string, as stored in session filters.
+pub fn project_of(cwd: &str) -> String {
+    project_root(Path::new(cwd)).display().to_string()
+}
+
+/// Directory name for a project's sessions, e.g. `--home-me-code-pi--`.
+pub fn project_dir_name(project: &str) -> String {
+    let encoded: String = project
+        .trim_matches(|c| c == '/' || c == '\\')
+        .chars()
+        .map(|c| match c {
+            '/' | '\\' | ':' => '-',
+            c if c.is_alphanumeric() || matches!(c, '-' | '_' | '.') => c,
+            _ => '_',
+        })
+        .collect();
+    format!("--{encoded}--")
+}
+
+/// Whether working directory `cwd` is `project` or inside it.
+pub fn in_project(cwd: &str, project: &str) -> bool {
+    let project = project.trim_end_matches(['/', '\\']);
+    cwd == project
+        || cwd
+            .strip_prefix(project)
+            .is_some_and(|rest| rest.starts_with(['/', '\\']))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_root_and_dir_name() {
+        let tmp = tempfile::tempdir().unwrap();
+        let repo = tmp.path().join("repo");
+        std::fs::create_dir_all(repo.join(".git")).unwrap();
+        std::fs::create_dir_all(repo.join("crates/a")).unwrap();
+        assert_eq!(project_root(&repo.join("crates/a")), repo);
+        assert_eq!(project_root(tmp.path()), tmp.path());
+
+        assert_eq!(project_dir_name("/home/me/code/pi"), "--home-me-code-pi--");
+        assert_eq!(project_dir_name(r"C:\work\site"), "--C--work-site--");
+        assert!(in_project("/work/pi/src", "/work/pi/"));
+        assert!(!in_project("/work/pipeline", "/work/pi"));
+    }
+}
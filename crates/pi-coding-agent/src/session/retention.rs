@@ -0,0 +1,404 @@
+//! Session retention: pinning, pruning old sessions and removing files left
+//! behind by deleted sessions.
+
+use std::collections::{BTreeSet, HashSet};
+use std::path::{Path, PathBuf};
+
+use serde::Serialize;
+
+use crate::config::paths;
+use crate::error::CodingAgentError;
+use crate::session::manager::SessionManager;
+use crate::session::search::SearchIndex;
+use crate::session::types::SessionInfo;
+use crate::settings::types::RetentionSettings;
+
+const DAY_MS: i64 = 86_400_000;
+
+/// Sessions exempt from retention, stored in `pinned-sessions.json`.
+#[derive(Debug, Default)]
+pub struct PinnedSessions {
+    path: PathBuf,
+    ids: BTreeSet<String>,
+}
+
+impl PinnedSessions {
+    pub fn load(base_dir: &Path) -> Self {
+        let path = paths::pinned_sessions_file(base_dir);
+        let ids = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+        Self { path, ids }
+    }
+
+    pub fn save(&self) -> Result<(), CodingAgentError> {
+        if let Some(parent) = self.path.parent() {
+            paths::ensure_dir(parent)?;
+        }
+        std::fs::write(&self.path, serde_json::to_string_pretty(&self.ids)?)?;
+        Ok(())
+    }
+
+    pub fn contains(&self, session_id: &str) -> bool {
+        self.ids.contains(session_id)
+    }
+
+    /// Returns false if the session was already pinned.
+    pub fn pin(&mut self, session_id: &str) -> bool {
+        self.ids.insert(session_id.to_string())
+    }
+
+    /// Returns false if the session was not pinned.
+    pub fn unpin(&mut self, session_id: &str) -> bool {
+        self.ids.remove(session_id)
+    }
+
+    pub fn ids(&self) -> impl Iterator<Item = &str> {
+        self.ids.iter().map(String::as_str)
+    }
+}
+
+/// Sessions to delete under `policy`, oldest first.
+///
+/// `sessions` must be sorted by `updated_at` descending, as returned by
+/// [`SessionManager::list`]. Pinned and `protected` sessions are never
+/// chosen, but still count toward the total size.
+pub fn plan_prune(
+    sessions: &[SessionInfo],
+    policy: &RetentionSettings,
+    pinned: &PinnedSessions,
+    protected: &[&str],
+    now_ms: i64,
+) -> Vec<String> {
+    let deletable = |info: &SessionInfo| {
+        !pinned.contains(&info.session_id) && !protected.contains(&info.session_id.as_str())
+    };
+    let mut delete = vec![false; sessions.len()];
+
+    if let Some(days) = policy.max_age_days {
+        let cutoff = now_ms - days as i64 * DAY_MS;
+        for (i, info) in sessions.iter().enumerate() {
+            delete[i] = deletable(info) && info.updated_at < cutoff;
+        }
+    }
+
+    if let Some(max_count) = policy.max_count {
+        let mut kept = 0;
+        for (i, info) in sessions.iter().enumerate() {
+            if delete[i] || pinned.contains(&info.session_id) {
+                continue;
+            }
+            kept += 1;
+            if kept > max_count && deletable(info) {
+                delete[i] = true;
+            }
+        }
+    }
+
+    if let Some(max_mb) = policy.max_total_mb {
+        let limit = max_mb.saturating_mul(1024 * 1024);
+        let mut total: u64 = sessions
+            .iter()
+            .zip(&delete)
+            .filter(|(_, deleted)| !**deleted)
+            .map(|(info, _)| info.size_bytes)
+            .sum();
+        for (i, info) in sessions.iter().enumerate().rev() {
+            if total <= limit {
+                break;
+            }
+            if !delete[i] && deletable(info) {
+                delete[i] = true;
+                total -= info.size_bytes;
+            }
+        }
+    }
+
+    sessions
+        .iter()
+        .zip(delete)
+        .rev()
+        .filter(|(_, deleted)| *deleted)
+        .map(|(info, _)| info.session_id.clone())
+        .collect()
+}
+
+/// Result of [`prune`].
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PruneReport {
+    pub deleted: Vec<String>,
+    pub freed_bytes: u64,
+    pub remaining: usize,
+}
+
+/// Delete the sessions `policy` does not retain, except `protected` ones.
+/// With `dry_run`, only reports what would be deleted.
+pub fn prune(
+    manager: &SessionManager,
+    policy: &RetentionSettings,
+    protected: &[&str],
+    dry_run: bool,
+) -> Result<PruneReport, CodingAgentError> {
+    let sessions = manager.list()?;
+    let now = chrono::Utc::now().timestamp_millis();
+    prune_listed(manager, &sessions, policy, protected, dry_run, now)
+}
+
+/// Sessions updated this recently may be open in another process and are
+/// left alone by [`auto_prune`].
+pub const AUTO_PRUNE_IDLE_MS: i64 = 10 * 60 * 1000;
+
+/// The pruning run at startup. Other processes may have sessions open, so
+/// only `max_age_days` is applied and sessions updated within
+/// [`AUTO_PRUNE_IDLE_MS`] are kept; count and size limits are left to
+/// `pi sessions prune`.
+pub fn auto_prune(
+    manager: &SessionManager,
+    policy: &RetentionSettings,
+    protected: &[&str],
+) -> Result<PruneReport, CodingAgentError> {
+    if policy.max_age_days.is_none() {
+        return Ok(PruneReport::default());
+    }
+    let age_only = RetentionSettings {
+        max_age_days: policy.max_age_days,
+        ..RetentionSettings::default()
+    };
+    let sessions = manager.list()?;
+    let now = chrono::Utc::now().timestamp_millis();
+    let protected: Vec<&str> = sessions
+        .iter()
+        .filter(|info| now - info.updated_at < AUTO_PRUNE_IDLE_MS)
+        .map(|info| info.session_id.as_str())
+        .chain(protected.iter().copied())
+        .collect();
+    prune_listed(manager, &sessions, &age_only, &protected, false, now)
+}
+
+fn prune_listed(
+    manager: &SessionManager,
+    sessions: &[SessionInfo],
+    policy: &RetentionSettings,
+    protected: &[&str],
+    dry_run: bool,
+    now: i64,
+) -> Result<PruneReport, CodingAgentError> {
+    let pinned = PinnedSessions::load(manager.base_dir());
+    let deleted = plan_prune(sessions, policy, &pinned, protected, now);
+
+    let deleted_set: HashSet<&str> = deleted.iter().map(String::as_str).collect();
+    let freed_bytes = sessions
+        .iter()
+        .filter(|info| deleted_set.contains(info.session_id.as_str()))
+        .map(|info| info.size_bytes)
+        .sum();
+    if !dry_run {
+        for session_id in &deleted {
+            manager.delete(session_id)?;
+        }
+    }
+    Ok(PruneReport {
+        remaining: sessions.len() - deleted.len(),
+        deleted,
+        freed_bytes,
+    })
+}
+
+/// Result of [`gc`].
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GcReport {
+    /// Session file directories whose session no longer exists.
+    pub removed_file_dirs: Vec<String>,
+    pub freed_bytes: u64,
+    /// Pins of sessions that no longer exist.
+    pub removed_pins: usize,
+}
+
+fn dir_size(path: &Path) -> u64 {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(Result::ok)
+        .filter_map(|entry| entry.metadata().ok())
+        .filter(|metadata| metadata.is_file())
+        .map(|metadata| metadata.len())
+        .sum()
+}
+
+/// Remove spill and attachment directories, pins and search index entries
+/// left behind by deleted sessions.
+pub fn gc(manager: &SessionManager, dry_run: bool) -> Result<GcReport, CodingAgentError> {
+    let base_dir = manager.base_dir();
+    let ids: HashSet<String> = manager.list_all()?.into_iter().collect();
+    let mut report = GcReport::default();
+
+    let files_dir = paths::session_files_dir(base_dir);
+    if files_dir.is_dir() {
+        for entry in std::fs::read_dir(&files_dir)? {
+            let entry = entry?;
+            let name = entry.file_name().to_string_lossy().to_string();
+            if ids.contains(&name) {
+                continue;
+            }
+            let path = entry.path();
+            report.freed_bytes += dir_size(&path);
+            if !dry_run {
+                // A symlink is removed itself, never its target.
+                if entry.file_type()?.is_dir() {
+                    std::fs::remove_dir_all(&path)?;
+                } else {
+                    std::fs::remove_file(&path)?;
+                }
+            }
+            report.removed_file_dirs.push(name);
+        }
+    }
+
+    let mut pinned = PinnedSessions::load(base_dir);
+    let stale: Vec<String> = pinned
+        .ids()
+        .filter(|id| !ids.contains(*id))
+        .map(str::to_string)
+        .collect();
+    report.removed_pins = stale.len();
+    if !dry_run {
+        if !stale.is_empty() {
+            for id in &stale {
+                pinned.unpin(id);
+            }
+            pinned.save()?;
+        }
+        SearchIndex::load(base_dir).update(base_dir, manager.store())?;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(id: &str, days_old: i64, size_bytes: u64) -> SessionInfo {
+        let updated_at = 100 * DAY_MS - days_old * DAY_MS;
+        SessionInfo {
+            session_id: id.to_string(),
+            title: None,
+            summary: None,
+            cwd: String::new(),
+            created_at: updated_at,
+            updated_at,
+            entry_count: 1,
+            size_bytes,
+            parent_session_id: None,
+        }
+    }
+
+    #[test]
+    fn test_plan_prune_respects_pins_and_protection() {
+        let sessions = vec![
+            info("new", 0, 400),
+            info("mid", 10, 400),
+            info("old", 40, 400),
+            info("ancient", 90, 400),
+        ];
+        let mut pinned = PinnedSessions::default();
+        pinned.pin("ancient");
+        let now = 100 * DAY_MS;
+
+        let by_age = RetentionSettings {
+            max_age_days: Some(30),
+            ..RetentionSettings::default()
+        };
+        assert_eq!(plan_prune(&sessions, &by_age, &pinned, &[], now), ["old"]);
+        assert!(plan_prune(&sessions, &by_age, &pinned, &["old"], now).is_empty());
+
+        let by_count = RetentionSettings {
+            max_count: Some(1),
+            ..RetentionSettings::default()
+        };
+        assert_eq!(
+            plan_prune(&sessions, &by_count, &pinned, &[], now),
+            ["old", "mid"]
+        );
+
+        // 1600 bytes total against a 0 MB limit: everything unpinned goes.
+        let by_size = RetentionSettings {
+            max_total_mb: Some(0),
+            ..RetentionSettings::default()
+        };
+        assert_eq!(
+            plan_prune(&sessions, &by_size, &pinned, &["new"], now),
+            ["old", "mid"]
+        );
+    }
+
+    #[test]
+    fn test_auto_prune_skips_recent_sessions_and_other_limits() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new(tmp.path());
+        manager.create_in("active", None, "/work").unwrap();
+
+        let policy = RetentionSettings {
+            max_age_days: Some(0),
+            max_count: Some(0),
+            max_total_mb: Some(0),
+        };
+        assert!(
+            auto_prune(&manager, &policy, &[])
+                .unwrap()
+                .deleted
+                .is_empty()
+        );
+        assert!(manager.exists("active"));
+        assert_eq!(
+            prune(&manager, &policy, &[], true).unwrap().deleted,
+            ["active"]
+        );
+    }
+
+    #[test]
+    fn test_prune_and_gc_remove_sessions_and_orphans() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new(tmp.path());
+        manager.create_in("keep", None, "/work").unwrap();
+        manager.create_in("drop", None, "/work").unwrap();
+        for id in ["keep", "drop", "gone"] {
+            let dir = paths::session_files_dir(tmp.path()).join(id);
+            std::fs::create_dir_all(&dir).unwrap();
+            std::fs::write(dir.join("output.txt"), "spilled").unwrap();
+        }
+        let mut pinned = PinnedSessions::load(tmp.path());
+        pinned.pin("keep");
+        pinned.pin("gone");
+        pinned.save().unwrap();
+
+        let policy = RetentionSettings {
+            max_count: Some(0),
+            ..RetentionSettings::default()
+        };
+        let dry = prune(&manager, &policy, &[], true).unwrap();
+        assert_eq!(dry.deleted, ["drop"]);
+        assert!(manager.exists("drop"));
+
+        prune(&manager, &policy, &[], false).unwrap();
+        assert!(!manager.exists("drop"));
+        assert!(manager.exists("keep"));
+        assert!(!paths::session_files_dir(tmp.path()).join("drop").exists());
+
+        // Only the session files directory is scanned.
+        std::fs::create_dir_all(tmp.path().join("gone")).unwrap();
+        let dry = gc(&manager, true).unwrap();
+        assert_eq!(dry.removed_file_dirs, ["gone"]);
+        assert_eq!(dry.freed_bytes, 7);
+        assert!(paths::session_files_dir(tmp.path()).join("gone").exists());
+
+        let report = gc(&manager, false).unwrap();
+        assert_eq!(report.removed_file_dirs, ["gone"]);
+        assert!(!paths::session_files_dir(tmp.path()).join("gone").exists());
+        assert!(tmp.path().join("gone").exists());
+        assert_eq!(report.removed_pins, 1);
+        assert!(paths::session_files_dir(tmp.path()).join("keep").exists());
+        assert!(PinnedSessions::load(tmp.path()).contains("keep"));
+    }
+}
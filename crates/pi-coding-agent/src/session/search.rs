@@ -0,0 +1,785 @@
+//! Full-text search across saved sessions.
+//!
+//! The index lives in `session-index.json` next to the sessions directory
+//! and stores the searchable text of every session: user and assistant
+//! text, summaries, tool names, file paths passed to tools, the models used
+//! and the session's working directory. Session files are append-only, so
+//! [`SearchIndex::update`] only parses the bytes added since the last run
+//! and re-reads a file from scratch when it shrank or was rewritten.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::io::{BufRead, BufReader, Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+
+use pi_agent_core::types::{AssistantMessage, ContentBlock, Message, UserContent};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::config::paths;
+use crate::error::CodingAgentError;
+use crate::session::project::in_project;
+use crate::session::store::{JsonlStore, SessionFilter, SessionStore, SessionStoreKind};
+use crate::session::types::{SessionEntry, SessionHeader, SessionInfo};
+
+/// Bumped when the indexed representation changes; older indexes are rebuilt.
+const INDEX_VERSION: u32 = 1;
+
+/// Tool argument keys holding file paths.
+const PATH_ARGUMENTS: &[&str] = &["path", "file_path", "filePath", "paths", "files"];
+
+/// Characters of context on each side of a match in snippets.
+const SNIPPET_CONTEXT: usize = 60;
+
+/// What an indexed document was extracted from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DocKind {
+    User,
+    Assistant,
+    Summary,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedDoc {
+    entry_id: String,
+    timestamp: i64,
+    kind: DocKind,
+    text: String,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IndexedSession {
+    /// Bytes of the session file already indexed (JSONL store).
+    indexed_len: u64,
+    /// Entries already indexed.
+    #[serde(default)]
+    indexed_entries: usize,
+    /// File modification time for JSONL, last update time otherwise.
+    modified_ms: i64,
+    title: Option<String>,
+    cwd: String,
+    created_at: i64,
+    updated_at: i64,
+    models: BTreeSet<String>,
+    tools: BTreeSet<String>,
+    paths: BTreeSet<String>,
+    docs: Vec<IndexedDoc>,
+}
+
+impl IndexedSession {
+    fn set_header(&mut self, header: &SessionHeader) {
+        self.title = header.title.clone();
+        self.cwd = header.cwd.clone();
+        self.created_at = header.timestamp_ms();
+        self.updated_at = self.created_at;
+    }
+
+    fn add_entry(&mut self, entry: &SessionEntry) {
+        self.indexed_entries += 1;
+        let timestamp = entry.timestamp();
+        self.updated_at = self.updated_at.max(timestamp);
+        let mut push = |kind: DocKind, text: String| {
+            if !text.trim().is_empty() {
+                self.docs.push(IndexedDoc {
+                    entry_id: entry.id().to_string(),
+                    timestamp,
+                    kind,
+                    text,
+                });
+            }
+        };
+
+        match entry {
+            SessionEntry::Message { message, .. } => match message {
+                Message::User(user) => push(DocKind::User, user_text(&user.content)),
+                Message::Assistant(assistant) => self.add_assistant(entry.id(), assistant),
+                Message::ToolResult(result) => {
+                    self.tools.insert(result.tool_name.clone());
+                }
+            },
+            SessionEntry::LegacyUser { content, .. } => push(DocKind::User, content.clone()),
+            SessionEntry::LegacyAssistant { message, .. } => {
+                if let Ok(assistant) = serde_json::from_value::<AssistantMessage>(message.clone()) {
+                    self.add_assistant(entry.id(), &assistant);
+                }
+            }
+            SessionEntry::Compaction { summary, .. }
+            | SessionEntry::BranchSummary { summary, .. }
+            | SessionEntry::LegacySummary { summary, .. } => {
+                push(DocKind::Summary, summary.clone())
+            }
+            SessionEntry::LegacyToolUse {
+                tool_name,
+                arguments,
+                ..
+            } => {
+                self.tools.insert(tool_name.clone());
+                collect_paths(arguments, &mut self.paths);
+            }
+            SessionEntry::ModelChange {
+                provider, model_id, ..
+            } => {
+                self.models.insert(format!("{provider}/{model_id}"));
+            }
+            SessionEntry::SessionInfo { name, summary, .. } => {
+                if let Some(name) = name {
+                    self.title = Some(name.clone());
+                }
+                if let Some(summary) = summary {
+                    push(DocKind::Summary, summary.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn add_assistant(&mut self, entry_id: &str, message: &AssistantMessage) {
+        if !message.model.is_empty() {
+            self.models
+                .insert(format!("{}/{}", message.provider, message.model));
+        }
+        let mut text = Vec::new();
+        for block in &message.content {
+            match block {
+                ContentBlock::Text(t) => text.push(t.text.as_str()),
+                ContentBlock::ToolCall(call) => {
+                    self.tools.insert(call.name.clone());
+                    collect_paths(&call.arguments, &mut self.paths);
+                }
+                _ => {}
+            }
+        }
+        let text = text.join("\n");
+        if !text.trim().is_empty() {
+            self.docs.push(IndexedDoc {
+                entry_id: entry_id.to_string(),
+                timestamp: message.timestamp,
+                kind: DocKind::Assistant,
+                text,
+            });
+        }
+    }
+}
+
+fn user_text(content: &UserContent) -> String {
+    match content {
+        UserContent::Text(text) => text.clone(),
+        UserContent::Blocks(blocks) => blocks
+            .iter()
+            .filter_map(|block| match block {
+                ContentBlock::Text(t) => Some(t.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+    }
+}
+
+fn collect_paths(arguments: &Value, out: &mut BTreeSet<String>) {
+    for key in PATH_ARGUMENTS {
+        match arguments.get(key) {
+            Some(Value::String(path)) => {
+                out.insert(path.clone());
+            }
+            Some(Value::Array(items)) => {
+                out.extend(items.iter().filter_map(Value::as_str).map(str::to_string));
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Filters and options for [`SearchIndex::search`].
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchQuery {
+    #[serde(default, alias = "query")]
+    pub text: String,
+    /// Only sessions active at or after this Unix ms timestamp.
+    pub since: Option<i64>,
+    /// Only sessions started at or before this Unix ms timestamp.
+    pub until: Option<i64>,
+    /// Only sessions whose working directory is this path or below it.
+    pub project: Option<String>,
+    /// Only sessions that used a model containing this string.
+    pub model: Option<String>,
+    /// Maximum results; defaults to 20.
+    pub limit: Option<usize>,
+}
+
+/// A ranked search result.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SearchHit {
+    pub session_id: String,
+    pub title: Option<String>,
+    pub cwd: String,
+    pub created_at: i64,
+    pub updated_at: i64,
+    pub score: f64,
+    /// Entry whose text best matched, if the match was in text.
+    pub entry_id: Option<String>,
+    pub snippet: String,
+    pub models: Vec<String>,
+}
+
+/// On-disk search index over all sessions.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SearchIndex {
+    version: u32,
+    sessions: BTreeMap<String, IndexedSession>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl SearchIndex {
+    /// Load the index for the sessions under `base_dir`, or start empty.
+    pub fn load(base_dir: &Path) -> Self {
+        let path = paths::session_index_file(base_dir);
+        let index = std::fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str::<SearchIndex>(&content).ok())
+            .filter(|index| index.version == INDEX_VERSION)
+            .unwrap_or_default();
+        Self {
+            version: INDEX_VERSION,
+            path,
+            ..index
+        }
+    }
+
+    /// Index new and changed sessions in `store`, drop deleted ones, and
+    /// save the index if anything changed.
+    pub fn update(
+        &mut self,
+        base_dir: &Path,
+        store: &dyn SessionStore,
+    ) -> Result<(), CodingAgentError> {
+        let mut seen = BTreeSet::new();
+        let mut changed = false;
+
+        match store.kind() {
+            SessionStoreKind::Jsonl => {
+                for (id, path) in JsonlStore::new(&paths::sessions_dir(base_dir)).session_files()? {
+                    seen.insert(id.clone());
+                    match self.index_file(&id, &path) {
+                        Ok(updated) => changed |= updated,
+                        Err(e) => tracing::warn!("Failed to index session {id}: {e}"),
+                    }
+                }
+            }
+            _ => {
+                for info in store.list(&SessionFilter::default())? {
+                    seen.insert(info.session_id.clone());
+                    match self.index_stored(store, &info) {
+                        Ok(updated) => changed |= updated,
+                        Err(e) => {
+                            tracing::warn!("Failed to index session {}: {e}", info.session_id)
+                        }
+                    }
+                }
+            }
+        }
+
+        let before = self.sessions.len();
+        self.sessions.retain(|id, _| seen.contains(id));
+        changed |= self.sessions.len() != before;
+
+        if changed {
+            self.save()?;
+        }
+        Ok(())
+    }
+
+    /// Index one session loaded through a store; returns whether the index
+    /// changed. Sessions only grow, so entries past the indexed count are new.
+    fn index_stored(
+        &mut self,
+        store: &dyn SessionStore,
+        info: &SessionInfo,
+    ) -> Result<bool, CodingAgentError> {
+        let existing = self.sessions.get(&info.session_id);
+        if let Some(session) = existing
+            && session.indexed_entries == info.entry_count
+            && session.modified_ms == info.updated_at
+        {
+            return Ok(false);
+        }
+
+        let (header, entries) = store.load(&info.session_id)?;
+        let mut session = match existing {
+            Some(session)
+                if session.indexed_entries > 0 && session.indexed_entries < entries.len() =>
+            {
+                session.clone()
+            }
+            _ => {
+                let mut session = IndexedSession::default();
+                session.set_header(&header);
+                session
+            }
+        };
+        for entry in &entries[session.indexed_entries..] {
+            session.add_entry(entry);
+        }
+        session.modified_ms = info.updated_at;
+        self.sessions.insert(info.session_id.clone(), session);
+        Ok(true)
+    }
+
+    /// Index one session file; returns whether the index changed.
+    fn index_file(&mut self, id: &str, path: &Path) -> Result<bool, CodingAgentError> {
+        let metadata = std::fs::metadata(path)?;
+        let len = metadata.len();
+        let modified_ms = metadata
+            .modified()
+            .ok()
+            .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+            .map(|d| d.as_millis() as i64)
+            .unwrap_or(0);
+
+        let existing = self.sessions.get(id);
+        if let Some(session) = existing
+            && session.indexed_len == len
+            && session.modified_ms == modified_ms
+        {
+            return Ok(false);
+        }
+
+        // Appends continue from the last indexed byte; anything else is a
+        // rewrite and is indexed from scratch.
+        let mut session = match existing {
+            Some(session) if session.indexed_len > 0 && session.indexed_len < len => {
+                session.clone()
+            }
+            _ => IndexedSession::default(),
+        };
+
+        let mut file = std::fs::File::open(path)?;
+        file.seek(SeekFrom::Start(session.indexed_len))?;
+        let mut reader = BufReader::new(file.take(len - session.indexed_len));
+        let mut offset = session.indexed_len;
+        let mut line = String::new();
+        loop {
+            line.clear();
+            let read = reader.read_line(&mut line)?;
+            // Stop before a partially written trailing line.
+            if read == 0 || !line.ends_with('\n') {
+                break;
+            }
+            let is_header = offset == 0;
+            offset += read as u64;
+            let trimmed = line.trim();
+            if trimmed.is_empty() {
+                continue;
+            }
+            if is_header {
+                session.set_header(&serde_json::from_str(trimmed)?);
+                continue;
+            }
+            if let Ok(entry) = serde_json::from_str::<SessionEntry>(trimmed) {
+                session.add_entry(&entry);
+            }
+        }
+
+        session.indexed_len = offset;
+        session.modified_ms = modified_ms;
+        self.sessions.insert(id.to_string(), session);
+        Ok(true)
+    }
+
+    fn save(&self) -> Result<(), CodingAgentError> {
+        if let Some(parent) = self.path.parent() {
+            paths::ensure_dir(parent)?;
+        }
+        let tmp = self.path.with_extension("json.tmp");
+        std::fs::write(&tmp, serde_json::to_vec(self)?)?;
+        std::fs::rename(&tmp, &self.path)?;
+        Ok(())
+    }
+
+    /// Number of indexed sessions.
+    pub fn len(&self) -> usize {
+        self.sessions.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sessions.is_empty()
+    }
+
+    /// Sessions matching every query term, best first.
+    ///
+    /// Terms are matched case-insensitively as substrings, which also works
+    /// for text without word boundaries. Scores weigh rarer terms higher
+    /// (IDF), saturate repeated matches, and boost title matches and
+    /// sessions containing the query as a phrase.
+    pub fn search(&self, query: &SearchQuery) -> Vec<SearchHit> {
+        let phrase = query.text.trim().to_lowercase();
+        let terms: Vec<String> = phrase.split_whitespace().map(str::to_string).collect();
+        let candidates: Vec<(&String, &IndexedSession)> = self
+            .sessions
+            .iter()
+            .filter(|(_, session)| matches_filters(session, query))
+            .collect();
+        if candidates.is_empty() {
+            return Vec::new();
+        }
+
+        // Lowercase each session's text once.
+        let prepared: Vec<PreparedSession> = candidates
+            .iter()
+            .map(|(id, session)| PreparedSession::new(id, session))
+            .collect();
+
+        let total = prepared.len() as f64;
+        let idf: Vec<f64> = terms
+            .iter()
+            .map(|term| {
+                let df = prepared.iter().filter(|s| s.contains(term)).count() as f64;
+                (1.0 + (total - df + 0.5) / (df + 0.5)).ln()
+            })
+            .collect();
+
+        let mut hits: Vec<SearchHit> = prepared
+            .iter()
+            .filter_map(|session| session.score(&terms, &idf, &phrase))
+            .collect();
+        hits.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then(b.updated_at.cmp(&a.updated_at))
+        });
+        hits.truncate(query.limit.unwrap_or(20));
+        hits
+    }
+}
+
+fn matches_filters(session: &IndexedSession, query: &SearchQuery) -> bool {
+    if query.since.is_some_and(|since| session.updated_at < since) {
+        return false;
+    }
+    if query.until.is_some_and(|until| session.created_at > until) {
+        return false;
+    }
+    if let Some(project) = &query.project
+        && !in_project(&session.cwd, project)
+    {
+        return false;
+    }
+    if let Some(model) = &query.model {
+        let model = model.to_lowercase();
+        if !session
+            .models
+            .iter()
+            .any(|m| m.to_lowercase().contains(&model))
+        {
+            return false;
+        }
+    }
+    true
+}
+
+struct PreparedSession<'a> {
+    id: &'a str,
+    session: &'a IndexedSession,
+    title: String,
+    /// Tool names, paths and cwd.
+    metadata: String,
+    docs: Vec<String>,
+}
+
+impl<'a> PreparedSession<'a> {
+    fn new(id: &'a str, session: &'a IndexedSession) -> Self {
+        let metadata = session
+            .tools
+            .iter()
+            .chain(&session.paths)
+            .chain(std::iter::once(&session.cwd))
+            .map(|s| s.to_lowercase())
+            .collect::<Vec<_>>()
+            .join("\n");
+        Self {
+            id,
+            session,
+            title: session.title.as_deref().unwrap_or_default().to_lowercase(),
+            metadata,
+            docs: session
+                .docs
+                .iter()
+                .map(|doc| doc.text.to_lowercase())
+                .collect(),
+        }
+    }
+
+    fn contains(&self, term: &str) -> bool {
+        self.title.contains(term)
+            || self.metadata.contains(term)
+            || self.docs.iter().any(|doc| doc.contains(term))
+    }
+
+    fn score(&self, terms: &[String], idf: &[f64], phrase: &str) -> Option<SearchHit> {
+        const SATURATION: f64 = 1.2;
+
+        let mut score = 0.0;
+        for (term, idf) in terms.iter().zip(idf) {
+            let tf = (self.title.matches(term.as_str()).count() * 3
+                + self.metadata.matches(term.as_str()).count()
+                + self
+                    .docs
+                    .iter()
+                    .map(|doc| doc.matches(term.as_str()).count())
+                    .sum::<usize>()) as f64;
+            if tf == 0.0 {
+                return None;
+            }
+            score += idf * tf * (SATURATION + 1.0) / (tf + SATURATION);
+        }
+        if terms.len() > 1 && self.docs.iter().any(|doc| doc.contains(phrase)) {
+            score *= 1.5;
+        }
+
+        // The document matching the most terms supplies the snippet.
+        let best = self
+            .docs
+            .iter()
+            .enumerate()
+            .map(|(i, doc)| (i, terms.iter().filter(|t| doc.contains(t.as_str())).count()))
+            .filter(|(_, matched)| *matched > 0)
+            .max_by_key(|(i, matched)| (*matched, std::cmp::Reverse(*i)));
+        let (entry_id, snippet) = match best {
+            Some((i, _)) => {
+                let doc = &self.session.docs[i];
+                (
+                    Some(doc.entry_id.clone()),
+                    snippet(&doc.text, &self.docs[i], terms),
+                )
+            }
+            None if terms.is_empty() => (None, String::new()),
+            None => (None, self.metadata_snippet(terms)),
+        };
+
+        Some(SearchHit {
+            session_id: self.id.to_string(),
+            title: self.session.title.clone(),
+            cwd: self.session.cwd.clone(),
+            created_at: self.session.created_at,
+            updated_at: self.session.updated_at,
+            score,
+            entry_id,
+            snippet,
+            models: self.session.models.iter().cloned().collect(),
+        })
+    }
+
+    fn metadata_snippet(&self, terms: &[String]) -> String {
+        self.session
+            .tools
+            .iter()
+            .chain(&self.session.paths)
+            .chain(std::iter::once(&self.session.cwd))
+            .find(|value| {
+                let lower = value.to_lowercase();
+                terms.iter().any(|t| lower.contains(t.as_str()))
+            })
+            .cloned()
+            .unwrap_or_default()
+    }
+}
+
+/// Text around the first match of any term, on one line.
+fn snippet(text: &str, lower: &str, terms: &[String]) -> String {
+    // Lowercasing can change byte lengths; fall back to the start when the
+    // offsets do not line up.
+    let position = terms
+        .iter()
+        .filter_map(|t| lower.find(t.as_str()))
+        .min()
+        .filter(|_| lower.len() == text.len())
+        .unwrap_or(0);
+
+    let mut start = position.saturating_sub(SNIPPET_CONTEXT);
+    while !text.is_char_boundary(start) {
+        start -= 1;
+    }
+    let mut end = (position + SNIPPET_CONTEXT * 2).min(text.len());
+    while !text.is_char_boundary(end) {
+        end += 1;
+    }
+
+    let mut out = text[start..end]
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ");
+    if start > 0 {
+        out.insert(0, '…');
+    }
+    if end < text.len() {
+        out.push('…');
+    }
+    out
+}
+
+/// Update the index under `base_dir` from `store` and run `query` against it.
+pub fn search_sessions(
+    base_dir: &Path,
+    store: &dyn SessionStore,
+    query: &SearchQuery,
+) -> Result<Vec<SearchHit>, CodingAgentError> {
+    let mut index = SearchIndex::load(base_dir);
+    index.update(base_dir, store)?;
+    Ok(index.search(query))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::manager::SessionManager;
+    use pi_agent_core::types::{
+        StopReason, TextContent, ToolCall, Usage, UserContent, UserMessage,
+    };
+
+    fn user(text: &str) -> SessionEntry {
+        SessionEntry::Message {
+            id: SessionEntry::new_id(),
+            parent_id: None,
+            timestamp: "2026-03-01T10:00:00Z".to_string(),
+            message: Message::User(UserMessage {
+                content: UserContent::Text(text.to_string()),
+                timestamp: 0,
+            }),
+        }
+    }
+
+    fn assistant(text: &str, tool_path: Option<&str>) -> SessionEntry {
+        let mut content = vec![ContentBlock::Text(TextContent {
+            text: text.to_string(),
+            text_signature: None,
+            citations: None,
+        })];
+        if let Some(path) = tool_path {
+            content.push(ContentBlock::ToolCall(ToolCall {
+                id: "call-1".to_string(),
+                name: "edit".to_string(),
+                arguments: serde_json::json!({ "path": path }),
+                thought_signature: None,
+            }));
+        }
+        SessionEntry::Message {
+            id: SessionEntry::new_id(),
+            parent_id: None,
+            timestamp: "2026-03-01T10:00:01Z".to_string(),
+            message: Message::Assistant(AssistantMessage {
+                content,
+                api: "anthropic-messages".to_string(),
+                provider: "anthropic".to_string(),
+                model: "claude-sonnet-4-5".to_string(),
+                usage: Usage::default(),
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 1_772_359_201_000,
+                rate_limit: None,
+            }),
+        }
+    }
+
+    fn query(text: &str) -> SearchQuery {
+        SearchQuery {
+            text: text.to_string(),
+            ..SearchQuery::default()
+        }
+    }
+
+    #[test]
+    fn test_search_ranks_and_filters_sessions() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new(tmp.path());
+        manager
+            .create_in("bedrock", Some("Bedrock work"), "/work/pi")
+            .unwrap();
+        manager
+            .append_entries(
+                "bedrock",
+                &[
+                    user("The bedrock signing bug breaks SigV4 requests"),
+                    assistant(
+                        "Fixed the signing bug by including the port in the host header.",
+                        Some("crates/pi-agent-ai/src/aws.rs"),
+                    ),
+                ],
+            )
+            .unwrap();
+        manager.create_in("other", None, "/work/site").unwrap();
+        manager
+            .append_entries("other", &[user("Update the website signing page")])
+            .unwrap();
+
+        let mut index = SearchIndex::load(tmp.path());
+        index.update(tmp.path(), manager.store()).unwrap();
+        assert_eq!(index.len(), 2);
+
+        let hits = index.search(&query("signing bug"));
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].session_id, "bedrock");
+        assert!(hits[0].snippet.contains("signing bug"));
+
+        let hits = index.search(&query("signing"));
+        assert_eq!(hits.len(), 2);
+        assert_eq!(hits[0].session_id, "bedrock");
+
+        // File paths and cwd are searchable, and filters apply.
+        assert_eq!(index.search(&query("aws.rs")).len(), 1);
+        let mut filtered = query("signing");
+        filtered.project = Some("/work/site".to_string());
+        assert_eq!(index.search(&filtered)[0].session_id, "other");
+        filtered.project = None;
+        filtered.model = Some("sonnet".to_string());
+        assert_eq!(index.search(&filtered).len(), 1);
+        filtered.model = None;
+        filtered.since = Some(i64::MAX);
+        assert!(index.search(&filtered).is_empty());
+    }
+
+    #[test]
+    fn test_update_is_incremental_and_persisted() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manager = SessionManager::new(tmp.path());
+        manager.create_in("s1", None, "/work").unwrap();
+        manager.append_entry("s1", &user("first topic")).unwrap();
+
+        let hits = manager.search(&query("first")).unwrap();
+        assert_eq!(hits.len(), 1);
+        let indexed_len = SearchIndex::load(tmp.path()).sessions["s1"].indexed_len;
+
+        manager.append_entry("s1", &user("второй 话题")).unwrap();
+        let mut index = SearchIndex::load(tmp.path());
+        assert_eq!(index.sessions["s1"].docs.len(), 1);
+        index.update(tmp.path(), manager.store()).unwrap();
+        assert!(index.sessions["s1"].indexed_len > indexed_len);
+        assert_eq!(index.sessions["s1"].docs.len(), 2);
+        assert_eq!(index.search(&query("话题")).len(), 1);
+        assert_eq!(index.search(&query("ВТОРОЙ")).len(), 1);
+
+        manager.delete("s1").unwrap();
+        index.update(tmp.path(), manager.store()).unwrap();
+        assert!(index.is_empty());
+    }
+
+    #[test]
+    fn test_search_indexes_sqlite_store_incrementally() {
+        let tmp = tempfile::tempdir().unwrap();
+        let manager =
+            SessionManager::with_store(tmp.path(), crate::session::store::SessionStoreKind::Sqlite)
+                .unwrap();
+        manager.create_in("db", None, "/work").unwrap();
+        manager.append_entry("db", &user("sqlite backed")).unwrap();
+        assert_eq!(manager.search(&query("sqlite")).unwrap().len(), 1);
+
+        manager.append_entry("db", &user("later addition")).unwrap();
+        assert_eq!(manager.search(&query("addition")).unwrap().len(), 1);
+        let index = SearchIndex::load(tmp.path());
+        assert_eq!(index.sessions["db"].indexed_entries, 2);
+        assert_eq!(index.sessions["db"].docs.len(), 2);
+    }
+}
@@ -0,0 +1,859 @@
+//! Storage backends for session headers and entries.
+//!
+//! [`SessionStore`] is what [`SessionManager`](super::manager::SessionManager)
+//! persists through. [`JsonlStore`] keeps the original layout of one
+//! `<id>.jsonl` file per session; [`SqliteStore`] keeps every session in a
+//! single `sessions.db` with indexed listing and transactional appends.
+//! Both store entries exactly as the v3 [`SessionEntry`] JSON, so sessions
+//! can be copied between backends with [`copy_sessions`] without changing
+//! ids, parent links or entry types.
+
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use rusqlite::{Connection, OptionalExtension, TransactionBehavior, params};
+use serde::{Deserialize, Serialize};
+
+use crate::config::paths;
+use crate::error::CodingAgentError;
+use crate::session::project::{in_project, project_dir_name, project_of};
+use crate::session::types::{SessionEntry, SessionHeader, SessionInfo, latest_session_info};
+
+/// Available session storage backends.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SessionStoreKind {
+    #[default]
+    Jsonl,
+    Sqlite,
+}
+
+impl SessionStoreKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "jsonl" => Some(Self::Jsonl),
+            "sqlite" => Some(Self::Sqlite),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Self::Jsonl => "jsonl",
+            Self::Sqlite => "sqlite",
+        }
+    }
+
+    /// Open this backend's store under `base_dir`.
+    pub fn open(self, base_dir: &Path) -> Result<Box<dyn SessionStore>, CodingAgentError> {
+        Ok(match self {
+            Self::Jsonl => Box::new(JsonlStore::new(&paths::sessions_dir(base_dir))),
+            Self::Sqlite => Box::new(SqliteStore::open(&paths::sessions_db_file(base_dir))?),
+        })
+    }
+}
+
+/// Restricts [`SessionStore::list`].
+#[derive(Debug, Clone, Default)]
+pub struct SessionFilter {
+    /// Only sessions started in exactly this working directory.
+    pub cwd: Option<String>,
+    /// Only sessions started in this directory or below it.
+    pub project: Option<String>,
+    /// Only sessions updated at or after this Unix ms timestamp.
+    pub updated_since: Option<i64>,
+    /// Maximum number of sessions returned.
+    pub limit: Option<usize>,
+}
+
+impl SessionFilter {
+    fn matches(&self, info: &SessionInfo) -> bool {
+        self.cwd.as_ref().is_none_or(|cwd| &info.cwd == cwd)
+            && self
+                .project
+                .as_ref()
+                .is_none_or(|project| in_project(&info.cwd, project))
+            && self.updated_since.is_none_or(|t| info.updated_at >= t)
+    }
+}
+
+/// Persistent storage for sessions.
+///
+/// Session ids are validated by the caller. Entries are returned in the
+/// order they were appended.
+pub trait SessionStore: Send + Sync {
+    fn kind(&self) -> SessionStoreKind;
+
+    /// Create a session with its initial entries; fails if it exists.
+    fn create(
+        &self,
+        header: &SessionHeader,
+        entries: &[SessionEntry],
+    ) -> Result<(), CodingAgentError>;
+
+    /// Load a session's header and entries.
+    fn load(
+        &self,
+        session_id: &str,
+    ) -> Result<(SessionHeader, Vec<SessionEntry>), CodingAgentError>;
+
+    /// Append entries to an existing session.
+    fn append(&self, session_id: &str, entries: &[SessionEntry]) -> Result<(), CodingAgentError>;
+
+    /// Sessions matching `filter`, most recently updated first.
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionInfo>, CodingAgentError>;
+
+    /// Ids of all stored sessions.
+    fn ids(&self) -> Result<Vec<String>, CodingAgentError>;
+
+    fn exists(&self, session_id: &str) -> bool;
+
+    /// Delete a session; deleting a missing session is not an error.
+    fn delete(&self, session_id: &str) -> Result<(), CodingAgentError>;
+}
+
+fn not_found(session_id: &str) -> CodingAgentError {
+    CodingAgentError::Session(format!("Session not found: {session_id}"))
+}
+
+fn already_exists(session_id: &str) -> CodingAgentError {
+    CodingAgentError::Session(format!("Session already exists: {session_id}"))
+}
+
+/// Create a new file with restrictive permissions on Unix (0600).
+fn create_new_restricted(path: &Path) -> std::io::Result<std::fs::File> {
+    let mut opts = std::fs::OpenOptions::new();
+    opts.write(true).create_new(true);
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        opts.mode(0o600);
+    }
+
+    opts.open(path)
+}
+
+fn parse_timestamp_value(value: &serde_json::Value) -> Option<i64> {
+    match value {
+        serde_json::Value::Number(n) => n.as_i64(),
+        serde_json::Value::String(s) => {
+            if let Ok(ms) = s.parse::<i64>() {
+                return Some(ms);
+            }
+            chrono::DateTime::parse_from_rfc3339(s)
+                .ok()
+                .map(|dt| dt.timestamp_millis())
+        }
+        _ => None,
+    }
+}
+
+/// One `<id>.jsonl` file per session: the header line followed by one
+/// entry per line. Files live in a subdirectory per project (see
+/// [`project_dir_name`]); sessions without a working directory, and those
+/// written before sessions were grouped, live directly in `sessions/`.
+pub struct JsonlStore {
+    dir: PathBuf,
+}
+
+impl JsonlStore {
+    pub fn new(dir: &Path) -> Self {
+        Self {
+            dir: dir.to_path_buf(),
+        }
+    }
+
+    /// Find the file of an existing session.
+    pub fn session_path(&self, session_id: &str) -> Option<PathBuf> {
+        let file_name = format!("{session_id}.jsonl");
+        let flat = self.dir.join(&file_name);
+        if flat.exists() {
+            return Some(flat);
+        }
+        std::fs::read_dir(&self.dir)
+            .ok()?
+            .filter_map(Result::ok)
+            .map(|entry| entry.path().join(&file_name))
+            .find(|path| path.is_file())
+    }
+
+    /// Every session file, keyed by session id.
+    pub fn session_files(&self) -> Result<Vec<(String, PathBuf)>, CodingAgentError> {
+        if !self.dir.exists() {
+            return Ok(Vec::new());
+        }
+        let mut files = Vec::new();
+        for entry in std::fs::read_dir(&self.dir)? {
+            let path = entry?.path();
+            if path.is_dir() {
+                for nested in std::fs::read_dir(&path)? {
+                    Self::push_session_file(nested?.path(), &mut files);
+                }
+            } else {
+                Self::push_session_file(path, &mut files);
+            }
+        }
+        Ok(files)
+    }
+
+    fn push_session_file(path: PathBuf, files: &mut Vec<(String, PathBuf)>) {
+        if path.extension().is_some_and(|ext| ext == "jsonl")
+            && let Some(stem) = path.file_stem()
+        {
+            files.push((stem.to_string_lossy().to_string(), path));
+        }
+    }
+
+    /// Read lightweight session info from file.
+    fn read_session_info(path: &Path) -> Option<SessionInfo> {
+        let file = std::fs::File::open(path).ok()?;
+        let size_bytes = file.metadata().ok()?.len();
+        let reader = BufReader::new(file);
+        let mut lines = reader.lines();
+
+        let header_line = lines.next()?.ok()?;
+        let header: SessionHeader = serde_json::from_str(&header_line).ok()?;
+        if header.entry_type != "session" {
+            return None;
+        }
+
+        let mut entry_count = 0usize;
+        let mut last_timestamp = header.timestamp_ms();
+        let mut title = header.title.clone();
+        let mut summary = None;
+
+        for line in lines.map_while(Result::ok) {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            entry_count += 1;
+            let Ok(val) = serde_json::from_str::<serde_json::Value>(&line) else {
+                continue;
+            };
+            if let Some(ts) = val.get("timestamp").and_then(parse_timestamp_value) {
+                last_timestamp = last_timestamp.max(ts);
+            }
+            if val.get("type").and_then(|t| t.as_str()) == Some("session_info") {
+                let text = |key| val.get(key).and_then(|v| v.as_str()).map(str::to_string);
+                title = text("name").or(title);
+                summary = text("summary").or(summary);
+            }
+        }
+
+        let created_at = header.timestamp_ms();
+        Some(SessionInfo {
+            session_id: header.id,
+            title,
+            summary,
+            cwd: header.cwd,
+            created_at,
+            updated_at: last_timestamp,
+            entry_count,
+            size_bytes,
+            parent_session_id: header.parent_session,
+        })
+    }
+}
+
+impl SessionStore for JsonlStore {
+    fn kind(&self) -> SessionStoreKind {
+        SessionStoreKind::Jsonl
+    }
+
+    fn create(
+        &self,
+        header: &SessionHeader,
+        entries: &[SessionEntry],
+    ) -> Result<(), CodingAgentError> {
+        if self.session_path(&header.id).is_some() {
+            return Err(already_exists(&header.id));
+        }
+        let dir = if header.cwd.is_empty() {
+            self.dir.clone()
+        } else {
+            self.dir.join(project_dir_name(&project_of(&header.cwd)))
+        };
+        paths::ensure_dir(&dir)?;
+        let path = dir.join(format!("{}.jsonl", header.id));
+        let mut file = create_new_restricted(&path).map_err(|e| {
+            if e.kind() == std::io::ErrorKind::AlreadyExists {
+                already_exists(&header.id)
+            } else {
+                CodingAgentError::Io(e)
+            }
+        })?;
+        writeln!(file, "{}", serde_json::to_string(header)?)?;
+        for entry in entries {
+            writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        }
+        Ok(())
+    }
+
+    fn load(
+        &self,
+        session_id: &str,
+    ) -> Result<(SessionHeader, Vec<SessionEntry>), CodingAgentError> {
+        let path = self
+            .session_path(session_id)
+            .ok_or_else(|| not_found(session_id))?;
+
+        let file = std::fs::File::open(&path)?;
+        let reader = BufReader::new(file);
+        let mut lines = reader.lines();
+
+        let header_line = lines
+            .next()
+            .ok_or_else(|| CodingAgentError::Session("Empty session file".to_string()))??;
+        let header: SessionHeader = serde_json::from_str(&header_line)?;
+
+        if header.entry_type != "session" {
+            return Err(CodingAgentError::Session(format!(
+                "Invalid session header in {}",
+                path.display()
+            )));
+        }
+
+        let mut entries = Vec::new();
+        for line in lines {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            match serde_json::from_str::<SessionEntry>(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::warn!("Skipping malformed session entry: {e}"),
+            }
+        }
+
+        Ok((header, entries))
+    }
+
+    fn append(&self, session_id: &str, entries: &[SessionEntry]) -> Result<(), CodingAgentError> {
+        let path = self
+            .session_path(session_id)
+            .ok_or_else(|| not_found(session_id))?;
+
+        // Write all lines at once so concurrent appenders do not interleave
+        // partial entries.
+        let mut buffer = String::new();
+        for entry in entries {
+            buffer.push_str(&serde_json::to_string(entry)?);
+            buffer.push('\n');
+        }
+        let mut file = std::fs::OpenOptions::new().append(true).open(&path)?;
+        file.write_all(buffer.as_bytes())?;
+        Ok(())
+    }
+
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionInfo>, CodingAgentError> {
+        let mut sessions: Vec<SessionInfo> = self
+            .session_files()?
+            .iter()
+            .filter_map(|(_, path)| Self::read_session_info(path))
+            .filter(|info| filter.matches(info))
+            .collect();
+        sessions.sort_by_key(|s| std::cmp::Reverse(s.updated_at));
+        if let Some(limit) = filter.limit {
+            sessions.truncate(limit);
+        }
+        Ok(sessions)
+    }
+
+    fn ids(&self) -> Result<Vec<String>, CodingAgentError> {
+        Ok(self
+            .session_files()?
+            .into_iter()
+            .map(|(id, _)| id)
+            .collect())
+    }
+
+    fn exists(&self, session_id: &str) -> bool {
+        self.session_path(session_id).is_some()
+    }
+
+    fn delete(&self, session_id: &str) -> Result<(), CodingAgentError> {
+        if let Some(path) = self.session_path(session_id) {
+            std::fs::remove_file(&path)?;
+            // Drop the project directory once its last session is gone.
+            if let Some(parent) = path.parent()
+                && parent != self.dir
+            {
+                let _ = std::fs::remove_dir(parent);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Bumped with each schema migration in [`SqliteStore::open`].
+const SQLITE_SCHEMA_VERSION: i32 = 1;
+
+const SQLITE_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS sessions (
+    id TEXT PRIMARY KEY,
+    header TEXT NOT NULL,
+    cwd TEXT NOT NULL,
+    title TEXT,
+    summary TEXT,
+    parent_session TEXT,
+    created_at INTEGER NOT NULL,
+    updated_at INTEGER NOT NULL,
+    entry_count INTEGER NOT NULL DEFAULT 0,
+    size_bytes INTEGER NOT NULL DEFAULT 0
+);
+CREATE INDEX IF NOT EXISTS sessions_by_updated ON sessions (updated_at DESC);
+CREATE INDEX IF NOT EXISTS sessions_by_cwd ON sessions (cwd, updated_at DESC);
+CREATE TABLE IF NOT EXISTS entries (
+    session_id TEXT NOT NULL,
+    seq INTEGER NOT NULL,
+    entry_id TEXT NOT NULL,
+    entry_type TEXT NOT NULL,
+    timestamp INTEGER NOT NULL,
+    data TEXT NOT NULL,
+    PRIMARY KEY (session_id, seq)
+);
+";
+
+/// All sessions in one SQLite database.
+///
+/// Headers and entries are stored as their JSONL representation alongside
+/// indexed columns for listing. Appends run in an immediate transaction, so
+/// concurrent writers from several processes are serialized by SQLite.
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Open or create the database at `path`.
+    pub fn open(path: &Path) -> Result<Self, CodingAgentError> {
+        if let Some(parent) = path.parent() {
+            paths::ensure_dir(parent)?;
+        }
+        let conn = Connection::open(path)?;
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+        }
+        Self::init(conn)
+    }
+
+    /// Open a private in-memory database.
+    pub fn open_in_memory() -> Result<Self, CodingAgentError> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self, CodingAgentError> {
+        conn.busy_timeout(Duration::from_secs(5))?;
+        conn.pragma_update(None, "journal_mode", "WAL")?;
+        let version: i32 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+        if version > SQLITE_SCHEMA_VERSION {
+            return Err(CodingAgentError::Session(format!(
+                "Session database schema version {version} is newer than supported ({SQLITE_SCHEMA_VERSION})"
+            )));
+        }
+        conn.execute_batch(SQLITE_SCHEMA)?;
+        conn.pragma_update(None, "user_version", SQLITE_SCHEMA_VERSION)?;
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn insert_entries(
+        tx: &rusqlite::Transaction<'_>,
+        session_id: &str,
+        first_seq: i64,
+        entries: &[SessionEntry],
+    ) -> Result<(i64, i64), CodingAgentError> {
+        let mut stmt = tx.prepare_cached(
+            "INSERT INTO entries (session_id, seq, entry_id, entry_type, timestamp, data)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+        )?;
+        let mut updated_at = i64::MIN;
+        let mut size_bytes = 0;
+        for (i, entry) in entries.iter().enumerate() {
+            let timestamp = entry.timestamp();
+            updated_at = updated_at.max(timestamp);
+            let data = serde_json::to_string(entry)?;
+            size_bytes += data.len() as i64 + 1;
+            stmt.execute(params![
+                session_id,
+                first_seq + i as i64,
+                entry.id(),
+                entry.entry_type(),
+                timestamp,
+                data,
+            ])?;
+        }
+        Ok((updated_at, size_bytes))
+    }
+
+    fn row_to_info(row: &rusqlite::Row<'_>) -> rusqlite::Result<SessionInfo> {
+        Ok(SessionInfo {
+            session_id: row.get(0)?,
+            title: row.get(1)?,
+            summary: row.get(2)?,
+            cwd: row.get(3)?,
+            created_at: row.get(4)?,
+            updated_at: row.get(5)?,
+            entry_count: row.get::<_, i64>(6)? as usize,
+            size_bytes: row.get::<_, i64>(7)? as u64,
+            parent_session_id: row.get(8)?,
+        })
+    }
+}
+
+impl SessionStore for SqliteStore {
+    fn kind(&self) -> SessionStoreKind {
+        SessionStoreKind::Sqlite
+    }
+
+    fn create(
+        &self,
+        header: &SessionHeader,
+        entries: &[SessionEntry],
+    ) -> Result<(), CodingAgentError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let exists = tx
+            .query_row("SELECT 1 FROM sessions WHERE id = ?1", [&header.id], |_| {
+                Ok(())
+            })
+            .optional()?
+            .is_some();
+        if exists {
+            return Err(already_exists(&header.id));
+        }
+
+        let created_at = header.timestamp_ms();
+        let (updated_at, entries_size) = Self::insert_entries(&tx, &header.id, 0, entries)?;
+        let header_json = serde_json::to_string(header)?;
+        let size_bytes = header_json.len() as i64 + 1 + entries_size;
+        let (title, summary) = latest_session_info(entries);
+        tx.execute(
+            "INSERT INTO sessions
+                 (id, header, cwd, title, summary, parent_session, created_at, updated_at,
+                  entry_count, size_bytes)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+            params![
+                header.id,
+                header_json,
+                header.cwd,
+                title.or_else(|| header.title.clone()),
+                summary,
+                header.parent_session,
+                created_at,
+                updated_at.max(created_at),
+                entries.len() as i64,
+                size_bytes,
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn load(
+        &self,
+        session_id: &str,
+    ) -> Result<(SessionHeader, Vec<SessionEntry>), CodingAgentError> {
+        let conn = self.conn();
+        let header: Option<String> = conn
+            .query_row(
+                "SELECT header FROM sessions WHERE id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let header: SessionHeader =
+            serde_json::from_str(&header.ok_or_else(|| not_found(session_id))?)?;
+
+        let mut stmt =
+            conn.prepare_cached("SELECT data FROM entries WHERE session_id = ?1 ORDER BY seq")?;
+        let mut entries = Vec::new();
+        for data in stmt.query_map([session_id], |row| row.get::<_, String>(0))? {
+            match serde_json::from_str::<SessionEntry>(&data?) {
+                Ok(entry) => entries.push(entry),
+                Err(e) => tracing::warn!("Skipping malformed session entry: {e}"),
+            }
+        }
+        Ok((header, entries))
+    }
+
+    fn append(&self, session_id: &str, entries: &[SessionEntry]) -> Result<(), CodingAgentError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        let count: Option<i64> = tx
+            .query_row(
+                "SELECT entry_count FROM sessions WHERE id = ?1",
+                [session_id],
+                |row| row.get(0),
+            )
+            .optional()?;
+        let count = count.ok_or_else(|| not_found(session_id))?;
+        let next_seq: i64 = tx.query_row(
+            "SELECT COALESCE(MAX(seq) + 1, 0) FROM entries WHERE session_id = ?1",
+            [session_id],
+            |row| row.get(0),
+        )?;
+
+        let (updated_at, size_bytes) = Self::insert_entries(&tx, session_id, next_seq, entries)?;
+        let (title, summary) = latest_session_info(entries);
+        if title.is_some() || summary.is_some() {
+            tx.execute(
+                "UPDATE sessions SET title = COALESCE(?2, title), summary = COALESCE(?3, summary)
+                 WHERE id = ?1",
+                params![session_id, title, summary],
+            )?;
+        }
+        tx.execute(
+            "UPDATE sessions
+             SET entry_count = ?2, updated_at = MAX(updated_at, ?3), size_bytes = size_bytes + ?4
+             WHERE id = ?1",
+            params![
+                session_id,
+                count + entries.len() as i64,
+                updated_at,
+                size_bytes
+            ],
+        )?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    fn list(&self, filter: &SessionFilter) -> Result<Vec<SessionInfo>, CodingAgentError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached(
+            "SELECT id, title, summary, cwd, created_at, updated_at, entry_count, size_bytes,
+                    parent_session
+             FROM sessions
+             WHERE (?1 IS NULL OR cwd = ?1)
+               AND (?2 IS NULL OR updated_at >= ?2)
+               AND (?4 IS NULL OR cwd = ?4 OR (cwd >= ?4 || '/' AND cwd < ?4 || '0'))
+             ORDER BY updated_at DESC
+             LIMIT ?3",
+        )?;
+        let limit = filter.limit.map_or(-1, |limit| limit as i64);
+        let project = filter
+            .project
+            .as_deref()
+            .map(|p| p.trim_end_matches(['/', '\\']));
+        let rows = stmt.query_map(
+            params![filter.cwd, filter.updated_since, limit, project],
+            Self::row_to_info,
+        )?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn ids(&self) -> Result<Vec<String>, CodingAgentError> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare_cached("SELECT id FROM sessions")?;
+        let rows = stmt.query_map([], |row| row.get(0))?;
+        Ok(rows.collect::<Result<_, _>>()?)
+    }
+
+    fn exists(&self, session_id: &str) -> bool {
+        self.conn()
+            .query_row("SELECT 1 FROM sessions WHERE id = ?1", [session_id], |_| {
+                Ok(())
+            })
+            .optional()
+            .ok()
+            .flatten()
+            .is_some()
+    }
+
+    fn delete(&self, session_id: &str) -> Result<(), CodingAgentError> {
+        let mut conn = self.conn();
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        tx.execute("DELETE FROM entries WHERE session_id = ?1", [session_id])?;
+        tx.execute("DELETE FROM sessions WHERE id = ?1", [session_id])?;
+        tx.commit()?;
+        Ok(())
+    }
+}
+
+/// Result of [`copy_sessions`].
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CopyReport {
+    pub copied: usize,
+    /// Sessions already present in the target.
+    pub skipped: usize,
+}
+
+/// Copy every session in `from` that `to` does not already have.
+pub fn copy_sessions(
+    from: &dyn SessionStore,
+    to: &dyn SessionStore,
+) -> Result<CopyReport, CodingAgentError> {
+    let mut report = CopyReport::default();
+    for id in from.ids()? {
+        if to.exists(&id) {
+            report.skipped += 1;
+            continue;
+        }
+        let (header, entries) = from.load(&id)?;
+        to.create(&header, &entries)?;
+        report.copied += 1;
+    }
+    Ok(report)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::session::types::{CURRENT_SESSION_VERSION, now_iso_timestamp};
+    use pi_agent_core::types::{Message, UserContent, UserMessage};
+
+    fn header(id: &str, cwd: &str) -> SessionHeader {
+        SessionHeader {
+            entry_type: "session".to_string(),
+            version: Some(CURRENT_SESSION_VERSION),
+            id: id.to_string(),
+            timestamp: now_iso_timestamp(),
+            cwd: cwd.to_string(),
+            parent_session: None,
+            title: Some(format!("{id} title")),
+        }
+    }
+
+    fn user(id: &str, parent: Option<&str>, timestamp: &str) -> SessionEntry {
+        SessionEntry::Message {
+            id: id.to_string(),
+            parent_id: parent.map(str::to_string),
+            timestamp: timestamp.to_string(),
+            message: Message::User(UserMessage {
+                content: UserContent::Text(format!("message {id}")),
+                timestamp: 0,
+            }),
+        }
+    }
+
+    fn exercise(store: &dyn SessionStore) {
+        store.create(&header("a", "/work/one"), &[]).unwrap();
+        store
+            .create(
+                &header("b", "/work/two"),
+                &[user("b1", None, "2030-01-01T00:00:00.000Z")],
+            )
+            .unwrap();
+        assert!(store.create(&header("a", "/work/one"), &[]).is_err());
+        assert!(store.append("missing", &[]).is_err());
+
+        store
+            .append(
+                "a",
+                &[
+                    user("a1", None, "2031-01-01T00:00:00.000Z"),
+                    user("a2", Some("a1"), "2031-01-01T00:00:01.000Z"),
+                ],
+            )
+            .unwrap();
+        let (loaded, entries) = store.load("a").unwrap();
+        assert_eq!(loaded.cwd, "/work/one");
+        let ids: Vec<_> = entries.iter().map(SessionEntry::id).collect();
+        assert_eq!(ids, ["a1", "a2"]);
+        assert_eq!(entries[1].parent_id(), Some("a1"));
+
+        let all = store.list(&SessionFilter::default()).unwrap();
+        let order: Vec<_> = all.iter().map(|s| s.session_id.as_str()).collect();
+        assert_eq!(order, ["a", "b"]);
+        assert_eq!(all[0].entry_count, 2);
+        assert_eq!(all[0].title.as_deref(), Some("a title"));
+
+        let two = SessionFilter {
+            cwd: Some("/work/two".to_string()),
+            ..SessionFilter::default()
+        };
+        assert_eq!(store.list(&two).unwrap()[0].session_id, "b");
+        let project = SessionFilter {
+            project: Some("/work/".to_string()),
+            ..SessionFilter::default()
+        };
+        assert_eq!(store.list(&project).unwrap().len(), 2);
+        let recent = SessionFilter {
+            updated_since: Some(iso("2030-06-01T00:00:00Z")),
+            limit: Some(5),
+            ..SessionFilter::default()
+        };
+        assert_eq!(store.list(&recent).unwrap().len(), 1);
+
+        let info = SessionEntry::SessionInfo {
+            id: "b2".to_string(),
+            parent_id: None,
+            timestamp: "2030-01-01T00:00:01.000Z".to_string(),
+            name: Some("Fix the parser".to_string()),
+            summary: Some("Fixed a parser bug.".to_string()),
+        };
+        store.append("b", &[info]).unwrap();
+        let b = &store.list(&two).unwrap()[0];
+        assert_eq!(b.title.as_deref(), Some("Fix the parser"));
+        assert_eq!(b.summary.as_deref(), Some("Fixed a parser bug."));
+
+        store.delete("a").unwrap();
+        store.delete("a").unwrap();
+        assert!(!store.exists("a"));
+        assert_eq!(store.ids().unwrap(), ["b"]);
+    }
+
+    fn iso(value: &str) -> i64 {
+        chrono::DateTime::parse_from_rfc3339(value)
+            .unwrap()
+            .timestamp_millis()
+    }
+
+    #[test]
+    fn test_jsonl_and_sqlite_stores_behave_alike() {
+        let tmp = tempfile::tempdir().unwrap();
+        exercise(&JsonlStore::new(&tmp.path().join("sessions")));
+        exercise(&SqliteStore::open(&tmp.path().join("sessions.db")).unwrap());
+    }
+
+    #[test]
+    fn test_copy_sessions_round_trips_entries() {
+        let tmp = tempfile::tempdir().unwrap();
+        let jsonl = JsonlStore::new(&tmp.path().join("sessions"));
+        jsonl
+            .create(
+                &header("s1", "/work"),
+                &[
+                    user("e1", None, "2030-01-01T00:00:00.000Z"),
+                    SessionEntry::Label {
+                        id: "l1".to_string(),
+                        parent_id: Some("e1".to_string()),
+                        timestamp: "2030-01-01T00:00:01.000Z".to_string(),
+                        target_id: "e1".to_string(),
+                        label: Some("checkpoint".to_string()),
+                    },
+                ],
+            )
+            .unwrap();
+
+        let sqlite = SqliteStore::open_in_memory().unwrap();
+        let report = copy_sessions(&jsonl, &sqlite).unwrap();
+        assert_eq!(report.copied, 1);
+        assert_eq!(copy_sessions(&jsonl, &sqlite).unwrap().skipped, 1);
+
+        let back = JsonlStore::new(&tmp.path().join("exported"));
+        copy_sessions(&sqlite, &back).unwrap();
+        let original = jsonl.session_path("s1").unwrap();
+        let exported = back.session_path("s1").unwrap();
+        assert!(exported.ends_with("--work--/s1.jsonl"));
+        assert_eq!(
+            std::fs::read_to_string(&original).unwrap(),
+            std::fs::read_to_string(&exported).unwrap()
+        );
+        assert_eq!(
+            sqlite.list(&SessionFilter::default()).unwrap()[0].size_bytes,
+            std::fs::metadata(&original).unwrap().len()
+        );
+    }
+}
@@ -6,6 +6,8 @@ use crate::session::types::SessionEntry;
 #[derive(Debug, Clone)]
 pub struct TreeNode {
     pub entry: SessionEntry,
+    /// Parent entry ID within the tree.
+    pub parent: Option<String>,
     pub children: Vec<String>,
 }
 
@@ -19,28 +21,47 @@ pub struct SessionTree {
 impl SessionTree {
     /// Build a tree from a flat list of session entries.
     pub fn from_entries(entries: &[SessionEntry]) -> Self {
-        let mut nodes = HashMap::new();
+        Self::build(entries, false)
+    }
+
+    /// Build a tree where entries without a parent continue from the
+    /// previous entry, as sessions written without parent IDs are linear.
+    pub fn from_entries_chained(entries: &[SessionEntry]) -> Self {
+        Self::build(entries, true)
+    }
+
+    fn build(entries: &[SessionEntry], chain_orphans: bool) -> Self {
+        let mut nodes: HashMap<String, TreeNode> = HashMap::new();
         let mut roots = Vec::new();
+        let mut previous: Option<&str> = None;
 
         for entry in entries {
             let id = entry.id().to_string();
+            let parent_id = match entry.parent_id() {
+                Some(parent_id) => Some(parent_id),
+                None if chain_orphans => previous,
+                None => None,
+            };
+            previous = Some(entry.id());
+
+            let parent = match parent_id.and_then(|p| nodes.get_mut(p)) {
+                Some(parent) => {
+                    parent.children.push(id.clone());
+                    parent_id.map(str::to_string)
+                }
+                None => {
+                    roots.push(id.clone());
+                    entry.parent_id().map(str::to_string)
+                }
+            };
             nodes.insert(
-                id.clone(),
+                id,
                 TreeNode {
                     entry: entry.clone(),
+                    parent,
                     children: Vec::new(),
                 },
             );
-
-            if let Some(parent_id) = entry.parent_id() {
-                if let Some(parent) = nodes.get_mut(parent_id) {
-                    parent.children.push(id);
-                } else {
-                    roots.push(id);
-                }
-            } else {
-                roots.push(id);
-            }
         }
 
         Self { nodes, roots }
@@ -63,7 +84,7 @@ impl SessionTree {
 
         while let Some(node) = self.nodes.get(current) {
             path.push(&node.entry);
-            if let Some(parent_id) = node.entry.parent_id() {
+            if let Some(parent_id) = node.parent.as_deref() {
                 current = parent_id;
             } else {
                 break;
@@ -87,6 +108,25 @@ impl SessionTree {
         latest
     }
 
+    /// Get the latest leaf at or below `id`.
+    pub fn latest_leaf_under(&self, id: &str) -> Option<&SessionEntry> {
+        let mut latest: Option<&SessionEntry> = None;
+        let mut stack = vec![id];
+        while let Some(current) = stack.pop() {
+            let Some(node) = self.nodes.get(current) else {
+                continue;
+            };
+            if node.children.is_empty() {
+                if latest.is_none_or(|l| node.entry.timestamp() > l.timestamp()) {
+                    latest = Some(&node.entry);
+                }
+            } else {
+                stack.extend(node.children.iter().map(String::as_str));
+            }
+        }
+        latest
+    }
+
     /// Traverse entries in order (DFS, following the main branch — first child).
     pub fn traverse_main_branch(&self) -> Vec<&SessionEntry> {
         let mut result = Vec::new();
@@ -185,6 +225,30 @@ mod tests {
         assert_eq!(leaf.id(), "e3");
     }
 
+    #[test]
+    fn test_chained_tree_with_branch() {
+        let mut entries = make_entries();
+        entries.push(SessionEntry::LegacyUser {
+            id: "e4".to_string(),
+            parent_id: None,
+            timestamp: 1003,
+            content: "Another".to_string(),
+        });
+        entries.push(SessionEntry::LegacyUser {
+            id: "e5".to_string(),
+            parent_id: Some("e2".to_string()),
+            timestamp: 1004,
+            content: "Branch".to_string(),
+        });
+        let tree = SessionTree::from_entries_chained(&entries);
+        assert_eq!(tree.roots(), &["e1"]);
+        assert!(tree.has_branches("e2"));
+        assert_eq!(tree.path_to("e4").len(), 4);
+        assert_eq!(tree.latest_leaf_under("e1").unwrap().id(), "e5");
+        assert_eq!(tree.latest_leaf_under("e3").unwrap().id(), "e4");
+        assert_eq!(SessionTree::from_entries(&entries).roots().len(), 2);
+    }
+
     #[test]
     fn test_traverse_main_branch() {
         let entries = make_entries();
@@ -20,7 +20,7 @@ fn millis_to_iso(ms: i64) -> String {
         .to_rfc3339_opts(SecondsFormat::Millis, true)
 }
 
-fn iso_to_millis(value: &str) -> Option<i64> {
+pub(crate) fn iso_to_millis(value: &str) -> Option<i64> {
     if let Ok(v) = value.parse::<i64>() {
         return Some(v);
     }
@@ -264,6 +264,9 @@ pub enum SessionEntry {
         )]
         timestamp: String,
         name: Option<String>,
+        /// One-paragraph summary of the session so far.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        summary: Option<String>,
     },
 
     // ---------- Legacy Rust variants (read compatibility) ----------
@@ -464,15 +467,40 @@ impl SessionEntry {
     }
 }
 
+/// The latest title and summary set by `session_info` entries in `entries`.
+pub fn latest_session_info(entries: &[SessionEntry]) -> (Option<String>, Option<String>) {
+    let mut title = None;
+    let mut summary = None;
+    for entry in entries {
+        if let SessionEntry::SessionInfo {
+            name, summary: s, ..
+        } = entry
+        {
+            title = name.clone().or(title);
+            summary = s.clone().or(summary);
+        }
+    }
+    (title, summary)
+}
+
 /// Session metadata for listing.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct SessionInfo {
     pub session_id: String,
     pub title: Option<String>,
+    /// Generated one-paragraph summary, if any.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub summary: Option<String>,
+    /// Working directory the session was started in.
+    #[serde(default)]
+    pub cwd: String,
     pub created_at: i64,
     pub updated_at: i64,
     pub entry_count: usize,
+    /// Size of the session in its JSONL form.
+    #[serde(default)]
+    pub size_bytes: u64,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub parent_session_id: Option<String>,
 }
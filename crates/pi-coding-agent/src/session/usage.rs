@@ -0,0 +1,510 @@
+//! Token usage and cost accounting.
+//!
+//! Every assistant message already carries its [`Usage`], with cost filled
+//! in by the provider from the model's pricing. Calls made outside the
+//! conversation (compaction and summary generation) are persisted as
+//! `custom` entries of type [`USAGE_ENTRY_TYPE`] so session files remain the
+//! single source of truth. A [`UsageLedger`] aggregates records per model,
+//! provider and kind; [`UsageReport`] groups records across sessions by day,
+//! week or project for `pi usage`.
+
+use std::collections::{BTreeMap, HashSet};
+use std::sync::{Arc, Mutex};
+
+use chrono::{Datelike, Local, TimeZone};
+use pi_agent_core::types::{AssistantMessage, Message, Usage};
+use serde::{Deserialize, Serialize};
+
+use crate::session::manager::SessionManager;
+use crate::session::types::{SessionEntry, SessionHeader, iso_to_millis};
+
+/// `customType` of entries recording usage outside assistant messages.
+pub const USAGE_ENTRY_TYPE: &str = "usage";
+
+/// What a request was made for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum UsageKind {
+    /// A conversation turn.
+    Chat,
+    /// Summarizing history during compaction.
+    Compaction,
+    /// Titles, branch summaries and other auxiliary generation.
+    Summary,
+}
+
+impl UsageKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            UsageKind::Chat => "chat",
+            UsageKind::Compaction => "compaction",
+            UsageKind::Summary => "summary",
+        }
+    }
+}
+
+/// Summed tokens and cost.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    pub requests: u64,
+    pub input: u64,
+    pub output: u64,
+    pub cache_read: u64,
+    pub cache_write: u64,
+    /// Cost in USD.
+    pub cost: f64,
+}
+
+impl UsageTotals {
+    pub fn add(&mut self, usage: &Usage) {
+        self.requests += 1;
+        self.input += usage.input;
+        self.output += usage.output;
+        self.cache_read += usage.cache_read;
+        self.cache_write += usage.cache_write;
+        self.cost += usage.cost.total;
+    }
+
+    pub fn merge(&mut self, other: &UsageTotals) {
+        self.requests += other.requests;
+        self.input += other.input;
+        self.output += other.output;
+        self.cache_read += other.cache_read;
+        self.cache_write += other.cache_write;
+        self.cost += other.cost;
+    }
+
+    pub fn total_tokens(&self) -> u64 {
+        self.input + self.output + self.cache_read + self.cache_write
+    }
+}
+
+/// One request's usage.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageRecord {
+    pub kind: UsageKind,
+    pub provider: String,
+    pub model: String,
+    pub usage: Usage,
+    /// Unix milliseconds; `0` when not yet persisted.
+    #[serde(default, skip_serializing)]
+    pub timestamp: i64,
+}
+
+impl UsageRecord {
+    pub fn new(kind: UsageKind, provider: &str, model: &str, usage: Usage) -> Self {
+        Self {
+            kind,
+            provider: provider.to_string(),
+            model: model.to_string(),
+            usage,
+            timestamp: 0,
+        }
+    }
+
+    fn from_assistant(message: &AssistantMessage) -> Self {
+        Self {
+            kind: UsageKind::Chat,
+            provider: message.provider.clone(),
+            model: message.model.clone(),
+            usage: message.usage.clone(),
+            timestamp: message.timestamp,
+        }
+    }
+
+    /// The `custom` entry persisting this record.
+    pub fn to_entry(&self) -> SessionEntry {
+        SessionEntry::Custom {
+            id: SessionEntry::new_id(),
+            parent_id: None,
+            timestamp: crate::session::types::now_iso_timestamp(),
+            custom_type: USAGE_ENTRY_TYPE.to_string(),
+            data: serde_json::to_value(self).ok(),
+        }
+    }
+}
+
+/// Usage records in `entries`, keyed by entry id.
+pub fn usage_records(entries: &[SessionEntry]) -> Vec<(String, UsageRecord)> {
+    entries
+        .iter()
+        .filter_map(|entry| {
+            let record = match entry {
+                SessionEntry::Message {
+                    message: Message::Assistant(message),
+                    ..
+                } => UsageRecord::from_assistant(message),
+                SessionEntry::LegacyAssistant { message, .. } => {
+                    let message: AssistantMessage = serde_json::from_value(message.clone()).ok()?;
+                    UsageRecord::from_assistant(&message)
+                }
+                SessionEntry::Custom {
+                    timestamp,
+                    custom_type,
+                    data: Some(data),
+                    ..
+                } if custom_type == USAGE_ENTRY_TYPE => {
+                    let mut record: UsageRecord = serde_json::from_value(data.clone()).ok()?;
+                    record.timestamp = iso_to_millis(timestamp).unwrap_or(0);
+                    record
+                }
+                _ => return None,
+            };
+            Some((entry.id().to_string(), record))
+        })
+        .collect()
+}
+
+/// Cumulative usage broken down by model, provider and kind.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageLedger {
+    pub total: UsageTotals,
+    /// Keyed by `provider/model`.
+    pub by_model: BTreeMap<String, UsageTotals>,
+    pub by_provider: BTreeMap<String, UsageTotals>,
+    pub by_kind: BTreeMap<UsageKind, UsageTotals>,
+}
+
+impl UsageLedger {
+    /// Ledger of the usage recorded in a session's entries.
+    pub fn from_entries(entries: &[SessionEntry]) -> Self {
+        let mut ledger = Self::default();
+        for (_, record) in usage_records(entries) {
+            ledger.record(&record);
+        }
+        ledger
+    }
+
+    pub fn record(&mut self, record: &UsageRecord) {
+        self.total.add(&record.usage);
+        self.by_model
+            .entry(format!("{}/{}", record.provider, record.model))
+            .or_default()
+            .add(&record.usage);
+        self.by_provider
+            .entry(record.provider.clone())
+            .or_default()
+            .add(&record.usage);
+        self.by_kind
+            .entry(record.kind)
+            .or_default()
+            .add(&record.usage);
+    }
+}
+
+/// Collects usage from requests made outside the agent loop.
+///
+/// Clone the recorder into a summary function; the session drains and
+/// persists what it recorded after the call completes.
+#[derive(Clone, Default)]
+pub struct UsageRecorder {
+    pending: Arc<Mutex<Vec<UsageRecord>>>,
+}
+
+impl UsageRecorder {
+    pub fn record(&self, record: UsageRecord) {
+        self.pending
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(record);
+    }
+
+    pub fn drain(&self) -> Vec<UsageRecord> {
+        std::mem::take(&mut *self.pending.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+}
+
+// ============================================================================
+// Cross-session reports
+// ============================================================================
+
+/// A usage record located in a session.
+#[derive(Debug, Clone)]
+pub struct SessionUsageRecord {
+    pub session_id: String,
+    /// Working directory the session was started in; empty if unknown.
+    pub project: String,
+    pub record: UsageRecord,
+}
+
+/// Usage from every stored session.
+///
+/// Forked sessions copy their source's entries, so records are
+/// deduplicated by entry id.
+pub fn collect_usage(manager: &SessionManager) -> Vec<SessionUsageRecord> {
+    let mut seen = HashSet::new();
+    let mut records = Vec::new();
+    let mut ids = manager.list_all().unwrap_or_default();
+    ids.sort();
+    for id in ids {
+        let Ok((header, entries)) = manager.open(&id) else {
+            continue;
+        };
+        records.extend(session_usage_records(&header, &entries, &mut seen));
+    }
+    records
+}
+
+fn session_usage_records(
+    header: &SessionHeader,
+    entries: &[SessionEntry],
+    seen: &mut HashSet<String>,
+) -> Vec<SessionUsageRecord> {
+    usage_records(entries)
+        .into_iter()
+        .filter(|(entry_id, _)| seen.insert(entry_id.clone()))
+        .map(|(_, mut record)| {
+            if record.timestamp == 0 {
+                record.timestamp = header.timestamp_ms();
+            }
+            SessionUsageRecord {
+                session_id: header.id.clone(),
+                project: header.cwd.clone(),
+                record,
+            }
+        })
+        .collect()
+}
+
+/// How [`UsageReport`] rows are grouped.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UsageGrouping {
+    Day,
+    Week,
+    Project,
+    Model,
+    Provider,
+}
+
+impl UsageGrouping {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "day" | "daily" => Some(Self::Day),
+            "week" | "weekly" => Some(Self::Week),
+            "project" => Some(Self::Project),
+            "model" => Some(Self::Model),
+            "provider" => Some(Self::Provider),
+            _ => None,
+        }
+    }
+
+    fn key(&self, record: &SessionUsageRecord) -> String {
+        let local = || Local.timestamp_millis_opt(record.record.timestamp).single();
+        match self {
+            Self::Day => local()
+                .map(|t| t.format("%Y-%m-%d").to_string())
+                .unwrap_or_default(),
+            Self::Week => local()
+                .map(|t| {
+                    let week = t.iso_week();
+                    format!("{}-W{:02}", week.year(), week.week())
+                })
+                .unwrap_or_default(),
+            Self::Project if record.project.is_empty() => "(unknown)".to_string(),
+            Self::Project => record.project.clone(),
+            Self::Model => format!("{}/{}", record.record.provider, record.record.model),
+            Self::Provider => record.record.provider.clone(),
+        }
+    }
+}
+
+/// Usage totals grouped by one key.
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReport {
+    pub rows: Vec<UsageReportRow>,
+    pub total: UsageTotals,
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageReportRow {
+    pub key: String,
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+}
+
+impl UsageReport {
+    /// Group `records` at or after `since` (Unix ms), sorted by key.
+    pub fn build(records: &[SessionUsageRecord], grouping: UsageGrouping, since: i64) -> Self {
+        let mut groups: BTreeMap<String, UsageTotals> = BTreeMap::new();
+        let mut total = UsageTotals::default();
+        for record in records.iter().filter(|r| r.record.timestamp >= since) {
+            groups
+                .entry(grouping.key(record))
+                .or_default()
+                .add(&record.record.usage);
+            total.add(&record.record.usage);
+        }
+        Self {
+            rows: groups
+                .into_iter()
+                .map(|(key, totals)| UsageReportRow { key, totals })
+                .collect(),
+            total,
+        }
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut out = String::from("key,requests,input,output,cache_read,cache_write,cost\n");
+        for row in &self.rows {
+            let key = if row.key.contains([',', '"', '\n']) {
+                format!("\"{}\"", row.key.replace('"', "\"\""))
+            } else {
+                row.key.clone()
+            };
+            let t = &row.totals;
+            out.push_str(&format!(
+                "{key},{},{},{},{},{},{:.6}\n",
+                t.requests, t.input, t.output, t.cache_read, t.cache_write, t.cost
+            ));
+        }
+        out
+    }
+
+    pub fn to_table(&self) -> String {
+        let width = self
+            .rows
+            .iter()
+            .map(|row| row.key.chars().count())
+            .max()
+            .unwrap_or(0)
+            .max(5);
+        let line = |key: &str, t: &UsageTotals| {
+            format!(
+                "{key:<width$}  {:>8}  {:>12}  {:>12}  {:>12}  {:>12}  {:>10}\n",
+                t.requests,
+                t.input,
+                t.output,
+                t.cache_read,
+                t.cache_write,
+                format!("${:.4}", t.cost)
+            )
+        };
+        let mut out = format!(
+            "{:<width$}  {:>8}  {:>12}  {:>12}  {:>12}  {:>12}  {:>10}\n",
+            "", "requests", "input", "output", "cache read", "cache write", "cost"
+        );
+        for row in &self.rows {
+            out.push_str(&line(&row.key, &row.totals));
+        }
+        out.push_str(&line("total", &self.total));
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pi_agent_core::types::{ContentBlock, StopReason, TextContent, UsageCost};
+
+    fn usage(input: u64, output: u64, cost: f64) -> Usage {
+        Usage {
+            input,
+            output,
+            total_tokens: input + output,
+            cost: UsageCost {
+                total: cost,
+                ..UsageCost::default()
+            },
+            ..Usage::default()
+        }
+    }
+
+    fn assistant_entry(id: &str, provider: &str, model: &str, usage: Usage) -> SessionEntry {
+        SessionEntry::Message {
+            id: id.to_string(),
+            parent_id: None,
+            timestamp: "2026-01-05T12:00:00Z".to_string(),
+            message: Message::Assistant(AssistantMessage {
+                content: vec![ContentBlock::Text(TextContent {
+                    text: "ok".to_string(),
+                    text_signature: None,
+                    citations: None,
+                })],
+                api: "test".to_string(),
+                provider: provider.to_string(),
+                model: model.to_string(),
+                usage,
+                stop_reason: StopReason::Stop,
+                error_message: None,
+                timestamp: 1_767_614_400_000,
+                rate_limit: None,
+            }),
+        }
+    }
+
+    #[test]
+    fn test_ledger_aggregates_messages_and_usage_entries() {
+        let compaction = UsageRecord::new(
+            UsageKind::Compaction,
+            "openai",
+            "gpt-4o-mini",
+            usage(500, 100, 0.001),
+        );
+        let entries = vec![
+            assistant_entry("a", "anthropic", "claude", usage(100, 20, 0.01)),
+            assistant_entry("b", "anthropic", "claude", usage(200, 30, 0.02)),
+            compaction.to_entry(),
+        ];
+
+        let ledger = UsageLedger::from_entries(&entries);
+        assert_eq!(ledger.total.requests, 3);
+        assert_eq!(ledger.total.input, 800);
+        assert!((ledger.total.cost - 0.031).abs() < 1e-9);
+        assert_eq!(ledger.by_model["anthropic/claude"].output, 50);
+        assert_eq!(ledger.by_provider["openai"].input, 500);
+        assert_eq!(ledger.by_kind[&UsageKind::Compaction].requests, 1);
+        assert_eq!(ledger.by_kind[&UsageKind::Chat].requests, 2);
+    }
+
+    #[test]
+    fn test_report_deduplicates_forked_entries_and_groups_by_project() {
+        let header = |id: &str, cwd: &str| SessionHeader {
+            entry_type: "session".to_string(),
+            version: Some(3),
+            id: id.to_string(),
+            timestamp: "2026-01-05T12:00:00Z".to_string(),
+            cwd: cwd.to_string(),
+            parent_session: None,
+            title: None,
+        };
+        let shared = assistant_entry("a", "anthropic", "claude", usage(100, 10, 0.5));
+        let mut seen = HashSet::new();
+        let mut records = session_usage_records(
+            &header("s1", "/work/alpha"),
+            std::slice::from_ref(&shared),
+            &mut seen,
+        );
+        records.extend(session_usage_records(
+            &header("s2", "/work/alpha"),
+            &[
+                shared,
+                assistant_entry("b", "openai", "gpt", usage(50, 5, 0.25)),
+            ],
+            &mut seen,
+        ));
+        records.extend(session_usage_records(
+            &header("s3", ""),
+            &[assistant_entry("c", "openai", "gpt", usage(1, 1, 0.0))],
+            &mut seen,
+        ));
+
+        let report = UsageReport::build(&records, UsageGrouping::Project, 0);
+        assert_eq!(report.total.requests, 3);
+        assert_eq!(report.rows.len(), 2);
+        assert_eq!(report.rows[0].key, "(unknown)");
+        assert_eq!(report.rows[1].key, "/work/alpha");
+        assert!((report.rows[1].totals.cost - 0.75).abs() < 1e-9);
+
+        let csv = report.to_csv();
+        assert!(csv.starts_with("key,requests,"));
+        assert!(csv.contains("/work/alpha,2,150,15,0,0,0.750000"));
+
+        let later = UsageReport::build(&records, UsageGrouping::Day, i64::MAX);
+        assert!(later.rows.is_empty());
+    }
+}
@@ -1,7 +1,12 @@
+use pi_agent_ai::discovery::DiscoveryKind;
+use pi_agent_ai::http_client::HttpClientConfig;
+use pi_agent_core::types::{ModelCost, ToolChoice};
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 use std::collections::HashMap;
 
+use crate::session::store::SessionStoreKind;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct PackageSourceFilter {
@@ -105,6 +110,14 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub retry: Option<RetrySettings>,
 
+    /// Limits that stop the agent loop.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limits: Option<LimitSettings>,
+
+    /// Session storage settings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sessions: Option<SessionSettings>,
+
     /// Thinking level setting.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub thinking: Option<String>,
@@ -117,6 +130,35 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_tokens: Option<u64>,
 
+    /// Tool selection policy: "auto", "none", "required" or {"tool": "<name>"}.
+    /// "required" and {"tool": ...} apply to the first request of a prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_choice: Option<ToolChoice>,
+
+    /// Nucleus sampling probability mass.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_p: Option<f64>,
+
+    /// Top-k sampling cutoff.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub top_k: Option<u64>,
+
+    /// Sequences that stop generation.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stop_sequences: Option<Vec<String>>,
+
+    /// Sampling seed, for reproducible runs where supported.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub seed: Option<u64>,
+
+    /// Frequency penalty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub frequency_penalty: Option<f64>,
+
+    /// Presence penalty.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub presence_penalty: Option<f64>,
+
     /// System prompt override or additions.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub system_prompt: Option<String>,
@@ -137,6 +179,10 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub providers: Option<HashMap<String, ProviderSettings>>,
 
+    /// Default HTTP client settings (proxy, TLS, timeouts) for all providers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpClientConfig>,
+
     /// Extension configurations.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub extensions: Option<HashMap<String, Value>>,
@@ -145,19 +191,92 @@ pub struct Settings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub packages: Option<Vec<PackageSource>>,
 
+    /// Models tried, in order, when the current model fails with a
+    /// retryable error (overload, rate limit, 5xx).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fallback_models: Option<Vec<String>>,
+
     /// Optional model scope for cycling and startup selection.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enabled_models: Option<Vec<String>>,
 
+    /// Models discovered from provider model-list endpoints.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model_discovery: Option<ModelDiscoverySettings>,
+
     /// Quiet startup flag.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub quiet_startup: Option<bool>,
 
+    /// OpenAI-compatible gateway (`pi gateway serve`).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<GatewaySettings>,
+
     /// Any additional fields not covered above.
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
 
+/// Session storage settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSettings {
+    /// Storage backend: "jsonl" (default) or "sqlite".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub store: Option<SessionStoreKind>,
+
+    /// Old sessions deleted automatically at startup and by `pi sessions prune`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub retention: Option<RetentionSettings>,
+
+    /// Generate a title and summary after the first exchange and after
+    /// compaction. Defaults to true.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auto_title: Option<bool>,
+
+    /// Model for titles and summaries, e.g. "openai/gpt-4o-mini". Defaults
+    /// to the session's model.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title_model: Option<String>,
+}
+
+/// Session retention policy. Unset fields keep sessions forever; pinned
+/// sessions are never deleted. Startup applies only `max_age_days`, and
+/// never to recently active sessions; `pi sessions prune` applies all
+/// limits.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RetentionSettings {
+    /// Delete sessions not updated for this many days.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age_days: Option<u64>,
+
+    /// Keep at most this many unpinned sessions, newest first.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_count: Option<usize>,
+
+    /// Delete the oldest unpinned sessions while all sessions together
+    /// exceed this many megabytes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_total_mb: Option<u64>,
+}
+
+impl RetentionSettings {
+    /// `self` with every field set in `overrides` replaced.
+    pub fn overlay(&self, overrides: &RetentionSettings) -> RetentionSettings {
+        RetentionSettings {
+            max_age_days: overrides.max_age_days.or(self.max_age_days),
+            max_count: overrides.max_count.or(self.max_count),
+            max_total_mb: overrides.max_total_mb.or(self.max_total_mb),
+        }
+    }
+
+    /// Whether any limit is set.
+    pub fn is_enabled(&self) -> bool {
+        self.max_age_days.is_some() || self.max_count.is_some() || self.max_total_mb.is_some()
+    }
+}
+
 /// Compaction settings.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -173,6 +292,51 @@ pub struct CompactionSettings {
     /// Model to use for compaction (if different from main model).
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+
+    /// Ask the provider's count-tokens endpoint for exact context sizes
+    /// where one exists (Anthropic, Gemini) instead of estimating locally,
+    /// for context usage and the compaction threshold. Falls back to the
+    /// calibrated local count when the request fails.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub count_endpoint: Option<bool>,
+}
+
+/// Limits that stop the agent loop (`limits`). Unset fields are unlimited.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct LimitSettings {
+    /// Maximum assistant responses per prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+
+    /// Maximum tool executions per prompt.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_tool_calls: Option<u32>,
+
+    /// Wall-clock limit per prompt in seconds.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_duration_secs: Option<u64>,
+
+    /// Maximum spend per prompt in USD.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_cost: Option<f64>,
+
+    /// Maximum spend per session in USD.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_session_cost: Option<f64>,
+}
+
+impl LimitSettings {
+    /// `self` with every field set in `overrides` replaced.
+    pub fn overlay(&self, overrides: &LimitSettings) -> LimitSettings {
+        LimitSettings {
+            max_turns: overrides.max_turns.or(self.max_turns),
+            max_tool_calls: overrides.max_tool_calls.or(self.max_tool_calls),
+            max_duration_secs: overrides.max_duration_secs.or(self.max_duration_secs),
+            max_cost: overrides.max_cost.or(self.max_cost),
+            max_session_cost: overrides.max_session_cost.or(self.max_session_cost),
+        }
+    }
 }
 
 /// Retry settings.
@@ -213,10 +377,16 @@ pub struct PermissionSettings {
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ProviderSettings {
-    /// API key for this provider.
+    /// API key for this provider, or a reference to one (`env:VAR`,
+    /// `file:/path`, `cmd:command`) resolved when a request needs it.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub api_key: Option<String>,
 
+    /// Additional API keys (or key references); requests rotate
+    /// round-robin across all keys.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_keys: Option<Vec<String>>,
+
     /// Base URL override.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub base_url: Option<String>,
@@ -225,11 +395,82 @@ pub struct ProviderSettings {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
 
+    /// Client-side request rate limit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rate_limit: Option<ProviderRateLimitSettings>,
+
+    /// HTTP client overrides for this provider.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub http: Option<HttpClientConfig>,
+
     /// Any extra fields.
     #[serde(flatten)]
     pub extra: HashMap<String, Value>,
 }
 
+/// Client-side token-bucket limit for one provider.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ProviderRateLimitSettings {
+    /// Sustained requests per minute.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub requests_per_minute: Option<u32>,
+
+    /// Maximum burst size (default: requests per minute).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub burst: Option<u32>,
+}
+
+/// OpenAI-compatible gateway settings.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct GatewaySettings {
+    /// Client API keys by name; the name is recorded in the usage log.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub keys: Option<HashMap<String, String>>,
+
+    /// Model aliases: alias -> "provider/model-id".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub aliases: Option<HashMap<String, String>>,
+
+    /// Write per-key usage to `gateway-usage.jsonl` (default: true).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub usage_log: Option<bool>,
+}
+
+/// Model discovery settings (`modelDiscovery`).
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelDiscoverySettings {
+    /// How long discovered models are cached before they are fetched
+    /// again, in seconds (default: one day).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ttl_secs: Option<u64>,
+
+    /// Endpoints to list models from.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub sources: Option<Vec<DiscoverySourceSettings>>,
+}
+
+/// One model-list endpoint. Requests use the provider's API key and
+/// headers from `providers`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DiscoverySourceSettings {
+    /// Provider name for the discovered models, e.g. "ollama" or "lmstudio".
+    pub provider: String,
+
+    /// Endpoint kind: "openai", "ollama" or "openrouter". Defaults to the
+    /// provider's own kind for "ollama" and "openrouter", else "openai".
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kind: Option<DiscoveryKind>,
+
+    /// Server URL, e.g. "http://localhost:1234/v1". Optional for Ollama and
+    /// OpenRouter.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+}
+
 /// Custom model configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -268,4 +509,72 @@ pub struct CustomModelConfig {
     /// Custom headers.
     #[serde(skip_serializing_if = "Option::is_none")]
     pub headers: Option<HashMap<String, String>>,
+
+    /// Input types, e.g. ["text", "image"]. Defaults to text only.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub input: Option<Vec<String>>,
+
+    /// Price in USD per million tokens.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost: Option<ModelCost>,
+
+    /// `OpenAICompletionsCompat` fields, layered over the provider's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compat: Option<Value>,
+}
+
+/// Contents of `models.json`: providers and models. A bare list of models
+/// is also accepted.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModelsConfig {
+    /// Custom providers, and overrides for built-in ones, by name.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub providers: Option<HashMap<String, CustomProviderConfig>>,
+
+    /// Individual models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<CustomModelConfig>>,
+}
+
+/// A provider defined in `models.json`. For a built-in provider name the
+/// fields override the built-in models' settings and `models` adds to them.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CustomProviderConfig {
+    /// API implementation, e.g. "openai-completions" or "anthropic-messages".
+    /// Required for new providers.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api: Option<String>,
+
+    /// Base URL for the provider's models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub base_url: Option<String>,
+
+    /// API key, or a reference to one (`env:VAR`, `file:/path`,
+    /// `cmd:command`). A key in settings takes precedence.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key: Option<String>,
+
+    /// Environment variable holding the API key.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub api_key_env: Option<String>,
+
+    /// Extra header that carries the API key, e.g. "api-key" for gateways
+    /// that do not read the API's standard auth header.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_header: Option<String>,
+
+    /// Headers sent with every request.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub headers: Option<HashMap<String, String>>,
+
+    /// `OpenAICompletionsCompat` fields for the provider's models.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub compat: Option<Value>,
+
+    /// The provider's models; `api`, `provider` and `baseUrl` default to
+    /// the provider's.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub models: Option<Vec<CustomModelConfig>>,
 }
@@ -230,6 +230,7 @@ impl AgentTool for BashTool {
                 content: vec![ContentBlock::Text(TextContent {
                     text: truncated.content.clone(),
                     text_signature: None,
+                    citations: None,
                 })],
                 details: Some(details.clone()),
             });
@@ -239,6 +240,7 @@ impl AgentTool for BashTool {
             content: vec![ContentBlock::Text(TextContent {
                 text: truncated.content,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(details),
         })
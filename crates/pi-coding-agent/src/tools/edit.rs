@@ -227,6 +227,7 @@ impl AgentTool for EditTool {
             content: vec![ContentBlock::Text(TextContent {
                 text,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(json!({
                 "replacements": output.replacements,
@@ -202,6 +202,7 @@ impl AgentTool for FindTool {
             content: vec![ContentBlock::Text(TextContent {
                 text: output.0,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(json!({
                 "searchPath": resolved.display().to_string(),
@@ -326,6 +326,7 @@ impl AgentTool for GrepTool {
             content: vec![ContentBlock::Text(TextContent {
                 text: output.0,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(json!({
                 "path": resolved.display().to_string(),
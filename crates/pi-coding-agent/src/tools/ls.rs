@@ -161,6 +161,7 @@ impl AgentTool for LsTool {
             content: vec![ContentBlock::Text(TextContent {
                 text: output.0,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(details),
         })
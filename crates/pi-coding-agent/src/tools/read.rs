@@ -270,6 +270,7 @@ impl AgentTool for ReadTool {
             content: vec![ContentBlock::Text(TextContent {
                 text: truncated.content,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(json!({
                 "totalLines": output.total_lines,
@@ -170,6 +170,7 @@ impl AgentTool for WriteTool {
             content: vec![ContentBlock::Text(TextContent {
                 text,
                 text_signature: None,
+                citations: None,
             })],
             details: Some(json!({
                 "bytesWritten": output.bytes_written,